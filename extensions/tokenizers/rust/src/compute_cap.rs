@@ -1,30 +1,173 @@
+#[cfg(feature = "cuda")]
+use candle::cuda_backend::cudarc::driver::result;
+#[cfg(feature = "cuda")]
 use candle::cuda_backend::cudarc::driver::sys::CUdevice_attribute::{
     CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MAJOR, CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MINOR,
 };
+#[cfg(feature = "cuda")]
 use candle::cuda_backend::cudarc::driver::CudaDevice;
-use std::sync::Once;
+use candle::{Error, Result};
+use std::sync::OnceLock;
 
-static INIT: Once = Once::new();
-static mut RUNTIME_COMPUTE_CAP: usize = 0;
+/// Replaces the CUDA devices with mock ones, so the device enumeration can be exercised on a
+/// machine without GPU. Devices are separated by `;` and given as `name,compute_cap,total_memory`,
+/// e.g. `NVIDIA A10G,86,23836033024;Tesla T4,75,15642329088`.
+const MOCK_GPUS_ENV: &str = "DJL_RUST_MOCK_GPUS";
 
-fn init_compute_caps() {
-    unsafe {
-        INIT.call_once(|| {
-            let device = CudaDevice::new(0).expect("cuda is not available");
+static GPUS: OnceLock<std::result::Result<Vec<GpuInfo>, String>> = OnceLock::new();
+
+/// The properties of a GPU device.
+#[derive(Debug, Clone)]
+pub struct GpuInfo {
+    pub name: String,
+    pub compute_cap: usize,
+    pub total_memory: usize,
+}
+
+impl GpuInfo {
+    /// Whether the CUDA kernels are built for the compute capability of this device.
+    pub fn is_supported(&self) -> bool {
+        matches!(self.compute_cap, 75 | 80 | 86..=90)
+    }
+}
+
+/// Returns the GPU devices, indexed by their CUDA ordinal.
+pub fn get_gpus() -> Result<&'static [GpuInfo]> {
+    let gpus = GPUS.get_or_init(|| match std::env::var(MOCK_GPUS_ENV) {
+        Ok(spec) => parse_mock_gpus(&spec),
+        Err(_) => query_gpus(),
+    });
+    match gpus {
+        Ok(gpus) => Ok(gpus),
+        Err(msg) => Err(Error::Msg(msg.clone())),
+    }
+}
+
+/// Returns the number of GPU devices, 0 without the `cuda` feature. Devices with an unsupported
+/// compute capability are counted so that the count covers every CUDA ordinal.
+pub fn get_gpu_count() -> Result<usize> {
+    if cfg!(feature = "cuda") {
+        Ok(get_gpus()?.len())
+    } else {
+        Ok(0)
+    }
+}
+
+/// Whether a GPU device is supported by the CUDA kernels, false without the `cuda` feature.
+pub fn has_supported_gpu() -> Result<bool> {
+    Ok(cfg!(feature = "cuda") && get_gpus()?.iter().any(GpuInfo::is_supported))
+}
+
+pub fn get_gpu(device_id: usize) -> Result<&'static GpuInfo> {
+    let gpus = get_gpus()?;
+    gpus.get(device_id).ok_or_else(|| {
+        Error::Msg(format!(
+            "Invalid GPU device: {device_id}, {} GPUs available",
+            gpus.len()
+        ))
+    })
+}
+
+/// Fails if the device does not exist or its compute capability is not supported.
+#[cfg(feature = "cuda")]
+pub fn check_gpu(device_id: usize) -> Result<()> {
+    let gpu = get_gpu(device_id)?;
+    if !gpu.is_supported() {
+        candle::bail!(
+            "Unsupported compute capability {} of GPU {device_id}: {}",
+            gpu.compute_cap,
+            gpu.name
+        );
+    }
+    Ok(())
+}
+
+#[cfg(feature = "cuda")]
+fn query_gpus() -> std::result::Result<Vec<GpuInfo>, String> {
+    // No driver or no device
+    let Ok(count) = CudaDevice::count() else {
+        return Ok(Vec::new());
+    };
+    (0..count as usize)
+        .map(|ordinal| {
+            let device = CudaDevice::new(ordinal).map_err(|e| e.to_string())?;
             let major = device
                 .attribute(CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MAJOR)
-                .unwrap();
+                .map_err(|e| e.to_string())?;
             let minor = device
                 .attribute(CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MINOR)
-                .unwrap();
-            RUNTIME_COMPUTE_CAP = (major * 10 + minor) as usize;
-        });
-    }
+                .map_err(|e| e.to_string())?;
+            let total_memory = unsafe { result::device::total_mem(*device.cu_device()) }
+                .map_err(|e| e.to_string())?;
+            Ok(GpuInfo {
+                name: device.name().map_err(|e| e.to_string())?,
+                compute_cap: (major * 10 + minor) as usize,
+                total_memory,
+            })
+        })
+        .collect()
 }
 
-pub fn get_runtime_compute_cap() -> usize {
-    unsafe {
-        init_compute_caps();
-        RUNTIME_COMPUTE_CAP
+#[cfg(not(feature = "cuda"))]
+fn query_gpus() -> std::result::Result<Vec<GpuInfo>, String> {
+    Ok(Vec::new())
+}
+
+fn parse_mock_gpus(spec: &str) -> std::result::Result<Vec<GpuInfo>, String> {
+    spec.split(';')
+        .filter(|gpu| !gpu.trim().is_empty())
+        .map(|gpu| {
+            let invalid = || format!("Invalid {MOCK_GPUS_ENV} device: {gpu}");
+            let fields: Vec<&str> = gpu.split(',').map(str::trim).collect();
+            let [name, compute_cap, total_memory] = fields[..] else {
+                return Err(invalid());
+            };
+            Ok(GpuInfo {
+                name: name.to_string(),
+                compute_cap: compute_cap.parse().map_err(|_| invalid())?,
+                total_memory: total_memory.parse().map_err(|_| invalid())?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_gpus() -> Result<()> {
+        // The only test reading the devices, they are enumerated once per process
+        // An unsupported device before a supported one
+        std::env::set_var(
+            MOCK_GPUS_ENV,
+            "Tesla K80,37,11996954624; NVIDIA A10G,86,23836033024",
+        );
+        let gpus = get_gpus()?;
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].name, "Tesla K80");
+        assert!(!gpus[0].is_supported());
+        assert_eq!(get_gpu(1)?.name, "NVIDIA A10G");
+        assert_eq!(gpus[1].compute_cap, 86);
+        assert_eq!(gpus[1].total_memory, 23836033024);
+        assert!(gpus[1].is_supported());
+        assert!(get_gpu(2).is_err());
+        // Every ordinal is counted, so that the supported device stays reachable
+        let cuda = cfg!(feature = "cuda");
+        assert_eq!(get_gpu_count()?, if cuda { 2 } else { 0 });
+        assert_eq!(has_supported_gpu()?, cuda);
+        #[cfg(feature = "cuda")]
+        {
+            assert!(check_gpu(0).is_err());
+            check_gpu(1)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_mock_gpus() {
+        assert!(parse_mock_gpus("").unwrap().is_empty());
+        assert!(parse_mock_gpus("Tesla T4,75").is_err());
+        assert!(parse_mock_gpus("Tesla T4,7.5,15642329088").is_err());
     }
 }
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use jni::objects::{JLongArray, JObject, ReleaseMode};
+use jni::sys::{jboolean, jfloat, jint, jlong, jlongArray, JNI_FALSE, JNI_TRUE};
+use jni::JNIEnv;
+
+use crate::models::LoadedModel;
+use crate::{cast_handle, drop_handle, to_handle};
+
+mod scheduler;
+
+use scheduler::{GenerationParams, GenerationStream, Scheduler};
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_createScheduler(
+    mut env: JNIEnv,
+    _: JObject,
+    model_handle: jlong,
+    max_batch_size: jint,
+) -> jlong {
+    let model = cast_handle::<LoadedModel>(model_handle);
+    match Scheduler::new(model.model.clone(), max_batch_size as usize) {
+        Ok(scheduler) => to_handle(scheduler),
+        Err(err) => {
+            env.throw(err.to_string()).unwrap();
+            0
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_deleteScheduler(
+    _: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) {
+    drop_handle::<Scheduler>(handle);
+}
+
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_submitGeneration<'local>(
+    mut env: JNIEnv<'local>,
+    _: JObject,
+    handle: jlong,
+    input_ids: JLongArray<'local>,
+    max_new_tokens: jint,
+    temperature: jfloat,
+    top_p: jfloat,
+    top_k: jint,
+    seed: jlong,
+    eos_token_ids: JLongArray<'local>,
+) -> jlong {
+    let scheduler = cast_handle::<Scheduler>(handle);
+    let input_ids = as_token_ids(&mut env, &input_ids);
+    let eos_token_ids = if eos_token_ids.is_null() {
+        Vec::new()
+    } else {
+        as_token_ids(&mut env, &eos_token_ids)
+    };
+    let params = GenerationParams {
+        max_new_tokens: max_new_tokens.max(0) as usize,
+        temperature: temperature as f64,
+        top_p: top_p as f64,
+        top_k: top_k.max(0) as usize,
+        seed: seed as u64,
+        eos_token_ids,
+    };
+    match scheduler.submit(input_ids, params) {
+        Ok(stream) => to_handle(stream),
+        Err(err) => {
+            env.throw(err.to_string()).unwrap();
+            0
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_pollGeneration<'local>(
+    mut env: JNIEnv<'local>,
+    _: JObject,
+    handle: jlong,
+    timeout_millis: jlong,
+) -> jlongArray {
+    let stream = cast_handle::<Arc<GenerationStream>>(handle);
+    let timeout = Duration::from_millis(timeout_millis.max(0) as u64);
+    match stream.poll(timeout) {
+        Ok(tokens) => {
+            let tokens = tokens.into_iter().map(|t| t as jlong).collect::<Vec<_>>();
+            let array = env.new_long_array(tokens.len() as i32).unwrap();
+            env.set_long_array_region(&array, 0, &tokens).unwrap();
+            array.into_raw()
+        }
+        Err(err) => {
+            env.throw(err).unwrap();
+            JObject::null().into_raw()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_isGenerationFinished(
+    _: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jboolean {
+    let stream = cast_handle::<Arc<GenerationStream>>(handle);
+    if stream.is_finished() {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_cancelGeneration(
+    _: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) {
+    let stream = cast_handle::<Arc<GenerationStream>>(handle);
+    stream.cancel();
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_deleteGenerationRequest(
+    _: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) {
+    let stream = cast_handle::<Arc<GenerationStream>>(handle);
+    stream.cancel();
+    drop_handle::<Arc<GenerationStream>>(handle);
+}
+
+fn as_token_ids(env: &mut JNIEnv, array: &JLongArray) -> Vec<u32> {
+    let elements = unsafe { env.get_array_elements(array, ReleaseMode::NoCopyBack) }.unwrap();
+    elements.iter().map(|&i| i as u32).collect()
+}
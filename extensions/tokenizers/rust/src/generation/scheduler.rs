@@ -0,0 +1,445 @@
+use crate::layers::{KvCache, SequenceCache};
+use crate::models::{CausalLM, Model};
+use candle::{DType, Result, Tensor};
+use candle_transformers::generation::{LogitsProcessor, Sampling};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct GenerationParams {
+    pub max_new_tokens: usize,
+    pub temperature: f64,
+    pub top_p: f64,
+    pub top_k: usize,
+    pub seed: u64,
+    pub eos_token_ids: Vec<u32>,
+}
+
+impl GenerationParams {
+    fn sampling(&self) -> Sampling {
+        let temperature = self.temperature;
+        if temperature <= 0. {
+            return Sampling::ArgMax;
+        }
+        let top_p = self.top_p > 0. && self.top_p < 1.;
+        match (self.top_k, top_p) {
+            (0, false) => Sampling::All { temperature },
+            (0, true) => Sampling::TopP {
+                p: self.top_p,
+                temperature,
+            },
+            (k, false) => Sampling::TopK { k, temperature },
+            (k, true) => Sampling::TopKThenTopP {
+                k,
+                p: self.top_p,
+                temperature,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct StreamState {
+    tokens: VecDeque<u32>,
+    finished: bool,
+    error: Option<String>,
+}
+
+/// Tokens produced for a single generation request, consumed by polling from the Java side.
+#[derive(Debug, Default)]
+pub struct GenerationStream {
+    state: Mutex<StreamState>,
+    ready: Condvar,
+    cancelled: AtomicBool,
+}
+
+impl GenerationStream {
+    fn push(&self, token: u32) {
+        let mut state = self.state.lock().unwrap();
+        state.tokens.push_back(token);
+        self.ready.notify_all();
+    }
+
+    fn finish(&self, error: Option<String>) {
+        let mut state = self.state.lock().unwrap();
+        state.finished = true;
+        state.error = error;
+        self.ready.notify_all();
+    }
+
+    /// Waits up to `timeout` for new tokens and returns all tokens produced since the last poll.
+    ///
+    /// An empty result means that either the timeout expired or the generation is finished.
+    pub fn poll(&self, timeout: Duration) -> std::result::Result<Vec<u32>, String> {
+        let state = self.state.lock().unwrap();
+        let (mut state, _) = self
+            .ready
+            .wait_timeout_while(state, timeout, |s| s.tokens.is_empty() && !s.finished)
+            .unwrap();
+        if state.tokens.is_empty() {
+            if let Some(err) = state.error.take() {
+                return Err(err);
+            }
+        }
+        Ok(state.tokens.drain(..).collect())
+    }
+
+    /// Returns `true` once the generation is finished and all tokens have been polled.
+    pub fn is_finished(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.finished && state.tokens.is_empty() && state.error.is_none()
+    }
+
+    /// Asks the scheduler to stop the generation at the next step.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+struct Submission {
+    input_ids: Vec<u32>,
+    params: GenerationParams,
+    stream: Arc<GenerationStream>,
+}
+
+struct Sequence {
+    cache: SequenceCache,
+    last_token: u32,
+    generated: usize,
+    params: GenerationParams,
+    logits_processor: LogitsProcessor,
+    stream: Arc<GenerationStream>,
+}
+
+impl Sequence {
+    /// Records a sampled token, returns `true` when the sequence is done.
+    fn accept(&mut self, token: u32) -> bool {
+        self.generated += 1;
+        if self.params.eos_token_ids.contains(&token) {
+            self.stream.finish(None);
+            return true;
+        }
+        self.stream.push(token);
+        self.last_token = token;
+        if self.generated >= self.params.max_new_tokens {
+            self.stream.finish(None);
+            return true;
+        }
+        false
+    }
+}
+
+/// Merges concurrent generation requests into shared decoding steps.
+///
+/// Each sequence owns a slot of the key/value cache holding its past tokens, the cache has one
+/// slot per sequence of the batch. New requests join the running batch after their prompt has
+/// been processed and finished sequences leave it, freeing their slot, after every step.
+pub struct Scheduler {
+    sender: Option<Sender<Submission>>,
+    shutdown: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Scheduler {
+    pub fn new(model: Arc<dyn Model>, max_batch_size: usize) -> Result<Self> {
+        let Some(num_layers) = model.as_causal_lm().map(|lm| lm.num_layers()) else {
+            candle::bail!("The model does not support text generation")
+        };
+        let cache = KvCache::new(num_layers, max_batch_size.max(1));
+        Ok(Self::start(model, cache))
+    }
+
+    /// Starts decoding the requests of `model`, a causal language model, at most one sequence per
+    /// slot of `cache`.
+    fn start(model: Arc<dyn Model>, cache: Arc<KvCache>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let worker_shutdown = shutdown.clone();
+        let worker = std::thread::spawn(move || {
+            // The scheduler keeps the model alive even if its handle is deleted first
+            let model = model.as_causal_lm().unwrap();
+            run(model, &cache, receiver, &worker_shutdown)
+        });
+        Self {
+            sender: Some(sender),
+            shutdown,
+            worker: Some(worker),
+        }
+    }
+
+    pub fn submit(
+        &self,
+        input_ids: Vec<u32>,
+        params: GenerationParams,
+    ) -> Result<Arc<GenerationStream>> {
+        if input_ids.is_empty() {
+            candle::bail!("input_ids must not be empty")
+        }
+        let stream = Arc::new(GenerationStream::default());
+        let submission = Submission {
+            input_ids,
+            params,
+            stream: stream.clone(),
+        };
+        match &self.sender {
+            Some(sender) if sender.send(submission).is_ok() => Ok(stream),
+            _ => candle::bail!("scheduler is closed"),
+        }
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run(
+    model: &dyn CausalLM,
+    cache: &Arc<KvCache>,
+    receiver: Receiver<Submission>,
+    shutdown: &AtomicBool,
+) {
+    let mut pending: VecDeque<Submission> = VecDeque::new();
+    let mut active: Vec<Sequence> = Vec::new();
+    while !shutdown.load(Ordering::Relaxed) {
+        if active.is_empty() && pending.is_empty() {
+            match receiver.recv() {
+                Ok(submission) => pending.push_back(submission),
+                Err(_) => break,
+            }
+        }
+        while let Ok(submission) = receiver.try_recv() {
+            pending.push_back(submission);
+        }
+
+        active.retain(|seq| {
+            if seq.stream.is_cancelled() {
+                seq.stream.finish(None);
+                return false;
+            }
+            true
+        });
+        while !pending.is_empty() {
+            let Some(slot) = cache.acquire() else {
+                break;
+            };
+            let submission = pending.pop_front().unwrap();
+            if submission.stream.is_cancelled() {
+                submission.stream.finish(None);
+                continue;
+            }
+            let stream = submission.stream.clone();
+            match prefill(model, slot, submission) {
+                Ok(Some(seq)) => active.push(seq),
+                Ok(None) => {}
+                Err(err) => stream.finish(Some(err.to_string())),
+            }
+        }
+        if active.is_empty() {
+            continue;
+        }
+
+        if let Err(err) = decode_step(model, &mut active) {
+            for seq in active.drain(..) {
+                seq.stream.finish(Some(err.to_string()));
+            }
+        }
+    }
+
+    for seq in active {
+        seq.stream.finish(Some("scheduler is closed".to_string()));
+    }
+    for submission in pending {
+        submission
+            .stream
+            .finish(Some("scheduler is closed".to_string()));
+    }
+}
+
+/// Runs the prompt of a new request on its own and samples its first token.
+fn prefill(
+    model: &dyn CausalLM,
+    cache: SequenceCache,
+    submission: Submission,
+) -> Result<Option<Sequence>> {
+    let Submission {
+        input_ids,
+        params,
+        stream,
+    } = submission;
+    let mut seq = Sequence {
+        cache,
+        last_token: 0,
+        generated: 0,
+        logits_processor: LogitsProcessor::from_sampling(params.seed, params.sampling()),
+        params,
+        stream,
+    };
+    if seq.params.max_new_tokens == 0 {
+        seq.stream.finish(None);
+        return Ok(None);
+    }
+    let len = input_ids.len();
+    let input_ids = Tensor::from_vec(input_ids, (1, len), model.device())?;
+    let logits = model.forward_step(&input_ids, None, &mut [&mut seq.cache])?;
+    let logits = logits.squeeze(0)?.to_dtype(DType::F32)?;
+    let token = seq.logits_processor.sample(&logits)?;
+    if seq.accept(token) {
+        return Ok(None);
+    }
+    Ok(Some(seq))
+}
+
+/// Decodes one token for every active sequence in a single batched forward pass.
+fn decode_step(model: &dyn CausalLM, active: &mut Vec<Sequence>) -> Result<()> {
+    let last_tokens = active.iter().map(|seq| seq.last_token).collect::<Vec<_>>();
+    let input_ids = Tensor::from_vec(last_tokens, (active.len(), 1), model.device())?;
+    let logits = {
+        let mut caches = active
+            .iter_mut()
+            .map(|seq| &mut seq.cache)
+            .collect::<Vec<_>>();
+        model.forward_step(&input_ids, None, &mut caches)?
+    };
+    let logits = logits.to_dtype(DType::F32)?;
+    let mut finished = Vec::with_capacity(active.len());
+    for (i, seq) in active.iter_mut().enumerate() {
+        let token = seq.logits_processor.sample(&logits.get(i)?)?;
+        finished.push(seq.accept(token));
+    }
+    let mut finished = finished.into_iter();
+    active.retain(|_| !finished.next().unwrap_or(true));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layers::append_and_gather;
+    use crate::models::InputSpec;
+    use candle::{Device, IndexOp};
+
+    const VOCAB_SIZE: usize = 16;
+
+    /// Predicts `token + 1` after `token`, reading the last token back from its cache slot.
+    struct Counter {
+        device: Device,
+    }
+
+    impl Model for Counter {
+        fn get_input_spec(&self) -> Vec<InputSpec> {
+            vec![InputSpec::input_ids()]
+        }
+
+        fn as_causal_lm(&self) -> Option<&dyn CausalLM> {
+            Some(self)
+        }
+    }
+
+    impl CausalLM for Counter {
+        fn num_layers(&self) -> usize {
+            1
+        }
+
+        fn device(&self) -> &Device {
+            &self.device
+        }
+
+        fn forward_step(
+            &self,
+            input_ids: &Tensor,
+            _seq_lens: Option<&[usize]>,
+            caches: &mut [&mut SequenceCache],
+        ) -> Result<Tensor> {
+            let (b_sz, q_len) = input_ids.dims2()?;
+            let offsets = caches.iter().map(|c| c.seq_len()).collect::<Vec<_>>();
+            let states = input_ids
+                .to_dtype(DType::F32)?
+                .reshape((b_sz, 1, q_len, 1))?;
+            let (keys, _, _) = append_and_gather(caches, 0, &states, &states, &offsets, None)?;
+            let mut logits = vec![0f32; b_sz * VOCAB_SIZE];
+            for (i, offset) in offsets.iter().enumerate() {
+                let last = keys.i((i, 0, offset + q_len - 1, 0))?.to_scalar::<f32>()?;
+                logits[i * VOCAB_SIZE + (last as usize + 1) % VOCAB_SIZE] = 1.;
+            }
+            Tensor::from_vec(logits, (b_sz, VOCAB_SIZE), &self.device)
+        }
+    }
+
+    fn start(num_slots: usize) -> (Scheduler, Arc<KvCache>) {
+        let model = Arc::new(Counter {
+            device: Device::Cpu,
+        });
+        let cache = KvCache::new(1, num_slots);
+        (Scheduler::start(model, cache.clone()), cache)
+    }
+
+    fn params(max_new_tokens: usize, eos_token_ids: Vec<u32>) -> GenerationParams {
+        GenerationParams {
+            max_new_tokens,
+            temperature: 0.,
+            top_p: 1.,
+            top_k: 0,
+            seed: 0,
+            eos_token_ids,
+        }
+    }
+
+    fn collect(stream: &GenerationStream) -> Vec<u32> {
+        let mut tokens = Vec::new();
+        for _ in 0..100 {
+            if stream.is_finished() {
+                return tokens;
+            }
+            tokens.extend(stream.poll(Duration::from_secs(10)).unwrap());
+        }
+        panic!("the generation did not finish");
+    }
+
+    #[test]
+    fn test_admit_and_evict() -> Result<()> {
+        let (scheduler, cache) = start(2);
+        let a = scheduler.submit(vec![1, 2], params(3, vec![]))?;
+        let b = scheduler.submit(vec![7], params(2, vec![]))?;
+        // Only admitted once a slot is free, stops at the eos token
+        let c = scheduler.submit(vec![10, 11, 12], params(5, vec![14]))?;
+        assert_eq!(collect(&a), [3, 4, 5]);
+        assert_eq!(collect(&b), [8, 9]);
+        assert_eq!(collect(&c), [13]);
+
+        let d = scheduler.submit(vec![15], params(2, vec![]))?;
+        assert_eq!(collect(&d), [0, 1]);
+        drop(scheduler);
+        assert_eq!(cache.num_free_slots(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancel() -> Result<()> {
+        let (scheduler, cache) = start(1);
+        let a = scheduler.submit(vec![1], params(usize::MAX, vec![]))?;
+        let b = scheduler.submit(vec![4], params(2, vec![]))?;
+        assert_eq!(a.poll(Duration::from_secs(10)).unwrap()[0], 2);
+        a.cancel();
+        collect(&a);
+        // b takes over the slot of a
+        assert_eq!(collect(&b), [5, 6]);
+        drop(scheduler);
+        assert_eq!(cache.num_free_slots(), 1);
+        assert!(a.is_finished());
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use candle::{DType, Device, Result, Tensor};
+use std::sync::Mutex;
+
+/// Symmetric ALiBi attention biases, `-slope * |i - j|` for each head.
+///
+/// https://arxiv.org/abs/2108.12409
+#[derive(Debug)]
+pub struct Alibi {
+    slopes: Tensor,
+    dtype: DType,
+    /// The bias of the longest sequence so far, shorter ones are its top left corner.
+    cache: Mutex<Option<Tensor>>,
+}
+
+impl Alibi {
+    pub fn new(num_heads: usize, dtype: DType, device: &Device) -> Result<Self> {
+        let slopes = get_slopes(num_heads);
+        let slopes = Tensor::from_vec(slopes, (num_heads, 1, 1), device)?;
+        Ok(Self {
+            slopes,
+            dtype,
+            cache: Mutex::new(None),
+        })
+    }
+
+    /// Returns the `(1, num_heads, seq_len, seq_len)` bias, only recomputed when a sequence is
+    /// longer than all the previous ones.
+    pub fn bias(&self, seq_len: usize) -> Result<Tensor> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(bias) = cache.as_ref() {
+            if bias.dim(2)? >= seq_len {
+                return bias.narrow(2, 0, seq_len)?.narrow(3, 0, seq_len);
+            }
+        }
+        let distances: Vec<f32> = (0..seq_len)
+            .flat_map(|i| (0..seq_len).map(move |j| -(i.abs_diff(j) as f32)))
+            .collect();
+        let distances = Tensor::from_vec(distances, (1, seq_len, seq_len), self.slopes.device())?;
+        let bias = distances
+            .broadcast_mul(&self.slopes)?
+            .unsqueeze(0)?
+            .to_dtype(self.dtype)?;
+        *cache = Some(bias.clone());
+        Ok(bias)
+    }
+}
+
+// https://github.com/ofirpress/attention_with_linear_biases/blob/master/fairseq/models/transformer.py#L742
+fn get_slopes(num_heads: usize) -> Vec<f32> {
+    fn get_slopes_power_of_2(n: usize) -> Vec<f32> {
+        let start = 2f32.powf(-(2f32.powf(-((n as f32).log2() - 3.))));
+        (0..n).map(|i| start * start.powi(i as i32)).collect()
+    }
+
+    if num_heads.is_power_of_two() {
+        get_slopes_power_of_2(num_heads)
+    } else {
+        let closest_power_of_2 = 1 << num_heads.ilog2();
+        let mut slopes = get_slopes_power_of_2(closest_power_of_2);
+        slopes.extend(
+            get_slopes_power_of_2(2 * closest_power_of_2)
+                .into_iter()
+                .step_by(2)
+                .take(num_heads - closest_power_of_2),
+        );
+        slopes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle::IndexOp;
+
+    #[test]
+    fn test_bias() -> Result<()> {
+        let alibi = Alibi::new(2, DType::F32, &Device::Cpu)?;
+        let long = alibi.bias(4)?;
+        assert_eq!(long.dims4()?, (1, 2, 4, 4));
+        let short = alibi.bias(3)?;
+        assert_eq!(short.dims4()?, (1, 2, 3, 3));
+        // Narrowed from the cached bias rather than recomputed
+        assert_eq!(alibi.cache.lock().unwrap().as_ref().unwrap().dim(2)?, 4);
+        let slope = alibi.slopes.i((1, 0, 0))?.to_scalar::<f32>()?;
+        let row = short.i((0, 1, 2))?.to_vec1::<f32>()?;
+        assert_eq!(row, [-2. * slope, -slope, 0.]);
+        assert_eq!(
+            short.i(0)?.to_vec3::<f32>()?,
+            long.i((0, .., ..3, ..3))?.to_vec3::<f32>()?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use candle::{Result, Tensor};
+use std::sync::{Arc, Mutex};
+
+/// Number of positions first allocated per slot, doubled whenever a sequence outgrows it.
+const INITIAL_CAPACITY: usize = 256;
+
+/// Key/value cache shared by the sequences decoded together.
+///
+/// Every layer preallocates its keys and values with the shape
+/// `(num_slots, num_kv_heads, capacity, head_dim)` on the first write. Each sequence owns a slot
+/// and its new positions are written in place, the capacity doubles when a sequence outgrows it
+/// so cached positions are only copied a logarithmic number of times.
+#[derive(Debug)]
+pub struct KvCache {
+    num_layers: usize,
+    num_slots: usize,
+    initial_capacity: usize,
+    layers: Mutex<Vec<Option<(Tensor, Tensor)>>>,
+    free_slots: Mutex<Vec<usize>>,
+}
+
+impl KvCache {
+    pub fn new(num_layers: usize, num_slots: usize) -> Arc<Self> {
+        Self::with_capacity(num_layers, num_slots, INITIAL_CAPACITY)
+    }
+
+    fn with_capacity(num_layers: usize, num_slots: usize, initial_capacity: usize) -> Arc<Self> {
+        Arc::new(Self {
+            num_layers,
+            num_slots,
+            initial_capacity: initial_capacity.max(1),
+            layers: Mutex::new(vec![None; num_layers]),
+            // Hands out the lowest slots first
+            free_slots: Mutex::new((0..num_slots).rev().collect()),
+        })
+    }
+
+    /// Reserves a slot for a new sequence, `None` if every slot is in use.
+    pub fn acquire(self: &Arc<Self>) -> Option<SequenceCache> {
+        let slot = self.free_slots.lock().unwrap().pop()?;
+        Some(SequenceCache {
+            cache: self.clone(),
+            slot,
+            lens: vec![0; self.num_layers],
+        })
+    }
+
+    #[cfg(test)]
+    pub fn num_free_slots(&self) -> usize {
+        self.free_slots.lock().unwrap().len()
+    }
+
+    fn release(&self, slot: usize) {
+        self.free_slots.lock().unwrap().push(slot);
+    }
+
+    /// Returns the keys and values of `layer_idx`, allocated or grown to hold `len` positions.
+    fn reserve(&self, layer_idx: usize, like: &Tensor, len: usize) -> Result<(Tensor, Tensor)> {
+        let mut layers = self.layers.lock().unwrap();
+        let (_, num_kv_heads, _, head_dim) = like.dims4()?;
+        let mut capacity = match &layers[layer_idx] {
+            Some((k, v)) if k.dim(2)? >= len => return Ok((k.clone(), v.clone())),
+            Some((k, _)) => k.dim(2)?,
+            None => self.initial_capacity,
+        };
+        while capacity < len {
+            capacity *= 2;
+        }
+        let shape = (self.num_slots, num_kv_heads, capacity, head_dim);
+        let k = Tensor::zeros(shape, like.dtype(), like.device())?;
+        let v = Tensor::zeros(shape, like.dtype(), like.device())?;
+        if let Some((prev_k, prev_v)) = &layers[layer_idx] {
+            k.slice_set(prev_k, 2, 0)?;
+            v.slice_set(prev_v, 2, 0)?;
+        }
+        layers[layer_idx] = Some((k.clone(), v.clone()));
+        Ok((k, v))
+    }
+}
+
+/// The slot of a `KvCache` holding the past keys and values of one sequence, released on drop.
+#[derive(Debug)]
+pub struct SequenceCache {
+    cache: Arc<KvCache>,
+    slot: usize,
+    lens: Vec<usize>,
+}
+
+impl SequenceCache {
+    /// Creates the cache of a single sequence that is not decoded along with others.
+    #[cfg(test)]
+    pub fn new(num_layers: usize) -> Self {
+        KvCache::new(num_layers, 1).acquire().unwrap()
+    }
+
+    /// Number of positions already stored in the cache.
+    pub fn seq_len(&self) -> usize {
+        self.lens.first().copied().unwrap_or(0)
+    }
+
+    /// Drops the positions from `len` on, they are overwritten by the next step.
+    pub fn truncate(&mut self, len: usize) {
+        for l in self.lens.iter_mut() {
+            *l = (*l).min(len);
+        }
+    }
+}
+
+impl Drop for SequenceCache {
+    fn drop(&mut self) {
+        self.cache.release(self.slot);
+    }
+}
+
+/// Writes the keys and values of one batched decoder step into the slot of each sequence and
+/// returns the right padded keys, values and the additive attention mask of the whole batch.
+///
+/// `key_states` and `value_states` have the shape `(batch, num_kv_heads, seq_len, head_dim)`,
+/// row `i` extends `caches[i]` whose first new position is `offsets[i]`. All caches must share
+/// the same `KvCache`. The returned mask has the shape `(batch, 1, seq_len, max_kv_len)` and
+/// combines causal, padding and sliding window masking.
+pub fn append_and_gather(
+    caches: &mut [&mut SequenceCache],
+    layer_idx: usize,
+    key_states: &Tensor,
+    value_states: &Tensor,
+    offsets: &[usize],
+    sliding_window: Option<usize>,
+) -> Result<(Tensor, Tensor, Tensor)> {
+    let (b_sz, _, q_len, _) = key_states.dims4()?;
+    if caches.len() != b_sz || offsets.len() != b_sz {
+        candle::bail!(
+            "batch size mismatch: {b_sz} rows, {} caches, {} offsets",
+            caches.len(),
+            offsets.len()
+        )
+    }
+    let pool = caches[0].cache.clone();
+    if caches.iter().any(|c| !Arc::ptr_eq(&c.cache, &pool)) {
+        candle::bail!("the sequences of a batch must share their key/value cache")
+    }
+
+    let kv_lens = offsets.iter().map(|o| o + q_len).collect::<Vec<_>>();
+    let max_len = kv_lens.iter().copied().max().unwrap_or(0);
+    let (k_all, v_all) = pool.reserve(layer_idx, key_states, max_len)?;
+    for (i, cache) in caches.iter_mut().enumerate() {
+        let k = key_states.narrow(0, i, 1)?.contiguous()?;
+        let v = value_states.narrow(0, i, 1)?.contiguous()?;
+        k_all
+            .narrow(0, cache.slot, 1)?
+            .slice_set(&k, 2, offsets[i])?;
+        v_all
+            .narrow(0, cache.slot, 1)?
+            .slice_set(&v, 2, offsets[i])?;
+        cache.lens[layer_idx] = kv_lens[i];
+    }
+
+    let slots = caches.iter().map(|c| c.slot).collect::<Vec<_>>();
+    let k_all = k_all.narrow(2, 0, max_len)?;
+    let v_all = v_all.narrow(2, 0, max_len)?;
+    let (keys, values) = if slots.windows(2).all(|w| w[1] == w[0] + 1) {
+        // Consecutive slots are a view of the cache
+        (
+            k_all.narrow(0, slots[0], b_sz)?,
+            v_all.narrow(0, slots[0], b_sz)?,
+        )
+    } else {
+        let gather = |all: &Tensor| {
+            let rows = slots
+                .iter()
+                .map(|&slot| all.narrow(0, slot, 1))
+                .collect::<Result<Vec<_>>>()?;
+            Tensor::cat(&rows, 0)
+        };
+        (gather(&k_all)?, gather(&v_all)?)
+    };
+
+    let mask: Vec<f32> = (0..b_sz)
+        .flat_map(|b| {
+            let kv_len = kv_lens[b];
+            let offset = offsets[b];
+            (0..q_len).flat_map(move |i| {
+                let pos = offset + i;
+                (0..max_len).map(move |j| {
+                    let outside_window = sliding_window.is_some_and(|w| j + w < pos);
+                    if j >= kv_len || j > pos || outside_window {
+                        f32::NEG_INFINITY
+                    } else {
+                        0.
+                    }
+                })
+            })
+        })
+        .collect();
+    let mask = Tensor::from_vec(mask, (b_sz, 1, q_len, max_len), key_states.device())?
+        .to_dtype(key_states.dtype())?;
+    Ok((keys, values, mask))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle::{Device, IndexOp};
+
+    /// Keys of one step, `(batch, 1, q_len, 1)` holding `value` at every position.
+    fn states(values: &[f32], q_len: usize) -> Result<Tensor> {
+        let data = values
+            .iter()
+            .flat_map(|&v| std::iter::repeat_n(v, q_len))
+            .collect::<Vec<_>>();
+        Tensor::from_vec(data, (values.len(), 1, q_len, 1), &Device::Cpu)
+    }
+
+    fn positions(keys: &Tensor, row: usize) -> Result<Vec<f32>> {
+        keys.i((row, 0, .., 0))?.to_vec1()
+    }
+
+    #[test]
+    fn test_append_and_gather() -> Result<()> {
+        let pool = KvCache::with_capacity(1, 2, 2);
+        let mut a = pool.acquire().unwrap();
+        let mut b = pool.acquire().unwrap();
+
+        // a prefills 3 positions on its own, growing the capacity from 2 to 4
+        let k = states(&[1.], 3)?;
+        let (keys, _, mask) = append_and_gather(&mut [&mut a], 0, &k, &k, &[0], None)?;
+        assert_eq!(positions(&keys, 0)?, [1., 1., 1.]);
+        assert_eq!(mask.dims4()?, (1, 1, 3, 3));
+        assert_eq!(a.seq_len(), 3);
+
+        // b prefills 1 position
+        let k = states(&[2.], 1)?;
+        append_and_gather(&mut [&mut b], 0, &k, &k, &[0], None)?;
+
+        // both decode one step together, b is right padded
+        let k = states(&[3., 4.], 1)?;
+        let (keys, values, mask) =
+            append_and_gather(&mut [&mut a, &mut b], 0, &k, &k, &[3, 1], None)?;
+        assert_eq!(keys.dims4()?, (2, 1, 4, 1));
+        assert_eq!(positions(&keys, 0)?, [1., 1., 1., 3.]);
+        assert_eq!(positions(&values, 1)?[..2], [2., 4.]);
+        let inf = f32::NEG_INFINITY;
+        assert_eq!(
+            mask.i((.., 0, 0))?.to_vec2::<f32>()?,
+            [[0., 0., 0., 0.], [0., 0., inf, inf]]
+        );
+        assert_eq!((a.seq_len(), b.seq_len()), (4, 2));
+
+        // a drops its last position as padding, the next step overwrites it
+        a.truncate(3);
+        assert_eq!(a.seq_len(), 3);
+
+        // out of order slots are gathered
+        let k = states(&[6., 5.], 1)?;
+        let (keys, _, _) = append_and_gather(&mut [&mut b, &mut a], 0, &k, &k, &[2, 3], None)?;
+        assert_eq!(positions(&keys, 0)?[..3], [2., 4., 6.]);
+        assert_eq!(positions(&keys, 1)?, [1., 1., 1., 5.]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sliding_window() -> Result<()> {
+        let mut cache = SequenceCache::new(1);
+        let k = states(&[1.], 4)?;
+        let (_, _, mask) = append_and_gather(&mut [&mut cache], 0, &k, &k, &[0], Some(2))?;
+        let mask = mask.i((0, 0, 3))?.to_vec1::<f32>()?;
+        assert_eq!(mask, [f32::NEG_INFINITY, 0., 0., 0.]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_slots() -> Result<()> {
+        let pool = KvCache::new(2, 2);
+        let a = pool.acquire().unwrap();
+        let b = pool.acquire().unwrap();
+        assert!(pool.acquire().is_none());
+        drop(a);
+        assert_eq!(pool.num_free_slots(), 1);
+        let c = pool.acquire().unwrap();
+        assert_eq!(c.seq_len(), 0);
+        drop((b, c));
+        assert_eq!(pool.num_free_slots(), 2);
+
+        let mut a = pool.acquire().unwrap();
+        let mut other = SequenceCache::new(2);
+        let k = states(&[1., 2.], 1)?;
+        assert!(append_and_gather(&mut [&mut a, &mut other], 0, &k, &k, &[0, 0], None).is_err());
+        Ok(())
+    }
+}
@@ -1,10 +1,12 @@
 use candle::{DType, Device, Result, Tensor, D};
 use candle_nn::VarBuilder;
 
+use crate::layers::get_optional;
+
 #[derive(Debug)]
 pub struct LayerNorm {
     weight: Tensor,
-    bias: Tensor,
+    bias: Option<Tensor>,
     epsilon: f32,
     span: tracing::Span,
 }
@@ -15,9 +17,7 @@ impl LayerNorm {
             weight: vb
                 .get(hidden_size, "weight")
                 .or_else(|_| vb.get(hidden_size, "gamma"))?,
-            bias: vb
-                .get(hidden_size, "bias")
-                .or_else(|_| vb.get(hidden_size, "beta"))?,
+            bias: get_optional(&vb, hidden_size, &["bias", "beta"])?,
             epsilon,
             span: tracing::span!(tracing::Level::TRACE, "layer-norm"),
         })
@@ -49,7 +49,10 @@ impl LayerNorm {
                 let hidden_states = hidden_states_normed
                     .to_dtype(hidden_states_dtype)?
                     .broadcast_mul(&self.weight)?;
-                hidden_states.broadcast_add(&self.bias)
+                match &self.bias {
+                    Some(bias) => hidden_states.broadcast_add(bias),
+                    None => Ok(hidden_states),
+                }
             }
             Device::Cuda(_) => {
                 #[cfg(feature = "cuda")]
@@ -66,12 +69,17 @@ impl LayerNorm {
                             &hidden_states,
                             &residual,
                             &self.weight,
-                            Some(&self.bias),
+                            self.bias.as_ref(),
                             self.epsilon,
                         )?;
                         Ok(result)
                     } else {
-                        layer_norm(&hidden_states, &self.weight, Some(&self.bias), self.epsilon)
+                        layer_norm(
+                            &hidden_states,
+                            &self.weight,
+                            self.bias.as_ref(),
+                            self.epsilon,
+                        )
                     }?;
                     result.reshape(original_shape)
                 }
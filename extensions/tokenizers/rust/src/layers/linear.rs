@@ -1,7 +1,48 @@
 use crate::layers::cublaslt::{get_cublas_lt_wrapper, CublasLtWrapper};
-use candle::{Device, Result, Tensor};
+use candle::quantized::{GgmlDType, QMatMul, QTensor};
+use candle::{DType, Device, Module, Result, Tensor};
 use candle_nn::VarBuilder;
 use serde::Deserialize;
+use std::cell::Cell;
+use std::sync::Arc;
+
+thread_local! {
+    static QUANTIZATION: Cell<Option<GgmlDType>> = const { Cell::new(None) };
+}
+
+/// Modules of the task heads, kept dense as they are small and the most sensitive to
+/// quantization.
+const DENSE_MODULES: [&str; 7] = [
+    "classifier",
+    "cls",
+    "lm_head",
+    "pre_classifier",
+    "qa_outputs",
+    "score",
+    "vocab_transform",
+];
+
+/// Restores the quantization of the thread on drop, also when unwinding.
+struct RestoreQuantization(Option<GgmlDType>);
+
+impl Drop for RestoreQuantization {
+    fn drop(&mut self) {
+        QUANTIZATION.with(|q| q.set(self.0));
+    }
+}
+
+/// Runs `f` with the weights loaded by `Linear::load` on this thread quantized to `dtype`, e.g.
+/// `GgmlDType::Q8_0` for int8 weights. The task heads, e.g. `classifier`, stay dense.
+pub fn with_quantization<T>(dtype: Option<GgmlDType>, f: impl FnOnce() -> T) -> T {
+    let _restore = RestoreQuantization(QUANTIZATION.with(|q| q.replace(dtype)));
+    f()
+}
+
+fn is_head(prefix: &str) -> bool {
+    prefix
+        .split('.')
+        .any(|module| DENSE_MODULES.contains(&module))
+}
 
 #[derive(Debug, Deserialize, PartialEq, Clone)]
 #[serde(rename_all = "lowercase")]
@@ -12,9 +53,15 @@ pub enum HiddenAct {
     Swiglu,
 }
 
+#[derive(Debug)]
+enum Weight {
+    Dense(Tensor),
+    Quantized(QMatMul),
+}
+
 #[derive(Debug)]
 pub struct Linear {
-    weight: Tensor,
+    weight: Weight,
     bias: Option<Tensor>,
     act: Option<HiddenAct>,
     cublaslt: Option<CublasLtWrapper>,
@@ -22,6 +69,17 @@ pub struct Linear {
 }
 
 impl Linear {
+    pub fn new(weight: Tensor, bias: Option<Tensor>, act: Option<HiddenAct>) -> Self {
+        let cublaslt = get_cublas_lt_wrapper(weight.device());
+        Self {
+            weight: Weight::Dense(weight),
+            bias,
+            act,
+            cublaslt,
+            span: tracing::span!(tracing::Level::TRACE, "linear"),
+        }
+    }
+
     pub fn load(
         vb: VarBuilder,
         in_dim: usize,
@@ -32,12 +90,29 @@ impl Linear {
             Ok(w) => Some(w),
             Err(_) => None,
         };
-        let cublaslt = get_cublas_lt_wrapper(&vb.device());
+        let weight = vb.get((out_dim, in_dim), "weight")?;
+        match QUANTIZATION.with(Cell::get) {
+            // Quantized blocks span the input dimension, keep the odd sized layers dense
+            Some(dtype) if in_dim.is_multiple_of(dtype.block_size()) && !is_head(&vb.prefix()) => {
+                let weight = QTensor::quantize(&weight, dtype)?;
+                Self::from_qtensor(Arc::new(weight), bias, act)
+            }
+            _ => Ok(Self::new(weight, bias, act)),
+        }
+    }
+
+    /// Creates a layer that multiplies by a quantized `(out_dim, in_dim)` weight, e.g. one read
+    /// from a GGUF file.
+    pub fn from_qtensor(
+        weight: Arc<QTensor>,
+        bias: Option<Tensor>,
+        act: Option<HiddenAct>,
+    ) -> Result<Self> {
         Ok(Self {
-            weight: vb.get((out_dim, in_dim), "weight")?,
+            weight: Weight::Quantized(QMatMul::from_arc(weight)?),
             bias,
             act,
-            cublaslt,
+            cublaslt: None,
             span: tracing::span!(tracing::Level::TRACE, "linear"),
         })
     }
@@ -45,11 +120,23 @@ impl Linear {
     pub fn forward(&self, x: &Tensor) -> Result<Tensor> {
         let _enter = self.span.enter();
 
+        let weight = match &self.weight {
+            Weight::Dense(weight) => weight,
+            Weight::Quantized(weight) => {
+                // The quantized kernels only take contiguous f32 inputs
+                let dtype = x.dtype();
+                let x = weight
+                    .forward(&x.to_dtype(DType::F32)?.contiguous()?)?
+                    .to_dtype(dtype)?;
+                return self.bias_act(x);
+            }
+        };
+
         #[allow(unused)]
         if let (Device::Cuda(_), Some(cublaslt)) = (x.device(), self.cublaslt.clone()) {
             match x.dims() {
                 &[bsize, _, _] => cublaslt.batch_matmul(
-                    &self.weight.broadcast_left(bsize)?,
+                    &weight.broadcast_left(bsize)?,
                     x,
                     None,
                     None,
@@ -58,7 +145,7 @@ impl Linear {
                     self.act.clone(),
                 ),
                 _ => cublaslt.matmul(
-                    &self.weight,
+                    weight,
                     x,
                     None,
                     None,
@@ -69,23 +156,56 @@ impl Linear {
             }
         } else {
             let w = match x.dims() {
-                &[bsize, _, _] => self.weight.broadcast_left(bsize)?.t()?,
-                _ => self.weight.t()?,
+                &[bsize, _, _] => weight.broadcast_left(bsize)?.t()?,
+                _ => weight.t()?,
             };
-            let x = x.matmul(&w)?;
-            let x = match &self.bias {
-                None => Ok(x),
-                Some(bias) => x.broadcast_add(bias),
-            }?;
-            if let Some(act) = &self.act {
-                match act {
-                    HiddenAct::Gelu => x.gelu(),
-                    HiddenAct::Relu => x.relu(),
-                    HiddenAct::Swiglu => candle_nn::ops::swiglu(&x),
-                }
-            } else {
-                Ok(x)
+            self.bias_act(x.matmul(&w)?)
+        }
+    }
+
+    fn bias_act(&self, x: Tensor) -> Result<Tensor> {
+        let x = match &self.bias {
+            None => Ok(x),
+            Some(bias) => x.broadcast_add(bias),
+        }?;
+        if let Some(act) = &self.act {
+            match act {
+                HiddenAct::Gelu => x.gelu(),
+                HiddenAct::Relu => x.relu(),
+                HiddenAct::Swiglu => candle_nn::ops::swiglu(&x),
             }
+        } else {
+            Ok(x)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_quantized(vb: &VarBuilder, name: &str) -> Result<bool> {
+        let linear = Linear::load(vb.pp(name), 32, 2, None)?;
+        Ok(matches!(linear.weight, Weight::Quantized(_)))
+    }
+
+    #[test]
+    fn test_with_quantization() -> Result<()> {
+        let vb = VarBuilder::zeros(DType::F32, &Device::Cpu);
+        with_quantization(Some(GgmlDType::Q8_0), || {
+            assert!(is_quantized(&vb, "encoder.layer.0.dense")?);
+            assert!(!is_quantized(&vb, "classifier.out_proj")?);
+            assert!(!is_quantized(&vb, "score")?);
+            Ok::<_, candle::Error>(())
+        })?;
+        assert!(!is_quantized(&vb, "encoder.layer.0.dense")?);
+
+        // A panic while loading does not leak the quantization into later loads
+        let result = std::panic::catch_unwind(|| {
+            with_quantization(Some(GgmlDType::Q8_0), || panic!("load failed"))
+        });
+        assert!(result.is_err());
+        assert_eq!(QUANTIZATION.with(Cell::get), None);
+        Ok(())
+    }
+}
@@ -1,10 +1,51 @@
+mod alibi;
 #[allow(dead_code, unused)]
 mod cublaslt;
+mod kv_cache;
 mod layer_norm;
 mod linear;
 #[allow(dead_code, unused)]
 mod rms_norm;
 
+pub use alibi::Alibi;
+pub use kv_cache::{append_and_gather, KvCache, SequenceCache};
 pub use layer_norm::LayerNorm;
-pub use linear::{HiddenAct, Linear};
+pub use linear::{with_quantization, HiddenAct, Linear};
 pub use rms_norm::RmsNorm;
+
+use candle::{Result, Shape, Tensor};
+use candle_nn::VarBuilder;
+
+/// Loads the first of `names` present in `vb`, `None` if none is. Errors loading a present
+/// tensor, e.g. a shape mismatch, are returned rather than treated as a missing tensor.
+pub fn get_optional<S: Into<Shape>>(
+    vb: &VarBuilder,
+    shape: S,
+    names: &[&str],
+) -> Result<Option<Tensor>> {
+    names
+        .iter()
+        .find(|name| vb.contains_tensor(name))
+        .map(|name| vb.get(shape, name))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle::{DType, Device};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_get_optional() -> Result<()> {
+        let device = Device::Cpu;
+        let tensors = HashMap::from([("beta".to_string(), Tensor::zeros(4, DType::F32, &device)?)]);
+        let vb = VarBuilder::from_tensors(tensors, DType::F32, &device);
+        let bias = get_optional(&vb, 4, &["bias", "beta"])?;
+        assert_eq!(bias.unwrap().dims(), [4]);
+        assert!(get_optional(&vb, 4, &["bias"])?.is_none());
+        // A present tensor of the wrong shape is an error, not a missing tensor
+        assert!(get_optional(&vb, 3, &["bias", "beta"]).is_err());
+        Ok(())
+    }
+}
@@ -8,6 +8,12 @@ pub struct RmsNorm {
 }
 
 impl RmsNorm {
+    pub fn new(weight: Tensor, eps: f64) -> Self {
+        let span = tracing::span!(tracing::Level::TRACE, "rms-norm");
+        let inner = candle_nn::RmsNorm::new(weight, eps);
+        Self { inner, span }
+    }
+
     pub fn load(vb: VarBuilder, size: usize, eps: f64) -> Result<Self> {
         let span = tracing::span!(tracing::Level::TRACE, "rms-norm");
         let inner = candle_nn::rms_norm(size, eps, vb)?;
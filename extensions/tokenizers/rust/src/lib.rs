@@ -14,16 +14,15 @@
 
 mod ndarray;
 
-#[cfg(feature = "cuda")]
 mod compute_cap;
+mod generation;
 mod layers;
 mod models;
 mod utils;
 
 extern crate tokenizers as tk;
 
-#[cfg(feature = "cuda")]
-use crate::compute_cap::get_runtime_compute_cap;
+use crate::compute_cap::{get_gpu, get_gpu_count, has_supported_gpu};
 
 use std::str::FromStr;
 
@@ -815,14 +814,46 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_isCudaAvailable<'loca
     _: JNIEnv,
     _: JObject,
 ) -> jboolean {
-    #[cfg(feature = "cuda")]
-    match get_runtime_compute_cap() {
-        75 | 80 | 86..=90 => JNI_TRUE,
+    match has_supported_gpu() {
+        Ok(true) => JNI_TRUE,
         _ => JNI_FALSE,
     }
-    #[cfg(not(feature = "cuda"))]
-    {
-        JNI_FALSE
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_getGpuCount(
+    mut env: JNIEnv,
+    _: JObject,
+) -> jint {
+    match get_gpu_count() {
+        Ok(count) => count as jint,
+        Err(err) => {
+            env.throw(err.to_string()).unwrap();
+            0
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_getDeviceInfo(
+    mut env: JNIEnv,
+    _: JObject,
+    device_id: jint,
+) -> jobjectArray {
+    match get_gpu(device_id as usize) {
+        Ok(gpu) => {
+            let info = vec![
+                gpu.name.clone(),
+                gpu.compute_cap.to_string(),
+                gpu.total_memory.to_string(),
+                gpu.is_supported().to_string(),
+            ];
+            to_string_array(&mut env, info).unwrap()
+        }
+        Err(err) => {
+            env.throw(err.to_string()).unwrap();
+            std::ptr::null_mut()
+        }
     }
 }
 
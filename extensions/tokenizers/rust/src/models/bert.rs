@@ -1,5 +1,6 @@
-use crate::layers::{LayerNorm, Linear};
-use crate::models::Model;
+use crate::layers::{get_optional, Alibi, LayerNorm, Linear};
+use crate::models::inputs::{embeds_inputs, token_inputs, InputSpec, ModelInputs};
+use crate::models::{span_output_names, span_outputs, Model, ModelOutputs};
 use candle::{Device, IndexOp, Result, Tensor};
 use candle_nn::{embedding, Embedding, Module, VarBuilder};
 use serde::Deserialize;
@@ -131,11 +132,15 @@ impl BertEmbeddings {
             config.hidden_size,
             vb.pp("word_embeddings"),
         )?;
-        let position_embeddings = embedding(
-            config.max_position_embeddings,
-            config.hidden_size,
-            vb.pp("position_embeddings"),
-        )?;
+        // ALiBi models encode positions in the attention biases instead
+        let position_embeddings = match config.position_embedding_type {
+            PositionEmbeddingType::Alibi => None,
+            _ => Some(embedding(
+                config.max_position_embeddings,
+                config.hidden_size,
+                vb.pp("position_embeddings"),
+            )?),
+        };
         let token_type_embeddings = embedding(
             config.type_vocab_size,
             config.hidden_size,
@@ -148,7 +153,7 @@ impl BertEmbeddings {
         )?;
         Ok(Self {
             word_embeddings,
-            position_embeddings: Some(position_embeddings),
+            position_embeddings,
             token_type_embeddings,
             layer_norm,
             dropout: Dropout::new(config.hidden_dropout_prob),
@@ -156,16 +161,22 @@ impl BertEmbeddings {
         })
     }
 
-    fn forward(&self, input_ids: &Tensor, token_type_ids: &Tensor) -> Result<Tensor> {
+    /// Adds the token type and position embeddings to the word embeddings `inputs_embeds`.
+    fn forward(
+        &self,
+        inputs_embeds: &Tensor,
+        token_type_ids: &Tensor,
+        position_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
         let _enter = self.span.enter();
-        let (_bsize, seq_len) = input_ids.dims2()?;
-        let input_embeddings = self.word_embeddings.forward(input_ids)?;
+        let (_bsize, seq_len, _) = inputs_embeds.dims3()?;
         let token_type_embeddings = self.token_type_embeddings.forward(token_type_ids)?;
-        let mut embeddings = (&input_embeddings + token_type_embeddings)?;
+        let mut embeddings = (inputs_embeds + token_type_embeddings)?;
         if let Some(position_embeddings) = &self.position_embeddings {
-            // TODO: Proper absolute positions?
-            let position_ids = (0..seq_len as u32).collect::<Vec<_>>();
-            let position_ids = Tensor::new(&position_ids[..], input_ids.device())?;
+            let position_ids = match position_ids {
+                Some(position_ids) => position_ids.clone(),
+                None => Tensor::arange(0u32, seq_len as u32, inputs_embeds.device())?,
+            };
             embeddings = embeddings.broadcast_add(&position_embeddings.forward(&position_ids)?)?
         }
         let embeddings = self.layer_norm.forward(&embeddings, None)?;
@@ -234,8 +245,14 @@ fn flash_attn(_: &Tensor, _: &Tensor, _: &Tensor, _: f32, _: bool) -> Result<Ten
     unimplemented!("compile with '--features flash-attn'")
 }
 
-impl Module for BertSelfAttention {
-    fn forward(&self, hidden_states: &Tensor) -> Result<Tensor> {
+impl BertSelfAttention {
+    /// Also returns the attention probabilities if `output_attentions` is set.
+    fn forward(
+        &self,
+        hidden_states: &Tensor,
+        attention_bias: Option<&Tensor>,
+        output_attentions: bool,
+    ) -> Result<(Tensor, Option<Tensor>)> {
         let _enter = self.span.enter();
         let query_layer = self.query.forward(hidden_states)?;
         let key_layer = self.key.forward(hidden_states)?;
@@ -245,16 +262,22 @@ impl Module for BertSelfAttention {
         let key_layer = self.transpose_for_scores(&key_layer)?;
         let value_layer = self.transpose_for_scores(&value_layer)?;
 
-        let context_layer = if self.use_flash_attn {
+        let use_flash_attn = self.use_flash_attn && attention_bias.is_none() && !output_attentions;
+        let (context_layer, attention_probs) = if use_flash_attn {
             // flash-attn expects (b_sz, seq_len, nheads, head_dim)
             let q = query_layer.transpose(1, 2)?;
             let k = key_layer.transpose(1, 2)?;
             let v = value_layer.transpose(1, 2)?;
             let softmax_scale = 1f32 / (self.attention_head_size as f32).sqrt();
-            flash_attn(&q, &k, &v, softmax_scale, false)?.transpose(1, 2)?
+            let context_layer = flash_attn(&q, &k, &v, softmax_scale, false)?.transpose(1, 2)?;
+            (context_layer, None)
         } else {
             let attention_scores = query_layer.matmul(&key_layer.t()?)?;
             let attention_scores = (attention_scores / (self.attention_head_size as f64).sqrt())?;
+            let attention_scores = match attention_bias {
+                Some(bias) => attention_scores.broadcast_add(bias)?,
+                None => attention_scores,
+            };
             let attention_probs = {
                 let _enter_sm = self.span_softmax.enter();
                 candle_nn::ops::softmax(&attention_scores, candle::D::Minus1)?
@@ -262,12 +285,12 @@ impl Module for BertSelfAttention {
             let attention_probs = self.dropout.forward(&attention_probs)?;
 
             let context_layer = attention_probs.matmul(&value_layer)?;
-            context_layer
+            (context_layer, output_attentions.then_some(attention_probs))
         };
 
         let context_layer = context_layer.transpose(1, 2)?.contiguous()?;
         let context_layer = context_layer.flatten_from(candle::D::Minus2)?;
-        Ok(context_layer)
+        Ok((context_layer, attention_probs))
     }
 }
 
@@ -322,12 +345,19 @@ impl BertAttention {
     }
 }
 
-impl Module for BertAttention {
-    fn forward(&self, hidden_states: &Tensor) -> Result<Tensor> {
+impl BertAttention {
+    fn forward(
+        &self,
+        hidden_states: &Tensor,
+        attention_bias: Option<&Tensor>,
+        output_attentions: bool,
+    ) -> Result<(Tensor, Option<Tensor>)> {
         let _enter = self.span.enter();
-        let self_outputs = self.self_attention.forward(hidden_states)?;
+        let (self_outputs, attention_probs) =
+            self.self_attention
+                .forward(hidden_states, attention_bias, output_attentions)?;
         let attention_output = self.self_output.forward(&self_outputs, hidden_states)?;
-        Ok(attention_output)
+        Ok((attention_output, attention_probs))
     }
 }
 
@@ -423,10 +453,17 @@ impl BertLayer {
     }
 }
 
-impl Module for BertLayer {
-    fn forward(&self, hidden_states: &Tensor) -> Result<Tensor> {
+impl BertLayer {
+    fn forward(
+        &self,
+        hidden_states: &Tensor,
+        attention_bias: Option<&Tensor>,
+        output_attentions: bool,
+    ) -> Result<(Tensor, Option<Tensor>)> {
         let _enter = self.span.enter();
-        let attention_output = self.attention.forward(hidden_states)?;
+        let (attention_output, attention_probs) =
+            self.attention
+                .forward(hidden_states, attention_bias, output_attentions)?;
         // TODO: Support cross-attention?
         // https://github.com/huggingface/transformers/blob/6eedfa6dd15dc1e22a55ae036f681914e5a0d9a1/src/transformers/models/bert/modeling_bert.py#L523
         // TODO: Support something similar to `apply_chunking_to_forward`?
@@ -434,7 +471,7 @@ impl Module for BertLayer {
         let layer_output = self
             .output
             .forward(&intermediate_output, &attention_output)?;
-        Ok(layer_output)
+        Ok((layer_output, attention_probs))
     }
 }
 
@@ -454,15 +491,43 @@ impl BertEncoder {
     }
 }
 
-impl Module for BertEncoder {
-    fn forward(&self, hidden_states: &Tensor) -> Result<Tensor> {
+struct BertEncoderOutput {
+    last_hidden_state: Tensor,
+    // The embedding output followed by the output of every layer
+    hidden_states: Vec<Tensor>,
+    attentions: Vec<Tensor>,
+}
+
+impl BertEncoder {
+    fn forward(
+        &self,
+        hidden_states: &Tensor,
+        attention_bias: Option<&Tensor>,
+        output_hidden_states: bool,
+        output_attentions: bool,
+    ) -> Result<BertEncoderOutput> {
         let _enter = self.span.enter();
         let mut hidden_states = hidden_states.clone();
+        let mut all_hidden_states = Vec::new();
+        let mut all_attentions = Vec::new();
         // Use a loop rather than a fold as it's easier to modify when adding debug/...
         for layer in self.layers.iter() {
-            hidden_states = layer.forward(&hidden_states)?
+            if output_hidden_states {
+                all_hidden_states.push(hidden_states.clone());
+            }
+            let (layer_output, attention_probs) =
+                layer.forward(&hidden_states, attention_bias, output_attentions)?;
+            hidden_states = layer_output;
+            all_attentions.extend(attention_probs);
         }
-        Ok(hidden_states)
+        if output_hidden_states {
+            all_hidden_states.push(hidden_states.clone());
+        }
+        Ok(BertEncoderOutput {
+            last_hidden_state: hidden_states,
+            hidden_states: all_hidden_states,
+            attentions: all_attentions,
+        })
     }
 }
 
@@ -531,6 +596,8 @@ impl ClassificationHead for BertClassificationHead {
 pub struct BertModel {
     embeddings: BertEmbeddings,
     encoder: BertEncoder,
+    pooler: Option<Linear>,
+    alibi: Option<Alibi>,
     #[allow(unused)]
     pub device: Device,
     span: tracing::Span,
@@ -554,22 +621,72 @@ impl BertModel {
                 }
             }
         };
+        let pooler = ["pooler.dense", "bert.pooler.dense"]
+            .iter()
+            .find(|name| vb.contains_tensor(&format!("{name}.weight")))
+            .map(|name| Linear::load(vb.pp(name), config.hidden_size, config.hidden_size, None))
+            .transpose()?;
+        let alibi = match config.position_embedding_type {
+            PositionEmbeddingType::Alibi => Some(Alibi::new(
+                config.num_attention_heads,
+                vb.dtype(),
+                vb.device(),
+            )?),
+            _ => None,
+        };
         Ok(Self {
             embeddings,
             encoder,
+            pooler,
+            alibi,
             device: vb.device().clone(),
             span: tracing::span!(tracing::Level::TRACE, "model"),
         })
     }
+
+    fn encode(
+        &self,
+        inputs_embeds: &Tensor,
+        token_type_ids: &Tensor,
+        position_ids: Option<&Tensor>,
+        output_hidden_states: bool,
+        output_attentions: bool,
+    ) -> Result<BertEncoderOutput> {
+        let _enter = self.span.enter();
+        let embedding_output =
+            self.embeddings
+                .forward(inputs_embeds, token_type_ids, position_ids)?;
+        let attention_bias = match &self.alibi {
+            Some(alibi) => Some(alibi.bias(inputs_embeds.dim(1)?)?),
+            None => None,
+        };
+        self.encoder.forward(
+            &embedding_output,
+            attention_bias.as_ref(),
+            output_hidden_states,
+            output_attentions,
+        )
+    }
 }
 
 impl Model for BertModel {
-    fn get_input_names(&self) -> Vec<String> {
-        return vec![
-            "input_ids".to_string(),
-            "attention_mask".to_string(),
-            "token_type_ids".to_string(),
-        ];
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        let mut specs = embeds_inputs(true);
+        // ALiBi models have no absolute positions
+        if self.embeddings.position_embeddings.is_some() {
+            specs.push(InputSpec::position_ids());
+        }
+        specs
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        let mut names = vec!["last_hidden_state".to_string()];
+        if self.pooler.is_some() {
+            names.push("pooler_output".to_string());
+        }
+        names.push("hidden_states".to_string());
+        names.push("attentions".to_string());
+        names
     }
 
     fn forward(
@@ -578,18 +695,91 @@ impl Model for BertModel {
         _attention_mask: &Tensor,
         token_type_ids: Option<&Tensor>,
     ) -> Result<Tensor> {
-        let _enter = self.span.enter();
-        let embedding_output = self
-            .embeddings
-            .forward(input_ids, token_type_ids.unwrap())?;
-        let sequence_output = self.encoder.forward(&embedding_output)?;
-        Ok(sequence_output)
+        let inputs_embeds = self.embeddings.word_embeddings.forward(input_ids)?;
+        let output = self.encode(&inputs_embeds, token_type_ids.unwrap(), None, false, false)?;
+        Ok(output.last_hidden_state)
+    }
+
+    /// `hidden_states` stacks the embedding output and the output of every layer,
+    /// `(num_layers + 1, batch, seq_len, hidden_size)`, and `attentions` the attention
+    /// probabilities of every layer, `(num_layers, batch, num_heads, seq_len, seq_len)`.
+    fn forward_outputs(
+        &self,
+        input_ids: &Tensor,
+        _attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+        requested: &[String],
+    ) -> Result<ModelOutputs> {
+        let inputs_embeds = self.embeddings.word_embeddings.forward(input_ids)?;
+        self.outputs(&inputs_embeds, token_type_ids.unwrap(), None, requested)
+    }
+
+    fn forward_inputs(&self, inputs: &ModelInputs, requested: &[String]) -> Result<ModelOutputs> {
+        let inputs_embeds = match inputs.get_opt("inputs_embeds") {
+            Some(inputs_embeds) => inputs_embeds.clone(),
+            None => self
+                .embeddings
+                .word_embeddings
+                .forward(inputs.get("input_ids")?)?,
+        };
+        self.outputs(
+            &inputs_embeds,
+            inputs.get("token_type_ids")?,
+            inputs.get_opt("position_ids"),
+            requested,
+        )
+    }
+}
+
+impl BertModel {
+    fn outputs(
+        &self,
+        inputs_embeds: &Tensor,
+        token_type_ids: &Tensor,
+        position_ids: Option<&Tensor>,
+        requested: &[String],
+    ) -> Result<ModelOutputs> {
+        let available = self.get_output_names();
+        if let Some(name) = requested.iter().find(|r| !available.contains(r)) {
+            candle::bail!("Unsupported output `{name}`, available outputs: {available:?}");
+        }
+        let requested = if requested.is_empty() {
+            &available[..1]
+        } else {
+            requested
+        };
+        let output_hidden_states = requested.iter().any(|r| r == "hidden_states");
+        let output_attentions = requested.iter().any(|r| r == "attentions");
+        let output = self.encode(
+            inputs_embeds,
+            token_type_ids,
+            position_ids,
+            output_hidden_states,
+            output_attentions,
+        )?;
+        requested
+            .iter()
+            .map(|name| {
+                let tensor = match name.as_str() {
+                    "pooler_output" => match &self.pooler {
+                        Some(pooler) => pooler
+                            .forward(&output.last_hidden_state.i((.., 0))?)?
+                            .tanh()?,
+                        None => unreachable!(),
+                    },
+                    "hidden_states" => Tensor::stack(&output.hidden_states, 0)?,
+                    "attentions" => Tensor::stack(&output.attentions, 0)?,
+                    _ => output.last_hidden_state.clone(),
+                };
+                Ok((name.clone(), tensor))
+            })
+            .collect()
     }
 }
 
 pub struct BertForSequenceClassification {
     bert: Box<BertModel>,
-    classifier: Box<dyn ClassificationHead + Send>,
+    classifier: Box<dyn ClassificationHead + Send + Sync>,
     #[allow(unused)]
     pub device: Device,
     span: tracing::Span,
@@ -598,7 +788,7 @@ pub struct BertForSequenceClassification {
 impl BertForSequenceClassification {
     pub fn load(vb: VarBuilder, config: &BertConfig) -> Result<Self> {
         let bert = Box::new(BertModel::load(vb.clone(), &config)?);
-        let classifier: Box<dyn ClassificationHead + Send> =
+        let classifier: Box<dyn ClassificationHead + Send + Sync> =
             Box::new(BertClassificationHead::load(vb.pp("classifier"), config)?);
         Ok(Self {
             bert,
@@ -610,12 +800,12 @@ impl BertForSequenceClassification {
 }
 
 impl Model for BertForSequenceClassification {
-    fn get_input_names(&self) -> Vec<String> {
-        return vec![
-            "input_ids".to_string(),
-            "attention_mask".to_string(),
-            "token_type_ids".to_string(),
-        ];
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(true)
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
     }
 
     fn forward(
@@ -633,3 +823,277 @@ impl Model for BertForSequenceClassification {
         Ok(logits)
     }
 }
+
+pub struct BertForTokenClassification {
+    bert: Box<BertModel>,
+    classifier: Linear,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl BertForTokenClassification {
+    pub fn load(vb: VarBuilder, config: &BertConfig) -> Result<Self> {
+        let n_classes = match &config.id2label {
+            None => candle::bail!("`id2label` must be set for classifier models"),
+            Some(id2label) => id2label.len(),
+        };
+        let bert = Box::new(BertModel::load(vb.clone(), config)?);
+        let classifier = Linear::load(vb.pp("classifier"), config.hidden_size, n_classes, None)?;
+        Ok(Self {
+            bert,
+            classifier,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for BertForTokenClassification {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(true)
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    /// Returns the per-token logits, `(batch, seq_len, num_labels)`.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let sequence_output = self
+            .bert
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        self.classifier.forward(&sequence_output)
+    }
+}
+
+pub struct BertForQuestionAnswering {
+    bert: Box<BertModel>,
+    qa_outputs: Linear,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl BertForQuestionAnswering {
+    pub fn load(vb: VarBuilder, config: &BertConfig) -> Result<Self> {
+        let bert = Box::new(BertModel::load(vb.clone(), config)?);
+        let qa_outputs = Linear::load(vb.pp("qa_outputs"), config.hidden_size, 2, None)?;
+        Ok(Self {
+            bert,
+            qa_outputs,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for BertForQuestionAnswering {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(true)
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        span_output_names()
+    }
+
+    /// Returns the span logits, `(batch, seq_len, 2)`, with the start logits at index 0 and the
+    /// end logits at index 1 of the last dimension.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let sequence_output = self
+            .bert
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        self.qa_outputs.forward(&sequence_output)
+    }
+
+    fn forward_outputs(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+        requested: &[String],
+    ) -> Result<ModelOutputs> {
+        let logits = self.forward(input_ids, attention_mask, token_type_ids)?;
+        span_outputs(logits, requested)
+    }
+}
+
+// https://github.com/huggingface/transformers/blob/6eedfa6dd15dc1e22a55ae036f681914e5a0d9a1/src/transformers/models/bert/modeling_bert.py#L685
+pub struct BertLMPredictionHead {
+    dense: Linear,
+    activation: HiddenActLayer,
+    layer_norm: LayerNorm,
+    decoder: Linear,
+    span: tracing::Span,
+}
+
+impl BertLMPredictionHead {
+    pub(crate) fn load(vb: VarBuilder, config: &BertConfig) -> Result<Self> {
+        let hidden_size = config.hidden_size;
+        let dense = Linear::load(
+            vb.pp("cls.predictions.transform.dense"),
+            hidden_size,
+            hidden_size,
+            None,
+        )?;
+        let layer_norm = LayerNorm::load(
+            vb.pp("cls.predictions.transform.LayerNorm"),
+            hidden_size,
+            config.layer_norm_eps as f32,
+        )?;
+        // The decoder is usually tied to the word embeddings and not serialized
+        let weight = vb
+            .get(
+                (config.vocab_size, hidden_size),
+                "cls.predictions.decoder.weight",
+            )
+            .or_else(|_| {
+                vb.get(
+                    (config.vocab_size, hidden_size),
+                    "bert.embeddings.word_embeddings.weight",
+                )
+            })
+            .or_else(|_| {
+                vb.get(
+                    (config.vocab_size, hidden_size),
+                    "embeddings.word_embeddings.weight",
+                )
+            })?;
+        let bias = get_optional(
+            &vb,
+            config.vocab_size,
+            &["cls.predictions.decoder.bias", "cls.predictions.bias"],
+        )?;
+        Ok(Self {
+            dense,
+            activation: HiddenActLayer::new(config.hidden_act),
+            layer_norm,
+            decoder: Linear::new(weight, bias, None),
+            span: tracing::span!(tracing::Level::TRACE, "lm-head"),
+        })
+    }
+
+    fn forward(&self, hidden_states: &Tensor) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let hidden_states = self.dense.forward(hidden_states)?;
+        let hidden_states = self.activation.forward(&hidden_states)?;
+        let hidden_states = self.layer_norm.forward(&hidden_states, None)?;
+        self.decoder.forward(&hidden_states)
+    }
+}
+
+pub struct BertForMaskedLM {
+    bert: Box<BertModel>,
+    lm_head: BertLMPredictionHead,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl BertForMaskedLM {
+    pub fn load(vb: VarBuilder, config: &BertConfig) -> Result<Self> {
+        let bert = Box::new(BertModel::load(vb.clone(), config)?);
+        let lm_head = BertLMPredictionHead::load(vb.clone(), config)?;
+        Ok(Self {
+            bert,
+            lm_head,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for BertForMaskedLM {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(true)
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    /// Returns the vocabulary logits of every token, `(batch, seq_len, vocab_size)`.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let sequence_output = self
+            .bert
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        self.lm_head.forward(&sequence_output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle::DType;
+
+    fn outputs(model: &dyn Model, requested: &[&str]) -> Result<ModelOutputs> {
+        let input_ids = Tensor::new(&[[1u32, 2, 3]], &Device::Cpu)?;
+        let mask = input_ids.ones_like()?;
+        let token_type_ids = input_ids.zeros_like()?;
+        let requested: Vec<String> = requested.iter().map(|r| r.to_string()).collect();
+        model.forward_outputs(&input_ids, &mask, Some(&token_type_ids), &requested)
+    }
+
+    #[test]
+    fn test_outputs() -> Result<()> {
+        let config = BertConfig {
+            vocab_size: 8,
+            hidden_size: 4,
+            num_hidden_layers: 2,
+            num_attention_heads: 2,
+            intermediate_size: 8,
+            ..Default::default()
+        };
+        let vb = VarBuilder::zeros(DType::F32, &Device::Cpu);
+        let model = BertModel::load(vb, &config)?;
+        assert_eq!(
+            model.get_output_names(),
+            [
+                "last_hidden_state",
+                "pooler_output",
+                "hidden_states",
+                "attentions"
+            ]
+        );
+
+        let default = outputs(&model, &[])?;
+        assert_eq!(default.len(), 1);
+        assert_eq!(default[0].0, "last_hidden_state");
+        assert_eq!(default[0].1.dims(), [1, 3, 4]);
+
+        let requested = outputs(&model, &["attentions", "hidden_states", "pooler_output"])?;
+        let names: Vec<&str> = requested.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["attentions", "hidden_states", "pooler_output"]);
+        assert_eq!(requested[0].1.dims(), [2, 1, 2, 3, 3]);
+        assert_eq!(requested[1].1.dims(), [3, 1, 3, 4]);
+        assert_eq!(requested[2].1.dims(), [1, 4]);
+
+        assert!(outputs(&model, &["logits"]).is_err());
+
+        // The word embeddings can be given instead of `input_ids`
+        let inputs_embeds = Tensor::ones((1, 3, 4), DType::F32, &Device::Cpu)?;
+        let inputs = vec![("inputs_embeds".to_string(), inputs_embeds)];
+        let inputs = ModelInputs::bind(&model.get_input_spec(), inputs)?;
+        let embedded = model.forward_inputs(&inputs, &[])?;
+        assert_eq!(embedded[0].1.dims(), [1, 3, 4]);
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
-use crate::layers::{LayerNorm, Linear};
+use crate::layers::{get_optional, LayerNorm, Linear};
+use crate::models::inputs::{token_inputs, InputSpec};
 use crate::models::Model;
-use candle::{Device, Result, Tensor};
+use candle::{Device, IndexOp, Result, Tensor};
 use candle_nn::{embedding, Embedding, Module, VarBuilder};
 use serde::Deserialize;
 use std::collections::HashMap;
@@ -469,6 +470,46 @@ impl Module for BertEncoder {
     }
 }
 
+pub trait ClassificationHead {
+    fn forward(&self, hidden_states: &Tensor) -> Result<Tensor>;
+}
+
+pub struct CamembertClassificationHead {
+    intermediate: Linear,
+    output: Linear,
+    span: tracing::Span,
+}
+
+impl CamembertClassificationHead {
+    pub(crate) fn load(vb: VarBuilder, config: &CamembertConfig) -> Result<Self> {
+        let n_classes = match &config.id2label {
+            None => candle::bail!("`id2label` must be set for classifier models"),
+            Some(id2label) => id2label.len(),
+        };
+        let intermediate =
+            Linear::load(vb.pp("dense"), config.hidden_size, config.hidden_size, None)?;
+        let output = Linear::load(vb.pp("out_proj"), config.hidden_size, n_classes, None)?;
+        Ok(Self {
+            intermediate,
+            output,
+            span: tracing::span!(tracing::Level::TRACE, "classifier"),
+        })
+    }
+}
+
+impl ClassificationHead for CamembertClassificationHead {
+    fn forward(&self, hidden_states: &Tensor) -> Result<Tensor> {
+        let _enter = self.span.enter();
+
+        let hidden_states = hidden_states.unsqueeze(1)?;
+        let hidden_states = self.intermediate.forward(&hidden_states)?;
+        let hidden_states = hidden_states.tanh()?;
+        let hidden_states = self.output.forward(&hidden_states)?;
+        let hidden_states = hidden_states.squeeze(1)?;
+        Ok(hidden_states)
+    }
+}
+
 pub struct CamembertModel {
     embeddings: BertEmbeddings,
     encoder: BertEncoder,
@@ -510,12 +551,8 @@ impl CamembertModel {
 }
 
 impl Model for CamembertModel {
-    fn get_input_names(&self) -> Vec<String> {
-        return vec![
-            "input_ids".to_string(),
-            "attention_mask".to_string(),
-            "token_type_ids".to_string(),
-        ];
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(true)
     }
 
     fn forward(
@@ -532,3 +569,150 @@ impl Model for CamembertModel {
         Ok(sequence_output)
     }
 }
+
+pub struct CamembertForSequenceClassification {
+    roberta: Box<CamembertModel>,
+    classifier: Box<dyn ClassificationHead + Send + Sync>,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl CamembertForSequenceClassification {
+    pub fn load(vb: VarBuilder, config: &CamembertConfig) -> Result<Self> {
+        let roberta = Box::new(CamembertModel::load(vb.clone(), config)?);
+        let classifier: Box<dyn ClassificationHead + Send + Sync> = Box::new(
+            CamembertClassificationHead::load(vb.pp("classifier"), config)?,
+        );
+        Ok(Self {
+            roberta,
+            classifier,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for CamembertForSequenceClassification {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        self.roberta.get_input_spec()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let embeddings = self
+            .roberta
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        let sequence_output = embeddings.i((.., 0))?;
+        let logits = self.classifier.forward(&sequence_output)?;
+        Ok(logits)
+    }
+}
+
+// https://github.com/huggingface/transformers/blob/6eedfa6dd15dc1e22a55ae036f681914e5a0d9a1/src/transformers/models/roberta/modeling_roberta.py#L1122
+pub struct CamembertLMHead {
+    dense: Linear,
+    layer_norm: LayerNorm,
+    decoder: Linear,
+    span: tracing::Span,
+}
+
+impl CamembertLMHead {
+    pub(crate) fn load(vb: VarBuilder, config: &CamembertConfig) -> Result<Self> {
+        let hidden_size = config.hidden_size;
+        let dense = Linear::load(vb.pp("lm_head.dense"), hidden_size, hidden_size, None)?;
+        let layer_norm = LayerNorm::load(
+            vb.pp("lm_head.layer_norm"),
+            hidden_size,
+            config.layer_norm_eps as f32,
+        )?;
+        // The decoder is usually tied to the word embeddings and not serialized
+        let weight = vb
+            .get((config.vocab_size, hidden_size), "lm_head.decoder.weight")
+            .or_else(|_| {
+                vb.get(
+                    (config.vocab_size, hidden_size),
+                    "roberta.embeddings.word_embeddings.weight",
+                )
+            })
+            .or_else(|_| {
+                vb.get(
+                    (config.vocab_size, hidden_size),
+                    "embeddings.word_embeddings.weight",
+                )
+            })?;
+        let bias = get_optional(
+            &vb,
+            config.vocab_size,
+            &["lm_head.decoder.bias", "lm_head.bias"],
+        )?;
+        Ok(Self {
+            dense,
+            layer_norm,
+            decoder: Linear::new(weight, bias, None),
+            span: tracing::span!(tracing::Level::TRACE, "lm-head"),
+        })
+    }
+
+    fn forward(&self, hidden_states: &Tensor) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let hidden_states = self.dense.forward(hidden_states)?;
+        let hidden_states = hidden_states.gelu_erf()?;
+        let hidden_states = self.layer_norm.forward(&hidden_states, None)?;
+        self.decoder.forward(&hidden_states)
+    }
+}
+
+pub struct CamembertForMaskedLM {
+    roberta: Box<CamembertModel>,
+    lm_head: CamembertLMHead,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl CamembertForMaskedLM {
+    pub fn load(vb: VarBuilder, config: &CamembertConfig) -> Result<Self> {
+        let roberta = Box::new(CamembertModel::load(vb.clone(), config)?);
+        let lm_head = CamembertLMHead::load(vb.clone(), config)?;
+        Ok(Self {
+            roberta,
+            lm_head,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for CamembertForMaskedLM {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        self.roberta.get_input_spec()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    /// Returns the vocabulary logits of every token, `(batch, seq_len, vocab_size)`.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let sequence_output = self
+            .roberta
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        self.lm_head.forward(&sequence_output)
+    }
+}
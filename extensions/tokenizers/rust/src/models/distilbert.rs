@@ -1,9 +1,12 @@
-use candle::{DType, Device, Result, Tensor};
+use candle::{DType, Device, IndexOp, Result, Tensor};
 use candle_nn::{Embedding, Module, VarBuilder};
-use candle_transformers::models::with_tracing::{layer_norm, linear, LayerNorm, Linear};
+use candle_transformers::models::with_tracing::{layer_norm, LayerNorm};
 use serde::Deserialize;
+use std::collections::HashMap;
 
-use crate::models::Model;
+use crate::layers::{get_optional, Linear};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::{span_output_names, span_outputs, Model, ModelOutputs};
 
 fn masked_fill(on_false: &Tensor, mask: &Tensor, on_true: f32) -> Result<Tensor> {
     let shape = mask.shape();
@@ -51,6 +54,8 @@ enum PositionEmbeddingType {
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct DistilBertConfig {
+    #[serde(default)]
+    pub architectures: Vec<String>,
     vocab_size: usize,
     dim: usize,
     n_layers: usize,
@@ -66,11 +71,13 @@ pub struct DistilBertConfig {
     use_cache: bool,
     pub use_flash_attn: Option<bool>,
     model_type: Option<String>,
+    pub id2label: Option<HashMap<String, String>>,
 }
 
 impl Default for DistilBertConfig {
     fn default() -> Self {
         Self {
+            architectures: Vec::new(),
             vocab_size: 30522,
             dim: 768,
             n_layers: 12,
@@ -84,6 +91,7 @@ impl Default for DistilBertConfig {
             use_cache: true,
             use_flash_attn: Some(false),
             model_type: Some("distilbert".to_string()),
+            id2label: None,
         }
     }
 }
@@ -159,10 +167,10 @@ impl MultiHeadSelfAttention {
         let attention_head_size = config.dim / config.n_heads;
         let all_head_size = config.n_heads * attention_head_size;
         let dim = config.dim;
-        let q_lin = linear(dim, all_head_size, vb.pp("q_lin"))?;
-        let v_lin = linear(dim, all_head_size, vb.pp("v_lin"))?;
-        let k_lin = linear(dim, all_head_size, vb.pp("k_lin"))?;
-        let out_lin = linear(all_head_size, dim, vb.pp("out_lin"))?;
+        let q_lin = Linear::load(vb.pp("q_lin"), dim, all_head_size, None)?;
+        let v_lin = Linear::load(vb.pp("v_lin"), dim, all_head_size, None)?;
+        let k_lin = Linear::load(vb.pp("k_lin"), dim, all_head_size, None)?;
+        let out_lin = Linear::load(vb.pp("out_lin"), all_head_size, dim, None)?;
         Ok(Self {
             q_lin,
             k_lin,
@@ -236,8 +244,8 @@ struct FFN {
 
 impl FFN {
     fn load(vb: VarBuilder, config: &DistilBertConfig) -> Result<Self> {
-        let lin1 = linear(config.dim, config.hidden_dim, vb.pp("lin1"))?;
-        let lin2 = linear(config.hidden_dim, config.dim, vb.pp("lin2"))?;
+        let lin1 = Linear::load(vb.pp("lin1"), config.dim, config.hidden_dim, None)?;
+        let lin2 = Linear::load(vb.pp("lin2"), config.hidden_dim, config.dim, None)?;
         Ok(Self {
             lin1,
             lin2,
@@ -250,10 +258,8 @@ impl FFN {
 impl Module for FFN {
     fn forward(&self, hidden_states: &Tensor) -> Result<Tensor> {
         let _enter = self.span.enter();
-        hidden_states
-            .apply(&self.lin1)?
-            .apply(&self.activation)?
-            .apply(&self.lin2)
+        let hidden_states = self.lin1.forward(hidden_states)?.apply(&self.activation)?;
+        self.lin2.forward(&hidden_states)
     }
 }
 
@@ -362,8 +368,8 @@ impl DistilBertModel {
 }
 
 impl Model for DistilBertModel {
-    fn get_input_names(&self) -> Vec<String> {
-        return vec!["input_ids".to_string(), "attention_mask".to_string()];
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(false)
     }
 
     fn forward(
@@ -380,3 +386,234 @@ impl Model for DistilBertModel {
         Ok(sequence_output)
     }
 }
+
+// https://github.com/huggingface/transformers/blob/6eedfa6dd15dc1e22a55ae036f681914e5a0d9a1/src/transformers/models/distilbert/modeling_distilbert.py#L742
+pub struct DistilBertForSequenceClassification {
+    distilbert: Box<DistilBertModel>,
+    pre_classifier: Linear,
+    classifier: Linear,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl DistilBertForSequenceClassification {
+    pub fn load(vb: VarBuilder, config: &DistilBertConfig) -> Result<Self> {
+        let n_classes = match &config.id2label {
+            None => candle::bail!("`id2label` must be set for classifier models"),
+            Some(id2label) => id2label.len(),
+        };
+        let distilbert = Box::new(DistilBertModel::load(vb.clone(), config)?);
+        let pre_classifier = Linear::load(vb.pp("pre_classifier"), config.dim, config.dim, None)?;
+        let classifier = Linear::load(vb.pp("classifier"), config.dim, n_classes, None)?;
+        Ok(Self {
+            distilbert,
+            pre_classifier,
+            classifier,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for DistilBertForSequenceClassification {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        self.distilbert.get_input_spec()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let hidden_states = self
+            .distilbert
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        let pooled_output = hidden_states.i((.., 0))?;
+        let pooled_output = self.pre_classifier.forward(&pooled_output)?.relu()?;
+        self.classifier.forward(&pooled_output)
+    }
+}
+
+pub struct DistilBertForTokenClassification {
+    distilbert: Box<DistilBertModel>,
+    classifier: Linear,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl DistilBertForTokenClassification {
+    pub fn load(vb: VarBuilder, config: &DistilBertConfig) -> Result<Self> {
+        let n_classes = match &config.id2label {
+            None => candle::bail!("`id2label` must be set for classifier models"),
+            Some(id2label) => id2label.len(),
+        };
+        let distilbert = Box::new(DistilBertModel::load(vb.clone(), config)?);
+        let classifier = Linear::load(vb.pp("classifier"), config.dim, n_classes, None)?;
+        Ok(Self {
+            distilbert,
+            classifier,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for DistilBertForTokenClassification {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        self.distilbert.get_input_spec()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    /// Returns the per-token logits, `(batch, seq_len, num_labels)`.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let sequence_output = self
+            .distilbert
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        self.classifier.forward(&sequence_output)
+    }
+}
+
+pub struct DistilBertForQuestionAnswering {
+    distilbert: Box<DistilBertModel>,
+    qa_outputs: Linear,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl DistilBertForQuestionAnswering {
+    pub fn load(vb: VarBuilder, config: &DistilBertConfig) -> Result<Self> {
+        let distilbert = Box::new(DistilBertModel::load(vb.clone(), config)?);
+        let qa_outputs = Linear::load(vb.pp("qa_outputs"), config.dim, 2, None)?;
+        Ok(Self {
+            distilbert,
+            qa_outputs,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for DistilBertForQuestionAnswering {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        self.distilbert.get_input_spec()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        span_output_names()
+    }
+
+    /// Returns the span logits, `(batch, seq_len, 2)`, with the start logits at index 0 and the
+    /// end logits at index 1 of the last dimension.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let sequence_output = self
+            .distilbert
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        self.qa_outputs.forward(&sequence_output)
+    }
+
+    fn forward_outputs(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+        requested: &[String],
+    ) -> Result<ModelOutputs> {
+        let logits = self.forward(input_ids, attention_mask, token_type_ids)?;
+        span_outputs(logits, requested)
+    }
+}
+
+pub struct DistilBertForMaskedLM {
+    distilbert: Box<DistilBertModel>,
+    vocab_transform: Linear,
+    activation: HiddenActLayer,
+    vocab_layer_norm: LayerNorm,
+    vocab_projector: Linear,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl DistilBertForMaskedLM {
+    pub fn load(vb: VarBuilder, config: &DistilBertConfig) -> Result<Self> {
+        let distilbert = Box::new(DistilBertModel::load(vb.clone(), config)?);
+        let vocab_transform = Linear::load(vb.pp("vocab_transform"), config.dim, config.dim, None)?;
+        let vocab_layer_norm = layer_norm(config.dim, 1e-12, vb.pp("vocab_layer_norm"))?;
+        // The projector is usually tied to the word embeddings and not serialized
+        let weight = vb
+            .get((config.vocab_size, config.dim), "vocab_projector.weight")
+            .or_else(|_| {
+                vb.get(
+                    (config.vocab_size, config.dim),
+                    "distilbert.embeddings.word_embeddings.weight",
+                )
+            })
+            .or_else(|_| {
+                vb.get(
+                    (config.vocab_size, config.dim),
+                    "embeddings.word_embeddings.weight",
+                )
+            })?;
+        let bias = get_optional(&vb, config.vocab_size, &["vocab_projector.bias"])?;
+        Ok(Self {
+            distilbert,
+            vocab_transform,
+            activation: HiddenActLayer::new(config.activation),
+            vocab_layer_norm,
+            vocab_projector: Linear::new(weight, bias, None),
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for DistilBertForMaskedLM {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        self.distilbert.get_input_spec()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    /// Returns the vocabulary logits of every token, `(batch, seq_len, vocab_size)`.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let sequence_output = self
+            .distilbert
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        let hidden_states = self.vocab_transform.forward(&sequence_output)?;
+        let hidden_states = self.activation.forward(&hidden_states)?;
+        let hidden_states = self.vocab_layer_norm.forward(&hidden_states)?;
+        self.vocab_projector.forward(&hidden_states)
+    }
+}
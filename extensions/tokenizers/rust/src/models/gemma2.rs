@@ -1,6 +1,9 @@
-use crate::models::Model;
+use crate::layers::{append_and_gather, Linear, SequenceCache};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::{last_tokens, CausalLM, Model};
+use crate::utils::rope_with_offsets;
 use candle::{DType, Device, Module, Result, Tensor, D};
-use candle_nn::{linear_b as linear, Activation, Linear, VarBuilder};
+use candle_nn::{Activation, VarBuilder};
 use serde::Deserialize;
 use std::sync::Arc;
 
@@ -10,6 +13,8 @@ pub struct Gemma2Config {
     pub architectures: Vec<String>,
     #[allow(unused)]
     model_type: Option<String>,
+    // `Linear::load` reads the biases when the checkpoint has them
+    #[allow(unused)]
     pub attention_bias: bool,
     pub head_dim: usize,
     // The code gemma configs include both hidden_act and hidden_activation.
@@ -101,9 +106,20 @@ impl RotaryEmbedding {
         let k_embed = candle_nn::rotary_emb::rope(&k.contiguous()?, &cos, &sin)?;
         Ok((q_embed, k_embed))
     }
+
+    fn apply_rotary_emb_with_offsets(
+        &self,
+        q: &Tensor,
+        k: &Tensor,
+        offsets: &[usize],
+    ) -> Result<(Tensor, Tensor)> {
+        let q_embed = rope_with_offsets(q, &self.cos, &self.sin, offsets)?;
+        let k_embed = rope_with_offsets(k, &self.cos, &self.sin, offsets)?;
+        Ok((q_embed, k_embed))
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 #[allow(clippy::upper_case_acronyms)]
 struct MLP {
     gate_proj: Linear,
@@ -116,9 +132,9 @@ impl MLP {
     fn load(vb: VarBuilder, config: &Gemma2Config) -> Result<Self> {
         let hidden_sz = config.hidden_size;
         let intermediate_sz = config.intermediate_size;
-        let gate_proj = linear(hidden_sz, intermediate_sz, false, vb.pp("gate_proj"))?;
-        let up_proj = linear(hidden_sz, intermediate_sz, false, vb.pp("up_proj"))?;
-        let down_proj = linear(intermediate_sz, hidden_sz, false, vb.pp("down_proj"))?;
+        let gate_proj = Linear::load(vb.pp("gate_proj"), hidden_sz, intermediate_sz, None)?;
+        let up_proj = Linear::load(vb.pp("up_proj"), hidden_sz, intermediate_sz, None)?;
+        let down_proj = Linear::load(vb.pp("down_proj"), intermediate_sz, hidden_sz, None)?;
         Ok(Self {
             gate_proj,
             up_proj,
@@ -130,13 +146,13 @@ impl MLP {
 
 impl Module for MLP {
     fn forward(&self, xs: &Tensor) -> Result<Tensor> {
-        let lhs = xs.apply(&self.gate_proj)?.apply(&self.act_fn)?;
-        let rhs = xs.apply(&self.up_proj)?;
-        (lhs * rhs)?.apply(&self.down_proj)
+        let lhs = self.gate_proj.forward(xs)?.apply(&self.act_fn)?;
+        let rhs = self.up_proj.forward(xs)?;
+        self.down_proj.forward(&(lhs * rhs)?)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct Attention {
     q_proj: Linear,
     k_proj: Linear,
@@ -161,11 +177,10 @@ impl Attention {
         let num_kv_heads = config.num_key_value_heads;
         let num_kv_groups = num_heads / num_kv_heads;
         let head_dim = config.head_dim;
-        let bias = config.attention_bias;
-        let q_proj = linear(hidden_sz, num_heads * head_dim, bias, vb.pp("q_proj"))?;
-        let k_proj = linear(hidden_sz, num_kv_heads * head_dim, bias, vb.pp("k_proj"))?;
-        let v_proj = linear(hidden_sz, num_kv_heads * head_dim, bias, vb.pp("v_proj"))?;
-        let o_proj = linear(num_heads * head_dim, hidden_sz, bias, vb.pp("o_proj"))?;
+        let q_proj = Linear::load(vb.pp("q_proj"), hidden_sz, num_heads * head_dim, None)?;
+        let k_proj = Linear::load(vb.pp("k_proj"), hidden_sz, num_kv_heads * head_dim, None)?;
+        let v_proj = Linear::load(vb.pp("v_proj"), hidden_sz, num_kv_heads * head_dim, None)?;
+        let o_proj = Linear::load(vb.pp("o_proj"), num_heads * head_dim, hidden_sz, None)?;
         Ok(Self {
             q_proj,
             k_proj,
@@ -223,10 +238,52 @@ impl Attention {
             let attn_weights = candle_nn::ops::softmax_last_dim(&attn_weights)?;
             attn_weights.matmul(&value_states)?
         };
-        attn_output
+        let attn_output = attn_output.transpose(1, 2)?.reshape((b_sz, q_len, ()))?;
+        self.o_proj.forward(&attn_output)
+    }
+
+    fn forward_step(
+        &self,
+        xs: &Tensor,
+        caches: &mut [&mut SequenceCache],
+        layer_idx: usize,
+        offsets: &[usize],
+    ) -> Result<Tensor> {
+        let (b_sz, q_len, _) = xs.dims3()?;
+
+        let query_states = self.q_proj.forward(xs)?;
+        let key_states = self.k_proj.forward(xs)?;
+        let value_states = self.v_proj.forward(xs)?;
+
+        let query_states = query_states
+            .reshape((b_sz, q_len, self.num_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let key_states = key_states
+            .reshape((b_sz, q_len, self.num_kv_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let value_states = value_states
+            .reshape((b_sz, q_len, self.num_kv_heads, self.head_dim))?
+            .transpose(1, 2)?;
+
+        let (query_states, key_states) =
+            self.rotary_emb
+                .apply_rotary_emb_with_offsets(&query_states, &key_states, offsets)?;
+        let (key_states, value_states, attention_mask) =
+            append_and_gather(caches, layer_idx, &key_states, &value_states, offsets, None)?;
+
+        let key_states = crate::utils::repeat_kv(key_states, self.num_kv_groups)?.contiguous()?;
+        let value_states =
+            crate::utils::repeat_kv(value_states, self.num_kv_groups)?.contiguous()?;
+
+        let scale = 1f64 / f64::sqrt(self.head_dim as f64);
+        let attn_weights = (query_states.matmul(&key_states.transpose(2, 3)?)? * scale)?;
+        let attn_weights = attn_weights.broadcast_add(&attention_mask)?;
+        let attn_weights = candle_nn::ops::softmax_last_dim(&attn_weights)?;
+        let attn_output = attn_weights
+            .matmul(&value_states)?
             .transpose(1, 2)?
-            .reshape((b_sz, q_len, ()))?
-            .apply(&self.o_proj)
+            .reshape((b_sz, q_len, ()))?;
+        self.o_proj.forward(&attn_output)
     }
 }
 
@@ -246,7 +303,7 @@ fn flash_attn(_: &Tensor, _: &Tensor, _: &Tensor, _: f32, _: bool) -> Result<Ten
     unimplemented!("compile with '--features flash-attn'")
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct DecoderLayer {
     self_attn: Attention,
     mlp: MLP,
@@ -289,6 +346,24 @@ impl DecoderLayer {
         let xs = xs.apply(&self.post_attention_layernorm)?.apply(&self.mlp)?;
         residual + xs
     }
+
+    fn forward_step(
+        &self,
+        xs: &Tensor,
+        caches: &mut [&mut SequenceCache],
+        layer_idx: usize,
+        offsets: &[usize],
+    ) -> Result<Tensor> {
+        let residual = xs;
+        let xs = self.input_layernorm.forward(xs)?;
+        let xs = self
+            .self_attn
+            .forward_step(&xs, caches, layer_idx, offsets)?;
+        let xs = (xs + residual)?;
+        let residual = &xs;
+        let xs = xs.apply(&self.post_attention_layernorm)?.apply(&self.mlp)?;
+        residual + xs
+    }
 }
 
 #[derive(Debug)]
@@ -304,6 +379,12 @@ pub struct Gemma2Model {
 
 impl Gemma2Model {
     pub fn load(vb: VarBuilder, config: &Gemma2Config) -> Result<Self> {
+        // `Gemma2ForCausalLM` checkpoints nest the decoder under `model`
+        let vb = if vb.contains_tensor("model.embed_tokens.weight") {
+            vb.pp("model")
+        } else {
+            vb
+        };
         let embed_tokens =
             candle_nn::embedding(config.vocab_size, config.hidden_size, vb.pp("embed_tokens"))?;
         let rotary_emb = Arc::new(RotaryEmbedding::new(config, vb.dtype(), vb.device())?);
@@ -314,7 +395,7 @@ impl Gemma2Model {
             layers.push(layer)
         }
         let norm = RmsNorm::load(vb.pp("norm"), config.hidden_size, config.rms_norm_eps)?;
-        let lm_head = Linear::new(embed_tokens.embeddings().clone(), None);
+        let lm_head = Linear::new(embed_tokens.embeddings().clone(), None, None);
         Ok(Self {
             embed_tokens,
             layers,
@@ -337,8 +418,12 @@ impl Gemma2Model {
 }
 
 impl Model for Gemma2Model {
-    fn get_input_names(&self) -> Vec<String> {
-        return vec!["input_ids".to_string(), "attention_mask".to_string()];
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(false)
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
     }
 
     fn forward(
@@ -359,8 +444,37 @@ impl Model for Gemma2Model {
         for layer in self.layers.iter() {
             xs = layer.forward(&xs, attention_mask.as_ref())?
         }
-        xs.narrow(1, seq_len - 1, 1)?
-            .apply(&self.norm)?
-            .apply(&self.lm_head)
+        let xs = xs.narrow(1, seq_len - 1, 1)?.apply(&self.norm)?;
+        self.lm_head.forward(&xs)
+    }
+
+    fn as_causal_lm(&self) -> Option<&dyn CausalLM> {
+        Some(self)
+    }
+}
+
+impl CausalLM for Gemma2Model {
+    fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn device(&self) -> &Device {
+        &self.device
+    }
+
+    fn forward_step(
+        &self,
+        input_ids: &Tensor,
+        seq_lens: Option<&[usize]>,
+        caches: &mut [&mut SequenceCache],
+    ) -> Result<Tensor> {
+        let offsets = caches.iter().map(|c| c.seq_len()).collect::<Vec<_>>();
+        let xs = self.embed_tokens.forward(input_ids)?;
+        let mut xs = (xs * (self.hidden_size as f64).sqrt())?;
+        for (layer_idx, layer) in self.layers.iter().enumerate() {
+            xs = layer.forward_step(&xs, caches, layer_idx, &offsets)?
+        }
+        let xs = last_tokens(&xs, seq_lens, caches, &offsets)?.apply(&self.norm)?;
+        self.lm_head.forward(&xs)?.squeeze(1)
     }
 }
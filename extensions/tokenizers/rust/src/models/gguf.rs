@@ -0,0 +1,626 @@
+use crate::layers::{append_and_gather, Linear, RmsNorm, SequenceCache};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::{gather_last, last_tokens, mask_lens, CausalLM, Model};
+use crate::utils::repeat_kv;
+use candle::quantized::{gguf_file, QTensor};
+use candle::{DType, Device, Module, Result, Tensor};
+use candle_nn::{ops, rotary_emb, Embedding};
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A GGUF file, with tensors read on demand.
+struct GgufFile {
+    content: gguf_file::Content,
+    reader: File,
+    device: Device,
+    dtype: DType,
+}
+
+impl GgufFile {
+    fn open(path: &Path, dtype: DType, device: &Device) -> Result<Self> {
+        let mut reader = File::open(path)?;
+        let content = gguf_file::Content::read(&mut reader).map_err(|e| e.with_path(path))?;
+        Ok(Self {
+            content,
+            reader,
+            device: device.clone(),
+            dtype,
+        })
+    }
+
+    fn metadata(&self, key: &str) -> Result<&gguf_file::Value> {
+        match self.content.metadata.get(key) {
+            Some(value) => Ok(value),
+            None => candle::bail!("Missing GGUF metadata `{key}`"),
+        }
+    }
+
+    fn contains_tensor(&self, name: &str) -> bool {
+        self.content.tensor_infos.contains_key(name)
+    }
+
+    fn qtensor(&mut self, name: &str) -> Result<Arc<QTensor>> {
+        let tensor = self.content.tensor(&mut self.reader, name, &self.device)?;
+        Ok(Arc::new(tensor))
+    }
+
+    /// Reads and dequantizes a tensor, for the weights that are not multiplied with.
+    fn tensor(&mut self, name: &str) -> Result<Tensor> {
+        self.qtensor(name)?
+            .dequantize(&self.device)?
+            .to_dtype(self.dtype)
+    }
+
+    fn linear(&mut self, prefix: &str) -> Result<Linear> {
+        let weight = self.qtensor(&format!("{prefix}.weight"))?;
+        let bias = format!("{prefix}.bias");
+        let bias = if self.contains_tensor(&bias) {
+            Some(self.tensor(&bias)?)
+        } else {
+            None
+        };
+        Linear::from_qtensor(weight, bias, None)
+    }
+
+    fn rms_norm(&mut self, name: &str, eps: f64) -> Result<RmsNorm> {
+        Ok(RmsNorm::new(self.tensor(name)?, eps))
+    }
+}
+
+/// Hyper parameters read from the `{architecture}.*` GGUF metadata.
+#[derive(Debug, Clone)]
+pub struct GgufConfig {
+    pub architecture: String,
+    pub vocab_size: usize,
+    pub hidden_size: usize,
+    pub intermediate_size: usize,
+    pub num_hidden_layers: usize,
+    pub num_attention_heads: usize,
+    pub num_key_value_heads: usize,
+    pub head_dim: usize,
+    pub max_position_embeddings: usize,
+    pub rms_norm_eps: f64,
+    pub rope_theta: f32,
+}
+
+impl GgufConfig {
+    fn read(gguf: &GgufFile) -> Result<Self> {
+        let architecture = gguf.metadata("general.architecture")?.to_string()?.clone();
+        if architecture != "llama" && architecture != "qwen2" {
+            candle::bail!("Unsupported GGUF architecture: {architecture}");
+        }
+        let get = |key: &str| gguf.metadata(&format!("{architecture}.{key}"));
+        let get_usize = |key: &str| get(key).and_then(|v| v.to_u32()).map(|v| v as usize);
+
+        let vocab_size = match gguf.content.tensor_infos.get("token_embd.weight") {
+            Some(info) => info.shape.dims2()?.0,
+            None => candle::bail!("Missing GGUF tensor `token_embd.weight`"),
+        };
+        let hidden_size = get_usize("embedding_length")?;
+        let num_attention_heads = get_usize("attention.head_count")?;
+        Ok(Self {
+            vocab_size,
+            hidden_size,
+            intermediate_size: get_usize("feed_forward_length")?,
+            num_hidden_layers: get_usize("block_count")?,
+            num_attention_heads,
+            num_key_value_heads: get_usize("attention.head_count_kv")
+                .unwrap_or(num_attention_heads),
+            head_dim: get_usize("rope.dimension_count")
+                .unwrap_or(hidden_size / num_attention_heads),
+            max_position_embeddings: get_usize("context_length")?,
+            rms_norm_eps: get("attention.layer_norm_rms_epsilon")?.to_f32()? as f64,
+            rope_theta: get("rope.freq_base")
+                .and_then(|v| v.to_f32())
+                .unwrap_or(10_000.),
+            architecture,
+        })
+    }
+
+    /// The `config.json` equivalent of the GGUF metadata.
+    pub fn to_json(&self) -> String {
+        let architecture = match self.architecture.as_str() {
+            "qwen2" => "Qwen2ForCausalLM",
+            _ => "LlamaForCausalLM",
+        };
+        serde_json::json!({
+            "model_type": self.architecture,
+            "architectures": [architecture],
+            "vocab_size": self.vocab_size,
+            "hidden_size": self.hidden_size,
+            "intermediate_size": self.intermediate_size,
+            "num_hidden_layers": self.num_hidden_layers,
+            "num_attention_heads": self.num_attention_heads,
+            "num_key_value_heads": self.num_key_value_heads,
+            "max_position_embeddings": self.max_position_embeddings,
+        })
+        .to_string()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RotaryEmbedding {
+    sin: Tensor,
+    cos: Tensor,
+    // llama.cpp permutes the llama q/k weights to rotate adjacent pairs
+    interleaved: bool,
+}
+
+impl RotaryEmbedding {
+    fn new(gguf: &mut GgufFile, config: &GgufConfig) -> Result<Self> {
+        let dim = config.head_dim;
+        let max_seq_len = config.max_position_embeddings;
+        let inv_freq: Vec<_> = (0..dim)
+            .step_by(2)
+            .map(|i| 1f32 / config.rope_theta.powf(i as f32 / dim as f32))
+            .collect();
+        let inv_freq_len = inv_freq.len();
+        let mut inv_freq = Tensor::from_vec(inv_freq, (1, inv_freq_len), &gguf.device)?;
+        // Llama 3.1 stores its frequency scaling factors as a tensor
+        if gguf.contains_tensor("rope_freqs.weight") {
+            let factors = gguf
+                .qtensor("rope_freqs.weight")?
+                .dequantize(&gguf.device)?
+                .reshape((1, inv_freq_len))?;
+            inv_freq = (inv_freq / factors)?;
+        }
+        let t = Tensor::arange(0u32, max_seq_len as u32, &gguf.device)?
+            .to_dtype(DType::F32)?
+            .reshape((max_seq_len, 1))?;
+        let freqs = t.matmul(&inv_freq)?;
+        Ok(Self {
+            sin: freqs.sin()?.to_dtype(gguf.dtype)?,
+            cos: freqs.cos()?.to_dtype(gguf.dtype)?,
+            interleaved: config.architecture == "llama",
+        })
+    }
+
+    /// Rotates `(batch, heads, seq_len, head_dim)` queries or keys, row `i` of the batch starts
+    /// at position `offsets[i]`.
+    fn apply(&self, xs: &Tensor, offsets: &[usize]) -> Result<Tensor> {
+        let (_b_sz, _h, seq_len, _n_embd) = xs.dims4()?;
+        let rows = offsets
+            .iter()
+            .enumerate()
+            .map(|(i, &offset)| {
+                let cos = self.cos.narrow(0, offset, seq_len)?;
+                let sin = self.sin.narrow(0, offset, seq_len)?;
+                let xs = xs.narrow(0, i, 1)?.contiguous()?;
+                if self.interleaved {
+                    rotary_emb::rope_i(&xs, &cos, &sin)
+                } else {
+                    rotary_emb::rope(&xs, &cos, &sin)
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Tensor::cat(&rows, 0)
+    }
+}
+
+#[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
+struct MLP {
+    gate_proj: Linear,
+    up_proj: Linear,
+    down_proj: Linear,
+}
+
+impl MLP {
+    fn load(gguf: &mut GgufFile, prefix: &str) -> Result<Self> {
+        Ok(Self {
+            gate_proj: gguf.linear(&format!("{prefix}.ffn_gate"))?,
+            up_proj: gguf.linear(&format!("{prefix}.ffn_up"))?,
+            down_proj: gguf.linear(&format!("{prefix}.ffn_down"))?,
+        })
+    }
+}
+
+impl Module for MLP {
+    fn forward(&self, xs: &Tensor) -> Result<Tensor> {
+        let lhs = self.gate_proj.forward(xs)?.silu()?;
+        let rhs = self.up_proj.forward(xs)?;
+        self.down_proj.forward(&(lhs * rhs)?)
+    }
+}
+
+#[derive(Debug)]
+struct Attention {
+    q_proj: Linear,
+    k_proj: Linear,
+    v_proj: Linear,
+    o_proj: Linear,
+    num_heads: usize,
+    num_kv_heads: usize,
+    num_kv_groups: usize,
+    head_dim: usize,
+    rotary_emb: Arc<RotaryEmbedding>,
+}
+
+impl Attention {
+    fn load(
+        gguf: &mut GgufFile,
+        prefix: &str,
+        config: &GgufConfig,
+        rotary_emb: Arc<RotaryEmbedding>,
+    ) -> Result<Self> {
+        Ok(Self {
+            q_proj: gguf.linear(&format!("{prefix}.attn_q"))?,
+            k_proj: gguf.linear(&format!("{prefix}.attn_k"))?,
+            v_proj: gguf.linear(&format!("{prefix}.attn_v"))?,
+            o_proj: gguf.linear(&format!("{prefix}.attn_output"))?,
+            num_heads: config.num_attention_heads,
+            num_kv_heads: config.num_key_value_heads,
+            num_kv_groups: config.num_attention_heads / config.num_key_value_heads,
+            head_dim: config.head_dim,
+            rotary_emb,
+        })
+    }
+
+    fn project(&self, xs: &Tensor, offsets: &[usize]) -> Result<(Tensor, Tensor, Tensor)> {
+        let (b_sz, q_len, _) = xs.dims3()?;
+        let query_states = self
+            .q_proj
+            .forward(xs)?
+            .reshape((b_sz, q_len, self.num_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let key_states = self
+            .k_proj
+            .forward(xs)?
+            .reshape((b_sz, q_len, self.num_kv_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let value_states = self
+            .v_proj
+            .forward(xs)?
+            .reshape((b_sz, q_len, self.num_kv_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let query_states = self.rotary_emb.apply(&query_states, offsets)?;
+        let key_states = self.rotary_emb.apply(&key_states, offsets)?;
+        Ok((query_states, key_states, value_states))
+    }
+
+    fn attend(
+        &self,
+        query_states: &Tensor,
+        key_states: Tensor,
+        value_states: Tensor,
+        attention_mask: &Tensor,
+    ) -> Result<Tensor> {
+        let (b_sz, _, q_len, _) = query_states.dims4()?;
+        let key_states = repeat_kv(key_states, self.num_kv_groups)?.contiguous()?;
+        let value_states = repeat_kv(value_states, self.num_kv_groups)?.contiguous()?;
+
+        let scale = 1f64 / f64::sqrt(self.head_dim as f64);
+        let attn_weights = (query_states.matmul(&key_states.transpose(2, 3)?)? * scale)?;
+        let attn_weights = attn_weights.broadcast_add(attention_mask)?;
+        let attn_weights = ops::softmax_last_dim(&attn_weights)?;
+        let attn_output = attn_weights
+            .matmul(&value_states)?
+            .transpose(1, 2)?
+            .reshape((b_sz, q_len, self.num_heads * self.head_dim))?;
+        self.o_proj.forward(&attn_output)
+    }
+
+    fn forward(&self, xs: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        let offsets = vec![0; xs.dim(0)?];
+        let (query_states, key_states, value_states) = self.project(xs, &offsets)?;
+        self.attend(&query_states, key_states, value_states, attention_mask)
+    }
+
+    fn forward_step(
+        &self,
+        xs: &Tensor,
+        caches: &mut [&mut SequenceCache],
+        layer_idx: usize,
+        offsets: &[usize],
+    ) -> Result<Tensor> {
+        let (query_states, key_states, value_states) = self.project(xs, offsets)?;
+        let (key_states, value_states, attention_mask) =
+            append_and_gather(caches, layer_idx, &key_states, &value_states, offsets, None)?;
+        self.attend(&query_states, key_states, value_states, &attention_mask)
+    }
+}
+
+#[derive(Debug)]
+struct DecoderLayer {
+    self_attn: Attention,
+    mlp: MLP,
+    input_layernorm: RmsNorm,
+    post_attention_layernorm: RmsNorm,
+}
+
+impl DecoderLayer {
+    fn load(
+        gguf: &mut GgufFile,
+        index: usize,
+        config: &GgufConfig,
+        rotary_emb: Arc<RotaryEmbedding>,
+    ) -> Result<Self> {
+        let prefix = format!("blk.{index}");
+        Ok(Self {
+            self_attn: Attention::load(gguf, &prefix, config, rotary_emb)?,
+            mlp: MLP::load(gguf, &prefix)?,
+            input_layernorm: gguf
+                .rms_norm(&format!("{prefix}.attn_norm.weight"), config.rms_norm_eps)?,
+            post_attention_layernorm: gguf
+                .rms_norm(&format!("{prefix}.ffn_norm.weight"), config.rms_norm_eps)?,
+        })
+    }
+
+    fn forward(&self, xs: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        let residual = xs;
+        let xs = self.input_layernorm.forward(xs)?;
+        let xs = self.self_attn.forward(&xs, attention_mask)?;
+        let xs = (xs + residual)?;
+        let residual = &xs;
+        let xs = xs.apply(&self.post_attention_layernorm)?.apply(&self.mlp)?;
+        residual + xs
+    }
+
+    fn forward_step(
+        &self,
+        xs: &Tensor,
+        caches: &mut [&mut SequenceCache],
+        layer_idx: usize,
+        offsets: &[usize],
+    ) -> Result<Tensor> {
+        let residual = xs;
+        let xs = self.input_layernorm.forward(xs)?;
+        let xs = self
+            .self_attn
+            .forward_step(&xs, caches, layer_idx, offsets)?;
+        let xs = (xs + residual)?;
+        let residual = &xs;
+        let xs = xs.apply(&self.post_attention_layernorm)?.apply(&self.mlp)?;
+        residual + xs
+    }
+}
+
+/// Llama and Qwen2 decoders with the quantized weights of a llama.cpp GGUF file.
+#[derive(Debug)]
+pub struct GgufForCausalLM {
+    embed_tokens: Embedding,
+    layers: Vec<DecoderLayer>,
+    norm: RmsNorm,
+    lm_head: Linear,
+    device: Device,
+    dtype: DType,
+}
+
+impl GgufForCausalLM {
+    /// Loads the model in `path`, returns it with its `config.json` equivalent.
+    pub fn load(path: &Path, dtype: DType, device: &Device) -> Result<(Self, String)> {
+        let mut gguf = GgufFile::open(path, dtype, device)?;
+        let config = GgufConfig::read(&gguf)?;
+
+        let token_embd = gguf.qtensor("token_embd.weight")?;
+        let embed_tokens = Embedding::new(
+            token_embd.dequantize(device)?.to_dtype(dtype)?,
+            config.hidden_size,
+        );
+        let rotary_emb = Arc::new(RotaryEmbedding::new(&mut gguf, &config)?);
+        let layers = (0..config.num_hidden_layers)
+            .map(|index| DecoderLayer::load(&mut gguf, index, &config, rotary_emb.clone()))
+            .collect::<Result<Vec<_>>>()?;
+        let norm = gguf.rms_norm("output_norm.weight", config.rms_norm_eps)?;
+        // Tied embeddings have no `output` tensor
+        let lm_head = if gguf.contains_tensor("output.weight") {
+            gguf.linear("output")?
+        } else {
+            Linear::from_qtensor(token_embd, None, None)?
+        };
+        let model = Self {
+            embed_tokens,
+            layers,
+            norm,
+            lm_head,
+            device: device.clone(),
+            dtype,
+        };
+        Ok((model, config.to_json()))
+    }
+
+    fn prepare_attention_mask(&self, attention_mask: &Tensor, tgt_len: usize) -> Result<Tensor> {
+        let causal: Vec<_> = (0..tgt_len)
+            .flat_map(|i| (0..tgt_len).map(move |j| if i < j { f32::NEG_INFINITY } else { 0. }))
+            .collect();
+        let causal = Tensor::from_slice(&causal, (1, 1, tgt_len, tgt_len), &self.device)?;
+        let (b_size, _) = attention_mask.dims2()?;
+        let padding = attention_mask
+            .to_dtype(DType::F32)?
+            .affine(1e4, -1e4)?
+            .reshape((b_size, 1, 1, tgt_len))?;
+        causal.broadcast_add(&padding)?.to_dtype(self.dtype)
+    }
+}
+
+impl Model for GgufForCausalLM {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(false)
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        _token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let (_b_size, seq_len) = input_ids.dims2()?;
+        let attention_bias = self.prepare_attention_mask(attention_mask, seq_len)?;
+        let mut xs = self.embed_tokens.forward(input_ids)?;
+        for layer in self.layers.iter() {
+            xs = layer.forward(&xs, &attention_bias)?
+        }
+        // The last token of right padded rows is not at the last position
+        let xs = gather_last(&xs, &mask_lens(attention_mask)?)?.apply(&self.norm)?;
+        self.lm_head.forward(&xs)
+    }
+
+    fn as_causal_lm(&self) -> Option<&dyn CausalLM> {
+        Some(self)
+    }
+}
+
+impl CausalLM for GgufForCausalLM {
+    fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn device(&self) -> &Device {
+        &self.device
+    }
+
+    fn forward_step(
+        &self,
+        input_ids: &Tensor,
+        seq_lens: Option<&[usize]>,
+        caches: &mut [&mut SequenceCache],
+    ) -> Result<Tensor> {
+        let offsets = caches.iter().map(|c| c.seq_len()).collect::<Vec<_>>();
+        let mut xs = self.embed_tokens.forward(input_ids)?;
+        for (layer_idx, layer) in self.layers.iter().enumerate() {
+            xs = layer.forward_step(&xs, caches, layer_idx, &offsets)?
+        }
+        let xs = last_tokens(&xs, seq_lens, caches, &offsets)?.apply(&self.norm)?;
+        self.lm_head.forward(&xs)?.squeeze(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle::quantized::GgmlDType;
+    use candle::IndexOp;
+    use gguf_file::Value;
+    use std::path::PathBuf;
+
+    /// Writes a llama GGUF file with one layer, a hidden size of 4 and a single attention head.
+    fn write_gguf(path: &Path, architecture: &str) -> Result<()> {
+        let metadata = [
+            (
+                "general.architecture",
+                Value::String(architecture.to_string()),
+            ),
+            ("llama.embedding_length", Value::U32(4)),
+            ("llama.feed_forward_length", Value::U32(8)),
+            ("llama.block_count", Value::U32(1)),
+            ("llama.attention.head_count", Value::U32(1)),
+            ("llama.context_length", Value::U32(16)),
+            ("llama.attention.layer_norm_rms_epsilon", Value::F32(1e-5)),
+        ];
+        let shapes: [(&str, &[usize]); 11] = [
+            ("token_embd.weight", &[8, 4]),
+            ("blk.0.attn_q.weight", &[4, 4]),
+            ("blk.0.attn_k.weight", &[4, 4]),
+            ("blk.0.attn_v.weight", &[4, 4]),
+            ("blk.0.attn_output.weight", &[4, 4]),
+            ("blk.0.ffn_gate.weight", &[8, 4]),
+            ("blk.0.ffn_up.weight", &[8, 4]),
+            ("blk.0.ffn_down.weight", &[4, 8]),
+            ("blk.0.attn_norm.weight", &[4]),
+            ("blk.0.ffn_norm.weight", &[4]),
+            ("output_norm.weight", &[4]),
+        ];
+        let tensors = shapes
+            .iter()
+            .map(|(_, shape)| {
+                let tensor = Tensor::randn(0f32, 1., *shape, &Device::Cpu)?;
+                QTensor::quantize(&tensor, GgmlDType::F32)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let metadata: Vec<_> = metadata.iter().map(|(key, value)| (*key, value)).collect();
+        let tensors: Vec<_> = shapes.iter().map(|(name, _)| *name).zip(&tensors).collect();
+        let mut file = File::create(path)?;
+        gguf_file::write(&mut file, &metadata, &tensors)
+    }
+
+    fn temp_gguf(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("djl-{name}-{}.gguf", std::process::id()))
+    }
+
+    #[test]
+    fn test_config() -> Result<()> {
+        let path = temp_gguf("config");
+        write_gguf(&path, "llama")?;
+        let config = GgufConfig::read(&GgufFile::open(&path, DType::F32, &Device::Cpu)?);
+        write_gguf(&path, "gpt2")?;
+        let unsupported = GgufConfig::read(&GgufFile::open(&path, DType::F32, &Device::Cpu)?);
+        std::fs::remove_file(&path)?;
+
+        let config = config?;
+        assert_eq!(config.vocab_size, 8);
+        assert_eq!(config.hidden_size, 4);
+        assert_eq!(config.intermediate_size, 8);
+        assert_eq!(config.num_hidden_layers, 1);
+        assert_eq!(config.max_position_embeddings, 16);
+        // The defaults of the optional keys
+        assert_eq!(config.num_key_value_heads, 1);
+        assert_eq!(config.head_dim, 4);
+        assert_eq!(config.rope_theta, 10_000.);
+        let json: serde_json::Value = serde_json::from_str(&config.to_json()).unwrap();
+        assert_eq!(json["architectures"][0], "LlamaForCausalLM");
+        assert_eq!(json["num_attention_heads"], 1);
+        let err = unsupported.unwrap_err().to_string();
+        assert!(err.contains("Unsupported GGUF architecture: gpt2"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotary_embedding() -> Result<()> {
+        let path = temp_gguf("rope");
+        write_gguf(&path, "llama")?;
+        let mut gguf = GgufFile::open(&path, DType::F32, &Device::Cpu)?;
+        let config = GgufConfig::read(&gguf)?;
+        let rope = RotaryEmbedding::new(&mut gguf, &config);
+        std::fs::remove_file(&path)?;
+
+        // The second position rotates the first pair of dims by one radian
+        let rope = rope?;
+        let xs = Tensor::new(&[[1f32, 0., 0., 0.], [1., 0., 0., 0.]], &Device::Cpu)?;
+        let xs = xs.reshape((1, 1, 2, 4))?;
+        let rotate = |rope: &RotaryEmbedding| -> Result<Vec<f32>> {
+            rope.apply(&xs, &[0])?.i((0, 0, 1))?.to_vec1()
+        };
+        let (cos, sin) = (1f32.cos(), 1f32.sin());
+        // llama.cpp pairs adjacent dims, HF pairs the two halves
+        let interleaved = rotate(&rope)?;
+        assert!(rope.interleaved);
+        assert_eq!(interleaved, [cos, sin, 0., 0.]);
+        let halves = rotate(&RotaryEmbedding {
+            interleaved: false,
+            ..rope
+        })?;
+        assert_eq!(halves, [cos, 0., sin, 0.]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load() -> Result<()> {
+        let path = temp_gguf("load");
+        write_gguf(&path, "llama")?;
+        let loaded = GgufForCausalLM::load(&path, DType::F32, &Device::Cpu);
+        std::fs::remove_file(&path)?;
+
+        // Without an `output` tensor, the logits are tied to the token embeddings
+        let (model, config) = loaded?;
+        assert!(config.contains("\"vocab_size\":8"), "{config}");
+        let input_ids = Tensor::new(&[[1u32, 2, 3]], &Device::Cpu)?;
+        let attention_mask = Tensor::ones((1, 3), DType::U32, &Device::Cpu)?;
+        let logits = Model::forward(&model, &input_ids, &attention_mask, None)?;
+        assert_eq!(logits.elem_count(), 8);
+
+        // The decoding path with a cache gives the same logits
+        let mut cache = SequenceCache::new(model.num_layers());
+        let step = model.forward_step(&input_ids, None, &mut [&mut cache])?;
+        assert_eq!(cache.seq_len(), 3);
+        let diff = (logits.flatten_all()? - step.flatten_all()?)?
+            .abs()?
+            .max(0)?;
+        assert!(diff.to_scalar::<f32>()? < 1e-4);
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
-use crate::layers::{LayerNorm, Linear};
+use crate::layers::{Alibi, LayerNorm, Linear};
+use crate::models::inputs::{token_inputs, InputSpec};
 use crate::models::Model;
+use crate::utils::get_extended_attention_mask;
 use candle::{DType, Device, Result, Tensor};
 use candle_nn::{embedding, ops, rotary_emb, Activation, Embedding, Module, VarBuilder};
 use serde::Deserialize;
@@ -202,7 +204,7 @@ struct Attention {
     hidden_size: usize,
     num_heads: usize,
     attention_head_size: usize,
-    rotary_emb: Arc<RotaryEmbedding>,
+    rotary_emb: Option<Arc<RotaryEmbedding>>,
     use_flash_attn: bool,
 }
 
@@ -213,7 +215,14 @@ impl Attention {
         let attention_head_size = hidden_size / config.num_attention_heads;
         let qkv_proj = Linear::load(vb.pp("qkv_proj"), hidden_size, hidden_size * 3, None)?;
         let o_proj = Linear::load(vb.pp("o_proj"), hidden_size, hidden_size, None)?;
-        let rotary_emb = Arc::new(RotaryEmbedding::new(config, vb.dtype(), vb.device())?);
+        let rotary_emb = match config.position_embedding_type {
+            PositionEmbeddingType::Alibi => None,
+            _ => Some(Arc::new(RotaryEmbedding::new(
+                config,
+                vb.dtype(),
+                vb.device(),
+            )?)),
+        };
         Ok(Self {
             qkv_proj,
             o_proj,
@@ -241,9 +250,12 @@ impl Attention {
         let k = qkv.narrow(1, self.num_heads, self.num_heads)?;
         let v = qkv.narrow(1, self.num_heads * 2, self.num_heads)?;
 
-        let (q, k) = self.rotary_emb.apply_rotary_emb(&q, &k)?;
+        let (q, k) = match &self.rotary_emb {
+            Some(rotary_emb) => rotary_emb.apply_rotary_emb(&q, &k)?,
+            None => (q, k),
+        };
 
-        let attn_output = if self.use_flash_attn {
+        let attn_output = if self.use_flash_attn && self.rotary_emb.is_some() {
             // flash-attn expects (b_sz, seq_len, nheads, head_dim)
             let q = q.transpose(1, 2)?;
             let k = k.transpose(1, 2)?;
@@ -344,6 +356,8 @@ impl GTEEncoder {
 pub struct GTEModel {
     embeddings: GTEEmbeddings,
     encoder: GTEEncoder,
+    alibi: Option<Alibi>,
+    dtype: DType,
     #[allow(unused)]
     pub device: Device,
     span: tracing::Span,
@@ -367,9 +381,19 @@ impl GTEModel {
                 }
             }
         };
+        let alibi = match config.position_embedding_type {
+            PositionEmbeddingType::Alibi => Some(Alibi::new(
+                config.num_attention_heads,
+                vb.dtype(),
+                vb.device(),
+            )?),
+            _ => None,
+        };
         Ok(Self {
             embeddings,
             encoder,
+            alibi,
+            dtype: vb.dtype(),
             device: vb.device().clone(),
             span: tracing::span!(tracing::Level::TRACE, "model"),
         })
@@ -377,12 +401,8 @@ impl GTEModel {
 }
 
 impl Model for GTEModel {
-    fn get_input_names(&self) -> Vec<String> {
-        return vec![
-            "input_ids".to_string(),
-            "attention_mask".to_string(),
-            "token_type_ids".to_string(),
-        ];
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(true)
     }
 
     fn forward(
@@ -395,9 +415,12 @@ impl Model for GTEModel {
         let embedding_output = self
             .embeddings
             .forward(input_ids, token_type_ids.unwrap())?;
-        let sequence_output = self
-            .encoder
-            .forward(&embedding_output, attention_mask.as_ref())?;
+        let attention_mask = get_extended_attention_mask(attention_mask, self.dtype)?;
+        let attention_mask = match &self.alibi {
+            Some(alibi) => attention_mask.broadcast_add(&alibi.bias(input_ids.dim(1)?)?)?,
+            None => attention_mask,
+        };
+        let sequence_output = self.encoder.forward(&embedding_output, &attention_mask)?;
         Ok(sequence_output)
     }
 }
@@ -0,0 +1,85 @@
+use crate::models::Model;
+use candle::{DType, Device, DeviceLocation, Error, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Model metadata reported by `getModelInfo`, serialized as JSON.
+#[derive(Debug, Serialize)]
+pub(crate) struct ModelInfo {
+    model_type: Option<String>,
+    architectures: Vec<String>,
+    hidden_size: Option<usize>,
+    max_position_embeddings: Option<usize>,
+    id2label: Option<HashMap<String, String>>,
+    input_names: Vec<String>,
+    reranker: bool,
+    dtype: &'static str,
+    device_type: &'static str,
+    device_id: usize,
+}
+
+impl ModelInfo {
+    pub(crate) fn new(
+        config: &str,
+        model: &dyn Model,
+        reranker: bool,
+        dtype: DType,
+        device: &Device,
+    ) -> Result<Self> {
+        let config: Value = serde_json::from_str(config).map_err(Error::msg)?;
+        // Not every architecture uses the canonical `transformers` field names
+        let get_usize = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| config.get(*key).and_then(Value::as_u64))
+                .map(|v| v as usize)
+        };
+        let architectures = match config.get("architectures") {
+            Some(architectures) => {
+                serde_json::from_value(architectures.clone()).map_err(Error::msg)?
+            }
+            None => Vec::new(),
+        };
+        let id2label = match config.get("id2label") {
+            Some(id2label) => Some(serde_json::from_value(id2label.clone()).map_err(Error::msg)?),
+            None => None,
+        };
+        let (device_type, device_id) = match device.location() {
+            DeviceLocation::Cpu => ("cpu", 0),
+            DeviceLocation::Cuda { gpu_id } => ("gpu", gpu_id),
+            DeviceLocation::Metal { gpu_id } => ("mps", gpu_id),
+        };
+        Ok(Self {
+            model_type: config
+                .get("model_type")
+                .and_then(Value::as_str)
+                .map(String::from),
+            architectures,
+            hidden_size: get_usize(&["hidden_size", "dim", "n_embd", "d_model"]),
+            max_position_embeddings: get_usize(&["max_position_embeddings", "n_positions"]),
+            id2label,
+            input_names: model.get_input_names(),
+            reranker,
+            dtype: dtype_name(dtype),
+            device_type,
+            device_id,
+        })
+    }
+
+    pub(crate) fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(Error::msg)
+    }
+}
+
+// Matches the lower case names of `ai.djl.ndarray.types.DataType`
+fn dtype_name(dtype: DType) -> &'static str {
+    match dtype {
+        DType::F32 => "float32",
+        DType::F64 => "float64",
+        DType::F16 => "float16",
+        DType::BF16 => "bfloat16",
+        DType::U8 => "uint8",
+        DType::U32 => "uint32",
+        DType::I64 => "int64",
+    }
+}
@@ -0,0 +1,347 @@
+use candle::{DType, Result, Tensor};
+use std::collections::HashMap;
+
+const INDEX_DTYPES: &[DType] = &[DType::U8, DType::U32, DType::I64];
+const FLOAT_DTYPES: &[DType] = &[DType::F16, DType::BF16, DType::F32, DType::F64];
+// `transformers` also accepts float masks
+const MASK_DTYPES: &[DType] = &[
+    DType::U8,
+    DType::U32,
+    DType::I64,
+    DType::F16,
+    DType::BF16,
+    DType::F32,
+    DType::F64,
+];
+
+/// The axes of the token level inputs.
+const TOKEN_DIMS: &[&str] = &["batch", "seq_len"];
+
+/// How a missing input is filled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InputDefault {
+    Required,
+    /// Required unless the named input is given instead, e.g. `inputs_embeds` for `input_ids`.
+    /// Giving both is an error.
+    RequiredUnless(&'static str),
+    Zeros,
+    Ones,
+    /// Left out, the model computes it.
+    Omitted,
+}
+
+/// A model input. Its axes are named, e.g. `["batch", "seq_len"]`, the axes with the same name
+/// must have the same size in every input.
+#[derive(Debug, Clone)]
+pub(crate) struct InputSpec {
+    pub(crate) name: String,
+    pub(crate) dtypes: &'static [DType],
+    pub(crate) dims: &'static [&'static str],
+    pub(crate) default: InputDefault,
+}
+
+impl InputSpec {
+    fn new(
+        name: &str,
+        dtypes: &'static [DType],
+        dims: &'static [&'static str],
+        default: InputDefault,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            dtypes,
+            dims,
+            default,
+        }
+    }
+
+    pub(crate) fn input_ids() -> Self {
+        Self::new(
+            "input_ids",
+            INDEX_DTYPES,
+            TOKEN_DIMS,
+            InputDefault::Required,
+        )
+    }
+
+    /// Every token is attended to by default.
+    pub(crate) fn attention_mask() -> Self {
+        Self::new(
+            "attention_mask",
+            MASK_DTYPES,
+            TOKEN_DIMS,
+            InputDefault::Ones,
+        )
+    }
+
+    pub(crate) fn token_type_ids() -> Self {
+        Self::new(
+            "token_type_ids",
+            INDEX_DTYPES,
+            TOKEN_DIMS,
+            InputDefault::Zeros,
+        )
+    }
+
+    /// Absolute positions, the model numbers the tokens from 0 by default.
+    pub(crate) fn position_ids() -> Self {
+        Self::new(
+            "position_ids",
+            INDEX_DTYPES,
+            TOKEN_DIMS,
+            InputDefault::Omitted,
+        )
+    }
+
+    /// Word embeddings given instead of `input_ids`.
+    pub(crate) fn inputs_embeds() -> Self {
+        Self::new(
+            "inputs_embeds",
+            FLOAT_DTYPES,
+            &["batch", "seq_len", "hidden_size"],
+            InputDefault::RequiredUnless("input_ids"),
+        )
+    }
+}
+
+/// The inputs of most text models, `input_ids`, `attention_mask` and optionally
+/// `token_type_ids`.
+pub(crate) fn token_inputs(token_type_ids: bool) -> Vec<InputSpec> {
+    let mut specs = vec![InputSpec::input_ids(), InputSpec::attention_mask()];
+    if token_type_ids {
+        specs.push(InputSpec::token_type_ids());
+    }
+    specs
+}
+
+/// Like [token_inputs], with `inputs_embeds` accepted instead of `input_ids`.
+pub(crate) fn embeds_inputs(token_type_ids: bool) -> Vec<InputSpec> {
+    let mut specs = token_inputs(token_type_ids);
+    specs[0].default = InputDefault::RequiredUnless("inputs_embeds");
+    specs.push(InputSpec::inputs_embeds());
+    specs
+}
+
+/// Named model inputs, validated against the model's `InputSpec`s.
+pub(crate) struct ModelInputs {
+    tensors: HashMap<String, Tensor>,
+}
+
+impl ModelInputs {
+    pub(crate) fn bind(specs: &[InputSpec], inputs: Vec<(String, Tensor)>) -> Result<Self> {
+        let mut tensors = HashMap::with_capacity(specs.len());
+        for (name, tensor) in inputs {
+            let spec = match specs.iter().find(|spec| spec.name == name) {
+                Some(spec) => spec,
+                None => {
+                    let names: Vec<&str> = specs.iter().map(|spec| spec.name.as_str()).collect();
+                    candle::bail!("Unexpected input `{name}`, the model accepts {names:?}")
+                }
+            };
+            if !spec.dtypes.contains(&tensor.dtype()) {
+                candle::bail!(
+                    "Input `{name}` must be one of {:?}, got {:?}",
+                    spec.dtypes,
+                    tensor.dtype()
+                );
+            }
+            if tensors.insert(name.clone(), tensor).is_some() {
+                candle::bail!("Input `{name}` is given more than once");
+            }
+        }
+
+        // The size of every named axis, from the first input in spec order that has it
+        let mut sizes: HashMap<&str, (usize, &str)> = HashMap::new();
+        for spec in specs {
+            let Some(tensor) = tensors.get(&spec.name) else {
+                continue;
+            };
+            if tensor.rank() != spec.dims.len() {
+                candle::bail!(
+                    "Input `{}` must have the axes {:?}, got the shape {:?}",
+                    spec.name,
+                    spec.dims,
+                    tensor.dims()
+                );
+            }
+            for (&dim, &size) in spec.dims.iter().zip(tensor.dims()) {
+                let (expected, from) = *sizes.entry(dim).or_insert((size, spec.name.as_str()));
+                if size != expected {
+                    candle::bail!(
+                        "Input `{}` has {dim} {size}, but {dim} is {expected} in `{from}`",
+                        spec.name
+                    );
+                }
+            }
+        }
+
+        let mut defaults = Vec::new();
+        for spec in specs {
+            if tensors.contains_key(&spec.name) {
+                if let InputDefault::RequiredUnless(other) = spec.default {
+                    if tensors.contains_key(other) {
+                        candle::bail!("Inputs `{}` and `{other}` cannot both be given", spec.name);
+                    }
+                }
+                continue;
+            }
+            let ones = match spec.default {
+                InputDefault::Required => candle::bail!("Input `{}` is required", spec.name),
+                InputDefault::RequiredUnless(other) if !tensors.contains_key(other) => {
+                    candle::bail!("Input `{}` or `{other}` is required", spec.name)
+                }
+                InputDefault::RequiredUnless(_) | InputDefault::Omitted => continue,
+                InputDefault::Zeros => false,
+                InputDefault::Ones => true,
+            };
+            let shape = spec
+                .dims
+                .iter()
+                .map(|dim| match sizes.get(dim) {
+                    Some((size, _)) => Ok(*size),
+                    None => candle::bail!("Cannot infer the {dim} of input `{}`", spec.name),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            // Filled in like the given inputs it accepts, e.g. a mask like `input_ids`
+            let like = specs
+                .iter()
+                .filter_map(|spec| tensors.get(&spec.name))
+                .find(|tensor| spec.dtypes.contains(&tensor.dtype()));
+            let (dtype, device) = match like {
+                Some(tensor) => (tensor.dtype(), tensor.device().clone()),
+                None => match tensors.values().next() {
+                    Some(tensor) => (spec.dtypes[0], tensor.device().clone()),
+                    None => candle::bail!("No input is given"),
+                },
+            };
+            let tensor = if ones {
+                Tensor::ones(shape, dtype, &device)?
+            } else {
+                Tensor::zeros(shape, dtype, &device)?
+            };
+            defaults.push((spec.name.clone(), tensor));
+        }
+        tensors.extend(defaults);
+        Ok(Self { tensors })
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Result<&Tensor> {
+        match self.tensors.get(name) {
+            Some(tensor) => Ok(tensor),
+            None => candle::bail!("Input `{name}` is not supported by this model"),
+        }
+    }
+
+    pub(crate) fn get_opt(&self, name: &str) -> Option<&Tensor> {
+        self.tensors.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle::{Device, IndexOp};
+
+    fn inputs(names: &[&str]) -> Result<Vec<(String, Tensor)>> {
+        let ids = Tensor::new(&[[1u32, 2, 3]], &Device::Cpu)?;
+        Ok(names.iter().map(|n| (n.to_string(), ids.clone())).collect())
+    }
+
+    #[test]
+    fn test_bind() -> Result<()> {
+        let mut specs = token_inputs(true);
+        specs.push(InputSpec::position_ids());
+        let bound = ModelInputs::bind(&specs, inputs(&["input_ids"])?)?;
+        assert_eq!(bound.get("attention_mask")?.to_vec2::<u32>()?, [[1, 1, 1]]);
+        assert_eq!(bound.get("token_type_ids")?.to_vec2::<u32>()?, [[0, 0, 0]]);
+        assert!(bound.get_opt("position_ids").is_none());
+
+        let mut given = inputs(&["input_ids"])?;
+        let mask = Tensor::new(&[[1f32, 1., 0.]], &Device::Cpu)?;
+        given.push(("attention_mask".to_string(), mask));
+        assert!(ModelInputs::bind(&specs, given.clone()).is_ok());
+        assert!(ModelInputs::bind(&token_inputs(false), given).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_embeds() -> Result<()> {
+        let specs = embeds_inputs(true);
+        let embeds = Tensor::ones((2, 3, 4), DType::F32, &Device::Cpu)?;
+        let given = vec![("inputs_embeds".to_string(), embeds.clone())];
+        let bound = ModelInputs::bind(&specs, given)?;
+        // The defaults take the batch and seq_len of `inputs_embeds`
+        assert_eq!(bound.get("attention_mask")?.dims(), [2, 3]);
+        assert_eq!(bound.get("attention_mask")?.dtype(), DType::F32);
+        assert_eq!(bound.get("token_type_ids")?.dims(), [2, 3]);
+        assert_eq!(bound.get("token_type_ids")?.dtype(), DType::U8);
+        assert!(bound.get_opt("input_ids").is_none());
+
+        let mut both = inputs(&["input_ids"])?;
+        both.push(("inputs_embeds".to_string(), embeds.clone()));
+        assert!(ModelInputs::bind(&specs, both).is_err());
+        let mask = Tensor::ones((2, 3), DType::U8, &Device::Cpu)?;
+        let neither = vec![("attention_mask".to_string(), mask)];
+        assert!(ModelInputs::bind(&specs, neither).is_err());
+
+        // `input_ids` has a batch of 1
+        let mut mismatched = inputs(&["input_ids"])?;
+        mismatched.push((
+            "attention_mask".to_string(),
+            embeds.ones_like()?.i((.., .., 0))?,
+        ));
+        let err = ModelInputs::bind(&specs, mismatched)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("`attention_mask` has batch 2"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_pixel_values() -> Result<()> {
+        let image_dims = &["batch", "channels", "height", "width"];
+        let specs = [
+            InputSpec::new(
+                "pixel_values",
+                FLOAT_DTYPES,
+                image_dims,
+                InputDefault::Required,
+            ),
+            InputSpec::new(
+                "pixel_mask",
+                INDEX_DTYPES,
+                &["batch", "height", "width"],
+                InputDefault::Ones,
+            ),
+        ];
+        let pixels = Tensor::zeros((2, 3, 8, 6), DType::F32, &Device::Cpu)?;
+        let given = vec![("pixel_values".to_string(), pixels.clone())];
+        let bound = ModelInputs::bind(&specs, given)?;
+        assert_eq!(bound.get("pixel_mask")?.dims(), [2, 8, 6]);
+
+        let flat = vec![("pixel_values".to_string(), pixels.flatten_from(2)?)];
+        let err = ModelInputs::bind(&specs, flat).err().unwrap().to_string();
+        assert!(err.contains("must have the axes"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_bind_errors() -> Result<()> {
+        let specs = token_inputs(false);
+        let duplicate = ModelInputs::bind(&specs, inputs(&["input_ids", "input_ids"])?);
+        assert!(duplicate.is_err());
+        let unknown = ModelInputs::bind(&specs, inputs(&["input_ids", "token_type_ids"])?);
+        assert!(unknown.is_err());
+        let embeds = ModelInputs::bind(&specs, inputs(&["inputs_embeds"])?);
+        assert!(embeds.is_err());
+        assert!(ModelInputs::bind(&specs, inputs(&["attention_mask"])?).is_err());
+
+        let float_ids = vec![(
+            "input_ids".to_string(),
+            Tensor::new(&[[1f32]], &Device::Cpu)?,
+        )];
+        assert!(ModelInputs::bind(&specs, float_ids).is_err());
+        Ok(())
+    }
+}
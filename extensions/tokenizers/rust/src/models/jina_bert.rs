@@ -0,0 +1,275 @@
+use crate::layers::{Alibi, LayerNorm, Linear};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::Model;
+use crate::utils::get_extended_attention_mask;
+use candle::{DType, Device, Result, Tensor};
+use candle_nn::{embedding, ops, Embedding, Module, VarBuilder};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedForwardType {
+    #[default]
+    Geglu,
+    Reglu,
+}
+
+// https://huggingface.co/jinaai/jina-bert-implementation/blob/main/configuration_bert.py
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct JinaBertConfig {
+    pub architectures: Vec<String>,
+    model_type: Option<String>,
+    vocab_size: usize,
+    hidden_size: usize,
+    num_hidden_layers: usize,
+    num_attention_heads: usize,
+    intermediate_size: usize,
+    type_vocab_size: usize,
+    layer_norm_eps: f64,
+    #[serde(default)]
+    feed_forward_type: FeedForwardType,
+    pub use_flash_attn: Option<bool>,
+}
+
+struct JinaBertEmbeddings {
+    word_embeddings: Embedding,
+    token_type_embeddings: Embedding,
+    layer_norm: LayerNorm,
+    span: tracing::Span,
+}
+
+impl JinaBertEmbeddings {
+    fn load(vb: VarBuilder, config: &JinaBertConfig) -> Result<Self> {
+        let word_embeddings = embedding(
+            config.vocab_size,
+            config.hidden_size,
+            vb.pp("word_embeddings"),
+        )?;
+        let token_type_embeddings = embedding(
+            config.type_vocab_size,
+            config.hidden_size,
+            vb.pp("token_type_embeddings"),
+        )?;
+        let layer_norm = LayerNorm::load(
+            vb.pp("LayerNorm"),
+            config.hidden_size,
+            config.layer_norm_eps as f32,
+        )?;
+        Ok(Self {
+            word_embeddings,
+            token_type_embeddings,
+            layer_norm,
+            span: tracing::span!(tracing::Level::TRACE, "embeddings"),
+        })
+    }
+
+    fn forward(&self, input_ids: &Tensor, token_type_ids: &Tensor) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let input_embeddings = self.word_embeddings.forward(input_ids)?;
+        let token_type_embeddings = self.token_type_embeddings.forward(token_type_ids)?;
+        let embeddings = (&input_embeddings + token_type_embeddings)?;
+        self.layer_norm.forward(&embeddings, None)
+    }
+}
+
+struct JinaBertAttention {
+    query: Linear,
+    key: Linear,
+    value: Linear,
+    dense: Linear,
+    layer_norm: LayerNorm,
+    num_attention_heads: usize,
+    attention_head_size: usize,
+    span: tracing::Span,
+}
+
+impl JinaBertAttention {
+    fn load(vb: VarBuilder, config: &JinaBertConfig) -> Result<Self> {
+        let hidden_size = config.hidden_size;
+        let attention_head_size = hidden_size / config.num_attention_heads;
+        let query = Linear::load(vb.pp("self.query"), hidden_size, hidden_size, None)?;
+        let key = Linear::load(vb.pp("self.key"), hidden_size, hidden_size, None)?;
+        let value = Linear::load(vb.pp("self.value"), hidden_size, hidden_size, None)?;
+        let dense = Linear::load(vb.pp("output.dense"), hidden_size, hidden_size, None)?;
+        let layer_norm = LayerNorm::load(
+            vb.pp("output.LayerNorm"),
+            hidden_size,
+            config.layer_norm_eps as f32,
+        )?;
+        Ok(Self {
+            query,
+            key,
+            value,
+            dense,
+            layer_norm,
+            num_attention_heads: config.num_attention_heads,
+            attention_head_size,
+            span: tracing::span!(tracing::Level::TRACE, "attn"),
+        })
+    }
+
+    fn transpose_for_scores(&self, xs: &Tensor) -> Result<Tensor> {
+        let (b_sz, seq_len, _) = xs.dims3()?;
+        xs.reshape((
+            b_sz,
+            seq_len,
+            self.num_attention_heads,
+            self.attention_head_size,
+        ))?
+        .transpose(1, 2)?
+        .contiguous()
+    }
+
+    fn forward(&self, hidden_states: &Tensor, attention_bias: &Tensor) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let query_layer = self.transpose_for_scores(&self.query.forward(hidden_states)?)?;
+        let key_layer = self.transpose_for_scores(&self.key.forward(hidden_states)?)?;
+        let value_layer = self.transpose_for_scores(&self.value.forward(hidden_states)?)?;
+
+        let scale = 1f64 / (self.attention_head_size as f64).sqrt();
+        let attention_scores = (query_layer.matmul(&key_layer.t()?)? * scale)?;
+        let attention_scores = attention_scores.broadcast_add(attention_bias)?;
+        let attention_probs = ops::softmax_last_dim(&attention_scores)?;
+        let context_layer = attention_probs
+            .matmul(&value_layer)?
+            .transpose(1, 2)?
+            .flatten_from(candle::D::Minus2)?;
+
+        let output = self.dense.forward(&context_layer)?;
+        self.layer_norm.forward(&output, Some(hidden_states))
+    }
+}
+
+// https://huggingface.co/jinaai/jina-bert-implementation/blob/main/modeling_bert.py
+struct JinaBertGLUMLP {
+    gated_layers: Linear,
+    wo: Linear,
+    layer_norm: LayerNorm,
+    intermediate_size: usize,
+    feed_forward_type: FeedForwardType,
+    span: tracing::Span,
+}
+
+impl JinaBertGLUMLP {
+    fn load(vb: VarBuilder, config: &JinaBertConfig) -> Result<Self> {
+        let hidden_size = config.hidden_size;
+        let intermediate_size = config.intermediate_size;
+        let gated_layers = Linear::load(
+            vb.pp("gated_layers"),
+            hidden_size,
+            intermediate_size * 2,
+            None,
+        )?;
+        let wo = Linear::load(vb.pp("wo"), intermediate_size, hidden_size, None)?;
+        let layer_norm = LayerNorm::load(
+            vb.pp("layernorm"),
+            hidden_size,
+            config.layer_norm_eps as f32,
+        )?;
+        Ok(Self {
+            gated_layers,
+            wo,
+            layer_norm,
+            intermediate_size,
+            feed_forward_type: config.feed_forward_type,
+            span: tracing::span!(tracing::Level::TRACE, "mlp"),
+        })
+    }
+
+    fn forward(&self, hidden_states: &Tensor) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let gated_states = self.gated_layers.forward(hidden_states)?;
+        let gated = gated_states.narrow(2, 0, self.intermediate_size)?;
+        let non_gated = gated_states.narrow(2, self.intermediate_size, self.intermediate_size)?;
+        let gated = match self.feed_forward_type {
+            FeedForwardType::Geglu => gated.gelu_erf()?,
+            FeedForwardType::Reglu => gated.relu()?,
+        };
+        let output = self.wo.forward(&(gated * non_gated)?)?;
+        self.layer_norm.forward(&output, Some(hidden_states))
+    }
+}
+
+struct JinaBertLayer {
+    attention: JinaBertAttention,
+    mlp: JinaBertGLUMLP,
+    span: tracing::Span,
+}
+
+impl JinaBertLayer {
+    fn load(vb: VarBuilder, config: &JinaBertConfig) -> Result<Self> {
+        let attention = JinaBertAttention::load(vb.pp("attention"), config)?;
+        let mlp = JinaBertGLUMLP::load(vb.pp("mlp"), config)?;
+        Ok(Self {
+            attention,
+            mlp,
+            span: tracing::span!(tracing::Level::TRACE, "layer"),
+        })
+    }
+
+    fn forward(&self, hidden_states: &Tensor, attention_bias: &Tensor) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let attention_output = self.attention.forward(hidden_states, attention_bias)?;
+        self.mlp.forward(&attention_output)
+    }
+}
+
+pub struct JinaBertModel {
+    embeddings: JinaBertEmbeddings,
+    layers: Vec<JinaBertLayer>,
+    alibi: Alibi,
+    dtype: DType,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl JinaBertModel {
+    pub fn load(vb: VarBuilder, config: &JinaBertConfig) -> Result<Self> {
+        // `JinaBertForMaskedLM` checkpoints nest the encoder under `bert`
+        let vb = if vb.contains_tensor("bert.embeddings.word_embeddings.weight") {
+            vb.pp("bert")
+        } else {
+            vb
+        };
+        let embeddings = JinaBertEmbeddings::load(vb.pp("embeddings"), config)?;
+        let layers = (0..config.num_hidden_layers)
+            .map(|index| JinaBertLayer::load(vb.pp(format!("encoder.layer.{index}")), config))
+            .collect::<Result<Vec<_>>>()?;
+        let alibi = Alibi::new(config.num_attention_heads, vb.dtype(), vb.device())?;
+        Ok(Self {
+            embeddings,
+            layers,
+            alibi,
+            dtype: vb.dtype(),
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for JinaBertModel {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(true)
+    }
+
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let token_type_ids = match token_type_ids {
+            Some(token_type_ids) => token_type_ids.clone(),
+            None => input_ids.zeros_like()?,
+        };
+        let attention_bias = get_extended_attention_mask(attention_mask, self.dtype)?
+            .broadcast_add(&self.alibi.bias(input_ids.dim(1)?)?)?;
+        let mut hidden_states = self.embeddings.forward(input_ids, &token_type_ids)?;
+        for layer in self.layers.iter() {
+            hidden_states = layer.forward(&hidden_states, &attention_bias)?;
+        }
+        Ok(hidden_states)
+    }
+}
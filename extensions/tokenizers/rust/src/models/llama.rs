@@ -0,0 +1,666 @@
+use crate::layers::{append_and_gather, Linear, RmsNorm, SequenceCache};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::{gather_last, last_tokens, mask_lens, CausalLM, Model};
+use crate::utils::{repeat_kv, rope_with_offsets};
+use candle::{DType, Device, Module, Result, Tensor};
+use candle_nn::{embedding, ops, rotary_emb, Activation, Embedding, VarBuilder};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Llama3RopeConfig {
+    pub factor: f32,
+    pub low_freq_factor: f32,
+    pub high_freq_factor: f32,
+    pub original_max_position_embeddings: usize,
+}
+
+/// The `rope_scaling` of the config, its type is named `type` in older configs, e.g. Llama 2
+/// fine-tunes, and `rope_type` in newer ones.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RopeScaling {
+    Default,
+    Linear { factor: f32 },
+    Llama3(Llama3RopeConfig),
+    Unsupported(String),
+}
+
+impl<'de> Deserialize<'de> for RopeScaling {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Linear {
+            factor: f32,
+        }
+
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let rope_type = match value.get("rope_type").or_else(|| value.get("type")) {
+            Some(serde_json::Value::String(rope_type)) => rope_type.clone(),
+            _ => return Err(D::Error::custom("`rope_scaling` has no `rope_type`")),
+        };
+        Ok(match rope_type.as_str() {
+            "default" => Self::Default,
+            "linear" => {
+                let linear: Linear = serde_json::from_value(value).map_err(D::Error::custom)?;
+                Self::Linear {
+                    factor: linear.factor,
+                }
+            }
+            "llama3" => Self::Llama3(serde_json::from_value(value).map_err(D::Error::custom)?),
+            _ => Self::Unsupported(rope_type),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct LlamaConfig {
+    pub architectures: Vec<String>,
+    model_type: Option<String>,
+    pub vocab_size: usize,
+    pub hidden_size: usize,
+    pub intermediate_size: usize,
+    pub num_hidden_layers: usize,
+    pub num_attention_heads: usize,
+    pub num_key_value_heads: Option<usize>,
+    pub head_dim: Option<usize>,
+    #[serde(default = "default_hidden_act")]
+    pub hidden_act: Activation,
+    pub max_position_embeddings: usize,
+    pub rms_norm_eps: f64,
+    #[serde(default = "default_rope_theta")]
+    pub rope_theta: f64,
+    pub rope_scaling: Option<RopeScaling>,
+    #[serde(default)]
+    pub tie_word_embeddings: bool,
+    pub use_flash_attn: Option<bool>,
+}
+
+fn default_hidden_act() -> Activation {
+    Activation::Silu
+}
+
+fn default_rope_theta() -> f64 {
+    10_000.
+}
+
+impl LlamaConfig {
+    fn num_key_value_heads(&self) -> usize {
+        self.num_key_value_heads.unwrap_or(self.num_attention_heads)
+    }
+
+    fn head_dim(&self) -> usize {
+        self.head_dim
+            .unwrap_or(self.hidden_size / self.num_attention_heads)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RotaryEmbedding {
+    sin: Tensor,
+    cos: Tensor,
+}
+
+impl RotaryEmbedding {
+    fn new(config: &LlamaConfig, dtype: DType, dev: &Device) -> Result<Self> {
+        let rope_theta = config.rope_theta as f32;
+        let dim = config.head_dim();
+        let max_seq_len = config.max_position_embeddings;
+        let inv_freq: Vec<_> = (0..dim)
+            .step_by(2)
+            .map(|i| 1f32 / rope_theta.powf(i as f32 / dim as f32))
+            .collect();
+        let inv_freq = match &config.rope_scaling {
+            None | Some(RopeScaling::Default) => inv_freq,
+            // Positions are interpolated, `t / factor` rotates like `t * (inv_freq / factor)`
+            Some(RopeScaling::Linear { factor }) => {
+                inv_freq.into_iter().map(|freq| freq / factor).collect()
+            }
+            Some(RopeScaling::Llama3(rope_scaling)) => llama3_scaling(inv_freq, rope_scaling),
+            Some(RopeScaling::Unsupported(rope_type)) => {
+                candle::bail!("Unsupported rope_scaling type: {rope_type}")
+            }
+        };
+        let inv_freq_len = inv_freq.len();
+        let inv_freq = Tensor::from_vec(inv_freq, (1, inv_freq_len), dev)?;
+        let t = Tensor::arange(0u32, max_seq_len as u32, dev)?
+            .to_dtype(DType::F32)?
+            .reshape((max_seq_len, 1))?;
+        let freqs = t.matmul(&inv_freq)?;
+        Ok(Self {
+            sin: freqs.sin()?.to_dtype(dtype)?,
+            cos: freqs.cos()?.to_dtype(dtype)?,
+        })
+    }
+
+    fn apply_rotary_emb(&self, q: &Tensor, k: &Tensor) -> Result<(Tensor, Tensor)> {
+        let (_b_sz, _h, seq_len, _n_embd) = q.dims4()?;
+        let cos = self.cos.narrow(0, 0, seq_len)?;
+        let sin = self.sin.narrow(0, 0, seq_len)?;
+        let q_embed = rotary_emb::rope(&q.contiguous()?, &cos, &sin)?;
+        let k_embed = rotary_emb::rope(&k.contiguous()?, &cos, &sin)?;
+        Ok((q_embed, k_embed))
+    }
+
+    fn apply_rotary_emb_with_offsets(
+        &self,
+        q: &Tensor,
+        k: &Tensor,
+        offsets: &[usize],
+    ) -> Result<(Tensor, Tensor)> {
+        let q_embed = rope_with_offsets(q, &self.cos, &self.sin, offsets)?;
+        let k_embed = rope_with_offsets(k, &self.cos, &self.sin, offsets)?;
+        Ok((q_embed, k_embed))
+    }
+}
+
+/// Llama 3.1 frequency scaling, see `_compute_llama3_parameters` in
+/// https://github.com/huggingface/transformers/blob/main/src/transformers/modeling_rope_utils.py
+fn llama3_scaling(inv_freq: Vec<f32>, config: &Llama3RopeConfig) -> Vec<f32> {
+    let old_context_len = config.original_max_position_embeddings as f32;
+    let low_freq_wavelen = old_context_len / config.low_freq_factor;
+    let high_freq_wavelen = old_context_len / config.high_freq_factor;
+    inv_freq
+        .into_iter()
+        .map(|freq| {
+            let wavelen = 2. * std::f32::consts::PI / freq;
+            if wavelen < high_freq_wavelen {
+                freq
+            } else if wavelen > low_freq_wavelen {
+                freq / config.factor
+            } else {
+                let smooth = (old_context_len / wavelen - config.low_freq_factor)
+                    / (config.high_freq_factor - config.low_freq_factor);
+                (1. - smooth) * freq / config.factor + smooth * freq
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
+struct MLP {
+    gate_proj: Linear,
+    up_proj: Linear,
+    down_proj: Linear,
+    act_fn: Activation,
+}
+
+impl MLP {
+    fn load(vb: VarBuilder, config: &LlamaConfig) -> Result<Self> {
+        let hidden_sz = config.hidden_size;
+        let intermediate_sz = config.intermediate_size;
+        let gate_proj = Linear::load(vb.pp("gate_proj"), hidden_sz, intermediate_sz, None)?;
+        let up_proj = Linear::load(vb.pp("up_proj"), hidden_sz, intermediate_sz, None)?;
+        let down_proj = Linear::load(vb.pp("down_proj"), intermediate_sz, hidden_sz, None)?;
+        Ok(Self {
+            gate_proj,
+            up_proj,
+            down_proj,
+            act_fn: config.hidden_act,
+        })
+    }
+}
+
+impl Module for MLP {
+    fn forward(&self, xs: &Tensor) -> Result<Tensor> {
+        let lhs = self.gate_proj.forward(xs)?.apply(&self.act_fn)?;
+        let rhs = self.up_proj.forward(xs)?;
+        self.down_proj.forward(&(lhs * rhs)?)
+    }
+}
+
+#[cfg(feature = "flash-attn")]
+fn flash_attn(
+    q: &Tensor,
+    k: &Tensor,
+    v: &Tensor,
+    softmax_scale: f32,
+    causal: bool,
+) -> Result<Tensor> {
+    candle_flash_attn::flash_attn(q, k, v, softmax_scale, causal)
+}
+
+#[cfg(not(feature = "flash-attn"))]
+fn flash_attn(_: &Tensor, _: &Tensor, _: &Tensor, _: f32, _: bool) -> Result<Tensor> {
+    unimplemented!("compile with '--features flash-attn'")
+}
+
+#[derive(Debug)]
+struct Attention {
+    q_proj: Linear,
+    k_proj: Linear,
+    v_proj: Linear,
+    o_proj: Linear,
+    num_heads: usize,
+    num_kv_heads: usize,
+    num_kv_groups: usize,
+    head_dim: usize,
+    rotary_emb: Arc<RotaryEmbedding>,
+    use_flash_attn: bool,
+}
+
+impl Attention {
+    fn load(
+        vb: VarBuilder,
+        config: &LlamaConfig,
+        rotary_emb: Arc<RotaryEmbedding>,
+    ) -> Result<Self> {
+        let hidden_sz = config.hidden_size;
+        let num_heads = config.num_attention_heads;
+        let num_kv_heads = config.num_key_value_heads();
+        let num_kv_groups = num_heads / num_kv_heads;
+        let head_dim = config.head_dim();
+        let q_proj = Linear::load(vb.pp("q_proj"), hidden_sz, num_heads * head_dim, None)?;
+        let k_proj = Linear::load(vb.pp("k_proj"), hidden_sz, num_kv_heads * head_dim, None)?;
+        let v_proj = Linear::load(vb.pp("v_proj"), hidden_sz, num_kv_heads * head_dim, None)?;
+        let o_proj = Linear::load(vb.pp("o_proj"), num_heads * head_dim, hidden_sz, None)?;
+        Ok(Self {
+            q_proj,
+            k_proj,
+            v_proj,
+            o_proj,
+            num_heads,
+            num_kv_heads,
+            num_kv_groups,
+            head_dim,
+            rotary_emb,
+            use_flash_attn: config.use_flash_attn.unwrap_or(false),
+        })
+    }
+
+    fn project(&self, xs: &Tensor) -> Result<(Tensor, Tensor, Tensor)> {
+        let (b_sz, q_len, _) = xs.dims3()?;
+        let query_states = self
+            .q_proj
+            .forward(xs)?
+            .reshape((b_sz, q_len, self.num_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let key_states = self
+            .k_proj
+            .forward(xs)?
+            .reshape((b_sz, q_len, self.num_kv_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let value_states = self
+            .v_proj
+            .forward(xs)?
+            .reshape((b_sz, q_len, self.num_kv_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        Ok((query_states, key_states, value_states))
+    }
+
+    fn attend(
+        &self,
+        query_states: &Tensor,
+        key_states: Tensor,
+        value_states: Tensor,
+        attention_mask: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let (b_sz, _, q_len, _) = query_states.dims4()?;
+        let key_states = repeat_kv(key_states, self.num_kv_groups)?.contiguous()?;
+        let value_states = repeat_kv(value_states, self.num_kv_groups)?.contiguous()?;
+
+        let scale = 1f64 / f64::sqrt(self.head_dim as f64);
+        let attn_weights = (query_states.matmul(&key_states.transpose(2, 3)?)? * scale)?;
+        let attn_weights = match attention_mask {
+            None => attn_weights,
+            Some(mask) => attn_weights.broadcast_add(mask)?,
+        };
+        let attn_weights = ops::softmax_last_dim(&attn_weights)?;
+        let attn_output = attn_weights
+            .matmul(&value_states)?
+            .transpose(1, 2)?
+            .reshape((b_sz, q_len, self.num_heads * self.head_dim))?;
+        self.o_proj.forward(&attn_output)
+    }
+
+    fn forward(&self, xs: &Tensor, attention_mask: Option<&Tensor>) -> Result<Tensor> {
+        let (b_sz, q_len, _) = xs.dims3()?;
+        let (query_states, key_states, value_states) = self.project(xs)?;
+        let (query_states, key_states) = self
+            .rotary_emb
+            .apply_rotary_emb(&query_states, &key_states)?;
+
+        if self.use_flash_attn {
+            // flash-attn expects (b_sz, seq_len, nheads, head_dim)
+            let key_states = repeat_kv(key_states, self.num_kv_groups)?;
+            let value_states = repeat_kv(value_states, self.num_kv_groups)?;
+            let q = query_states.transpose(1, 2)?;
+            let k = key_states.transpose(1, 2)?;
+            let v = value_states.transpose(1, 2)?;
+            let softmax_scale = 1f32 / (self.head_dim as f32).sqrt();
+            let attn_output = flash_attn(&q, &k, &v, softmax_scale, q_len > 1)?.reshape((
+                b_sz,
+                q_len,
+                self.num_heads * self.head_dim,
+            ))?;
+            self.o_proj.forward(&attn_output)
+        } else {
+            self.attend(&query_states, key_states, value_states, attention_mask)
+        }
+    }
+
+    fn forward_step(
+        &self,
+        xs: &Tensor,
+        caches: &mut [&mut SequenceCache],
+        layer_idx: usize,
+        offsets: &[usize],
+    ) -> Result<Tensor> {
+        let (query_states, key_states, value_states) = self.project(xs)?;
+        let (query_states, key_states) =
+            self.rotary_emb
+                .apply_rotary_emb_with_offsets(&query_states, &key_states, offsets)?;
+        let (key_states, value_states, attention_mask) =
+            append_and_gather(caches, layer_idx, &key_states, &value_states, offsets, None)?;
+        self.attend(
+            &query_states,
+            key_states,
+            value_states,
+            Some(&attention_mask),
+        )
+    }
+}
+
+#[derive(Debug)]
+struct DecoderLayer {
+    self_attn: Attention,
+    mlp: MLP,
+    input_layernorm: RmsNorm,
+    post_attention_layernorm: RmsNorm,
+}
+
+impl DecoderLayer {
+    fn load(
+        vb: VarBuilder,
+        config: &LlamaConfig,
+        rotary_emb: Arc<RotaryEmbedding>,
+    ) -> Result<Self> {
+        let self_attn = Attention::load(vb.pp("self_attn"), config, rotary_emb)?;
+        let mlp = MLP::load(vb.pp("mlp"), config)?;
+        let input_layernorm = RmsNorm::load(
+            vb.pp("input_layernorm"),
+            config.hidden_size,
+            config.rms_norm_eps,
+        )?;
+        let post_attention_layernorm = RmsNorm::load(
+            vb.pp("post_attention_layernorm"),
+            config.hidden_size,
+            config.rms_norm_eps,
+        )?;
+        Ok(Self {
+            self_attn,
+            mlp,
+            input_layernorm,
+            post_attention_layernorm,
+        })
+    }
+
+    fn forward(&self, xs: &Tensor, attention_mask: Option<&Tensor>) -> Result<Tensor> {
+        let residual = xs;
+        let xs = self.input_layernorm.forward(xs)?;
+        let xs = self.self_attn.forward(&xs, attention_mask)?;
+        let xs = (xs + residual)?;
+        let residual = &xs;
+        let xs = xs.apply(&self.post_attention_layernorm)?.apply(&self.mlp)?;
+        residual + xs
+    }
+
+    fn forward_step(
+        &self,
+        xs: &Tensor,
+        caches: &mut [&mut SequenceCache],
+        layer_idx: usize,
+        offsets: &[usize],
+    ) -> Result<Tensor> {
+        let residual = xs;
+        let xs = self.input_layernorm.forward(xs)?;
+        let xs = self
+            .self_attn
+            .forward_step(&xs, caches, layer_idx, offsets)?;
+        let xs = (xs + residual)?;
+        let residual = &xs;
+        let xs = xs.apply(&self.post_attention_layernorm)?.apply(&self.mlp)?;
+        residual + xs
+    }
+}
+
+#[derive(Debug)]
+pub struct LlamaModel {
+    embed_tokens: Embedding,
+    layers: Vec<DecoderLayer>,
+    norm: RmsNorm,
+    device: Device,
+    dtype: DType,
+}
+
+impl LlamaModel {
+    pub fn load(vb: VarBuilder, config: &LlamaConfig) -> Result<Self> {
+        // `LlamaForCausalLM` checkpoints nest the decoder under `model`
+        let vb = if vb.contains_tensor("model.embed_tokens.weight") {
+            vb.pp("model")
+        } else {
+            vb
+        };
+        let embed_tokens = embedding(config.vocab_size, config.hidden_size, vb.pp("embed_tokens"))?;
+        let rotary_emb = Arc::new(RotaryEmbedding::new(config, vb.dtype(), vb.device())?);
+        let vb_l = vb.pp("layers");
+        let layers = (0..config.num_hidden_layers)
+            .map(|index| DecoderLayer::load(vb_l.pp(index), config, rotary_emb.clone()))
+            .collect::<Result<Vec<_>>>()?;
+        let norm = RmsNorm::load(vb.pp("norm"), config.hidden_size, config.rms_norm_eps)?;
+        Ok(Self {
+            embed_tokens,
+            layers,
+            norm,
+            device: vb.device().clone(),
+            dtype: vb.dtype(),
+        })
+    }
+
+    fn prepare_attention_mask(&self, attention_mask: &Tensor, tgt_len: usize) -> Result<Tensor> {
+        let causal: Vec<_> = (0..tgt_len)
+            .flat_map(|i| (0..tgt_len).map(move |j| if i < j { f32::NEG_INFINITY } else { 0. }))
+            .collect();
+        let causal = Tensor::from_slice(&causal, (1, 1, tgt_len, tgt_len), &self.device)?;
+        let (b_size, _) = attention_mask.dims2()?;
+        let padding = attention_mask
+            .to_dtype(DType::F32)?
+            .affine(1e4, -1e4)?
+            .reshape((b_size, 1, 1, tgt_len))?;
+        causal.broadcast_add(&padding)?.to_dtype(self.dtype)
+    }
+
+    fn forward_hidden_states(&self, input_ids: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        let (_b_size, seq_len) = input_ids.dims2()?;
+        let attention_mask = self.prepare_attention_mask(attention_mask, seq_len)?;
+        let mut xs = self.embed_tokens.forward(input_ids)?;
+        for layer in self.layers.iter() {
+            xs = layer.forward(&xs, Some(&attention_mask))?
+        }
+        xs.apply(&self.norm)
+    }
+}
+
+impl Model for LlamaModel {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(false)
+    }
+
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        _token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        self.forward_hidden_states(input_ids, attention_mask)
+    }
+}
+
+#[derive(Debug)]
+pub struct LlamaForCausalLM {
+    model: LlamaModel,
+    lm_head: Linear,
+}
+
+impl LlamaForCausalLM {
+    pub fn load(vb: VarBuilder, config: &LlamaConfig) -> Result<Self> {
+        let model = LlamaModel::load(vb.clone(), config)?;
+        let lm_head = if config.tie_word_embeddings || !vb.contains_tensor("lm_head.weight") {
+            Linear::new(model.embed_tokens.embeddings().clone(), None, None)
+        } else {
+            Linear::load(
+                vb.pp("lm_head"),
+                config.hidden_size,
+                config.vocab_size,
+                None,
+            )?
+        };
+        Ok(Self { model, lm_head })
+    }
+}
+
+impl Model for LlamaForCausalLM {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(false)
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        _token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let xs = self
+            .model
+            .forward_hidden_states(input_ids, attention_mask)?;
+        // The last token of right padded rows is not at the last position
+        let xs = gather_last(&xs, &mask_lens(attention_mask)?)?;
+        self.lm_head.forward(&xs)
+    }
+
+    fn as_causal_lm(&self) -> Option<&dyn CausalLM> {
+        Some(self)
+    }
+}
+
+impl CausalLM for LlamaForCausalLM {
+    fn num_layers(&self) -> usize {
+        self.model.layers.len()
+    }
+
+    fn device(&self) -> &Device {
+        &self.model.device
+    }
+
+    fn forward_step(
+        &self,
+        input_ids: &Tensor,
+        seq_lens: Option<&[usize]>,
+        caches: &mut [&mut SequenceCache],
+    ) -> Result<Tensor> {
+        let offsets = caches.iter().map(|c| c.seq_len()).collect::<Vec<_>>();
+        let mut xs = self.model.embed_tokens.forward(input_ids)?;
+        for (layer_idx, layer) in self.model.layers.iter().enumerate() {
+            xs = layer.forward_step(&xs, caches, layer_idx, &offsets)?
+        }
+        let xs = last_tokens(&xs, seq_lens, caches, &offsets)?.apply(&self.model.norm)?;
+        self.lm_head.forward(&xs)?.squeeze(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(rope_scaling: &str) -> serde_json::Result<LlamaConfig> {
+        serde_json::from_str(&format!(
+            r#"{{
+                "architectures": ["LlamaForCausalLM"],
+                "vocab_size": 8,
+                "hidden_size": 8,
+                "intermediate_size": 16,
+                "num_hidden_layers": 1,
+                "num_attention_heads": 2,
+                "max_position_embeddings": 16,
+                "rms_norm_eps": 1e-5,
+                "rope_scaling": {rope_scaling}
+            }}"#
+        ))
+    }
+
+    #[test]
+    fn test_rope_scaling() -> Result<()> {
+        let linear = config(r#"{"type": "linear", "factor": 4.0}"#).map_err(candle::Error::msg)?;
+        assert_eq!(
+            linear.rope_scaling,
+            Some(RopeScaling::Linear { factor: 4. })
+        );
+        let rope = RotaryEmbedding::new(&linear, DType::F32, &Device::Cpu)?;
+        // Position 4 rotates like position 1 without scaling
+        let unscaled = config("null").map_err(candle::Error::msg)?;
+        let unscaled = RotaryEmbedding::new(&unscaled, DType::F32, &Device::Cpu)?;
+        assert_eq!(
+            rope.cos.get(4)?.to_vec1::<f32>()?,
+            unscaled.cos.get(1)?.to_vec1::<f32>()?
+        );
+
+        // Newer configs have both keys
+        let llama3 = config(
+            r#"{"factor": 8.0, "low_freq_factor": 1.0, "high_freq_factor": 4.0,
+                "original_max_position_embeddings": 8192, "rope_type": "llama3", "type": "llama3"}"#,
+        )
+        .map_err(candle::Error::msg)?;
+        assert!(matches!(llama3.rope_scaling, Some(RopeScaling::Llama3(_))));
+
+        let dynamic =
+            config(r#"{"type": "dynamic", "factor": 2.0}"#).map_err(candle::Error::msg)?;
+        let err = RotaryEmbedding::new(&dynamic, DType::F32, &Device::Cpu).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unsupported rope_scaling type: dynamic"));
+        assert!(config(r#"{"type": "llama3", "factor": 8.0}"#).is_err());
+        assert!(config(r#"{"factor": 8.0}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_llama3_scaling() {
+        // Llama 3.1 8B, the reference values are the `inv_freq` of `transformers`
+        let rope_scaling = Llama3RopeConfig {
+            factor: 8.,
+            low_freq_factor: 1.,
+            high_freq_factor: 4.,
+            original_max_position_embeddings: 8192,
+        };
+        let dim = 128;
+        let inv_freq: Vec<f32> = (0..dim)
+            .step_by(2)
+            .map(|i| 1f32 / 500_000f32.powf(i as f32 / dim as f32))
+            .collect();
+        let scaled = llama3_scaling(inv_freq, &rope_scaling);
+        let expected = [
+            // high frequencies are kept
+            (0, 1.0),
+            (28, 3.211446e-3),
+            // medium frequencies are smoothed
+            (29, 2.166571e-3),
+            (32, 5.248462e-4),
+            (34, 1.785078e-4),
+            // low frequencies are divided by the factor
+            (35, 9.556212e-5),
+            (63, 3.068926e-7),
+        ];
+        for (i, value) in expected {
+            let error = (scaled[i] - value).abs() / value;
+            assert!(
+                error < 1e-5,
+                "inv_freq[{i}] = {}, expected {value}",
+                scaled[i]
+            );
+        }
+    }
+}
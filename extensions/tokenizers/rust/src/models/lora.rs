@@ -0,0 +1,320 @@
+use candle::{DType, Device, Error, Result, Shape, Tensor};
+use candle_nn::var_builder::SimpleBackend;
+use candle_nn::{Init, VarBuilder};
+use regex::Regex;
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const ADAPTER_CONFIG: &str = "adapter_config.json";
+const ADAPTER_WEIGHTS: &str = "adapter_model.safetensors";
+// PEFT prefixes the module paths of the wrapped model. Once stripped, adapter names are the
+// names of the base model checkpoint, e.g. `bert.encoder.layer.0.attention.self.query`, and are
+// matched exactly.
+const PEFT_PREFIX: &str = "base_model.model.";
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TargetModules {
+    Names(Vec<String>),
+    Regex(String),
+}
+
+/// `adapter_config.json` of a PEFT LoRA adapter.
+#[derive(Debug, Deserialize)]
+struct LoraConfig {
+    peft_type: Option<String>,
+    lora_alpha: f64,
+    target_modules: Option<TargetModules>,
+    /// In the order of the file, the first matching pattern applies
+    #[serde(default, deserialize_with = "ordered_entries")]
+    alpha_pattern: Vec<(String, f64)>,
+    #[serde(default)]
+    use_rslora: bool,
+    #[serde(default)]
+    fan_in_fan_out: bool,
+}
+
+impl LoraConfig {
+    /// Mirrors `check_target_module_exists` in PEFT.
+    fn is_target(&self, module: &str) -> Result<bool> {
+        match &self.target_modules {
+            None => Ok(true),
+            Some(TargetModules::Names(names)) => Ok(names
+                .iter()
+                .any(|name| module == name || module.ends_with(&format!(".{name}")))),
+            Some(TargetModules::Regex(regex)) => Ok(Regex::new(&format!("^(?:{regex})$"))
+                .map_err(Error::msg)?
+                .is_match(module)),
+        }
+    }
+
+    fn alpha(&self, module: &str) -> Result<f64> {
+        for (pattern, alpha) in self.alpha_pattern.iter() {
+            let regex = Regex::new(&format!(r"^(.*\.)?{pattern}$")).map_err(Error::msg)?;
+            if regex.is_match(module) {
+                return Ok(*alpha);
+            }
+        }
+        Ok(self.lora_alpha)
+    }
+}
+
+/// Reads a JSON object as its entries, in the order of the file.
+fn ordered_entries<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<(String, f64)>, D::Error> {
+    struct EntriesVisitor;
+
+    impl<'de> Visitor<'de> for EntriesVisitor {
+        type Value = Vec<(String, f64)>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map of module patterns to alphas")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> std::result::Result<Self::Value, A::Error> {
+            let mut entries = Vec::new();
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+            Ok(entries)
+        }
+    }
+
+    deserializer.deserialize_map(EntriesVisitor)
+}
+
+/// Weights of the base model with one or more LoRA adapters merged in.
+struct LoraBackend {
+    base: VarBuilder<'static>,
+    /// `scale * B @ A` of every adapted module, `(out_dim, in_dim)`
+    deltas: HashMap<String, Vec<Tensor>>,
+    /// Fully trained modules, `modules_to_save` in PEFT
+    overrides: HashMap<String, Tensor>,
+    /// Keys of `deltas` and `overrides` loaded by the model
+    used: Arc<Mutex<HashSet<String>>>,
+}
+
+impl LoraBackend {
+    fn mark_used(&self, key: &str) {
+        self.used.lock().unwrap().insert(key.to_string());
+    }
+}
+
+/// The adapter modules merged by `merge_lora`, to check that the model loaded all of them.
+pub(crate) struct LoraModules {
+    names: Vec<String>,
+    used: Arc<Mutex<HashSet<String>>>,
+}
+
+impl LoraModules {
+    /// Fails with the adapter modules the model did not load, e.g. an adapter trained for
+    /// another model.
+    pub(crate) fn check_all_used(&self) -> Result<()> {
+        let used = self.used.lock().unwrap();
+        let unused: Vec<&str> = self
+            .names
+            .iter()
+            .filter(|name| !used.contains(*name))
+            .map(String::as_str)
+            .collect();
+        if !unused.is_empty() {
+            candle::bail!(
+                "LoRA adapter modules not found in the model: {}",
+                unused.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+impl SimpleBackend for LoraBackend {
+    fn get(&self, s: Shape, name: &str, h: Init, dtype: DType, dev: &Device) -> Result<Tensor> {
+        if let Some(tensor) = self.overrides.get(name) {
+            self.mark_used(name);
+            if tensor.shape() != &s {
+                candle::bail!(
+                    "Adapter tensor `{name}` has shape {:?}, expected {s:?}",
+                    tensor.shape()
+                );
+            }
+            return tensor.to_device(dev)?.to_dtype(dtype);
+        }
+        let weight = self.base.get_with_hints_dtype(s, name, h, dtype)?;
+        let module = name.strip_suffix(".weight");
+        match module.and_then(|module| self.deltas.get(module)) {
+            Some(deltas) => {
+                self.mark_used(module.unwrap());
+                let mut merged = weight.to_dtype(DType::F32)?;
+                for delta in deltas {
+                    merged = (merged + delta.to_device(dev)?)?;
+                }
+                merged.to_dtype(dtype)
+            }
+            None => Ok(weight),
+        }
+    }
+
+    fn contains_tensor(&self, name: &str) -> bool {
+        self.base.contains_tensor(name) || self.overrides.contains_key(name)
+    }
+}
+
+/// Merges the PEFT LoRA adapters in `adapter_paths` into the weights of `base`, in order.
+///
+/// Adapter tensors are merged as the model loads them, call `LoraModules::check_all_used` once
+/// it is loaded.
+pub(crate) fn merge_lora(
+    base: VarBuilder<'static>,
+    adapter_paths: &[impl AsRef<Path>],
+) -> Result<(VarBuilder<'static>, LoraModules)> {
+    let dtype = base.dtype();
+    let device = base.device().clone();
+    let mut deltas: HashMap<String, Vec<Tensor>> = HashMap::new();
+    let mut overrides = HashMap::new();
+    for adapter_path in adapter_paths {
+        let adapter_path = adapter_path.as_ref();
+        let config_str = std::fs::read_to_string(adapter_path.join(ADAPTER_CONFIG))?;
+        let config: LoraConfig = serde_json::from_str(&config_str).map_err(Error::msg)?;
+        if let Some(peft_type) = &config.peft_type {
+            if peft_type != "LORA" {
+                candle::bail!("Unsupported adapter type: {peft_type}");
+            }
+        }
+
+        let mut tensors = candle::safetensors::load(adapter_path.join(ADAPTER_WEIGHTS), &device)?;
+        let names: Vec<String> = tensors.keys().cloned().collect();
+        for name in names {
+            let key = name.strip_prefix(PEFT_PREFIX).unwrap_or(&name);
+            let Some(module) = key.strip_suffix(".lora_A.weight") else {
+                // `lora_B` is merged together with its `lora_A`
+                if key.ends_with(".lora_B.weight") {
+                    continue;
+                }
+                if key.contains(".lora_") {
+                    candle::bail!("Unsupported adapter tensor `{name}`");
+                }
+                overrides.insert(key.to_string(), tensors.remove(&name).unwrap());
+                continue;
+            };
+            if !config.is_target(module)? {
+                continue;
+            }
+            let b_name = name.replace(".lora_A.weight", ".lora_B.weight");
+            let lora_a = tensors.remove(&name).unwrap().to_dtype(DType::F32)?;
+            let lora_b = match tensors.remove(&b_name) {
+                Some(lora_b) => lora_b.to_dtype(DType::F32)?,
+                None => candle::bail!("Missing adapter tensor `{b_name}`"),
+            };
+            // The rank is read from the weights, they honor `rank_pattern`
+            let rank = lora_a.dim(0)? as f64;
+            let alpha = config.alpha(module)?;
+            let scale = if config.use_rslora {
+                alpha / rank.sqrt()
+            } else {
+                alpha / rank
+            };
+            let delta = (lora_b.matmul(&lora_a)? * scale)?;
+            let delta = if config.fan_in_fan_out {
+                delta.t()?
+            } else {
+                delta
+            };
+            deltas.entry(module.to_string()).or_default().push(delta);
+        }
+        tracing::info!("Merged LoRA adapter {}", adapter_path.display());
+    }
+
+    let mut names: Vec<String> = deltas.keys().chain(overrides.keys()).cloned().collect();
+    names.sort();
+    let used = Arc::new(Mutex::new(HashSet::new()));
+    let modules = LoraModules {
+        names,
+        used: used.clone(),
+    };
+    let backend = LoraBackend {
+        base,
+        deltas,
+        overrides,
+        used,
+    };
+    let vb = VarBuilder::from_backend(Box::new(backend), dtype, device);
+    Ok((vb, modules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_adapter(dir: &Path, modules: &[&str]) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(
+            dir.join(ADAPTER_CONFIG),
+            r#"{"peft_type":"LORA","lora_alpha":2}"#,
+        )?;
+        let mut tensors = HashMap::new();
+        for module in modules {
+            let lora_a = Tensor::ones((1, 2), DType::F32, &Device::Cpu)?;
+            let lora_b = Tensor::ones((2, 1), DType::F32, &Device::Cpu)?;
+            tensors.insert(format!("{PEFT_PREFIX}{module}.lora_A.weight"), lora_a);
+            tensors.insert(format!("{PEFT_PREFIX}{module}.lora_B.weight"), lora_b);
+        }
+        candle::safetensors::save(&tensors, dir.join(ADAPTER_WEIGHTS))
+    }
+
+    #[test]
+    fn test_merge_lora() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("djl-lora-{}", std::process::id()));
+        write_adapter(&dir, &["bert.query", "bert.value", "query"])?;
+        let base = HashMap::from([
+            (
+                "bert.query.weight".to_string(),
+                Tensor::zeros((2, 2), DType::F32, &Device::Cpu)?,
+            ),
+            (
+                "bert.value.weight".to_string(),
+                Tensor::zeros((2, 2), DType::F32, &Device::Cpu)?,
+            ),
+        ]);
+        let base = VarBuilder::from_tensors(base, DType::F32, &Device::Cpu);
+        let (vb, modules) = merge_lora(base, &[&dir])?;
+        std::fs::remove_dir_all(&dir)?;
+
+        // alpha / rank * B @ A
+        let query = vb.get((2, 2), "bert.query.weight")?;
+        assert_eq!(query.to_vec2::<f32>()?, [[2., 2.], [2., 2.]]);
+        let err = modules.check_all_used().unwrap_err().to_string();
+        assert!(err.ends_with("bert.value, query"), "{err}");
+
+        // Only exact names match, `query` is not merged into `bert.query`
+        vb.get((2, 2), "bert.value.weight")?;
+        let err = modules.check_all_used().unwrap_err().to_string();
+        assert!(err.ends_with(": query"), "{err}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_alpha_pattern_order() -> Result<()> {
+        let module = "bert.encoder.layer.0.attention.self.query";
+        let config: LoraConfig = serde_json::from_str(
+            r#"{"lora_alpha":2,"alpha_pattern":{"query":8,"attention.self.query":4}}"#,
+        )
+        .map_err(Error::msg)?;
+        assert_eq!(config.alpha(module)?, 8.);
+        assert_eq!(config.alpha("bert.pooler.dense")?, 2.);
+
+        let config: LoraConfig = serde_json::from_str(
+            r#"{"lora_alpha":2,"alpha_pattern":{"attention.self.query":4,"query":8}}"#,
+        )
+        .map_err(Error::msg)?;
+        assert_eq!(config.alpha(module)?, 4.);
+        Ok(())
+    }
+}
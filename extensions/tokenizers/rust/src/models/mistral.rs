@@ -1,6 +1,7 @@
-use crate::layers::{Linear, RmsNorm};
-use crate::models::Model;
-use crate::utils::repeat_kv;
+use crate::layers::{append_and_gather, Linear, RmsNorm, SequenceCache};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::{last_tokens, CausalLM, Model};
+use crate::utils::{repeat_kv, rope_with_offsets};
 use candle::{DType, Device, Module, Result, Tensor};
 use candle_nn::{embedding, ops, rotary_emb, Activation, Embedding, VarBuilder};
 use std::sync::Arc;
@@ -79,6 +80,17 @@ impl RotaryEmbedding {
         let k_embed = rotary_emb::rope(k, &cos, &sin)?;
         Ok((q_embed, k_embed))
     }
+
+    fn apply_rotary_emb_with_offsets(
+        &self,
+        q: &Tensor,
+        k: &Tensor,
+        offsets: &[usize],
+    ) -> Result<(Tensor, Tensor)> {
+        let q_embed = rope_with_offsets(q, &self.cos, &self.sin, offsets)?;
+        let k_embed = rope_with_offsets(k, &self.cos, &self.sin, offsets)?;
+        Ok((q_embed, k_embed))
+    }
 }
 
 #[derive(Debug)]
@@ -222,6 +234,56 @@ impl Attention {
         let attn_output = self.o_proj.forward(&attn_output)?;
         Ok(attn_output)
     }
+
+    fn forward_step(
+        &self,
+        hidden_states: &Tensor,
+        caches: &mut [&mut SequenceCache],
+        layer_idx: usize,
+        offsets: &[usize],
+        sliding_window: Option<usize>,
+    ) -> Result<Tensor> {
+        let (b_sz, q_len, _) = hidden_states.dims3()?;
+
+        let query_states = self.q_proj.forward(hidden_states)?;
+        let key_states = self.k_proj.forward(hidden_states)?;
+        let value_states = self.v_proj.forward(hidden_states)?;
+
+        let query_states = query_states
+            .reshape((b_sz, q_len, self.num_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let key_states = key_states
+            .reshape((b_sz, q_len, self.num_kv_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let value_states = value_states
+            .reshape((b_sz, q_len, self.num_kv_heads, self.head_dim))?
+            .transpose(1, 2)?;
+
+        let (query_states, key_states) =
+            self.rotary_emb
+                .apply_rotary_emb_with_offsets(&query_states, &key_states, offsets)?;
+        let (key_states, value_states, mask) = append_and_gather(
+            caches,
+            layer_idx,
+            &key_states,
+            &value_states,
+            offsets,
+            sliding_window,
+        )?;
+
+        let key_states = repeat_kv(key_states, self.num_kv_groups)?.contiguous()?;
+        let value_states = repeat_kv(value_states, self.num_kv_groups)?.contiguous()?;
+
+        let scale = 1f64 / f64::sqrt(self.head_dim as f64);
+        let attn_weights = (query_states.matmul(&key_states.transpose(2, 3)?)? * scale)?;
+        let attn_weights = attn_weights.broadcast_add(&mask)?;
+        let attn_weights = ops::softmax_last_dim(&attn_weights)?;
+        let attn_output = attn_weights.matmul(&value_states)?;
+        let attn_output = attn_output
+            .transpose(1, 2)?
+            .reshape((b_sz, q_len, self.hidden_size))?;
+        self.o_proj.forward(&attn_output)
+    }
 }
 
 #[derive(Debug)]
@@ -261,6 +323,23 @@ impl DecoderLayer {
         let xs = xs.apply(&self.post_attention_layernorm)?.apply(&self.mlp)?;
         residual + xs
     }
+
+    fn forward_step(
+        &self,
+        xs: &Tensor,
+        caches: &mut [&mut SequenceCache],
+        layer_idx: usize,
+        offsets: &[usize],
+        sliding_window: Option<usize>,
+    ) -> Result<Tensor> {
+        let residual = xs;
+        let xs = self.input_layernorm.forward(xs)?;
+        let xs = self
+            .self_attn
+            .forward_step(&xs, caches, layer_idx, offsets, sliding_window)?;
+        let xs = xs.apply(&self.post_attention_layernorm)?.apply(&self.mlp)?;
+        residual + xs
+    }
 }
 
 #[derive(Debug)]
@@ -268,6 +347,7 @@ pub struct MistralModel {
     embed_tokens: Embedding,
     layers: Vec<DecoderLayer>,
     norm: RmsNorm,
+    lm_head: Option<Linear>,
     sliding_window: Option<usize>,
     #[allow(unused)]
     pub device: Device,
@@ -276,15 +356,36 @@ pub struct MistralModel {
 
 impl MistralModel {
     pub fn load(vb: VarBuilder, config: &MistralConfig) -> Result<Self> {
-        let embed_tokens = embedding(config.vocab_size, config.hidden_size, vb.pp("embed_tokens"))?; // TODO
+        // `MistralForCausalLM` checkpoints nest the decoder under `model`
+        let vb_m = if vb.contains_tensor("model.embed_tokens.weight") {
+            vb.pp("model")
+        } else {
+            vb.clone()
+        };
+        let embed_tokens = embedding(
+            config.vocab_size,
+            config.hidden_size,
+            vb_m.pp("embed_tokens"),
+        )?;
         let layers = (0..config.num_hidden_layers)
-            .map(|index| DecoderLayer::load(vb.pp(&format!("layers.{index}")), config))
+            .map(|index| DecoderLayer::load(vb_m.pp(&format!("layers.{index}")), config))
             .collect::<Result<Vec<_>>>()?;
-        let norm = RmsNorm::load(vb.pp("norm"), config.hidden_size, config.rms_norm_eps)?;
+        let norm = RmsNorm::load(vb_m.pp("norm"), config.hidden_size, config.rms_norm_eps)?;
+        let lm_head = if vb.contains_tensor("lm_head.weight") {
+            Some(Linear::load(
+                vb.pp("lm_head"),
+                config.hidden_size,
+                config.vocab_size,
+                None,
+            )?)
+        } else {
+            None
+        };
         Ok(Self {
             embed_tokens,
             layers,
             norm,
+            lm_head,
             sliding_window: config.sliding_window,
             device: vb.device().clone(),
             dtype: vb.dtype(),
@@ -310,8 +411,8 @@ impl MistralModel {
 }
 
 impl Model for MistralModel {
-    fn get_input_names(&self) -> Vec<String> {
-        return vec!["input_ids".to_string(), "attention_mask".to_string()];
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(false)
     }
 
     fn forward(
@@ -335,4 +436,38 @@ impl Model for MistralModel {
         let xs = self.norm.forward(&xs)?;
         Ok(xs)
     }
+
+    fn as_causal_lm(&self) -> Option<&dyn CausalLM> {
+        self.lm_head.as_ref().map(|_| self as &dyn CausalLM)
+    }
+}
+
+impl CausalLM for MistralModel {
+    fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn device(&self) -> &Device {
+        &self.device
+    }
+
+    fn forward_step(
+        &self,
+        input_ids: &Tensor,
+        seq_lens: Option<&[usize]>,
+        caches: &mut [&mut SequenceCache],
+    ) -> Result<Tensor> {
+        let lm_head = match &self.lm_head {
+            Some(lm_head) => lm_head,
+            None => candle::bail!("`lm_head` is required for generation"),
+        };
+        let offsets = caches.iter().map(|c| c.seq_len()).collect::<Vec<_>>();
+        let mut xs = self.embed_tokens.forward(input_ids)?;
+        for (layer_idx, layer) in self.layers.iter().enumerate() {
+            xs = layer.forward_step(&xs, caches, layer_idx, &offsets, self.sliding_window)?
+        }
+        let xs = last_tokens(&xs, seq_lens, caches, &offsets)?;
+        let xs = self.norm.forward(&xs)?;
+        lm_head.forward(&xs)?.squeeze(1)
+    }
 }
@@ -2,30 +2,41 @@ mod bert;
 mod camembert;
 mod distilbert;
 mod gemma2;
+mod gguf;
 mod gte;
+mod info;
+mod inputs;
+mod jina_bert;
+mod llama;
+mod lora;
 mod mistral;
+mod modernbert;
+mod nomic_bert;
 mod qwen2;
+mod registry;
+mod reranker;
 mod roberta;
+mod weights;
 mod xlm_roberta;
 
+use crate::layers::{with_quantization, SequenceCache};
 use crate::ndarray::{as_data_type, as_device};
 use crate::{cast_handle, drop_handle, to_handle, to_string_array};
-use bert::{BertConfig, BertForSequenceClassification, BertModel};
-use camembert::{CamembertConfig, CamembertModel};
-use candle::{DType, Device, Error, Result, Tensor};
-use candle_nn::VarBuilder;
-use distilbert::{DistilBertConfig, DistilBertModel};
-use gemma2::{Gemma2Config, Gemma2Model};
-use gte::{GTEConfig, GTEModel};
-use jni::objects::{JLongArray, JObject, JString, ReleaseMode};
-use jni::sys::{jint, jlong, jobjectArray};
+use candle::quantized::GgmlDType;
+use candle::{DType, Device, IndexOp, Result, Tensor};
+use gguf::GgufForCausalLM;
+use info::ModelInfo;
+pub(crate) use inputs::InputSpec;
+use inputs::ModelInputs;
+use jni::objects::{JLongArray, JObject, JObjectArray, JString, ReleaseMode};
+use jni::sys::{jboolean, jint, jlong, jobjectArray, jsize, jstring, JNI_TRUE};
 use jni::JNIEnv;
-use mistral::{MistralConfig, MistralModel};
-use qwen2::{Qwen2Config, Qwen2Model};
-use roberta::{RobertaConfig, RobertaForSequenceClassification, RobertaModel};
-use serde::Deserialize;
-use std::path::PathBuf;
-use xlm_roberta::{XLMRobertaConfig, XLMRobertaForSequenceClassification, XLMRobertaModel};
+use lora::merge_lora;
+use registry::ModelRegistry;
+use reranker::load_reranker;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use weights::load_weights;
 
 #[derive(Debug, PartialEq, Clone)]
 #[allow(dead_code, unused)]
@@ -36,23 +47,25 @@ pub enum Pool {
     LastToken,
 }
 
-#[derive(Deserialize)]
-#[serde(tag = "model_type", rename_all = "kebab-case")]
-enum Config {
-    Bert(BertConfig),
-    Camembert(CamembertConfig),
-    Roberta(RobertaConfig),
-    XlmRoberta(XLMRobertaConfig),
-    Distilbert(DistilBertConfig),
-    Mistral(MistralConfig),
-    Qwen2(Qwen2Config),
-    #[serde(rename = "new")]
-    GTE(GTEConfig),
-    Gemma2(Gemma2Config),
-}
+/// Named model outputs, in the order they were requested.
+pub(crate) type ModelOutputs = Vec<(String, Tensor)>;
+
+pub(crate) trait Model: Send + Sync {
+    /// Validation rules and defaults of the inputs accepted by `forward_inputs`.
+    fn get_input_spec(&self) -> Vec<InputSpec>;
+
+    fn get_input_names(&self) -> Vec<String> {
+        self.get_input_spec()
+            .into_iter()
+            .map(|spec| spec.name)
+            .collect()
+    }
 
-pub(crate) trait Model {
-    fn get_input_names(&self) -> Vec<String>;
+    /// Names of the outputs `forward_outputs` can compute, the first one is returned by
+    /// `forward`.
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["last_hidden_state".to_string()]
+    }
 
     fn forward(
         &self,
@@ -62,111 +75,253 @@ pub(crate) trait Model {
     ) -> Result<Tensor> {
         candle::bail!("`forward` is not implemented for this model");
     }
+
+    /// Computes only the `requested` outputs, or the model's default outputs if `requested` is
+    /// empty.
+    fn forward_outputs(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+        requested: &[String],
+    ) -> Result<ModelOutputs> {
+        let name = self.get_output_names().swap_remove(0);
+        if requested.iter().any(|r| *r != name) {
+            candle::bail!("Unsupported outputs {requested:?}, available outputs: [{name}]");
+        }
+        let output = self.forward(input_ids, attention_mask, token_type_ids)?;
+        Ok(vec![(name, output); requested.len().max(1)])
+    }
+
+    /// Runs the model on named inputs, see `forward_outputs`.
+    fn forward_inputs(&self, inputs: &ModelInputs, requested: &[String]) -> Result<ModelOutputs> {
+        self.forward_outputs(
+            inputs.get("input_ids")?,
+            inputs.get("attention_mask")?,
+            inputs.get_opt("token_type_ids"),
+            requested,
+        )
+    }
+
+    fn as_causal_lm(&self) -> Option<&dyn CausalLM> {
+        None
+    }
 }
 
-fn load_model(model_path: String, dtype: DType, device: Device) -> Result<Box<dyn Model>> {
-    let model_path = PathBuf::from(model_path);
+/// Output names of the `*ForQuestionAnswering` models.
+pub(crate) fn span_output_names() -> Vec<String> {
+    vec![
+        "logits".to_string(),
+        "start_logits".to_string(),
+        "end_logits".to_string(),
+    ]
+}
 
-    // Load config
-    let config: String = std::fs::read_to_string(model_path.join("config.json"))?;
-    let config: Config = serde_json::from_str(&config).map_err(Error::msg)?;
+/// Splits `(batch, seq_len, 2)` span logits into the requested question answering outputs,
+/// `start_logits` and `end_logits` by default.
+pub(crate) fn span_outputs(logits: Tensor, requested: &[String]) -> Result<ModelOutputs> {
+    let default = ["start_logits".to_string(), "end_logits".to_string()];
+    let requested = if requested.is_empty() {
+        &default[..]
+    } else {
+        requested
+    };
+    requested
+        .iter()
+        .map(|name| {
+            let output = match name.as_str() {
+                "logits" => logits.clone(),
+                "start_logits" => logits.i((.., .., 0))?,
+                "end_logits" => logits.i((.., .., 1))?,
+                _ => candle::bail!("Unsupported output `{name}`"),
+            };
+            Ok((name.clone(), output))
+        })
+        .collect()
+}
 
-    // Load safetensors
-    let safetensors_paths: Vec<PathBuf> = std::fs::read_dir(model_path)?
-        .filter_map(|entry| {
-            let path = entry.ok()?.path();
-            if path.extension()?.to_str()? == "safetensors" {
-                Some(path)
-            } else {
-                None
-            }
+/// Decoder models that can generate tokens incrementally against a key/value cache.
+pub(crate) trait CausalLM {
+    fn num_layers(&self) -> usize;
+
+    fn device(&self) -> &Device;
+
+    /// Runs one forward step for a batch of sequences.
+    ///
+    /// Row `i` of `input_ids` (shape `(batch, seq_len)`) extends the sequence cached in
+    /// `caches[i]` with its first `seq_lens[i]` tokens, or all of them if `seq_lens` is `None`.
+    /// The rest of the row is right padding, dropped from the cache. Returns the logits of the
+    /// last token of each row, `(batch, vocab_size)`.
+    fn forward_step(
+        &self,
+        input_ids: &Tensor,
+        seq_lens: Option<&[usize]>,
+        caches: &mut [&mut SequenceCache],
+    ) -> Result<Tensor>;
+}
+
+/// Selects the hidden states of the last token of every row of a `forward_step`,
+/// `(batch, 1, hidden_size)`, and drops the right padding of the rows from `caches`.
+pub(crate) fn last_tokens(
+    xs: &Tensor,
+    seq_lens: Option<&[usize]>,
+    caches: &mut [&mut SequenceCache],
+    offsets: &[usize],
+) -> Result<Tensor> {
+    let Some(seq_lens) = seq_lens else {
+        return xs.narrow(1, xs.dim(1)? - 1, 1);
+    };
+    let xs = gather_last(xs, seq_lens)?;
+    for ((cache, offset), len) in caches.iter_mut().zip(offsets).zip(seq_lens) {
+        cache.truncate(offset + len);
+    }
+    Ok(xs)
+}
+
+/// The number of positions of every row of `attention_mask` up to its last token, left padded
+/// rows end at the last position.
+pub(crate) fn mask_lens(attention_mask: &Tensor) -> Result<Vec<usize>> {
+    let mask = attention_mask.to_dtype(DType::U32)?.to_vec2::<u32>()?;
+    let lens = mask
+        .iter()
+        .map(|row| {
+            row.iter()
+                .rposition(|&m| m != 0)
+                .map_or(row.len(), |i| i + 1)
         })
         .collect();
+    Ok(lens)
+}
 
-    let vb = unsafe { VarBuilder::from_mmaped_safetensors(&safetensors_paths, dtype, &device)? };
+/// Selects position `seq_lens[i] - 1` of every row `i` of `xs`, `(batch, 1, hidden_size)`.
+pub(crate) fn gather_last(xs: &Tensor, seq_lens: &[usize]) -> Result<Tensor> {
+    let (b_size, seq_len, _) = xs.dims3()?;
+    if seq_lens.len() != b_size || seq_lens.iter().any(|&len| len == 0 || len > seq_len) {
+        candle::bail!("Invalid sequence lengths {seq_lens:?} of a ({b_size}, {seq_len}) batch");
+    }
+    let rows = seq_lens
+        .iter()
+        .enumerate()
+        .map(|(i, &len)| xs.narrow(0, i, 1)?.narrow(1, len - 1, 1))
+        .collect::<Result<Vec<_>>>()?;
+    Tensor::cat(&rows, 0)
+}
 
-    let use_flash_attn = cfg!(feature = "cuda")
-        && cfg!(feature = "flash-attn")
-        && dtype == DType::F16
-        && std::env::var("USE_FLASH_ATTENTION")
-            .ok()
-            .map_or(true, |v| v.parse().unwrap_or(true));
+/// The object behind a model handle.
+pub(crate) struct LoadedModel {
+    pub(crate) model: Arc<dyn Model>,
+    info: ModelInfo,
+}
 
-    let model: Result<Box<dyn Model>> = match (config, &device) {
-        #[cfg(not(feature = "cuda"))]
-        (_, Device::Cuda(_)) => panic!("`cuda` feature is not enabled"),
-        (Config::Bert(mut config), _) => {
-            tracing::info!("Starting Bert model on {:?}", device);
-            config.use_flash_attn = Some(use_flash_attn);
-            match config.architectures.first() {
-                Some(arch) => match arch.as_str() {
-                    "BertForSequenceClassification" => {
-                        Ok(Box::new(BertForSequenceClassification::load(vb, &config)?))
-                    }
-                    _ => Ok(Box::new(BertModel::load(vb, &config)?)),
-                },
-                None => Ok(Box::new(BertModel::load(vb, &config)?)),
-            }
-        }
-        (Config::Camembert(mut config), _) => {
-            tracing::info!("Starting Camembert model on {:?}", device);
-            config.use_flash_attn = Some(use_flash_attn);
-            Ok(Box::new(CamembertModel::load(vb, &config)?))
-        }
-        (Config::Roberta(mut config), _) => {
-            tracing::info!("Starting Roberta model on {:?}", device);
-            config.use_flash_attn = Some(use_flash_attn);
-            match config.architectures.first() {
-                Some(arch) => match arch.as_str() {
-                    "RobertaForSequenceClassification" => Ok(Box::new(
-                        RobertaForSequenceClassification::load(vb, &config)?,
-                    )),
-                    _ => Ok(Box::new(RobertaModel::load(vb, &config)?)),
-                },
-                None => Ok(Box::new(RobertaModel::load(vb, &config)?)),
+/// Loads the model in `model_path`, a llama.cpp GGUF file or a directory with either a GGUF file
+/// or a `config.json` with safetensors or PyTorch weights. The `Linear` weights of the latter are
+/// quantized to `quantization` if set, after merging the PEFT LoRA adapters in `adapter_paths`.
+/// In `reranker` mode the model returns one relevance score per query/document pair instead of
+/// its raw outputs.
+fn load_model(
+    model_path: String,
+    dtype: DType,
+    device: Device,
+    reranker: bool,
+    quantization: Option<GgmlDType>,
+    adapter_paths: Vec<String>,
+) -> Result<LoadedModel> {
+    let mut model_path = PathBuf::from(model_path);
+
+    let (model, config_str) = match find_gguf(&model_path)? {
+        Some(gguf_path) => {
+            if !adapter_paths.is_empty() {
+                candle::bail!("LoRA adapters are not supported for GGUF models");
             }
-        }
-        (Config::XlmRoberta(mut config), _) => {
-            tracing::info!("Starting XlmRoberta model on {:?}", device);
-            config.use_flash_attn = Some(use_flash_attn);
-            match config.architectures.first() {
-                Some(arch) => match arch.as_str() {
-                    "XLMRobertaForSequenceClassification" => Ok(Box::new(
-                        XLMRobertaForSequenceClassification::load(vb, &config)?,
-                    )),
-                    _ => Ok(Box::new(XLMRobertaModel::load(vb, &config)?)),
-                },
-                None => Ok(Box::new(XLMRobertaModel::load(vb, &config)?)),
+            if quantization.is_some() {
+                candle::bail!("GGUF models are already quantized, remove the quantization option");
             }
+            tracing::info!("Starting GGUF model on {:?}", device);
+            let (model, config_str) = GgufForCausalLM::load(&gguf_path, dtype, &device)?;
+            (Box::new(model) as Box<dyn Model>, config_str)
         }
-        (Config::Distilbert(mut config), _) => {
-            tracing::info!("Starting DistilBert model on {:?}", device);
-            config.use_flash_attn = Some(use_flash_attn);
-            Ok(Box::new(DistilBertModel::load(vb, &config)?))
-        }
-        (Config::Mistral(mut config), _) => {
-            tracing::info!("Starting Mistral model on {:?}", device);
-            config.use_flash_attn = Some(use_flash_attn);
-            Ok(Box::new(MistralModel::load(vb, &config)?))
-        }
-        (Config::Qwen2(mut config), _) => {
-            tracing::info!("Starting Qwen2 model on {:?}", device);
-            config.use_flash_attn = Some(use_flash_attn);
-            Ok(Box::new(Qwen2Model::load(vb, &config)?))
-        }
-        (Config::GTE(mut config), _) => {
-            tracing::info!("Starting GTE model on {:?}", device);
-            config.use_flash_attn = Some(use_flash_attn);
-            Ok(Box::new(GTEModel::load(vb, &config)?))
-        }
-        (Config::Gemma2(mut config), _) => {
-            tracing::info!("Starting Gemma2 model on {:?}", device);
-            config.use_flash_attn = Some(use_flash_attn);
-            Ok(Box::new(Gemma2Model::load(vb, &config)?))
+        None => {
+            let config_str = std::fs::read_to_string(model_path.join("config.json"))?;
+            let model = with_quantization(quantization, || {
+                load_transformers_model(&model_path, &config_str, dtype, &device, &adapter_paths)
+            })?;
+            (model, config_str)
         }
     };
+    if model_path.is_file() {
+        // The tokenizer of a GGUF file lives next to it
+        model_path.pop();
+    }
+
+    let model = if reranker {
+        load_reranker(&model_path, &config_str, model)?
+    } else {
+        model
+    };
+    let info = ModelInfo::new(&config_str, model.as_ref(), reranker, dtype, &device)?;
+    Ok(LoadedModel {
+        model: Arc::from(model),
+        info,
+    })
+}
 
-    model
+/// Returns `model_path` if it is a GGUF file, or the only GGUF file in the `model_path` directory.
+fn find_gguf(model_path: &Path) -> Result<Option<PathBuf>> {
+    let is_gguf = |path: &Path| path.extension().is_some_and(|ext| ext == "gguf");
+    if model_path.is_file() {
+        return Ok(is_gguf(model_path).then(|| model_path.to_path_buf()));
+    }
+    let mut gguf_paths: Vec<PathBuf> = std::fs::read_dir(model_path)?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| is_gguf(path))
+        .collect();
+    match gguf_paths.len() {
+        0 | 1 => Ok(gguf_paths.pop()),
+        _ => candle::bail!("Found more than one GGUF file in {}", model_path.display()),
+    }
+}
+
+/// The quantization type of the `quantization` model option.
+fn as_quantization(quantization: &str) -> Result<GgmlDType> {
+    match quantization {
+        "int8" => Ok(GgmlDType::Q8_0),
+        _ => candle::bail!("Unsupported quantization: {quantization}"),
+    }
+}
+
+fn load_transformers_model(
+    model_path: &Path,
+    config_str: &str,
+    dtype: DType,
+    device: &Device,
+    adapter_paths: &[String],
+) -> Result<Box<dyn Model>> {
+    let vb = load_weights(model_path, dtype, device)?;
+    let (vb, lora_modules) = if adapter_paths.is_empty() {
+        (vb, None)
+    } else {
+        let (vb, lora_modules) = merge_lora(vb, adapter_paths)?;
+        (vb, Some(lora_modules))
+    };
+
+    let use_flash_attn = cfg!(feature = "cuda")
+        && cfg!(feature = "flash-attn")
+        && dtype == DType::F16
+        && std::env::var("USE_FLASH_ATTENTION")
+            .ok()
+            .map_or(true, |v| v.parse().unwrap_or(true));
+
+    #[cfg(not(feature = "cuda"))]
+    if device.is_cuda() {
+        panic!("`cuda` feature is not enabled");
+    }
+    let (name, loader) = ModelRegistry::get()?.loader(config_str)?;
+    tracing::info!("Starting {name} model on {:?}", device);
+    let model = loader(vb, config_str, use_flash_attn)?;
+    if let Some(lora_modules) = lora_modules {
+        lora_modules.check_all_used()?;
+    }
+    Ok(model)
 }
 
 #[no_mangle]
@@ -177,15 +332,35 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_loadModel<'local>(
     dtype: jint,
     device_type: JString,
     device_id: jint,
+    reranker: jboolean,
+    quantization: JString,
+    adapter_paths: JObjectArray,
 ) -> jlong {
     let model = || {
         let model_path: String = env
             .get_string(&model_path)
             .expect("Couldn't get java string!")
             .into();
+        let quantization = if quantization.is_null() {
+            None
+        } else {
+            let quantization: String = env
+                .get_string(&quantization)
+                .expect("Couldn't get java string!")
+                .into();
+            Some(as_quantization(&quantization)?)
+        };
+        let adapter_paths = to_strings(&mut env, &adapter_paths);
         let dtype = as_data_type(dtype)?;
         let device = as_device(&mut env, device_type, device_id as usize)?;
-        load_model(model_path, dtype, device)
+        load_model(
+            model_path,
+            dtype,
+            device,
+            reranker == JNI_TRUE,
+            quantization,
+            adapter_paths,
+        )
     };
     let ret = model();
 
@@ -204,7 +379,7 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_deleteModel<'local>(
     _: JObject,
     handle: jlong,
 ) {
-    drop_handle::<Box<dyn Model>>(handle);
+    drop_handle::<LoadedModel>(handle);
 }
 
 #[no_mangle]
@@ -213,39 +388,142 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_getInputNames<'local>
     _: JObject,
     handle: jlong,
 ) -> jobjectArray {
-    let model = cast_handle::<Box<dyn Model>>(handle);
-    let input_names: Vec<String> = model.get_input_names();
+    let model = cast_handle::<LoadedModel>(handle);
+    let input_names: Vec<String> = model.model.get_input_names();
     to_string_array(&mut env, input_names).unwrap()
 }
 
 #[no_mangle]
-pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_runInference<'local>(
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_getModelInfo<'local>(
+    mut env: JNIEnv<'local>,
+    _: JObject,
+    handle: jlong,
+) -> jstring {
+    let model = cast_handle::<LoadedModel>(handle);
+    match model.info.to_json() {
+        Ok(info) => env
+            .new_string(info)
+            .expect("Couldn't create java string!")
+            .into_raw(),
+        Err(err) => {
+            env.throw(err.to_string()).unwrap();
+            std::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_getOutputNames<'local>(
     mut env: JNIEnv,
     _: JObject,
     handle: jlong,
+) -> jobjectArray {
+    let model = cast_handle::<LoadedModel>(handle);
+    let output_names: Vec<String> = model.model.get_output_names();
+    to_string_array(&mut env, output_names).unwrap()
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_runInference<'local>(
+    mut env: JNIEnv<'local>,
+    _: JObject,
+    handle: jlong,
+    input_names: JObjectArray<'local>,
     input_handles: JLongArray<'local>,
-) -> jlong {
-    let model = cast_handle::<Box<dyn Model>>(handle);
+    output_names: JObjectArray<'local>,
+) -> JLongArray<'local> {
+    let model = &cast_handle::<LoadedModel>(handle).model;
+    let input_names = to_strings(&mut env, &input_names);
     let input_handles =
         unsafe { env.get_array_elements(&input_handles, ReleaseMode::NoCopyBack) }.unwrap();
+    let inputs: Vec<(String, Tensor)> = input_names
+        .into_iter()
+        .zip(input_handles.iter())
+        .map(|(name, &handle)| (name, cast_handle::<Tensor>(handle).clone()))
+        .collect();
+    drop(input_handles);
+    let requested = to_strings(&mut env, &output_names);
 
-    let mut input_vec: Vec<&Tensor> = Vec::new();
-    for &i in input_handles.iter() {
-        let tensor = cast_handle::<Tensor>(i);
-        input_vec.push(tensor);
-    }
-
-    let result = model.forward(
-        input_vec.get(0).unwrap(),
-        input_vec.get(1).unwrap(),
-        input_vec.get(2).map(|&x| x),
-    );
+    let result = ModelInputs::bind(&model.get_input_spec(), inputs)
+        .and_then(|inputs| model.forward_inputs(&inputs, &requested));
 
     match result {
-        Ok(output) => to_handle(output),
+        Ok(outputs) => {
+            let handles = outputs
+                .into_iter()
+                .map(|(_, tensor)| to_handle(tensor))
+                .collect::<Vec<_>>();
+            let ret = env.new_long_array(handles.len() as jsize).unwrap();
+            env.set_long_array_region(&ret, 0, &handles).unwrap();
+            ret
+        }
         Err(err) => {
             env.throw(err.to_string()).unwrap();
-            0
+            JLongArray::default()
+        }
+    }
+}
+
+fn to_strings(env: &mut JNIEnv, array: &JObjectArray) -> Vec<String> {
+    let len = env.get_array_length(array).unwrap();
+    let mut strings: Vec<String> = Vec::new();
+    for i in 0..len {
+        let item = env.get_object_array_element(array, i).unwrap().into();
+        let value: String = env
+            .get_string(&item)
+            .expect("Couldn't get java string!")
+            .into();
+        strings.push(value);
+    }
+    strings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An encoder with only the default output.
+    struct Encoder;
+
+    impl Model for Encoder {
+        fn get_input_spec(&self) -> Vec<InputSpec> {
+            vec![InputSpec::input_ids(), InputSpec::attention_mask()]
         }
+
+        fn forward(&self, input_ids: &Tensor, _: &Tensor, _: Option<&Tensor>) -> Result<Tensor> {
+            input_ids.unsqueeze(2)
+        }
+    }
+
+    #[test]
+    fn test_gather_last() -> Result<()> {
+        let mask = Tensor::new(
+            &[[1u32, 1, 0], [0, 1, 1], [1, 0, 0], [0, 0, 0]],
+            &Device::Cpu,
+        )?;
+        let seq_lens = mask_lens(&mask)?;
+        assert_eq!(seq_lens, [2, 3, 1, 3]);
+        let xs = Tensor::arange(0f32, 12., &Device::Cpu)?.reshape((4, 3, 1))?;
+        let last = gather_last(&xs, &seq_lens)?;
+        assert_eq!(last.flatten_all()?.to_vec1::<f32>()?, [1., 5., 6., 11.]);
+        assert!(gather_last(&xs, &[1, 2, 4, 1]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_outputs() -> Result<()> {
+        let input_ids = Tensor::new(&[[1u32, 2]], &Device::Cpu)?;
+        let outputs = Encoder.forward_outputs(&input_ids, &input_ids, None, &[])?;
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].0, "last_hidden_state");
+        assert_eq!(outputs[0].1.dims(), [1, 2, 1]);
+
+        // Outputs only implemented by some models are rejected rather than ignored
+        let requested = ["last_hidden_state".to_string(), "hidden_states".to_string()];
+        let err = Encoder
+            .forward_outputs(&input_ids, &input_ids, None, &requested)
+            .unwrap_err();
+        assert!(err.to_string().contains("[last_hidden_state]"), "{err}");
+        Ok(())
     }
 }
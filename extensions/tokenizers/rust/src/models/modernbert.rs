@@ -0,0 +1,401 @@
+use crate::layers::{LayerNorm, Linear};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::Model;
+use crate::utils::get_extended_attention_mask;
+use candle::{DType, Device, Result, Tensor};
+use candle_nn::{embedding, ops, rotary_emb, Activation, Embedding, Module, VarBuilder};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ModernBertConfig {
+    pub architectures: Vec<String>,
+    model_type: Option<String>,
+    pub vocab_size: usize,
+    pub hidden_size: usize,
+    pub intermediate_size: usize,
+    pub num_hidden_layers: usize,
+    pub num_attention_heads: usize,
+    #[serde(default = "default_hidden_activation")]
+    pub hidden_activation: Activation,
+    #[serde(default = "default_max_position_embeddings")]
+    pub max_position_embeddings: usize,
+    #[serde(default = "default_norm_eps")]
+    pub norm_eps: f64,
+    #[serde(default = "default_global_rope_theta")]
+    pub global_rope_theta: f64,
+    pub local_rope_theta: Option<f64>,
+    #[serde(default = "default_global_attn_every_n_layers")]
+    pub global_attn_every_n_layers: usize,
+    #[serde(default = "default_local_attention")]
+    pub local_attention: usize,
+    pub use_flash_attn: Option<bool>,
+}
+
+fn default_hidden_activation() -> Activation {
+    Activation::Gelu
+}
+
+fn default_max_position_embeddings() -> usize {
+    8192
+}
+
+fn default_norm_eps() -> f64 {
+    1e-5
+}
+
+fn default_global_rope_theta() -> f64 {
+    160_000.
+}
+
+fn default_global_attn_every_n_layers() -> usize {
+    3
+}
+
+fn default_local_attention() -> usize {
+    128
+}
+
+#[derive(Debug, Clone)]
+struct RotaryEmbedding {
+    sin: Tensor,
+    cos: Tensor,
+}
+
+impl RotaryEmbedding {
+    fn new(config: &ModernBertConfig, rope_theta: f64, dtype: DType, dev: &Device) -> Result<Self> {
+        let rope_theta = rope_theta as f32;
+        let dim = config.hidden_size / config.num_attention_heads;
+        let max_seq_len = config.max_position_embeddings;
+        let inv_freq: Vec<_> = (0..dim)
+            .step_by(2)
+            .map(|i| 1f32 / rope_theta.powf(i as f32 / dim as f32))
+            .collect();
+        let inv_freq_len = inv_freq.len();
+        let inv_freq = Tensor::from_vec(inv_freq, (1, inv_freq_len), dev)?;
+        let t = Tensor::arange(0u32, max_seq_len as u32, dev)?
+            .to_dtype(DType::F32)?
+            .reshape((max_seq_len, 1))?;
+        let freqs = t.matmul(&inv_freq)?;
+        Ok(Self {
+            sin: freqs.sin()?.to_dtype(dtype)?,
+            cos: freqs.cos()?.to_dtype(dtype)?,
+        })
+    }
+
+    fn apply_rotary_emb(&self, q: &Tensor, k: &Tensor) -> Result<(Tensor, Tensor)> {
+        let (_b_sz, _h, seq_len, _n_embd) = q.dims4()?;
+        let cos = self.cos.narrow(0, 0, seq_len)?;
+        let sin = self.sin.narrow(0, 0, seq_len)?;
+        let q_embed = rotary_emb::rope(&q.contiguous()?, &cos, &sin)?;
+        let k_embed = rotary_emb::rope(&k.contiguous()?, &cos, &sin)?;
+        Ok((q_embed, k_embed))
+    }
+}
+
+struct ModernBertEmbeddings {
+    tok_embeddings: Embedding,
+    norm: LayerNorm,
+    span: tracing::Span,
+}
+
+impl ModernBertEmbeddings {
+    fn load(vb: VarBuilder, config: &ModernBertConfig) -> Result<Self> {
+        let tok_embeddings = embedding(
+            config.vocab_size,
+            config.hidden_size,
+            vb.pp("tok_embeddings"),
+        )?;
+        let norm = LayerNorm::load(vb.pp("norm"), config.hidden_size, config.norm_eps as f32)?;
+        Ok(Self {
+            tok_embeddings,
+            norm,
+            span: tracing::span!(tracing::Level::TRACE, "embeddings"),
+        })
+    }
+
+    fn forward(&self, input_ids: &Tensor) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let embeddings = self.tok_embeddings.forward(input_ids)?;
+        self.norm.forward(&embeddings, None)
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+struct MLP {
+    intermediate_size: usize,
+    wi: Linear,
+    wo: Linear,
+    act_fn: Activation,
+}
+
+impl MLP {
+    fn load(vb: VarBuilder, config: &ModernBertConfig) -> Result<Self> {
+        let hidden_size = config.hidden_size;
+        let intermediate_size = config.intermediate_size;
+        let wi = Linear::load(vb.pp("Wi"), hidden_size, intermediate_size * 2, None)?;
+        let wo = Linear::load(vb.pp("Wo"), intermediate_size, hidden_size, None)?;
+        Ok(Self {
+            intermediate_size,
+            wi,
+            wo,
+            act_fn: config.hidden_activation,
+        })
+    }
+}
+
+impl Module for MLP {
+    fn forward(&self, xs: &Tensor) -> Result<Tensor> {
+        let input_gate = self.wi.forward(xs)?;
+        let input = input_gate.narrow(2, 0, self.intermediate_size)?;
+        let gate = input_gate.narrow(2, self.intermediate_size, self.intermediate_size)?;
+        let input = input.apply(&self.act_fn)?;
+        self.wo.forward(&(input * gate)?)
+    }
+}
+
+struct Attention {
+    wqkv: Linear,
+    wo: Linear,
+    hidden_size: usize,
+    num_heads: usize,
+    attention_head_size: usize,
+    rotary_emb: Arc<RotaryEmbedding>,
+}
+
+impl Attention {
+    fn load(
+        vb: VarBuilder,
+        config: &ModernBertConfig,
+        rotary_emb: Arc<RotaryEmbedding>,
+    ) -> Result<Self> {
+        let hidden_size = config.hidden_size;
+        let num_heads = config.num_attention_heads;
+        let attention_head_size = hidden_size / num_heads;
+        let wqkv = Linear::load(vb.pp("Wqkv"), hidden_size, hidden_size * 3, None)?;
+        let wo = Linear::load(vb.pp("Wo"), hidden_size, hidden_size, None)?;
+        Ok(Self {
+            wqkv,
+            wo,
+            hidden_size,
+            num_heads,
+            attention_head_size,
+            rotary_emb,
+        })
+    }
+
+    fn forward(&self, hidden_states: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        let (b_sz, q_len, _) = hidden_states.dims3()?;
+
+        let qkv = self
+            .wqkv
+            .forward(hidden_states)?
+            .reshape((b_sz, q_len, self.num_heads * 3, self.attention_head_size))?
+            .transpose(1, 2)?
+            .contiguous()?;
+
+        let q = qkv.narrow(1, 0, self.num_heads)?;
+        let k = qkv.narrow(1, self.num_heads, self.num_heads)?;
+        let v = qkv.narrow(1, self.num_heads * 2, self.num_heads)?;
+
+        let (q, k) = self.rotary_emb.apply_rotary_emb(&q, &k)?;
+
+        let scale = 1f64 / f64::sqrt(self.attention_head_size as f64);
+        let attn_weights = (q.matmul(&k.transpose(2, 3)?)? * scale)?;
+        let attn_weights = attn_weights.broadcast_add(attention_mask)?;
+        let attn_weights = ops::softmax_last_dim(&attn_weights)?;
+        let attn_output = attn_weights
+            .matmul(&v.contiguous()?)?
+            .transpose(1, 2)?
+            .reshape((b_sz, q_len, self.hidden_size))?;
+        self.wo.forward(&attn_output)
+    }
+}
+
+struct ModernBertLayer {
+    attn_norm: Option<LayerNorm>,
+    attn: Attention,
+    mlp_norm: LayerNorm,
+    mlp: MLP,
+    use_local_attention: bool,
+    span: tracing::Span,
+}
+
+impl ModernBertLayer {
+    fn load(
+        vb: VarBuilder,
+        config: &ModernBertConfig,
+        layer_id: usize,
+        global_rotary_emb: Arc<RotaryEmbedding>,
+        local_rotary_emb: Arc<RotaryEmbedding>,
+    ) -> Result<Self> {
+        // The first layer reuses the normalized embeddings as is.
+        let attn_norm = if layer_id == 0 {
+            None
+        } else {
+            Some(LayerNorm::load(
+                vb.pp("attn_norm"),
+                config.hidden_size,
+                config.norm_eps as f32,
+            )?)
+        };
+        let use_local_attention = !layer_id.is_multiple_of(config.global_attn_every_n_layers);
+        let rotary_emb = if use_local_attention {
+            local_rotary_emb
+        } else {
+            global_rotary_emb
+        };
+        let attn = Attention::load(vb.pp("attn"), config, rotary_emb)?;
+        let mlp_norm = LayerNorm::load(
+            vb.pp("mlp_norm"),
+            config.hidden_size,
+            config.norm_eps as f32,
+        )?;
+        let mlp = MLP::load(vb.pp("mlp"), config)?;
+        Ok(Self {
+            attn_norm,
+            attn,
+            mlp_norm,
+            mlp,
+            use_local_attention,
+            span: tracing::span!(tracing::Level::TRACE, "layer"),
+        })
+    }
+
+    fn forward(
+        &self,
+        hidden_states: &Tensor,
+        global_attention_mask: &Tensor,
+        local_attention_mask: &Tensor,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let attention_mask = if self.use_local_attention {
+            local_attention_mask
+        } else {
+            global_attention_mask
+        };
+        let attn_input = match &self.attn_norm {
+            Some(attn_norm) => attn_norm.forward(hidden_states, None)?,
+            None => hidden_states.clone(),
+        };
+        let attn_output = self.attn.forward(&attn_input, attention_mask)?;
+        let hidden_states = (hidden_states + attn_output)?;
+        let mlp_output = self
+            .mlp
+            .forward(&self.mlp_norm.forward(&hidden_states, None)?)?;
+        hidden_states + mlp_output
+    }
+}
+
+// https://github.com/huggingface/transformers/blob/main/src/transformers/models/modernbert/modeling_modernbert.py
+pub struct ModernBertModel {
+    embeddings: ModernBertEmbeddings,
+    layers: Vec<ModernBertLayer>,
+    final_norm: LayerNorm,
+    local_attention: usize,
+    dtype: DType,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl ModernBertModel {
+    pub fn load(vb: VarBuilder, config: &ModernBertConfig) -> Result<Self> {
+        // `ModernBertForMaskedLM` checkpoints nest the encoder under `model`
+        let vb = if vb.contains_tensor("model.embeddings.tok_embeddings.weight") {
+            vb.pp("model")
+        } else {
+            vb
+        };
+        let embeddings = ModernBertEmbeddings::load(vb.pp("embeddings"), config)?;
+        let global_rotary_emb = Arc::new(RotaryEmbedding::new(
+            config,
+            config.global_rope_theta,
+            vb.dtype(),
+            vb.device(),
+        )?);
+        let local_rotary_emb = Arc::new(RotaryEmbedding::new(
+            config,
+            config.local_rope_theta.unwrap_or(config.global_rope_theta),
+            vb.dtype(),
+            vb.device(),
+        )?);
+        let layers = (0..config.num_hidden_layers)
+            .map(|index| {
+                ModernBertLayer::load(
+                    vb.pp(format!("layers.{index}")),
+                    config,
+                    index,
+                    global_rotary_emb.clone(),
+                    local_rotary_emb.clone(),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let final_norm = LayerNorm::load(
+            vb.pp("final_norm"),
+            config.hidden_size,
+            config.norm_eps as f32,
+        )?;
+        Ok(Self {
+            embeddings,
+            layers,
+            final_norm,
+            local_attention: config.local_attention,
+            dtype: vb.dtype(),
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+
+    /// Restricts the global mask to a window of `local_attention / 2` tokens on each side.
+    fn prepare_local_attention_mask(
+        &self,
+        global_attention_mask: &Tensor,
+        seq_len: usize,
+    ) -> Result<Tensor> {
+        let half_window = self.local_attention / 2;
+        let window: Vec<_> = (0..seq_len)
+            .flat_map(|i| {
+                (0..seq_len).map(move |j| {
+                    if i.abs_diff(j) > half_window {
+                        f32::NEG_INFINITY
+                    } else {
+                        0.
+                    }
+                })
+            })
+            .collect();
+        let window = Tensor::from_slice(&window, (1, 1, seq_len, seq_len), &self.device)?
+            .to_dtype(self.dtype)?;
+        global_attention_mask.broadcast_add(&window)
+    }
+}
+
+impl Model for ModernBertModel {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(false)
+    }
+
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        _token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let (_b_sz, seq_len) = input_ids.dims2()?;
+        let global_attention_mask = get_extended_attention_mask(attention_mask, self.dtype)?;
+        let local_attention_mask =
+            self.prepare_local_attention_mask(&global_attention_mask, seq_len)?;
+
+        let mut hidden_states = self.embeddings.forward(input_ids)?;
+        for layer in self.layers.iter() {
+            hidden_states = layer.forward(
+                &hidden_states,
+                &global_attention_mask,
+                &local_attention_mask,
+            )?;
+        }
+        self.final_norm.forward(&hidden_states, None)
+    }
+}
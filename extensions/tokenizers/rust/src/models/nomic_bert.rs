@@ -0,0 +1,368 @@
+use crate::layers::{LayerNorm, Linear};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::Model;
+use crate::utils::get_extended_attention_mask;
+use candle::{DType, Device, Result, Tensor};
+use candle_nn::{embedding, ops, rotary_emb, Embedding, Module, VarBuilder};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NomicBertConfig {
+    pub architectures: Vec<String>,
+    model_type: Option<String>,
+    pub vocab_size: usize,
+    pub n_embd: usize,
+    pub n_head: usize,
+    pub n_layer: usize,
+    pub n_inner: Option<usize>,
+    #[serde(default = "default_n_positions")]
+    pub n_positions: usize,
+    #[serde(default = "default_type_vocab_size")]
+    pub type_vocab_size: usize,
+    #[serde(default = "default_layer_norm_epsilon")]
+    pub layer_norm_epsilon: f64,
+    #[serde(default = "default_activation_function")]
+    pub activation_function: String,
+    #[serde(default = "default_rotary_emb_base")]
+    pub rotary_emb_base: f64,
+    #[serde(default = "default_rotary_emb_fraction")]
+    pub rotary_emb_fraction: f64,
+    #[serde(default)]
+    pub rotary_emb_interleaved: bool,
+    #[serde(default)]
+    pub prenorm: bool,
+    pub use_flash_attn: Option<bool>,
+}
+
+fn default_n_positions() -> usize {
+    8192
+}
+
+fn default_type_vocab_size() -> usize {
+    2
+}
+
+fn default_layer_norm_epsilon() -> f64 {
+    1e-12
+}
+
+fn default_activation_function() -> String {
+    "swiglu".to_string()
+}
+
+fn default_rotary_emb_base() -> f64 {
+    1000.
+}
+
+fn default_rotary_emb_fraction() -> f64 {
+    1.
+}
+
+#[derive(Debug, Clone)]
+struct RotaryEmbedding {
+    sin: Tensor,
+    cos: Tensor,
+    rotary_dim: usize,
+    interleaved: bool,
+}
+
+impl RotaryEmbedding {
+    fn new(config: &NomicBertConfig, dtype: DType, dev: &Device) -> Result<Self> {
+        let rope_theta = config.rotary_emb_base as f32;
+        let head_dim = config.n_embd / config.n_head;
+        let dim = (head_dim as f64 * config.rotary_emb_fraction) as usize;
+        let max_seq_len = config.n_positions;
+        let inv_freq: Vec<_> = (0..dim)
+            .step_by(2)
+            .map(|i| 1f32 / rope_theta.powf(i as f32 / dim as f32))
+            .collect();
+        let inv_freq_len = inv_freq.len();
+        let inv_freq = Tensor::from_vec(inv_freq, (1, inv_freq_len), dev)?;
+        let t = Tensor::arange(0u32, max_seq_len as u32, dev)?
+            .to_dtype(DType::F32)?
+            .reshape((max_seq_len, 1))?;
+        let freqs = t.matmul(&inv_freq)?;
+        Ok(Self {
+            sin: freqs.sin()?.to_dtype(dtype)?,
+            cos: freqs.cos()?.to_dtype(dtype)?,
+            rotary_dim: dim,
+            interleaved: config.rotary_emb_interleaved,
+        })
+    }
+
+    fn rope(&self, xs: &Tensor, cos: &Tensor, sin: &Tensor) -> Result<Tensor> {
+        let head_dim = xs.dim(3)?;
+        let rot = xs.narrow(3, 0, self.rotary_dim)?.contiguous()?;
+        let rot = if self.interleaved {
+            rotary_emb::rope_i(&rot, cos, sin)?
+        } else {
+            rotary_emb::rope(&rot, cos, sin)?
+        };
+        if self.rotary_dim == head_dim {
+            Ok(rot)
+        } else {
+            let pass = xs.narrow(3, self.rotary_dim, head_dim - self.rotary_dim)?;
+            Tensor::cat(&[&rot, &pass], 3)
+        }
+    }
+
+    fn apply_rotary_emb(&self, q: &Tensor, k: &Tensor) -> Result<(Tensor, Tensor)> {
+        let (_b_sz, _h, seq_len, _n_embd) = q.dims4()?;
+        let cos = self.cos.narrow(0, 0, seq_len)?;
+        let sin = self.sin.narrow(0, 0, seq_len)?;
+        let q_embed = self.rope(q, &cos, &sin)?;
+        let k_embed = self.rope(k, &cos, &sin)?;
+        Ok((q_embed, k_embed))
+    }
+}
+
+struct NomicBertEmbeddings {
+    word_embeddings: Embedding,
+    token_type_embeddings: Option<Embedding>,
+    span: tracing::Span,
+}
+
+impl NomicBertEmbeddings {
+    fn load(vb: VarBuilder, config: &NomicBertConfig) -> Result<Self> {
+        let word_embeddings =
+            embedding(config.vocab_size, config.n_embd, vb.pp("word_embeddings"))?;
+        let token_type_embeddings = if config.type_vocab_size > 0 {
+            Some(embedding(
+                config.type_vocab_size,
+                config.n_embd,
+                vb.pp("token_type_embeddings"),
+            )?)
+        } else {
+            None
+        };
+        Ok(Self {
+            word_embeddings,
+            token_type_embeddings,
+            span: tracing::span!(tracing::Level::TRACE, "embeddings"),
+        })
+    }
+
+    fn forward(&self, input_ids: &Tensor, token_type_ids: Option<&Tensor>) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let embeddings = self.word_embeddings.forward(input_ids)?;
+        match &self.token_type_embeddings {
+            Some(token_type_embeddings) => {
+                let token_type_ids = match token_type_ids {
+                    Some(token_type_ids) => token_type_ids.clone(),
+                    None => input_ids.zeros_like()?,
+                };
+                embeddings + token_type_embeddings.forward(&token_type_ids)?
+            }
+            None => Ok(embeddings),
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+struct MLP {
+    fc11: Linear,
+    fc12: Linear,
+    fc2: Linear,
+}
+
+impl MLP {
+    fn load(vb: VarBuilder, config: &NomicBertConfig) -> Result<Self> {
+        if config.activation_function != "swiglu" {
+            candle::bail!(
+                "Unsupported activation_function: {}",
+                config.activation_function
+            )
+        }
+        let hidden_size = config.n_embd;
+        let intermediate_size = config.n_inner.unwrap_or(4 * hidden_size);
+        let fc11 = Linear::load(vb.pp("fc11"), hidden_size, intermediate_size, None)?;
+        let fc12 = Linear::load(vb.pp("fc12"), hidden_size, intermediate_size, None)?;
+        let fc2 = Linear::load(vb.pp("fc2"), intermediate_size, hidden_size, None)?;
+        Ok(Self { fc11, fc12, fc2 })
+    }
+}
+
+impl Module for MLP {
+    fn forward(&self, xs: &Tensor) -> Result<Tensor> {
+        let y = self.fc11.forward(xs)?;
+        let gate = ops::silu(&self.fc12.forward(xs)?)?;
+        self.fc2.forward(&(y * gate)?)
+    }
+}
+
+struct Attention {
+    wqkv: Linear,
+    out_proj: Linear,
+    hidden_size: usize,
+    num_heads: usize,
+    attention_head_size: usize,
+    rotary_emb: Arc<RotaryEmbedding>,
+}
+
+impl Attention {
+    fn load(
+        vb: VarBuilder,
+        config: &NomicBertConfig,
+        rotary_emb: Arc<RotaryEmbedding>,
+    ) -> Result<Self> {
+        let hidden_size = config.n_embd;
+        let num_heads = config.n_head;
+        let attention_head_size = hidden_size / num_heads;
+        let wqkv = Linear::load(vb.pp("Wqkv"), hidden_size, hidden_size * 3, None)?;
+        let out_proj = Linear::load(vb.pp("out_proj"), hidden_size, hidden_size, None)?;
+        Ok(Self {
+            wqkv,
+            out_proj,
+            hidden_size,
+            num_heads,
+            attention_head_size,
+            rotary_emb,
+        })
+    }
+
+    fn forward(&self, hidden_states: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        let (b_sz, q_len, _) = hidden_states.dims3()?;
+
+        let qkv = self
+            .wqkv
+            .forward(hidden_states)?
+            .reshape((b_sz, q_len, self.num_heads * 3, self.attention_head_size))?
+            .transpose(1, 2)?
+            .contiguous()?;
+
+        let q = qkv.narrow(1, 0, self.num_heads)?;
+        let k = qkv.narrow(1, self.num_heads, self.num_heads)?;
+        let v = qkv.narrow(1, self.num_heads * 2, self.num_heads)?;
+
+        let (q, k) = self.rotary_emb.apply_rotary_emb(&q, &k)?;
+
+        let scale = 1f64 / f64::sqrt(self.attention_head_size as f64);
+        let attn_weights = (q.matmul(&k.transpose(2, 3)?)? * scale)?;
+        let attn_weights = attn_weights.broadcast_add(attention_mask)?;
+        let attn_weights = ops::softmax_last_dim(&attn_weights)?;
+        let attn_output = attn_weights
+            .matmul(&v.contiguous()?)?
+            .transpose(1, 2)?
+            .reshape((b_sz, q_len, self.hidden_size))?;
+        self.out_proj.forward(&attn_output)
+    }
+}
+
+struct NomicBertLayer {
+    attn: Attention,
+    mlp: MLP,
+    norm1: LayerNorm,
+    norm2: LayerNorm,
+    prenorm: bool,
+    span: tracing::Span,
+}
+
+impl NomicBertLayer {
+    fn load(
+        vb: VarBuilder,
+        config: &NomicBertConfig,
+        rotary_emb: Arc<RotaryEmbedding>,
+    ) -> Result<Self> {
+        let attn = Attention::load(vb.pp("attn"), config, rotary_emb)?;
+        let mlp = MLP::load(vb.pp("mlp"), config)?;
+        let eps = config.layer_norm_epsilon as f32;
+        let norm1 = LayerNorm::load(vb.pp("norm1"), config.n_embd, eps)?;
+        let norm2 = LayerNorm::load(vb.pp("norm2"), config.n_embd, eps)?;
+        Ok(Self {
+            attn,
+            mlp,
+            norm1,
+            norm2,
+            prenorm: config.prenorm,
+            span: tracing::span!(tracing::Level::TRACE, "layer"),
+        })
+    }
+
+    fn forward(&self, hidden_states: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        if self.prenorm {
+            let attn_output = self
+                .attn
+                .forward(&self.norm1.forward(hidden_states, None)?, attention_mask)?;
+            let hidden_states = (hidden_states + attn_output)?;
+            let mlp_output = self
+                .mlp
+                .forward(&self.norm2.forward(&hidden_states, None)?)?;
+            hidden_states + mlp_output
+        } else {
+            let attn_output = self.attn.forward(hidden_states, attention_mask)?;
+            let hidden_states = self.norm1.forward(&attn_output, Some(hidden_states))?;
+            let mlp_output = self.mlp.forward(&hidden_states)?;
+            self.norm2.forward(&mlp_output, Some(&hidden_states))
+        }
+    }
+}
+
+// https://huggingface.co/nomic-ai/nomic-bert-2048/blob/main/modeling_hf_nomic_bert.py
+pub struct NomicBertModel {
+    embeddings: NomicBertEmbeddings,
+    emb_ln: LayerNorm,
+    layers: Vec<NomicBertLayer>,
+    dtype: DType,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl NomicBertModel {
+    pub fn load(vb: VarBuilder, config: &NomicBertConfig) -> Result<Self> {
+        // `NomicBertForPreTraining` checkpoints nest the encoder under `bert`
+        let vb = if vb.contains_tensor("bert.embeddings.word_embeddings.weight") {
+            vb.pp("bert")
+        } else {
+            vb
+        };
+        let embeddings = NomicBertEmbeddings::load(vb.pp("embeddings"), config)?;
+        let emb_ln = LayerNorm::load(
+            vb.pp("emb_ln"),
+            config.n_embd,
+            config.layer_norm_epsilon as f32,
+        )?;
+        let rotary_emb = Arc::new(RotaryEmbedding::new(config, vb.dtype(), vb.device())?);
+        let layers = (0..config.n_layer)
+            .map(|index| {
+                NomicBertLayer::load(
+                    vb.pp(format!("encoder.layers.{index}")),
+                    config,
+                    rotary_emb.clone(),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            embeddings,
+            emb_ln,
+            layers,
+            dtype: vb.dtype(),
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for NomicBertModel {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(true)
+    }
+
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let attention_mask = get_extended_attention_mask(attention_mask, self.dtype)?;
+        let embeddings = self.embeddings.forward(input_ids, token_type_ids)?;
+        let mut hidden_states = self.emb_ln.forward(&embeddings, None)?;
+        for layer in self.layers.iter() {
+            hidden_states = layer.forward(&hidden_states, &attention_mask)?;
+        }
+        Ok(hidden_states)
+    }
+}
@@ -1,5 +1,7 @@
-use crate::layers::{Linear, RmsNorm};
-use crate::models::Model;
+use crate::layers::{append_and_gather, Linear, RmsNorm, SequenceCache};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::{last_tokens, CausalLM, Model};
+use crate::utils::rope_with_offsets;
 use candle::{DType, Device, IndexOp, Module, Result, Tensor};
 use candle_nn::{Activation, VarBuilder};
 use std::sync::Arc;
@@ -59,6 +61,17 @@ impl RotaryEmbedding {
         let k_embed = candle_nn::rotary_emb::rope(&k.contiguous()?, &cos, &sin)?;
         Ok((q_embed, k_embed))
     }
+
+    fn apply_rotary_emb_with_offsets(
+        &self,
+        q: &Tensor,
+        k: &Tensor,
+        offsets: &[usize],
+    ) -> Result<(Tensor, Tensor)> {
+        let q_embed = rope_with_offsets(q, &self.cos, &self.sin, offsets)?;
+        let k_embed = rope_with_offsets(k, &self.cos, &self.sin, offsets)?;
+        Ok((q_embed, k_embed))
+    }
 }
 
 #[derive(Debug)]
@@ -176,6 +189,50 @@ impl Attention {
         let attn_output = self.o_proj.forward(&attn_output)?;
         Ok(attn_output)
     }
+
+    fn forward_step(
+        &self,
+        xs: &Tensor,
+        caches: &mut [&mut SequenceCache],
+        layer_idx: usize,
+        offsets: &[usize],
+    ) -> Result<Tensor> {
+        let (b_sz, q_len, _) = xs.dims3()?;
+
+        let query_states = self.q_proj.forward(xs)?;
+        let key_states = self.k_proj.forward(xs)?;
+        let value_states = self.v_proj.forward(xs)?;
+
+        let query_states = query_states
+            .reshape((b_sz, q_len, self.num_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let key_states = key_states
+            .reshape((b_sz, q_len, self.num_kv_heads, self.head_dim))?
+            .transpose(1, 2)?;
+        let value_states = value_states
+            .reshape((b_sz, q_len, self.num_kv_heads, self.head_dim))?
+            .transpose(1, 2)?;
+
+        let (query_states, key_states) =
+            self.rotary_emb
+                .apply_rotary_emb_with_offsets(&query_states, &key_states, offsets)?;
+        let (key_states, value_states, attention_mask) =
+            append_and_gather(caches, layer_idx, &key_states, &value_states, offsets, None)?;
+
+        let key_states = crate::utils::repeat_kv(key_states, self.num_kv_groups)?.contiguous()?;
+        let value_states =
+            crate::utils::repeat_kv(value_states, self.num_kv_groups)?.contiguous()?;
+
+        let scale = 1f64 / f64::sqrt(self.head_dim as f64);
+        let attn_weights = (query_states.matmul(&key_states.transpose(2, 3)?)? * scale)?;
+        let attn_weights = attn_weights.broadcast_add(&attention_mask)?;
+        let attn_weights = candle_nn::ops::softmax_last_dim(&attn_weights)?;
+        let attn_output = attn_weights.matmul(&value_states)?;
+        let attn_output = attn_output
+            .transpose(1, 2)?
+            .reshape((b_sz, q_len, self.hidden_size))?;
+        self.o_proj.forward(&attn_output)
+    }
 }
 
 #[derive(Debug)]
@@ -221,6 +278,24 @@ impl DecoderLayer {
         let xs = xs.apply(&self.post_attention_layernorm)?.apply(&self.mlp)?;
         residual + xs
     }
+
+    fn forward_step(
+        &self,
+        xs: &Tensor,
+        caches: &mut [&mut SequenceCache],
+        layer_idx: usize,
+        offsets: &[usize],
+    ) -> Result<Tensor> {
+        let residual = xs;
+        let xs = self.input_layernorm.forward(xs)?;
+        let xs = self
+            .self_attn
+            .forward_step(&xs, caches, layer_idx, offsets)?;
+        let xs = (xs + residual)?;
+        let residual = &xs;
+        let xs = xs.apply(&self.post_attention_layernorm)?.apply(&self.mlp)?;
+        residual + xs
+    }
 }
 
 #[derive(Debug)]
@@ -228,6 +303,7 @@ pub struct Qwen2Model {
     embed_tokens: candle_nn::Embedding,
     layers: Vec<DecoderLayer>,
     norm: RmsNorm,
+    lm_head: Option<candle_nn::Linear>,
     #[allow(unused)]
     device: Device,
     dtype: DType,
@@ -249,10 +325,25 @@ impl Qwen2Model {
             layers.push(layer)
         }
         let norm = RmsNorm::load(vb_m.pp("norm"), config.hidden_size, config.rms_norm_eps)?;
+        let lm_head = if config.tie_word_embeddings {
+            Some(candle_nn::Linear::new(
+                embed_tokens.embeddings().clone(),
+                None,
+            ))
+        } else if vb.contains_tensor("lm_head.weight") {
+            Some(candle_nn::linear_no_bias(
+                config.hidden_size,
+                config.vocab_size,
+                vb.pp("lm_head"),
+            )?)
+        } else {
+            None
+        };
         Ok(Self {
             embed_tokens,
             layers,
             norm,
+            lm_head,
             device: vb.device().clone(),
             dtype: vb.dtype(),
         })
@@ -274,8 +365,8 @@ impl Qwen2Model {
 }
 
 impl Model for Qwen2Model {
-    fn get_input_names(&self) -> Vec<String> {
-        return vec!["input_ids".to_string(), "attention_mask".to_string()];
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(false)
     }
 
     fn forward(
@@ -291,4 +382,37 @@ impl Model for Qwen2Model {
         }
         xs.apply(&self.norm)
     }
+
+    fn as_causal_lm(&self) -> Option<&dyn CausalLM> {
+        self.lm_head.as_ref().map(|_| self as &dyn CausalLM)
+    }
+}
+
+impl CausalLM for Qwen2Model {
+    fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn device(&self) -> &Device {
+        &self.device
+    }
+
+    fn forward_step(
+        &self,
+        input_ids: &Tensor,
+        seq_lens: Option<&[usize]>,
+        caches: &mut [&mut SequenceCache],
+    ) -> Result<Tensor> {
+        let lm_head = match &self.lm_head {
+            Some(lm_head) => lm_head,
+            None => candle::bail!("`lm_head` is required for generation"),
+        };
+        let offsets = caches.iter().map(|c| c.seq_len()).collect::<Vec<_>>();
+        let mut xs = self.embed_tokens.forward(input_ids)?;
+        for (layer_idx, layer) in self.layers.iter().enumerate() {
+            xs = layer.forward_step(&xs, caches, layer_idx, &offsets)?
+        }
+        let xs = last_tokens(&xs, seq_lens, caches, &offsets)?.apply(&self.norm)?;
+        xs.apply(lm_head)?.squeeze(1)
+    }
 }
@@ -0,0 +1,245 @@
+use crate::models::bert::{
+    BertConfig, BertForMaskedLM, BertForQuestionAnswering, BertForSequenceClassification,
+    BertForTokenClassification, BertModel,
+};
+use crate::models::camembert::{
+    CamembertConfig, CamembertForMaskedLM, CamembertForSequenceClassification, CamembertModel,
+};
+use crate::models::distilbert::{
+    DistilBertConfig, DistilBertForMaskedLM, DistilBertForQuestionAnswering,
+    DistilBertForSequenceClassification, DistilBertForTokenClassification, DistilBertModel,
+};
+use crate::models::gemma2::{Gemma2Config, Gemma2Model};
+use crate::models::gte::{GTEConfig, GTEModel};
+use crate::models::jina_bert::{JinaBertConfig, JinaBertModel};
+use crate::models::llama::{LlamaConfig, LlamaForCausalLM, LlamaModel};
+use crate::models::mistral::{MistralConfig, MistralModel};
+use crate::models::modernbert::{ModernBertConfig, ModernBertModel};
+use crate::models::nomic_bert::{NomicBertConfig, NomicBertModel};
+use crate::models::qwen2::{Qwen2Config, Qwen2Model};
+use crate::models::roberta::{
+    RobertaConfig, RobertaForMaskedLM, RobertaForQuestionAnswering,
+    RobertaForSequenceClassification, RobertaForTokenClassification, RobertaModel,
+};
+use crate::models::xlm_roberta::{
+    XLMRobertaConfig, XLMRobertaForMaskedLM, XLMRobertaForQuestionAnswering,
+    XLMRobertaForSequenceClassification, XLMRobertaForTokenClassification, XLMRobertaModel,
+};
+use crate::models::Model;
+use candle::{Error, Result};
+use candle_nn::VarBuilder;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Builds a model from its weights and `config.json`.
+pub(crate) type Loader = fn(VarBuilder, &str, bool) -> Result<Box<dyn Model>>;
+
+// Parses the `config.json` into `$config` and loads `$model` with it
+macro_rules! loader {
+    ($config:ty, $model:ty) => {
+        |vb: VarBuilder, config: &str, use_flash_attn: bool| -> Result<Box<dyn Model>> {
+            let mut config: $config = serde_json::from_str(config).map_err(Error::msg)?;
+            config.use_flash_attn = Some(use_flash_attn);
+            Ok(Box::new(<$model>::load(vb, &config)?))
+        }
+    };
+}
+
+// Model types that share an implementation with a registered one, e.g. `new` for GTE
+const ALIASES: &str = include_str!("aliases.json");
+
+/// The fields of `config.json` that select the loader.
+#[derive(Debug, Deserialize)]
+struct ModelSelector {
+    model_type: Option<String>,
+    architectures: Option<Vec<String>>,
+}
+
+/// Loaders by `model_type`, and by architecture for the task specific heads.
+pub(crate) struct ModelRegistry {
+    model_types: HashMap<String, Loader>,
+    architectures: HashMap<String, Loader>,
+    aliases: HashMap<String, String>,
+}
+
+impl ModelRegistry {
+    fn new() -> Self {
+        Self {
+            model_types: HashMap::new(),
+            architectures: HashMap::new(),
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// The registry of the built-in models, with the aliases of `aliases.json` and of the JSON
+    /// file in the `MODEL_TYPE_ALIASES` environment variable.
+    pub(crate) fn get() -> Result<&'static ModelRegistry> {
+        static REGISTRY: OnceLock<ModelRegistry> = OnceLock::new();
+        if let Some(registry) = REGISTRY.get() {
+            return Ok(registry);
+        }
+        let mut registry = Self::builtin();
+        registry.add_aliases(ALIASES)?;
+        if let Ok(path) = std::env::var("MODEL_TYPE_ALIASES") {
+            registry.add_aliases(&std::fs::read_to_string(path)?)?;
+        }
+        Ok(REGISTRY.get_or_init(|| registry))
+    }
+
+    pub(crate) fn register_model_type(&mut self, model_type: &str, loader: Loader) {
+        self.model_types.insert(model_type.to_string(), loader);
+    }
+
+    pub(crate) fn register_architecture(&mut self, architecture: &str, loader: Loader) {
+        self.architectures.insert(architecture.to_string(), loader);
+    }
+
+    /// Adds a JSON object of `alias: target` pairs, where `target` is a registered model type or
+    /// architecture.
+    pub(crate) fn add_aliases(&mut self, aliases: &str) -> Result<()> {
+        let aliases: HashMap<String, String> = serde_json::from_str(aliases).map_err(Error::msg)?;
+        self.aliases.extend(aliases);
+        Ok(())
+    }
+
+    fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.get(name).map_or(name, String::as_str)
+    }
+
+    /// Returns the loader of the first architecture of `config` if one is registered, otherwise
+    /// the loader of its `model_type`.
+    pub(crate) fn loader(&self, config: &str) -> Result<(String, Loader)> {
+        let selector: ModelSelector = serde_json::from_str(config).map_err(Error::msg)?;
+        let architecture = selector.architectures.as_ref().and_then(|a| a.first());
+        if let Some(architecture) = architecture {
+            let architecture = self.resolve(architecture);
+            if let Some(loader) = self.architectures.get(architecture) {
+                return Ok((architecture.to_string(), *loader));
+            }
+        }
+        if let Some(model_type) = &selector.model_type {
+            let model_type = self.resolve(model_type);
+            if let Some(loader) = self.model_types.get(model_type) {
+                return Ok((model_type.to_string(), *loader));
+            }
+        }
+
+        let mut model_types: Vec<&String> = self.model_types.keys().collect();
+        model_types.extend(
+            self.aliases
+                .keys()
+                .filter(|a| self.model_types.contains_key(self.resolve(a))),
+        );
+        model_types.sort();
+        candle::bail!(
+            "Unsupported model_type {:?} with architectures {:?}, supported model types: {:?}",
+            selector.model_type.unwrap_or_default(),
+            selector.architectures.unwrap_or_default(),
+            model_types
+        )
+    }
+
+    fn builtin() -> Self {
+        let mut registry = Self::new();
+
+        registry.register_model_type("bert", loader!(BertConfig, BertModel));
+        registry.register_architecture(
+            "BertForSequenceClassification",
+            loader!(BertConfig, BertForSequenceClassification),
+        );
+        registry.register_architecture(
+            "BertForTokenClassification",
+            loader!(BertConfig, BertForTokenClassification),
+        );
+        registry.register_architecture(
+            "BertForQuestionAnswering",
+            loader!(BertConfig, BertForQuestionAnswering),
+        );
+        registry.register_architecture("BertForMaskedLM", loader!(BertConfig, BertForMaskedLM));
+
+        registry.register_model_type("camembert", loader!(CamembertConfig, CamembertModel));
+        registry.register_architecture(
+            "CamembertForSequenceClassification",
+            loader!(CamembertConfig, CamembertForSequenceClassification),
+        );
+        registry.register_architecture(
+            "CamembertForMaskedLM",
+            loader!(CamembertConfig, CamembertForMaskedLM),
+        );
+
+        registry.register_model_type("roberta", loader!(RobertaConfig, RobertaModel));
+        registry.register_architecture(
+            "RobertaForSequenceClassification",
+            loader!(RobertaConfig, RobertaForSequenceClassification),
+        );
+        registry.register_architecture(
+            "RobertaForTokenClassification",
+            loader!(RobertaConfig, RobertaForTokenClassification),
+        );
+        registry.register_architecture(
+            "RobertaForQuestionAnswering",
+            loader!(RobertaConfig, RobertaForQuestionAnswering),
+        );
+        registry.register_architecture(
+            "RobertaForMaskedLM",
+            loader!(RobertaConfig, RobertaForMaskedLM),
+        );
+
+        registry.register_model_type("xlm-roberta", loader!(XLMRobertaConfig, XLMRobertaModel));
+        registry.register_architecture(
+            "XLMRobertaForSequenceClassification",
+            loader!(XLMRobertaConfig, XLMRobertaForSequenceClassification),
+        );
+        registry.register_architecture(
+            "XLMRobertaForTokenClassification",
+            loader!(XLMRobertaConfig, XLMRobertaForTokenClassification),
+        );
+        registry.register_architecture(
+            "XLMRobertaForQuestionAnswering",
+            loader!(XLMRobertaConfig, XLMRobertaForQuestionAnswering),
+        );
+        registry.register_architecture(
+            "XLMRobertaForMaskedLM",
+            loader!(XLMRobertaConfig, XLMRobertaForMaskedLM),
+        );
+
+        registry.register_model_type("distilbert", loader!(DistilBertConfig, DistilBertModel));
+        registry.register_architecture(
+            "DistilBertForSequenceClassification",
+            loader!(DistilBertConfig, DistilBertForSequenceClassification),
+        );
+        registry.register_architecture(
+            "DistilBertForTokenClassification",
+            loader!(DistilBertConfig, DistilBertForTokenClassification),
+        );
+        registry.register_architecture(
+            "DistilBertForQuestionAnswering",
+            loader!(DistilBertConfig, DistilBertForQuestionAnswering),
+        );
+        registry.register_architecture(
+            "DistilBertForMaskedLM",
+            loader!(DistilBertConfig, DistilBertForMaskedLM),
+        );
+
+        registry.register_model_type("llama", loader!(LlamaConfig, LlamaModel));
+        registry.register_architecture("LlamaForCausalLM", loader!(LlamaConfig, LlamaForCausalLM));
+
+        registry.register_model_type("mistral", loader!(MistralConfig, MistralModel));
+        registry.register_model_type("qwen2", loader!(Qwen2Config, Qwen2Model));
+        registry.register_model_type("gte", loader!(GTEConfig, GTEModel));
+        registry.register_model_type("gemma2", loader!(Gemma2Config, Gemma2Model));
+        registry.register_model_type("modernbert", loader!(ModernBertConfig, ModernBertModel));
+        registry.register_model_type("nomic_bert", loader!(NomicBertConfig, NomicBertModel));
+
+        // jina-embeddings-v2 ships with `model_type: bert`
+        registry.register_model_type("jina-bert", loader!(JinaBertConfig, JinaBertModel));
+        registry.register_architecture("JinaBertModel", loader!(JinaBertConfig, JinaBertModel));
+        registry.register_architecture(
+            "JinaBertForMaskedLM",
+            loader!(JinaBertConfig, JinaBertModel),
+        );
+
+        registry
+    }
+}
@@ -0,0 +1,269 @@
+use crate::layers::{KvCache, SequenceCache};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::Model;
+use candle::{DType, IndexOp, Result, Tensor};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Activation {
+    Sigmoid,
+    Softmax,
+    Identity,
+}
+
+impl Activation {
+    // sentence-transformers stores the fully qualified torch class name,
+    // e.g. `torch.nn.modules.activation.Sigmoid`
+    fn from_class_name(name: &str) -> Result<Self> {
+        match name.rsplit('.').next() {
+            Some("Sigmoid") => Ok(Activation::Sigmoid),
+            Some("Softmax") => Ok(Activation::Softmax),
+            Some("Identity") => Ok(Activation::Identity),
+            _ => candle::bail!("Unsupported cross-encoder activation function: {name}"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SentenceTransformersConfig {
+    activation_fn: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RerankerConfig {
+    model_type: Option<String>,
+    type_vocab_size: Option<usize>,
+    sentence_transformers: Option<SentenceTransformersConfig>,
+    sbert_ce_default_activation_function: Option<String>,
+}
+
+/// Wraps a model loaded by `load_model` so that it returns one relevance score per
+/// query/document pair, `(batch,)`.
+pub(crate) fn load_reranker(
+    model_path: &Path,
+    config: &str,
+    model: Box<dyn Model>,
+) -> Result<Box<dyn Model>> {
+    let config: RerankerConfig = serde_json::from_str(config).map_err(candle::Error::msg)?;
+    if model.as_causal_lm().is_some() {
+        // bge-reranker-v2.5-gemma2 prompts for "Yes"/"No", Qwen based rerankers for "yes"/"no"
+        let (yes, no) = match config.model_type.as_deref() {
+            Some("gemma2") => ("Yes", "No"),
+            _ => ("yes", "no"),
+        };
+        let tokenizer = tk::Tokenizer::from_file(model_path.join("tokenizer.json"))
+            .map_err(candle::Error::msg)?;
+        let token_id = |token: &str| match tokenizer.token_to_id(token) {
+            Some(id) => Ok(id),
+            None => candle::bail!("Token `{token}` is not in the vocabulary"),
+        };
+        return Ok(Box::new(CausalLMReranker {
+            model,
+            yes_token_id: token_id(yes)?,
+            no_token_id: token_id(no)?,
+        }));
+    }
+
+    let activation = config
+        .sentence_transformers
+        .and_then(|c| c.activation_fn)
+        .or(config.sbert_ce_default_activation_function)
+        .map(|name| Activation::from_class_name(&name))
+        .transpose()?;
+    Ok(Box::new(Reranker {
+        model,
+        activation,
+        // Roberta style models only have a single segment embedding
+        use_token_type_ids: config.type_vocab_size.unwrap_or(0) > 1,
+    }))
+}
+
+/// Cross-encoder on top of a `*ForSequenceClassification` model.
+struct Reranker {
+    model: Box<dyn Model>,
+    activation: Option<Activation>,
+    use_token_type_ids: bool,
+}
+
+impl Model for Reranker {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        self.model.get_input_spec()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["scores".to_string()]
+    }
+
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let token_type_ids = match token_type_ids {
+            Some(token_type_ids) if self.use_token_type_ids => token_type_ids.clone(),
+            _ => input_ids.zeros_like()?,
+        };
+        let logits = self
+            .model
+            .forward(input_ids, attention_mask, Some(&token_type_ids))?
+            .to_dtype(DType::F32)?;
+        if logits.rank() != 2 {
+            candle::bail!("reranker mode requires a `*ForSequenceClassification` model");
+        }
+        let num_labels = logits.dim(1)?;
+        // Mirrors the sentence-transformers CrossEncoder default
+        let activation = self.activation.unwrap_or(if num_labels == 1 {
+            Activation::Sigmoid
+        } else {
+            Activation::Softmax
+        });
+        let scores = match activation {
+            Activation::Sigmoid => candle_nn::ops::sigmoid(&logits)?,
+            Activation::Softmax => candle_nn::ops::softmax_last_dim(&logits)?,
+            Activation::Identity => logits,
+        };
+        // The last label is the "relevant" class for multi-label heads
+        scores.i((.., num_labels - 1))
+    }
+}
+
+/// Decoder reranker that scores the probability of answering "yes" rather than "no" after the
+/// prompt.
+struct CausalLMReranker {
+    model: Box<dyn Model>,
+    yes_token_id: u32,
+    no_token_id: u32,
+}
+
+impl Model for CausalLMReranker {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(false)
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["scores".to_string()]
+    }
+
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        _token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let model = self.model.as_causal_lm().unwrap();
+        let mask = attention_mask.to_dtype(DType::U32)?.to_vec2::<u32>()?;
+        let rows = input_ids.to_dtype(DType::U32)?.to_vec2::<u32>()?;
+        // Strips the left or right padding, the prompts are right padded again to run together
+        let prompts = rows
+            .iter()
+            .zip(&mask)
+            .map(|(row, mask)| {
+                let start = mask.iter().position(|&m| m != 0).unwrap_or(0);
+                let len = mask.iter().filter(|&&m| m != 0).count().max(1);
+                &row[start..start + len]
+            })
+            .collect::<Vec<_>>();
+        let seq_lens = prompts.iter().map(|p| p.len()).collect::<Vec<_>>();
+        let max_len = seq_lens.iter().copied().max().unwrap_or(1);
+        let padded = prompts
+            .iter()
+            .flat_map(|p| {
+                p.iter()
+                    .copied()
+                    .chain(std::iter::repeat_n(0, max_len - p.len()))
+            })
+            .collect::<Vec<_>>();
+        let input_ids = Tensor::from_vec(padded, (prompts.len(), max_len), model.device())?;
+
+        let pool = KvCache::new(model.num_layers(), prompts.len());
+        let mut caches = (0..prompts.len())
+            .map(|_| pool.acquire().unwrap())
+            .collect::<Vec<SequenceCache>>();
+        let mut caches = caches.iter_mut().collect::<Vec<_>>();
+        let logits = model
+            .forward_step(&input_ids, Some(&seq_lens), &mut caches)?
+            .to_dtype(DType::F32)?;
+        let yes = logits.i((.., self.yes_token_id as usize))?;
+        let no = logits.i((.., self.no_token_id as usize))?;
+        // softmax over [no, yes]
+        candle_nn::ops::sigmoid(&(yes - no)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{last_tokens, CausalLM};
+    use candle::Device;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Predicts the last token of the prompt again.
+    struct Echo {
+        device: Device,
+        steps: Arc<AtomicUsize>,
+    }
+
+    impl Model for Echo {
+        fn get_input_spec(&self) -> Vec<InputSpec> {
+            token_inputs(false)
+        }
+
+        fn as_causal_lm(&self) -> Option<&dyn CausalLM> {
+            Some(self)
+        }
+    }
+
+    impl CausalLM for Echo {
+        fn num_layers(&self) -> usize {
+            1
+        }
+
+        fn device(&self) -> &Device {
+            &self.device
+        }
+
+        fn forward_step(
+            &self,
+            input_ids: &Tensor,
+            seq_lens: Option<&[usize]>,
+            caches: &mut [&mut SequenceCache],
+        ) -> Result<Tensor> {
+            self.steps.fetch_add(1, Ordering::Relaxed);
+            let one_hot = candle_nn::Embedding::new(Tensor::eye(4, DType::F32, &self.device)?, 4);
+            let xs = candle::Module::forward(&one_hot, input_ids)?;
+            let offsets = vec![0; caches.len()];
+            last_tokens(&xs, seq_lens, caches, &offsets)?.squeeze(1)
+        }
+    }
+
+    #[test]
+    fn test_causal_lm_reranker() -> Result<()> {
+        let device = Device::Cpu;
+        let steps = Arc::new(AtomicUsize::new(0));
+        let reranker = CausalLMReranker {
+            model: Box::new(Echo {
+                device: device.clone(),
+                steps: steps.clone(),
+            }),
+            yes_token_id: 1,
+            no_token_id: 0,
+        };
+        // A right padded, a left padded and an unpadded prompt
+        let input_ids = Tensor::new(&[[2u32, 1, 0, 0], [0, 0, 3, 0], [2, 3, 2, 1]], &device)?;
+        let mask = Tensor::new(&[[1u32, 1, 0, 0], [0, 0, 1, 1], [1, 1, 1, 1]], &device)?;
+        let scores = reranker
+            .forward(&input_ids, &mask, None)?
+            .to_vec1::<f32>()?;
+        // sigmoid(logit("yes") - logit("no"))
+        let expected = [1f32, -1., 1.].map(|x| 1. / (1. + (-x).exp()));
+        for (score, expected) in scores.iter().zip(expected) {
+            assert!((score - expected).abs() < 1e-6, "{scores:?}");
+        }
+        // The prompts run as a single batch
+        assert_eq!(steps.load(Ordering::Relaxed), 1);
+        Ok(())
+    }
+}
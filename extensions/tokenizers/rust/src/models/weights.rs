@@ -0,0 +1,257 @@
+use candle::{DType, Device, Error, Result, Tensor};
+use candle_nn::VarBuilder;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const SAFETENSORS_INDEX: &str = "model.safetensors.index.json";
+const SAFETENSORS: &str = "model.safetensors";
+const PYTORCH_INDEX: &str = "pytorch_model.bin.index.json";
+const PYTORCH: &str = "pytorch_model.bin";
+
+/// `*.index.json` of a sharded `transformers` checkpoint.
+#[derive(Debug, Deserialize)]
+struct WeightIndex {
+    weight_map: HashMap<String, String>,
+}
+
+/// Loads the weights in `model_path`, in order of precedence:
+///
+/// - the shards listed in `model.safetensors.index.json`
+/// - `model.safetensors`
+/// - every `model*.safetensors` file in the directory
+/// - the shards listed in `pytorch_model.bin.index.json`
+/// - `pytorch_model.bin`
+pub(crate) fn load_weights(
+    model_path: &Path,
+    dtype: DType,
+    device: &Device,
+) -> Result<VarBuilder<'static>> {
+    if model_path.join(SAFETENSORS_INDEX).exists() {
+        let shards = read_index(model_path, SAFETENSORS_INDEX)?;
+        return load_safetensors(&shards, dtype, device);
+    }
+    if model_path.join(SAFETENSORS).exists() {
+        return load_safetensors(&[(model_path.join(SAFETENSORS), None)], dtype, device);
+    }
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(model_path)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?;
+            if name.starts_with("model") && name.ends_with(".safetensors") {
+                Some(path)
+            } else {
+                None
+            }
+        })
+        .collect();
+    if !paths.is_empty() {
+        paths.sort();
+        let shards: Vec<_> = paths.into_iter().map(|path| (path, None)).collect();
+        return load_safetensors(&shards, dtype, device);
+    }
+    if model_path.join(PYTORCH_INDEX).exists() {
+        let shards = read_index(model_path, PYTORCH_INDEX)?;
+        return load_pytorch(&shards, dtype, device);
+    }
+    if model_path.join(PYTORCH).exists() {
+        return load_pytorch(&[(model_path.join(PYTORCH), None)], dtype, device);
+    }
+    candle::bail!(
+        "No safetensors or {PYTORCH} weights found in {}",
+        model_path.display()
+    )
+}
+
+/// A weight file, with the tensors the index expects to find in it.
+type Shard = (PathBuf, Option<Vec<String>>);
+
+fn read_index(model_path: &Path, index: &str) -> Result<Vec<Shard>> {
+    let index_str = std::fs::read_to_string(model_path.join(index))?;
+    let index: WeightIndex = serde_json::from_str(&index_str).map_err(Error::msg)?;
+    let mut shards: HashMap<String, Vec<String>> = HashMap::new();
+    for (name, file) in index.weight_map {
+        shards.entry(file).or_default().push(name);
+    }
+    let mut shards: Vec<Shard> = shards
+        .into_iter()
+        .map(|(file, names)| (model_path.join(file), Some(names)))
+        .collect();
+    shards.sort_by(|a, b| a.0.cmp(&b.0));
+    for (path, _) in shards.iter() {
+        if !path.exists() {
+            candle::bail!("Shard {} listed in the index is missing", path.display());
+        }
+    }
+    Ok(shards)
+}
+
+/// Fails if a tensor is stored in more than one shard, or is not in the shard the index points
+/// to. `names[i]` are the tensors stored in `shards[i]`.
+fn check_shards(shards: &[Shard], names: &[Vec<String>]) -> Result<()> {
+    let mut owners: HashMap<&str, &Path> = HashMap::new();
+    for ((path, expected), names) in shards.iter().zip(names) {
+        if let Some(expected) = expected {
+            if let Some(name) = expected.iter().find(|name| !names.contains(name)) {
+                candle::bail!("Tensor `{name}` is missing from {}", path.display());
+            }
+        }
+        for name in names {
+            if let Some(owner) = owners.insert(name, path) {
+                candle::bail!(
+                    "Tensor `{name}` is duplicated in {} and {}",
+                    owner.display(),
+                    path.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn load_safetensors(
+    shards: &[Shard],
+    dtype: DType,
+    device: &Device,
+) -> Result<VarBuilder<'static>> {
+    let names = shards
+        .iter()
+        .map(|(path, _)| safetensors_names(path))
+        .collect::<Result<Vec<_>>>()?;
+    check_shards(shards, &names)?;
+    let paths: Vec<&PathBuf> = shards.iter().map(|(path, _)| path).collect();
+    unsafe { VarBuilder::from_mmaped_safetensors(&paths, dtype, device) }
+}
+
+/// Reads the tensor names from the JSON header of a safetensors file.
+fn safetensors_names(path: &Path) -> Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let mut len = [0u8; 8];
+    file.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > file.metadata()?.len() {
+        candle::bail!("Invalid safetensors header in {}", path.display());
+    }
+    let mut header = vec![0u8; len as usize];
+    file.read_exact(&mut header)?;
+    let header: HashMap<String, serde_json::Value> =
+        serde_json::from_slice(&header).map_err(|e| Error::msg(e).with_path(path))?;
+    Ok(header
+        .into_keys()
+        .filter(|name| name != "__metadata__")
+        .collect())
+}
+
+fn load_pytorch(shards: &[Shard], dtype: DType, device: &Device) -> Result<VarBuilder<'static>> {
+    let tensors = shards
+        .iter()
+        .map(|(path, _)| candle::pickle::read_all(path).map_err(|e| e.with_path(path)))
+        .collect::<Result<Vec<_>>>()?;
+    let names: Vec<Vec<String>> = tensors
+        .iter()
+        .map(|shard| shard.iter().map(|(name, _)| name.clone()).collect())
+        .collect();
+    check_shards(shards, &names)?;
+    let tensors: HashMap<String, Tensor> = tensors
+        .into_iter()
+        .flatten()
+        .map(|(name, tensor)| (rename_legacy(name), tensor))
+        .collect();
+    Ok(VarBuilder::from_tensors(tensors, dtype, device))
+}
+
+// Old TensorFlow ported BERT checkpoints name the LayerNorm parameters `gamma` and `beta`
+fn rename_legacy(name: String) -> String {
+    let is_layer_norm = |prefix: &str| {
+        let module = prefix.rsplit('.').next().unwrap_or(prefix).to_lowercase();
+        module.contains("layernorm") || module.contains("layer_norm")
+    };
+    match name.rsplit_once('.') {
+        Some((prefix, "gamma")) if is_layer_norm(prefix) => format!("{prefix}.weight"),
+        Some((prefix, "beta")) if is_layer_norm(prefix) => format!("{prefix}.bias"),
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shard(path: &str, names: Option<&[&str]>) -> Shard {
+        let names = names.map(|names| names.iter().map(|name| name.to_string()).collect());
+        (PathBuf::from(path), names)
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_check_shards() {
+        let shards = [shard("a", Some(&["x", "y"])), shard("b", Some(&["z"]))];
+        assert!(check_shards(&shards, &[names(&["x", "y"]), names(&["z"])]).is_ok());
+
+        let err = check_shards(&shards, &[names(&["x"]), names(&["y", "z"])]).unwrap_err();
+        assert!(
+            err.to_string().contains("Tensor `y` is missing from a"),
+            "{err}"
+        );
+
+        let shards = [shard("a", None), shard("b", None)];
+        let err = check_shards(&shards, &[names(&["x"]), names(&["x"])]).unwrap_err();
+        assert!(
+            err.to_string().contains("`x` is duplicated in a and b"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_read_index() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("djl-weights-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let index = r#"{"metadata": {}, "weight_map": {
+            "x": "model-00002.safetensors",
+            "y": "model-00001.safetensors",
+            "z": "model-00002.safetensors"
+        }}"#;
+        std::fs::write(dir.join(SAFETENSORS_INDEX), index)?;
+        let missing = read_index(&dir, SAFETENSORS_INDEX).unwrap_err();
+        std::fs::write(dir.join("model-00001.safetensors"), "")?;
+        std::fs::write(dir.join("model-00002.safetensors"), "")?;
+        let shards = read_index(&dir, SAFETENSORS_INDEX);
+        std::fs::remove_dir_all(&dir)?;
+
+        assert!(missing
+            .to_string()
+            .contains("listed in the index is missing"));
+        let mut shards = shards?;
+        for (_, names) in shards.iter_mut() {
+            names.as_mut().unwrap().sort();
+        }
+        assert_eq!(
+            shards,
+            [
+                (dir.join("model-00001.safetensors"), Some(names(&["y"]))),
+                (
+                    dir.join("model-00002.safetensors"),
+                    Some(names(&["x", "z"]))
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_legacy() {
+        let rename = |name: &str| rename_legacy(name.to_string());
+        assert_eq!(
+            rename("bert.embeddings.LayerNorm.gamma"),
+            "bert.embeddings.LayerNorm.weight"
+        );
+        assert_eq!(rename("encoder.layer_norm.beta"), "encoder.layer_norm.bias");
+        assert_eq!(rename("attention.gamma"), "attention.gamma");
+        assert_eq!(rename("LayerNorm.beta.weight"), "LayerNorm.beta.weight");
+    }
+}
@@ -1,5 +1,6 @@
-use crate::layers::{LayerNorm, Linear};
-use crate::models::Model;
+use crate::layers::{get_optional, LayerNorm, Linear};
+use crate::models::inputs::{token_inputs, InputSpec};
+use crate::models::{span_output_names, span_outputs, Model, ModelOutputs};
 use candle::{Device, IndexOp, Result, Tensor};
 use candle_nn::{embedding, Embedding, Module, VarBuilder};
 use serde::Deserialize;
@@ -551,12 +552,8 @@ impl XLMRobertaModel {
 }
 
 impl Model for XLMRobertaModel {
-    fn get_input_names(&self) -> Vec<String> {
-        return vec![
-            "input_ids".to_string(),
-            "attention_mask".to_string(),
-            "token_type_ids".to_string(),
-        ];
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(true)
     }
 
     fn forward(
@@ -576,7 +573,7 @@ impl Model for XLMRobertaModel {
 
 pub struct XLMRobertaForSequenceClassification {
     roberta: Box<XLMRobertaModel>,
-    classifier: Box<dyn ClassificationHead + Send>,
+    classifier: Box<dyn ClassificationHead + Send + Sync>,
     #[allow(unused)]
     pub device: Device,
     span: tracing::Span,
@@ -585,7 +582,7 @@ pub struct XLMRobertaForSequenceClassification {
 impl XLMRobertaForSequenceClassification {
     pub fn load(vb: VarBuilder, config: &XLMRobertaConfig) -> Result<Self> {
         let roberta = Box::new(XLMRobertaModel::load(vb.clone(), &config)?);
-        let classifier: Box<dyn ClassificationHead + Send> = Box::new(
+        let classifier: Box<dyn ClassificationHead + Send + Sync> = Box::new(
             XLMRobertaClassificationHead::load(vb.pp("classifier"), config)?,
         );
         Ok(Self {
@@ -598,12 +595,12 @@ impl XLMRobertaForSequenceClassification {
 }
 
 impl Model for XLMRobertaForSequenceClassification {
-    fn get_input_names(&self) -> Vec<String> {
-        return vec![
-            "input_ids".to_string(),
-            "attention_mask".to_string(),
-            "token_type_ids".to_string(),
-        ];
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        token_inputs(true)
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
     }
 
     fn forward(
@@ -621,3 +618,208 @@ impl Model for XLMRobertaForSequenceClassification {
         Ok(logits)
     }
 }
+
+pub struct XLMRobertaForTokenClassification {
+    roberta: Box<XLMRobertaModel>,
+    classifier: Linear,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl XLMRobertaForTokenClassification {
+    pub fn load(vb: VarBuilder, config: &XLMRobertaConfig) -> Result<Self> {
+        let n_classes = match &config.id2label {
+            None => candle::bail!("`id2label` must be set for classifier models"),
+            Some(id2label) => id2label.len(),
+        };
+        let roberta = Box::new(XLMRobertaModel::load(vb.clone(), config)?);
+        let classifier = Linear::load(vb.pp("classifier"), config.hidden_size, n_classes, None)?;
+        Ok(Self {
+            roberta,
+            classifier,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for XLMRobertaForTokenClassification {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        self.roberta.get_input_spec()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    /// Returns the per-token logits, `(batch, seq_len, num_labels)`.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let sequence_output = self
+            .roberta
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        self.classifier.forward(&sequence_output)
+    }
+}
+
+pub struct XLMRobertaForQuestionAnswering {
+    roberta: Box<XLMRobertaModel>,
+    qa_outputs: Linear,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl XLMRobertaForQuestionAnswering {
+    pub fn load(vb: VarBuilder, config: &XLMRobertaConfig) -> Result<Self> {
+        let roberta = Box::new(XLMRobertaModel::load(vb.clone(), config)?);
+        let qa_outputs = Linear::load(vb.pp("qa_outputs"), config.hidden_size, 2, None)?;
+        Ok(Self {
+            roberta,
+            qa_outputs,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for XLMRobertaForQuestionAnswering {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        self.roberta.get_input_spec()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        span_output_names()
+    }
+
+    /// Returns the span logits, `(batch, seq_len, 2)`, with the start logits at index 0 and the
+    /// end logits at index 1 of the last dimension.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let sequence_output = self
+            .roberta
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        self.qa_outputs.forward(&sequence_output)
+    }
+
+    fn forward_outputs(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+        requested: &[String],
+    ) -> Result<ModelOutputs> {
+        let logits = self.forward(input_ids, attention_mask, token_type_ids)?;
+        span_outputs(logits, requested)
+    }
+}
+
+// https://github.com/huggingface/transformers/blob/6eedfa6dd15dc1e22a55ae036f681914e5a0d9a1/src/transformers/models/roberta/modeling_roberta.py#L1122
+pub struct XLMRobertaLMHead {
+    dense: Linear,
+    layer_norm: LayerNorm,
+    decoder: Linear,
+    span: tracing::Span,
+}
+
+impl XLMRobertaLMHead {
+    pub(crate) fn load(vb: VarBuilder, config: &XLMRobertaConfig) -> Result<Self> {
+        let hidden_size = config.hidden_size;
+        let dense = Linear::load(vb.pp("lm_head.dense"), hidden_size, hidden_size, None)?;
+        let layer_norm = LayerNorm::load(
+            vb.pp("lm_head.layer_norm"),
+            hidden_size,
+            config.layer_norm_eps as f32,
+        )?;
+        // The decoder is usually tied to the word embeddings and not serialized
+        let weight = vb
+            .get((config.vocab_size, hidden_size), "lm_head.decoder.weight")
+            .or_else(|_| {
+                vb.get(
+                    (config.vocab_size, hidden_size),
+                    "roberta.embeddings.word_embeddings.weight",
+                )
+            })
+            .or_else(|_| {
+                vb.get(
+                    (config.vocab_size, hidden_size),
+                    "embeddings.word_embeddings.weight",
+                )
+            })?;
+        let bias = get_optional(
+            &vb,
+            config.vocab_size,
+            &["lm_head.decoder.bias", "lm_head.bias"],
+        )?;
+        Ok(Self {
+            dense,
+            layer_norm,
+            decoder: Linear::new(weight, bias, None),
+            span: tracing::span!(tracing::Level::TRACE, "lm-head"),
+        })
+    }
+
+    fn forward(&self, hidden_states: &Tensor) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let hidden_states = self.dense.forward(hidden_states)?;
+        let hidden_states = hidden_states.gelu_erf()?;
+        let hidden_states = self.layer_norm.forward(&hidden_states, None)?;
+        self.decoder.forward(&hidden_states)
+    }
+}
+
+pub struct XLMRobertaForMaskedLM {
+    roberta: Box<XLMRobertaModel>,
+    lm_head: XLMRobertaLMHead,
+    #[allow(unused)]
+    pub device: Device,
+    span: tracing::Span,
+}
+
+impl XLMRobertaForMaskedLM {
+    pub fn load(vb: VarBuilder, config: &XLMRobertaConfig) -> Result<Self> {
+        let roberta = Box::new(XLMRobertaModel::load(vb.clone(), config)?);
+        let lm_head = XLMRobertaLMHead::load(vb.clone(), config)?;
+        Ok(Self {
+            roberta,
+            lm_head,
+            device: vb.device().clone(),
+            span: tracing::span!(tracing::Level::TRACE, "model"),
+        })
+    }
+}
+
+impl Model for XLMRobertaForMaskedLM {
+    fn get_input_spec(&self) -> Vec<InputSpec> {
+        self.roberta.get_input_spec()
+    }
+
+    fn get_output_names(&self) -> Vec<String> {
+        vec!["logits".to_string()]
+    }
+
+    /// Returns the vocabulary logits of every token, `(batch, seq_len, vocab_size)`.
+    fn forward(
+        &self,
+        input_ids: &Tensor,
+        attention_mask: &Tensor,
+        token_type_ids: Option<&Tensor>,
+    ) -> Result<Tensor> {
+        let _enter = self.span.enter();
+        let sequence_output = self
+            .roberta
+            .forward(input_ids, attention_mask, token_type_ids)?;
+        self.lm_head.forward(&sequence_output)
+    }
+}
@@ -0,0 +1,224 @@
+use crate::ndarray::{return_handle, throw_error};
+use crate::{cast_handle, drop_handle, to_handle};
+use candle::{Result, Tensor, TensorId, Var};
+use jni::objects::JObject;
+use jni::sys::{jboolean, jlong, JNI_FALSE, JNI_TRUE};
+use jni::JNIEnv;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+/// The accumulated gradient of every tensor that requires one, `None` until the first backward
+/// pass reaches it.
+fn gradients() -> MutexGuard<'static, HashMap<TensorId, Option<Tensor>>> {
+    static GRADIENTS: OnceLock<Mutex<HashMap<TensorId, Option<Tensor>>>> = OnceLock::new();
+    GRADIENTS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// The variables reached by the backward passes of a gradient collector.
+#[derive(Default)]
+struct GradientCollector {
+    variables: HashSet<TensorId>,
+}
+
+impl GradientCollector {
+    /// Runs a backward pass from `tensor` and accumulates the gradients of the variables.
+    fn backward(&mut self, tensor: &Tensor) -> Result<()> {
+        let grads = tensor.backward()?;
+        // Gradients accumulate over backward passes until they are zeroed
+        let mut gradients = gradients();
+        for id in grads.get_ids() {
+            if let Some(gradient) = gradients.get_mut(id) {
+                let grad = grads.get_id(*id).unwrap().detach();
+                *gradient = match gradient.take() {
+                    Some(acc) => Some((acc + grad)?),
+                    None => Some(grad),
+                };
+                self.variables.insert(*id);
+            }
+        }
+        Ok(())
+    }
+
+    fn zero_gradients(&self) {
+        let mut gradients = gradients();
+        for id in &self.variables {
+            if let Some(gradient) = gradients.get_mut(id) {
+                *gradient = None;
+            }
+        }
+    }
+}
+
+/// Forgets the gradient of a variable whose handle is deleted. Arrays sharing the variable, e.g.
+/// the result of a conversion to its own dtype, lose the gradient as well.
+pub(crate) fn remove_gradient(tensor: &Tensor) {
+    if tensor.is_variable() {
+        gradients().remove(&tensor.id());
+    }
+}
+
+/// Replaces the value of the tensor behind `handle`. Variables are updated in place so that they
+/// keep their id, and with it their gradient.
+pub(crate) fn assign(handle: jlong, value: Tensor) -> Result<()> {
+    let tensor = cast_handle::<Tensor>(handle);
+    if tensor.is_variable() {
+        Var::from_tensor(tensor)?.set(&value.to_dtype(tensor.dtype())?)
+    } else {
+        *tensor = value;
+        Ok(())
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_setRequiresGradient(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    requires_grad: jboolean,
+) {
+    let tensor = cast_handle::<Tensor>(handle);
+    if requires_grad == JNI_TRUE {
+        match Var::from_tensor(tensor) {
+            Ok(var) => {
+                *tensor = var.into_inner();
+                gradients().entry(tensor.id()).or_insert(None);
+            }
+            Err(e) => throw_error(&mut env, e),
+        }
+    } else if tensor.is_variable() {
+        gradients().remove(&tensor.id());
+        *tensor = tensor.detach();
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_requiresGradient(
+    _: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jboolean {
+    let tensor = cast_handle::<Tensor>(handle);
+    if tensor.is_variable() {
+        JNI_TRUE
+    } else {
+        JNI_FALSE
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_getGradient(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    // Before the first backward pass the gradient is zero
+    let gradient = gradients().get(&tensor.id()).cloned().flatten();
+    match gradient {
+        Some(gradient) => to_handle(gradient),
+        None => return_handle(&mut env, tensor.zeros_like()),
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_stopGradient(
+    _: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    to_handle(tensor.detach())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_createGradientCollector(
+    _: JNIEnv,
+    _: JObject,
+) -> jlong {
+    to_handle(GradientCollector::default())
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_deleteGradientCollector(
+    _: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) {
+    drop_handle::<GradientCollector>(handle);
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_backward(
+    mut env: JNIEnv,
+    _: JObject,
+    collector_handle: jlong,
+    handle: jlong,
+) {
+    let collector = cast_handle::<GradientCollector>(collector_handle);
+    let tensor = cast_handle::<Tensor>(handle);
+    if let Err(e) = collector.backward(tensor) {
+        throw_error(&mut env, e);
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_zeroGradients(
+    _: JNIEnv,
+    _: JObject,
+    collector_handle: jlong,
+) {
+    let collector = cast_handle::<GradientCollector>(collector_handle);
+    collector.zero_gradients();
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_zeroGradient(
+    _: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) {
+    let tensor = cast_handle::<Tensor>(handle);
+    if let Some(gradient) = gradients().get_mut(&tensor.id()) {
+        *gradient = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle::Device;
+
+    fn gradient(tensor: &Tensor) -> Option<Vec<f32>> {
+        let gradient = gradients().get(&tensor.id()).cloned().flatten();
+        gradient.map(|g| g.to_vec1().unwrap())
+    }
+
+    #[test]
+    fn test_gradients() -> Result<()> {
+        let x = Var::new(&[1f32, 2.], &Device::Cpu)?.into_inner();
+        let y = Var::new(&[3f32], &Device::Cpu)?.into_inner();
+        gradients().insert(x.id(), None);
+        gradients().insert(y.id(), None);
+
+        let mut first = GradientCollector::default();
+        first.backward(&x.sqr()?.sum_all()?)?;
+        first.backward(&x.sum_all()?)?;
+        assert_eq!(gradient(&x), Some(vec![3., 5.]));
+        let mut second = GradientCollector::default();
+        second.backward(&y.sum_all()?)?;
+
+        // Only the variables reached by a collector are zeroed
+        first.zero_gradients();
+        assert_eq!(gradient(&x), None);
+        assert_eq!(gradient(&y), Some(vec![1.]));
+
+        remove_gradient(&x);
+        remove_gradient(&y);
+        assert!(!gradients().contains_key(&x.id()));
+        assert!(!gradients().contains_key(&y.id()));
+        Ok(())
+    }
+}
@@ -1,10 +1,209 @@
-use candle::Tensor;
+use candle::cpu_backend::{binary_map, Map2};
+use candle::{CpuStorage, CustomOp2, DType, Device, Layout, Result, Shape, Tensor, WithDType};
 use jni::objects::JObject;
-use jni::sys::jlong;
+use jni::sys::{jdouble, jlong};
 use jni::JNIEnv;
 
 use crate::cast_handle;
+use crate::ndarray::as_float_dtype;
 use crate::ndarray::return_handle;
+use crate::ndarray::unary::map_unary;
+
+/// An elementwise kernel of two tensors, the function is evaluated in f64 for every dtype.
+struct Binary<F> {
+    name: &'static str,
+    func: F,
+}
+
+impl<F: Fn(f64, f64) -> f64> Map2 for Binary<F> {
+    const OP: &'static str = "binary";
+
+    fn f<T: WithDType>(&self, v1: &[T], l1: &Layout, v2: &[T], l2: &Layout) -> Result<Vec<T>> {
+        Ok(binary_map(l1, l2, v1, v2, |a, b| {
+            T::from_f64((self.func)(a.to_f64(), b.to_f64()))
+        }))
+    }
+}
+
+impl<F: Fn(f64, f64) -> f64> CustomOp2 for Binary<F> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn cpu_fwd(
+        &self,
+        s1: &CpuStorage,
+        l1: &Layout,
+        s2: &CpuStorage,
+        l2: &Layout,
+    ) -> Result<(CpuStorage, Shape)> {
+        Ok((self.map(s1, l1, s2, l2)?, l1.shape().clone()))
+    }
+}
+
+/// Applies `func` to every pair of broadcast elements, in the dtype of `lhs`. The kernel runs on
+/// the CPU, tensors on other devices are copied over and back.
+fn map_binary<F: Fn(f64, f64) -> f64>(
+    lhs: &Tensor,
+    rhs: &Tensor,
+    name: &'static str,
+    func: F,
+) -> Result<Tensor> {
+    let rhs = rhs.to_dtype(lhs.dtype())?;
+    let shape = lhs.shape().broadcast_shape_binary_op(rhs.shape(), name)?;
+    // The broadcast views have zero strides, the kernel reads them in place
+    let cpu = lhs.to_device(&Device::Cpu)?.broadcast_as(&shape)?;
+    let rhs = rhs.to_device(&Device::Cpu)?.broadcast_as(&shape)?;
+    cpu.apply_op2_no_bwd(&rhs, &Binary { name, func })?
+        .to_device(lhs.device())
+}
+
+/// Like [map_binary], returns a boolean (U8) tensor.
+fn map_bool<F: Fn(f64, f64) -> bool>(
+    lhs: &Tensor,
+    rhs: &Tensor,
+    name: &'static str,
+    func: F,
+) -> Result<Tensor> {
+    map_binary(lhs, rhs, name, |a, b| if func(a, b) { 1.0 } else { 0.0 })?.to_dtype(DType::U8)
+}
+
+/// An elementwise kernel of two integer tensors, the function is evaluated in i64 so that values
+/// beyond 2^53 stay exact. It returns an error message for undefined results.
+struct IntBinary<F> {
+    name: &'static str,
+    func: F,
+}
+
+impl<F: Fn(i64, i64) -> std::result::Result<i64, &'static str>> IntBinary<F> {
+    fn apply<T: Copy>(
+        &self,
+        (v1, l1): (&[T], &Layout),
+        (v2, l2): (&[T], &Layout),
+        to_i64: fn(T) -> i64,
+        from_i64: fn(i64) -> T,
+    ) -> Result<Vec<T>> {
+        let mut error = None;
+        let vs = binary_map(l1, l2, v1, v2, |a, b| {
+            (self.func)(to_i64(a), to_i64(b)).unwrap_or_else(|msg| {
+                error = Some(msg);
+                0
+            })
+        });
+        match error {
+            Some(msg) => candle::bail!("{}: {msg}", self.name),
+            None => Ok(vs.into_iter().map(from_i64).collect()),
+        }
+    }
+}
+
+impl<F: Fn(i64, i64) -> std::result::Result<i64, &'static str>> CustomOp2 for IntBinary<F> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn cpu_fwd(
+        &self,
+        s1: &CpuStorage,
+        l1: &Layout,
+        s2: &CpuStorage,
+        l2: &Layout,
+    ) -> Result<(CpuStorage, Shape)> {
+        // Results out of range wrap around, like the integer ops of PyTorch
+        let storage = match (s1, s2) {
+            (CpuStorage::U8(v1), CpuStorage::U8(v2)) => {
+                CpuStorage::U8(self.apply((v1, l1), (v2, l2), i64::from, |v| v as u8)?)
+            }
+            (CpuStorage::U32(v1), CpuStorage::U32(v2)) => {
+                CpuStorage::U32(self.apply((v1, l1), (v2, l2), i64::from, |v| v as u32)?)
+            }
+            (CpuStorage::I64(v1), CpuStorage::I64(v2)) => {
+                CpuStorage::I64(self.apply((v1, l1), (v2, l2), |v| v, |v| v)?)
+            }
+            _ => candle::bail!("{}: expected integer tensors of the same dtype", self.name),
+        };
+        Ok((storage, l1.shape().clone()))
+    }
+}
+
+/// Applies `func` to every pair of broadcast elements of two integer tensors, in the dtype of
+/// `lhs`. The kernel runs on the CPU, tensors on other devices are copied over and back.
+fn map_int_binary<F: Fn(i64, i64) -> std::result::Result<i64, &'static str>>(
+    lhs: &Tensor,
+    rhs: &Tensor,
+    name: &'static str,
+    func: F,
+) -> Result<Tensor> {
+    let rhs = rhs.to_dtype(lhs.dtype())?;
+    let shape = lhs.shape().broadcast_shape_binary_op(rhs.shape(), name)?;
+    let cpu = lhs.to_device(&Device::Cpu)?.broadcast_as(&shape)?;
+    let rhs = rhs.to_device(&Device::Cpu)?.broadcast_as(&shape)?;
+    cpu.apply_op2_no_bwd(&rhs, &IntBinary { name, func })?
+        .to_device(lhs.device())
+}
+
+/// The remainder with the sign of the divisor, like Python's `%`.
+fn remainder(lhs: &Tensor, rhs: &Tensor) -> Result<Tensor> {
+    let rhs = rhs.to_dtype(lhs.dtype())?;
+    if lhs.dtype().is_int() {
+        map_int_binary(lhs, &rhs, "remainder", |a, b| {
+            if b == 0 {
+                return Err("integer division by zero");
+            }
+            let r = a.wrapping_rem(b);
+            Ok(if r != 0 && (r < 0) != (b < 0) {
+                r + b
+            } else {
+                r
+            })
+        })
+    } else {
+        // `lhs - rhs * floor(lhs / rhs)` with native ops, so that it has a gradient
+        lhs.broadcast_sub(&lhs.broadcast_div(&rhs)?.floor()?.broadcast_mul(&rhs)?)
+    }
+}
+
+/// Raises `lhs` to the power `rhs`, integer tensors are computed with exact integer powers.
+fn pow(lhs: &Tensor, rhs: &Tensor) -> Result<Tensor> {
+    let rhs = rhs.to_dtype(lhs.dtype())?;
+    if lhs.dtype().is_int() {
+        map_int_binary(lhs, &rhs, "pow", |mut base, mut exp| {
+            if exp < 0 {
+                return Err("integers to negative integer powers are not allowed");
+            }
+            let mut acc = 1i64;
+            while exp > 0 {
+                if exp & 1 == 1 {
+                    acc = acc.wrapping_mul(base);
+                }
+                base = base.wrapping_mul(base);
+                exp >>= 1;
+            }
+            Ok(acc)
+        })
+    } else {
+        lhs.broadcast_pow(&rhs)
+    }
+}
+
+/// Returns `tensor` and `value` as a rank 0 tensor of the same dtype and device. An integer
+/// tensor is converted to F32 first if `value` has a fractional part, like PyTorch promotes it.
+fn with_scalar(tensor: &Tensor, value: f64) -> Result<(Tensor, Tensor)> {
+    let tensor = if tensor.dtype().is_int() && value.fract() != 0.0 {
+        tensor.to_dtype(DType::F32)?
+    } else {
+        tensor.clone()
+    };
+    let device = tensor.device();
+    // Integers are not converted through f64 tensors, which round beyond 2^53
+    let scalar = match tensor.dtype() {
+        DType::U8 => Tensor::new(value as u8, device)?,
+        DType::U32 => Tensor::new(value as u32, device)?,
+        DType::I64 => Tensor::new(value as i64, device)?,
+        dtype => Tensor::new(value, device)?.to_dtype(dtype)?,
+    };
+    Ok((tensor, scalar))
+}
 
 #[no_mangle]
 pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_add<'local>(
@@ -111,8 +310,8 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_pow<'local>(
 ) -> jlong {
     let op = || {
         let lhs = cast_handle::<Tensor>(handle);
-        let rhs = cast_handle::<Tensor>(other_handle).to_dtype(lhs.dtype())?;
-        lhs.broadcast_pow(&rhs)
+        let rhs = cast_handle::<Tensor>(other_handle);
+        pow(lhs, rhs)
     };
     let ret = op();
     return_handle(&mut env, ret)
@@ -149,3 +348,269 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_batchMatMul<'local>(
     let ret = op();
     return_handle(&mut env, ret)
 }
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_batchDot(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    other_handle: jlong,
+) -> jlong {
+    let op = || {
+        let lhs = cast_handle::<Tensor>(handle);
+        let rhs = cast_handle::<Tensor>(other_handle).to_dtype(lhs.dtype())?;
+        // `(B_0, ..., B_i, N, M) x (B_0, ..., B_i, M, K)`, the batch dims are broadcast
+        lhs.broadcast_matmul(&rhs)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_remainder(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    other_handle: jlong,
+) -> jlong {
+    let op = || {
+        let lhs = cast_handle::<Tensor>(handle);
+        let rhs = cast_handle::<Tensor>(other_handle);
+        remainder(lhs, rhs)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_xlogy(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    other_handle: jlong,
+) -> jlong {
+    let op = || {
+        let lhs = cast_handle::<Tensor>(handle);
+        let rhs = cast_handle::<Tensor>(other_handle);
+        let lhs = lhs.to_dtype(as_float_dtype(lhs.dtype()))?;
+        map_binary(&lhs, rhs, "xlogy", |x, y| {
+            if x == 0.0 && !y.is_nan() {
+                0.0
+            } else {
+                x * y.ln()
+            }
+        })
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_atan2(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    other_handle: jlong,
+) -> jlong {
+    let op = || {
+        let lhs = cast_handle::<Tensor>(handle);
+        let rhs = cast_handle::<Tensor>(other_handle);
+        let lhs = lhs.to_dtype(as_float_dtype(lhs.dtype()))?;
+        map_binary(&lhs, rhs, "atan2", f64::atan2)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_logicalAnd(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    other_handle: jlong,
+) -> jlong {
+    let op = || {
+        let lhs = cast_handle::<Tensor>(handle);
+        let rhs = cast_handle::<Tensor>(other_handle);
+        map_bool(lhs, rhs, "logicalAnd", |a, b| a != 0.0 && b != 0.0)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_logicalOr(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    other_handle: jlong,
+) -> jlong {
+    let op = || {
+        let lhs = cast_handle::<Tensor>(handle);
+        let rhs = cast_handle::<Tensor>(other_handle);
+        map_bool(lhs, rhs, "logicalOr", |a, b| a != 0.0 || b != 0.0)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_logicalXor(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    other_handle: jlong,
+) -> jlong {
+    let op = || {
+        let lhs = cast_handle::<Tensor>(handle);
+        let rhs = cast_handle::<Tensor>(other_handle);
+        map_bool(lhs, rhs, "logicalXor", |a, b| (a != 0.0) != (b != 0.0))
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_remainderScalar(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    value: jdouble,
+) -> jlong {
+    let op = || {
+        let (tensor, scalar) = with_scalar(cast_handle::<Tensor>(handle), value)?;
+        remainder(&tensor, &scalar)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_powScalar(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    value: jdouble,
+) -> jlong {
+    let op = || {
+        let (tensor, scalar) = with_scalar(cast_handle::<Tensor>(handle), value)?;
+        if tensor.dtype().is_int() {
+            pow(&tensor, &scalar)
+        } else {
+            tensor.powf(value)
+        }
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_rsubScalar(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    value: jdouble,
+) -> jlong {
+    let op = || {
+        let (tensor, scalar) = with_scalar(cast_handle::<Tensor>(handle), value)?;
+        scalar.broadcast_sub(&tensor)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_rdivScalar(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    value: jdouble,
+) -> jlong {
+    let op = || {
+        let (tensor, scalar) = with_scalar(cast_handle::<Tensor>(handle), value)?;
+        scalar.broadcast_div(&tensor)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_rremainderScalar(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    value: jdouble,
+) -> jlong {
+    let op = || {
+        let (tensor, scalar) = with_scalar(cast_handle::<Tensor>(handle), value)?;
+        remainder(&scalar, &tensor)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_rpowScalar(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    value: jdouble,
+) -> jlong {
+    let op = || {
+        let (tensor, scalar) = with_scalar(cast_handle::<Tensor>(handle), value)?;
+        if tensor.dtype().is_int() {
+            pow(&scalar, &tensor)
+        } else if value > 0.0 {
+            // `exp(x * ln(value))` keeps the gradient
+            tensor.affine(value.ln(), 0.0)?.exp()
+        } else {
+            // The logarithm is undefined, e.g. `(-2)^x` for integral x
+            map_unary(&tensor, "rpowScalar", |v| value.powf(v))
+        }
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle::Var;
+
+    #[test]
+    fn test_remainder() -> Result<()> {
+        let device = Device::Cpu;
+        let (lhs, rhs) = with_scalar(&Tensor::new(&[5f32, -5., 5.5], &device)?, -2.0)?;
+        let ret = remainder(&lhs, &rhs)?.to_vec1::<f32>()?;
+        assert_eq!(ret, [-1., -1., -0.5]);
+
+        let lhs = Tensor::new(&[5i64, -5, (1 << 60) + 1], &device)?;
+        let (lhs, rhs) = with_scalar(&lhs, 3.0)?;
+        let ret = remainder(&lhs, &rhs)?.to_vec1::<i64>()?;
+        assert_eq!(ret, [2, 1, ((1i64 << 60) + 1) % 3]);
+        assert!(remainder(&lhs, &lhs.zeros_like()?).is_err());
+
+        // The gradient of the dividend is one
+        let x = Var::new(&[3f32, 7.], &device)?;
+        let (_, rhs) = with_scalar(&x, 2.0)?;
+        let grads = remainder(&x, &rhs)?.sum_all()?.backward()?;
+        assert_eq!(grads.get(&x).unwrap().to_vec1::<f32>()?, [1., 1.]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pow() -> Result<()> {
+        let device = Device::Cpu;
+        let (lhs, rhs) = with_scalar(&Tensor::new(&[3i64, -2], &device)?, 37.0)?;
+        let ret = pow(&lhs, &rhs)?.to_vec1::<i64>()?;
+        assert_eq!(ret, [3i64.pow(37), -(2i64.pow(37))]);
+        assert!(pow(&lhs, &Tensor::new(-1i64, &device)?).is_err());
+        let ret = pow(&rhs, &Tensor::new(&[2u8, 3], &device)?)?.to_vec1::<i64>()?;
+        assert_eq!(ret, [37 * 37, 37 * 37 * 37]);
+
+        // A fractional exponent promotes integers to F32
+        let (lhs, _) = with_scalar(&Tensor::new(&[4u32], &device)?, 0.5)?;
+        assert_eq!(lhs.powf(0.5)?.to_vec1::<f32>()?, [2.]);
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use crate::cast_handle;
+use crate::ndarray::dtype::DataType;
 use crate::ndarray::{as_data_type, as_device, as_shape, return_handle};
 use candle::{DType, Error, Tensor};
 use half::{bf16, f16};
@@ -20,12 +21,12 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_tensorOf<'local>(
     let tensor = || {
         let shape = as_shape(&mut env, &shape);
         let device = as_device(&mut env, device_type, device_id as usize)?;
-        let dtype = as_data_type(dtype)?;
+        let dtype = DataType::from_ordinal(dtype)?;
 
         let len = env.get_direct_buffer_capacity(&buffer).unwrap();
         let data = env.get_direct_buffer_address(&buffer).unwrap();
         let data = unsafe { slice::from_raw_parts(data, len) };
-        Tensor::from_raw_buffer(data, dtype, shape.dims(), &device)
+        dtype.decode(data, &shape, &device)
     };
     let ret = tensor();
     return_handle(&mut env, ret)
@@ -219,3 +220,26 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_duplicate<'local>(
     let tensor = cast_handle::<Tensor>(handle);
     return_handle(&mut env, tensor.copy())
 }
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_hannWindow(
+    mut env: JNIEnv,
+    _: JObject,
+    num_points: jlong,
+    device_type: JString,
+    device_id: jint,
+) -> jlong {
+    let tensor = || {
+        let device = as_device(&mut env, device_type, device_id as usize)?;
+        // The periodic window, `0.5 * (1 - cos(2 * pi * n / N))`
+        let n = num_points as usize;
+        let window: Vec<f32> = (0..n)
+            .map(|i| {
+                (0.5 * (1.0 - (2.0 * std::f64::consts::PI * i as f64 / n as f64).cos())) as f32
+            })
+            .collect();
+        Tensor::from_vec(window, n, &device)
+    };
+    let ret = tensor();
+    return_handle(&mut env, ret)
+}
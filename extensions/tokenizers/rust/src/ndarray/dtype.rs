@@ -0,0 +1,302 @@
+use candle::{CpuStorage, DType, Device, Result, Shape, Storage, Tensor, WithDType, D};
+use half::{bf16, f16};
+use std::slice;
+
+/// The DJL data types, see `ai.djl.ndarray.types.DataType`.
+///
+/// Candle has no storage for some of them, those are emulated with a wider candle dtype: signed
+/// integers are held in I64, UINT16 in U32, UINT64 in I64 (bit-cast), booleans in U8 as 0 or 1,
+/// and COMPLEX64 in F32 with a trailing dim of size 2 holding the real and imaginary parts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataType {
+    Float32,
+    Float64,
+    Float16,
+    Bfloat16,
+    Uint8,
+    Uint16,
+    Uint32,
+    Uint64,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Boolean,
+    Complex64,
+}
+
+impl DataType {
+    pub fn from_ordinal(ordinal: i32) -> Result<Self> {
+        let data_type = match ordinal {
+            0 => Self::Float32,
+            1 => Self::Float64,
+            2 => Self::Float16,
+            3 => Self::Uint8,
+            4 => Self::Int32,
+            5 => Self::Int8,
+            6 => Self::Int64,
+            7 => Self::Boolean,
+            8 => Self::Complex64,
+            11 => Self::Bfloat16,
+            12 => Self::Uint64,
+            13 => Self::Uint32,
+            14 => Self::Uint16,
+            15 => Self::Int16,
+            _ => candle::bail!("Unsupported data type: {ordinal}"),
+        };
+        Ok(data_type)
+    }
+
+    pub fn ordinal(self) -> i32 {
+        match self {
+            Self::Float32 => 0,
+            Self::Float64 => 1,
+            Self::Float16 => 2,
+            Self::Uint8 => 3,
+            Self::Int32 => 4,
+            Self::Int8 => 5,
+            Self::Int64 => 6,
+            Self::Boolean => 7,
+            Self::Complex64 => 8,
+            Self::Bfloat16 => 11,
+            Self::Uint64 => 12,
+            Self::Uint32 => 13,
+            Self::Uint16 => 14,
+            Self::Int16 => 15,
+        }
+    }
+
+    /// The data type of the values held in a candle dtype.
+    pub fn from_storage(dtype: DType) -> Self {
+        match dtype {
+            DType::F32 => Self::Float32,
+            DType::F64 => Self::Float64,
+            DType::F16 => Self::Float16,
+            DType::BF16 => Self::Bfloat16,
+            DType::U8 => Self::Uint8,
+            DType::U32 => Self::Uint32,
+            DType::I64 => Self::Int64,
+        }
+    }
+
+    /// The candle dtype holding the values.
+    pub fn storage(self) -> DType {
+        match self {
+            Self::Float32 | Self::Complex64 => DType::F32,
+            Self::Float64 => DType::F64,
+            Self::Float16 => DType::F16,
+            Self::Bfloat16 => DType::BF16,
+            Self::Uint8 | Self::Boolean => DType::U8,
+            Self::Uint16 | Self::Uint32 => DType::U32,
+            Self::Uint64 | Self::Int8 | Self::Int16 | Self::Int32 | Self::Int64 => DType::I64,
+        }
+    }
+
+    /// Whether the `dtype` storage holds the bytes of this data type as is.
+    pub fn is_native(self, dtype: DType) -> bool {
+        let widened = matches!(self, Self::Int8 | Self::Int16 | Self::Int32 | Self::Uint16);
+        self.storage() == dtype && !widened
+    }
+
+    /// Reads a tensor from the native-endian bytes of this data type.
+    pub fn decode(self, data: &[u8], shape: &Shape, device: &Device) -> Result<Tensor> {
+        match self {
+            Self::Int8 => decode_as(data, shape, device, |b| i8::from_ne_bytes(b) as i64),
+            Self::Int16 => decode_as(data, shape, device, |b| i16::from_ne_bytes(b) as i64),
+            Self::Int32 => decode_as(data, shape, device, |b| i32::from_ne_bytes(b) as i64),
+            Self::Uint16 => decode_as(data, shape, device, |b| u16::from_ne_bytes(b) as u32),
+            Self::Uint64 => decode_as(data, shape, device, |b| u64::from_ne_bytes(b) as i64),
+            Self::Boolean => decode_as(data, shape, device, |b: [u8; 1]| (b[0] != 0) as u8),
+            _ => {
+                if self == Self::Complex64 && shape.dims().last() != Some(&2) {
+                    candle::bail!("The last dimension of complex64 must be 2, got {shape:?}");
+                }
+                Tensor::from_raw_buffer(data, self.storage(), shape.dims(), device)
+            }
+        }
+    }
+
+    /// Writes the values of `tensor` as native-endian bytes of this data type.
+    pub fn encode(self, tensor: &Tensor) -> Result<Vec<u8>> {
+        let tensor = tensor.flatten_all()?.to_dtype(self.storage())?;
+        match self {
+            Self::Int8 => encode_as(&tensor, |v: i64| (v as i8).to_ne_bytes()),
+            Self::Int16 => encode_as(&tensor, |v: i64| (v as i16).to_ne_bytes()),
+            Self::Int32 => encode_as(&tensor, |v: i64| (v as i32).to_ne_bytes()),
+            Self::Uint16 => encode_as(&tensor, |v: u32| (v as u16).to_ne_bytes()),
+            Self::Uint64 => encode_as(&tensor, |v: i64| (v as u64).to_ne_bytes()),
+            Self::Boolean => encode_as(&tensor, |v: u8| [(v != 0) as u8]),
+            _ => Ok(match tensor.dtype() {
+                DType::U8 => tensor.to_vec1::<u8>()?,
+                DType::U32 => into_bytes::<u32>(tensor.to_vec1()?),
+                DType::I64 => into_bytes::<i64>(tensor.to_vec1()?),
+                DType::F16 => into_bytes::<f16>(tensor.to_vec1()?),
+                DType::BF16 => into_bytes::<bf16>(tensor.to_vec1()?),
+                DType::F32 => into_bytes::<f32>(tensor.to_vec1()?),
+                DType::F64 => into_bytes::<f64>(tensor.to_vec1()?),
+            }),
+        }
+    }
+
+    /// Writes the values of `tensor` into `out`, with a single copy when they are stored as is in
+    /// CPU memory.
+    pub fn write(self, tensor: &Tensor, out: &mut [u8]) -> Result<()> {
+        if self.is_native(tensor.dtype()) {
+            if let Some(ret) = with_cpu_bytes(tensor, |bytes| copy_bytes(bytes, out)) {
+                return ret;
+            }
+        }
+        copy_bytes(&self.encode(tensor)?, out)
+    }
+
+    /// Casts the values of `tensor`, held as this data type, to `to`.
+    ///
+    /// Floats are truncated toward zero when cast to integers, integers wrap around to the range
+    /// of a narrower integer type, non-zero values are `true` and complex numbers keep their real
+    /// part when cast to a real type.
+    pub fn cast(self, tensor: &Tensor, to: DataType) -> Result<Tensor> {
+        let tensor = if self == Self::Complex64 && to != Self::Complex64 {
+            tensor.narrow(D::Minus1, 0, 1)?.squeeze(D::Minus1)?
+        } else {
+            tensor.clone()
+        };
+        match to {
+            Self::Boolean => tensor.ne(&tensor.zeros_like()?),
+            Self::Complex64 if self != Self::Complex64 => {
+                let real = convert(&tensor, DType::F32)?;
+                Tensor::stack(&[&real, &real.zeros_like()?], real.rank())
+            }
+            _ if to.storage().is_float() => convert(&tensor, to.storage()),
+            _ => {
+                let tensor = convert(&tensor, DType::I64)?;
+                let wrap: fn(i64) -> i64 = match to {
+                    Self::Int8 => |v| v as i8 as i64,
+                    Self::Int16 => |v| v as i16 as i64,
+                    Self::Int32 => |v| v as i32 as i64,
+                    Self::Uint8 => |v| v as u8 as i64,
+                    Self::Uint16 => |v| v as u16 as i64,
+                    Self::Uint32 => |v| v as u32 as i64,
+                    _ => return tensor.to_dtype(to.storage()),
+                };
+                // wraps in i64 on the CPU, values beyond 2^53 don't fit a f64
+                let values: Vec<i64> = tensor
+                    .flatten_all()?
+                    .to_vec1::<i64>()?
+                    .into_iter()
+                    .map(wrap)
+                    .collect();
+                Tensor::from_vec(values, tensor.shape(), tensor.device())?.to_dtype(to.storage())
+            }
+        }
+    }
+}
+
+/// Calls `func` with the bytes of `tensor` if it is contiguous in CPU memory.
+pub fn with_cpu_bytes<R, F: FnOnce(&[u8]) -> R>(tensor: &Tensor, func: F) -> Option<R> {
+    let (storage, layout) = tensor.storage_and_layout();
+    let (start, end) = layout.contiguous_offsets()?;
+    let bytes = match &*storage {
+        Storage::Cpu(CpuStorage::U8(vs)) => as_bytes(vs),
+        Storage::Cpu(CpuStorage::U32(vs)) => as_bytes(vs),
+        Storage::Cpu(CpuStorage::I64(vs)) => as_bytes(vs),
+        Storage::Cpu(CpuStorage::BF16(vs)) => as_bytes(vs),
+        Storage::Cpu(CpuStorage::F16(vs)) => as_bytes(vs),
+        Storage::Cpu(CpuStorage::F32(vs)) => as_bytes(vs),
+        Storage::Cpu(CpuStorage::F64(vs)) => as_bytes(vs),
+        _ => return None,
+    };
+    let size = tensor.dtype().size_in_bytes();
+    Some(func(&bytes[start * size..end * size]))
+}
+
+fn as_bytes<T: WithDType>(vs: &[T]) -> &[u8] {
+    // SAFETY: every WithDType is plain old data, the bytes cover the same memory
+    unsafe { slice::from_raw_parts(vs.as_ptr() as *const u8, std::mem::size_of_val(vs)) }
+}
+
+fn copy_bytes(bytes: &[u8], out: &mut [u8]) -> Result<()> {
+    if bytes.len() != out.len() {
+        candle::bail!(
+            "Expected a buffer of {} bytes, got {}",
+            bytes.len(),
+            out.len()
+        );
+    }
+    out.copy_from_slice(bytes);
+    Ok(())
+}
+
+/// Converts between candle dtypes, half floats and integers go through F32 since not every
+/// device converts them directly.
+fn convert(tensor: &Tensor, dtype: DType) -> Result<Tensor> {
+    let is_half = |dtype: DType| matches!(dtype, DType::F16 | DType::BF16);
+    if (is_half(tensor.dtype()) && dtype.is_int()) || (tensor.dtype().is_int() && is_half(dtype)) {
+        tensor.to_dtype(DType::F32)?.to_dtype(dtype)
+    } else {
+        tensor.to_dtype(dtype)
+    }
+}
+
+fn decode_as<const N: usize, T: WithDType, F: Fn([u8; N]) -> T>(
+    data: &[u8],
+    shape: &Shape,
+    device: &Device,
+    func: F,
+) -> Result<Tensor> {
+    if data.len() != shape.elem_count() * N {
+        candle::bail!(
+            "Expected {} bytes for {shape:?}, got {}",
+            shape.elem_count() * N,
+            data.len()
+        );
+    }
+    let values: Vec<T> = data
+        .chunks_exact(N)
+        .map(|chunk| func(chunk.try_into().unwrap()))
+        .collect();
+    Tensor::from_vec(values, shape, device)
+}
+
+fn encode_as<const N: usize, T: WithDType, F: Fn(T) -> [u8; N]>(
+    tensor: &Tensor,
+    func: F,
+) -> Result<Vec<u8>> {
+    Ok(tensor.to_vec1::<T>()?.into_iter().flat_map(func).collect())
+}
+
+fn into_bytes<T: WithDType>(mut vs: Vec<T>) -> Vec<u8> {
+    let size_in_bytes = T::DTYPE.size_in_bytes();
+    let length = vs.len() * size_in_bytes;
+    let capacity = vs.capacity() * size_in_bytes;
+    let ptr = vs.as_mut_ptr() as *mut u8;
+    // Don't run the destructor for Vec<T>
+    std::mem::forget(vs);
+    // SAFETY:
+    //
+    // Every T is larger than u8, so there is no issue regarding alignment.
+    // This re-interpret the Vec<T> as a Vec<u8>.
+    unsafe { Vec::from_raw_parts(ptr, length, capacity) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cast_integers() -> Result<()> {
+        let big = (1i64 << 53) + 1;
+        let a = Tensor::new(&[big, -129, 65537], &Device::Cpu)?;
+        let cast = |to: DataType| -> Result<Vec<i64>> {
+            DataType::Int64
+                .cast(&a, to)?
+                .to_dtype(DType::I64)?
+                .to_vec1()
+        };
+        assert_eq!(cast(DataType::Int64)?, [big, -129, 65537]);
+        assert_eq!(cast(DataType::Int32)?, [1, -129, 65537]);
+        assert_eq!(cast(DataType::Int8)?, [1, 127, 1]);
+        assert_eq!(cast(DataType::Uint16)?, [1, 65407, 1]);
+        assert_eq!(cast(DataType::Uint32)?, [1, 4294967167, 65537]);
+        Ok(())
+    }
+}
@@ -0,0 +1,533 @@
+use crate::cast_handle;
+use crate::ndarray::{as_float_dtype, as_vec, return_handle};
+use candle::{DType, Device, Result, Tensor, D};
+use jni::objects::{JLongArray, JObject};
+use jni::sys::{jboolean, jlong, JNI_TRUE};
+use jni::JNIEnv;
+use std::f64::consts::PI;
+
+// There is no complex dtype in candle, complex64 is emulated by a trailing dim of size 2 holding
+// the real and imaginary parts, like `torch.view_as_real`. The caller tells whether an input is
+// complex, axes and lengths always refer to the complex shape without the trailing dim. The
+// transforms run in f64 on the CPU and complex results are complex64.
+
+/// Radix-2 Cooley-Tukey, `re.len()` must be a power of two.
+fn radix2(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * PI / len as f64;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (w_re, w_im) = ((angle * k as f64).cos(), (angle * k as f64).sin());
+                let (a, b) = (start + k, start + k + len / 2);
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Bluestein's algorithm, a DFT of any length as a convolution of power of two length.
+fn bluestein(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    let n = re.len();
+    let m = (2 * n - 1).next_power_of_two();
+    let sign = if inverse { 1.0 } else { -1.0 };
+    // `exp(sign * i * pi * k^2 / n)`, `k^2` is reduced modulo `2n` to keep the precision
+    let chirp: Vec<(f64, f64)> = (0..n)
+        .map(|k| {
+            let angle = sign * PI * ((k * k) % (2 * n)) as f64 / n as f64;
+            (angle.cos(), angle.sin())
+        })
+        .collect();
+
+    let (mut a_re, mut a_im) = (vec![0.0; m], vec![0.0; m]);
+    for k in 0..n {
+        let (c_re, c_im) = chirp[k];
+        a_re[k] = re[k] * c_re - im[k] * c_im;
+        a_im[k] = re[k] * c_im + im[k] * c_re;
+    }
+    let (mut b_re, mut b_im) = (vec![0.0; m], vec![0.0; m]);
+    for k in 0..n {
+        let (c_re, c_im) = chirp[k];
+        b_re[k] = c_re;
+        b_im[k] = -c_im;
+        if k > 0 {
+            b_re[m - k] = c_re;
+            b_im[m - k] = -c_im;
+        }
+    }
+    radix2(&mut a_re, &mut a_im, false);
+    radix2(&mut b_re, &mut b_im, false);
+    for k in 0..m {
+        let product_re = a_re[k] * b_re[k] - a_im[k] * b_im[k];
+        let product_im = a_re[k] * b_im[k] + a_im[k] * b_re[k];
+        a_re[k] = product_re;
+        a_im[k] = product_im;
+    }
+    radix2(&mut a_re, &mut a_im, true);
+    for k in 0..n {
+        let (c_re, c_im) = chirp[k];
+        let (x_re, x_im) = (a_re[k] / m as f64, a_im[k] / m as f64);
+        re[k] = x_re * c_re - x_im * c_im;
+        im[k] = x_re * c_im + x_im * c_re;
+    }
+}
+
+/// The unnormalized DFT of `re + i * im`, in place.
+fn dft(re: &mut [f64], im: &mut [f64], inverse: bool) {
+    match re.len() {
+        0 | 1 => {}
+        n if n.is_power_of_two() => radix2(re, im, inverse),
+        _ => bluestein(re, im, inverse),
+    }
+}
+
+/// Zero-pads or truncates to `n` elements.
+fn resize(row: &[f64], n: usize) -> Vec<f64> {
+    let mut row = row[..n.min(row.len())].to_vec();
+    row.resize(n, 0.0);
+    row
+}
+
+/// The real and imaginary parts of `tensor` as f64, `(re, im)`.
+fn as_parts(tensor: &Tensor, complex: bool) -> Result<(Tensor, Tensor)> {
+    let tensor = tensor.to_dtype(DType::F64)?;
+    if complex {
+        if tensor.dims().last() != Some(&2) {
+            candle::bail!(
+                "Expected complex numbers as a trailing dim of size 2, got {:?}",
+                tensor.shape()
+            );
+        }
+        let re = tensor.narrow(D::Minus1, 0, 1)?.squeeze(D::Minus1)?;
+        let im = tensor.narrow(D::Minus1, 1, 1)?.squeeze(D::Minus1)?;
+        Ok((re, im))
+    } else {
+        let im = tensor.zeros_like()?;
+        Ok((tensor, im))
+    }
+}
+
+fn as_complex(re: &Tensor, im: &Tensor) -> Result<Tensor> {
+    Tensor::stack(&[re, im], D::Minus1)?.to_dtype(DType::F32)
+}
+
+fn as_axis(rank: usize, axis: i64) -> Result<usize> {
+    let dim = if axis < 0 { rank as i64 + axis } else { axis };
+    if dim < 0 || dim >= rank as i64 {
+        candle::bail!("Axis {axis} is out of range for rank {rank}");
+    }
+    Ok(dim as usize)
+}
+
+// The transform length along `axis`, non positive lengths keep the input length
+fn as_length(tensor: &Tensor, axis: usize, length: i64) -> Result<usize> {
+    if length > 0 {
+        Ok(length as usize)
+    } else {
+        tensor.dim(axis)
+    }
+}
+
+/// Applies `f` to every row of `(re, im)` along `axis`.
+fn map_rows<F>(re: &Tensor, im: &Tensor, axis: usize, f: F) -> Result<(Tensor, Tensor)>
+where
+    F: Fn(&[f64], &[f64]) -> (Vec<f64>, Vec<f64>),
+{
+    let device = re.device();
+    let last = re.rank() - 1;
+    let to_rows = |t: &Tensor| -> Result<Vec<f64>> {
+        t.transpose(axis, last)?
+            .to_device(&Device::Cpu)?
+            .flatten_all()?
+            .to_vec1::<f64>()
+    };
+    let (in_re, in_im) = (to_rows(re)?, to_rows(im)?);
+    let mut dims = re.transpose(axis, last)?.dims().to_vec();
+    let len = dims[last];
+    let rows = dims[..last].iter().product::<usize>();
+    let (mut out_re, mut out_im) = (Vec::new(), Vec::new());
+    let mut out_len = f(&[], &[]).0.len();
+    for row in 0..rows {
+        let range = row * len..(row + 1) * len;
+        let (row_re, row_im) = f(&in_re[range.clone()], &in_im[range]);
+        out_len = row_re.len();
+        out_re.extend(row_re);
+        out_im.extend(row_im);
+    }
+    dims[last] = out_len;
+    let from_rows = |rows: Vec<f64>| -> Result<Tensor> {
+        Tensor::from_vec(rows, dims.as_slice(), device)?
+            .transpose(axis, last)?
+            .contiguous()
+    };
+    Ok((from_rows(out_re)?, from_rows(out_im)?))
+}
+
+/// The DFT of length `n` along `axis`, scaled by `1/n` if `inverse`.
+fn fft_axis(
+    re: &Tensor,
+    im: &Tensor,
+    axis: usize,
+    n: usize,
+    inverse: bool,
+) -> Result<(Tensor, Tensor)> {
+    map_rows(re, im, axis, |row_re, row_im| {
+        let (mut re, mut im) = (resize(row_re, n), resize(row_im, n));
+        dft(&mut re, &mut im, inverse);
+        if inverse {
+            re.iter_mut()
+                .chain(im.iter_mut())
+                .for_each(|v| *v /= n as f64);
+        }
+        (re, im)
+    })
+}
+
+fn fft(tensor: &Tensor, length: i64, axis: i64, complex: bool, inverse: bool) -> Result<Tensor> {
+    let (re, im) = as_parts(tensor, complex)?;
+    let axis = as_axis(re.rank(), axis)?;
+    let n = as_length(&re, axis, length)?;
+    let (re, im) = fft_axis(&re, &im, axis, n, inverse)?;
+    as_complex(&re, &im)
+}
+
+fn fft2(
+    tensor: &Tensor,
+    lengths: &[i64],
+    axes: &[i64],
+    complex: bool,
+    inverse: bool,
+) -> Result<Tensor> {
+    if lengths.len() != axes.len() {
+        candle::bail!("Got {} sizes for {} axes", lengths.len(), axes.len());
+    }
+    let (mut re, mut im) = as_parts(tensor, complex)?;
+    for (length, axis) in lengths.iter().zip(axes) {
+        let axis = as_axis(re.rank(), *axis)?;
+        let n = as_length(&re, axis, *length)?;
+        (re, im) = fft_axis(&re, &im, axis, n, inverse)?;
+    }
+    as_complex(&re, &im)
+}
+
+/// Only the `n / 2 + 1` non negative frequencies, the rest are their conjugates.
+fn rfft(tensor: &Tensor, length: i64, axis: i64, complex: bool) -> Result<Tensor> {
+    if complex {
+        candle::bail!("rfft expects a real input, use fft for complex numbers");
+    }
+    let (re, im) = as_parts(tensor, false)?;
+    let axis = as_axis(re.rank(), axis)?;
+    let n = as_length(&re, axis, length)?;
+    let (re, im) = map_rows(&re, &im, axis, |row_re, row_im| {
+        let (mut re, mut im) = (resize(row_re, n), resize(row_im, n));
+        dft(&mut re, &mut im, false);
+        re.truncate(n / 2 + 1);
+        im.truncate(n / 2 + 1);
+        (re, im)
+    })?;
+    as_complex(&re, &im)
+}
+
+/// The inverse of `rfft`, a real signal of `length`, `2 * (bins - 1)` by default.
+fn irfft(tensor: &Tensor, length: i64, axis: i64, complex: bool) -> Result<Tensor> {
+    let (re, im) = as_parts(tensor, complex)?;
+    let axis = as_axis(re.rank(), axis)?;
+    let n = if length > 0 {
+        length as usize
+    } else {
+        2 * (re.dim(axis)?.max(1) - 1)
+    };
+    let (re, _) = map_rows(&re, &im, axis, |row_re, row_im| {
+        // Rebuilds the full hermitian spectrum from the non negative frequencies
+        let bins = n / 2 + 1;
+        let (mut re, mut im) = (resize(row_re, bins), resize(row_im, bins));
+        re.resize(n, 0.0);
+        im.resize(n, 0.0);
+        for k in bins..n {
+            re[k] = re[n - k];
+            im[k] = -im[n - k];
+        }
+        dft(&mut re, &mut im, true);
+        re.iter_mut().for_each(|v| *v /= n as f64);
+        (re, im)
+    })?;
+    re.to_dtype(as_float_dtype(tensor.dtype()))
+}
+
+/// The STFT of a `(L)` or `(B, L)` signal, `(n_fft / 2 + 1, frames, 2)` per signal.
+fn stft(
+    tensor: &Tensor,
+    n_fft: usize,
+    hop_length: usize,
+    window: Option<&Tensor>,
+    center: bool,
+    normalize: bool,
+) -> Result<Tensor> {
+    if n_fft == 0 || hop_length == 0 {
+        candle::bail!("n_fft and hop_length must be positive");
+    }
+    let batched = match tensor.rank() {
+        1 => false,
+        2 => true,
+        _ => candle::bail!("Expected a 1D or 2D signal, got {:?}", tensor.shape()),
+    };
+    let signals = tensor
+        .to_dtype(DType::F64)?
+        .to_device(&Device::Cpu)?
+        .reshape(((), tensor.dim(D::Minus1)?))?
+        .to_vec2::<f64>()?;
+
+    // A shorter window is zero-padded on both sides to `n_fft`, no window is rectangular
+    let window = match window {
+        None => vec![1.0; n_fft],
+        Some(window) => {
+            let window = window
+                .to_dtype(DType::F64)?
+                .to_device(&Device::Cpu)?
+                .flatten_all()?
+                .to_vec1::<f64>()?;
+            if window.len() > n_fft {
+                candle::bail!("Window of {} is longer than n_fft {n_fft}", window.len());
+            }
+            let left = (n_fft - window.len()) / 2;
+            let mut padded = vec![0.0; n_fft];
+            padded[left..left + window.len()].copy_from_slice(&window);
+            padded
+        }
+    };
+    let scale = if normalize {
+        1.0 / (n_fft as f64).sqrt()
+    } else {
+        1.0
+    };
+
+    let bins = n_fft / 2 + 1;
+    let mut frames = 0;
+    let mut output = Vec::new();
+    for signal in signals {
+        // Reflect padding, like `torch.stft(pad_mode="reflect")`
+        let signal = if center {
+            let pad = n_fft / 2;
+            if pad >= signal.len() {
+                candle::bail!("Signal of {} is too short to pad by {pad}", signal.len());
+            }
+            let mut padded: Vec<f64> = signal[1..=pad].iter().rev().copied().collect();
+            padded.extend_from_slice(&signal);
+            padded.extend(
+                signal[signal.len() - 1 - pad..signal.len() - 1]
+                    .iter()
+                    .rev(),
+            );
+            padded
+        } else {
+            signal
+        };
+        if signal.len() < n_fft {
+            candle::bail!("Signal of {} is shorter than n_fft {n_fft}", signal.len());
+        }
+        frames = 1 + (signal.len() - n_fft) / hop_length;
+        let mut spectrum = vec![0.0; bins * frames * 2];
+        for frame in 0..frames {
+            let start = frame * hop_length;
+            let mut re: Vec<f64> = signal[start..start + n_fft]
+                .iter()
+                .zip(&window)
+                .map(|(x, w)| x * w)
+                .collect();
+            let mut im = vec![0.0; n_fft];
+            dft(&mut re, &mut im, false);
+            for bin in 0..bins {
+                let offset = (bin * frames + frame) * 2;
+                spectrum[offset] = re[bin] * scale;
+                spectrum[offset + 1] = im[bin] * scale;
+            }
+        }
+        output.extend(spectrum);
+    }
+
+    let batch_size = output.len() / (bins * frames * 2).max(1);
+    let output = Tensor::from_vec(output, (batch_size, bins, frames, 2), tensor.device())?;
+    let output = if batched { output } else { output.squeeze(0)? };
+    output.to_dtype(DType::F32)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_fft(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    length: jlong,
+    axis: jlong,
+    complex: jboolean,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = fft(tensor, length, axis, complex == JNI_TRUE, false);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_ifft(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    length: jlong,
+    axis: jlong,
+    complex: jboolean,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = fft(tensor, length, axis, complex == JNI_TRUE, true);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_rfft(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    length: jlong,
+    axis: jlong,
+    complex: jboolean,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = rfft(tensor, length, axis, complex == JNI_TRUE);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_irfft(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    length: jlong,
+    axis: jlong,
+    complex: jboolean,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = irfft(tensor, length, axis, complex == JNI_TRUE);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_fft2<'local>(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    sizes: JLongArray<'local>,
+    axes: JLongArray<'local>,
+    complex: jboolean,
+) -> jlong {
+    let sizes = as_vec(&mut env, &sizes);
+    let axes = as_vec(&mut env, &axes);
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = fft2(tensor, &sizes, &axes, complex == JNI_TRUE, false);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_ifft2<'local>(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    sizes: JLongArray<'local>,
+    axes: JLongArray<'local>,
+    complex: jboolean,
+) -> jlong {
+    let sizes = as_vec(&mut env, &sizes);
+    let axes = as_vec(&mut env, &axes);
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = fft2(tensor, &sizes, &axes, complex == JNI_TRUE, true);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_stft(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    n_fft: jlong,
+    hop_length: jlong,
+    window_handle: jlong,
+    center: jboolean,
+    normalize: jboolean,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    // 0 for a rectangular window
+    let window = match window_handle {
+        0 => None,
+        handle => Some(&*cast_handle::<Tensor>(handle)),
+    };
+    let ret = stft(
+        tensor,
+        n_fft as usize,
+        hop_length as usize,
+        window,
+        center == JNI_TRUE,
+        normalize == JNI_TRUE,
+    );
+    return_handle(&mut env, ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &Tensor, expected: &Tensor) -> Result<()> {
+        let diff = (actual - expected)?.abs()?.max_all()?.to_scalar::<f32>()?;
+        assert!(diff < 1e-4, "{actual} != {expected}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<()> {
+        let x = Tensor::new(
+            &[[1f32, 2., 3., 4., 5.], [0., -1., 2., 0.5, 7.]],
+            &Device::Cpu,
+        )?;
+        let spectrum = fft(&x, 0, -1, false, false)?;
+        assert_eq!(spectrum.dims(), [2, 5, 2]);
+        // The trailing dim of complex numbers is not an axis
+        let signal = fft(&spectrum, 0, -1, true, true)?;
+        assert_close(&signal.narrow(D::Minus1, 0, 1)?.squeeze(D::Minus1)?, &x)?;
+        assert_close(
+            &signal.narrow(D::Minus1, 1, 1)?,
+            &signal.narrow(D::Minus1, 1, 1)?.zeros_like()?,
+        )?;
+
+        // A forward transform of complex input, fft(ifft(x)) == x
+        let inverse = fft(&spectrum, 0, 1, true, true)?;
+        assert_close(&fft(&inverse, 0, 1, true, false)?, &spectrum)?;
+        let inverse = fft2(&spectrum, &[2, 5], &[0, 1], true, true)?;
+        assert_close(&fft2(&inverse, &[2, 5], &[0, 1], true, false)?, &spectrum)?;
+        assert_close(
+            &fft2(&x, &[2, 5], &[0, 1], false, false)?,
+            &fft(&spectrum, 0, 0, true, false)?,
+        )?;
+
+        let half = rfft(&x, 0, 1, false)?;
+        assert_close(&half, &spectrum.narrow(1, 0, 3)?)?;
+        assert_close(&irfft(&half, 5, 1, true)?, &x)?;
+        assert!(rfft(&spectrum, 0, 1, true).is_err());
+        Ok(())
+    }
+}
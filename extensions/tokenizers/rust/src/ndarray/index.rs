@@ -0,0 +1,400 @@
+use crate::cast_handle;
+use crate::ndarray::autograd::assign;
+use crate::ndarray::{as_vec, return_handle, throw_error};
+use candle::{DType, Device, Result, Tensor};
+use jni::objects::{JLongArray, JObject};
+use jni::sys::{jfloat, jint, jlong};
+use jni::JNIEnv;
+use std::collections::HashMap;
+
+/// The positions of `[min, max)` visited with `step`. A negative step walks the same range
+/// backward, from `max - 1`, like `a[min:max][::step]` in numpy, since an omitted bound arrives
+/// as `0` or the size of the axis whatever the sign of the step. Only `min > max` is taken as an
+/// explicit `a[min:max:step]`, which walks from `min` down to `max` excluded.
+fn axis_indices(min: usize, max: usize, step: i64) -> Result<Vec<u32>> {
+    let (min, max) = (min as u32, max as u32);
+    let stride = step.unsigned_abs() as usize;
+    match step {
+        0 => candle::bail!("Slice step cannot be zero"),
+        step if step > 0 => Ok((min..max.max(min)).step_by(stride).collect()),
+        _ if min > max => Ok((max + 1..=min).rev().step_by(stride).collect()),
+        _ => Ok((min..max).rev().step_by(stride).collect()),
+    }
+}
+
+/// Slices every axis of `tensor` to `[min, max)` with `step`.
+pub(super) fn strided_slice(
+    tensor: &Tensor,
+    min: &[i64],
+    max: &[i64],
+    step: &[i64],
+) -> Result<Tensor> {
+    let mut slice = tensor.clone();
+    for axis in 0..min.len() {
+        let (start, end) = (min[axis] as usize, max[axis] as usize);
+        let step = step.get(axis).copied().unwrap_or(1);
+        if step == 1 {
+            slice = slice.narrow(axis, start, end.max(start) - start)?;
+        } else {
+            let indices = axis_indices(start, end, step)?;
+            let len = indices.len();
+            let indices = Tensor::from_vec(indices, len, tensor.device())?;
+            slice = slice.index_select(&indices, axis)?;
+        }
+    }
+    Ok(slice)
+}
+
+/// Returns `tensor` with the elements at the flattened `indices` replaced by `values`. When an
+/// index is repeated, the last of its values is written.
+fn put_flat(tensor: &Tensor, indices: &Tensor, values: &Tensor) -> Result<Tensor> {
+    let flat = tensor.flatten_all()?;
+    let indices = indices
+        .flatten_all()?
+        .to_dtype(DType::U32)?
+        .to_vec1::<u32>()?;
+    let values = values.flatten_all()?.to_dtype(tensor.dtype())?;
+    let values = if values.elem_count() == 1 {
+        values.broadcast_as(indices.len())?.contiguous()?
+    } else {
+        values
+    };
+    if values.elem_count() != indices.len() {
+        candle::bail!(
+            "Expected {} values, got {}",
+            indices.len(),
+            values.elem_count()
+        );
+    }
+    // Keeps the last value of every index, index_add would sum them
+    let mut last = HashMap::new();
+    for (i, index) in indices.iter().enumerate() {
+        last.insert(*index, i as u32);
+    }
+    let (indices, sources): (Vec<u32>, Vec<u32>) = last.into_iter().unzip();
+    let len = indices.len();
+    let indices = Tensor::from_vec(indices, len, tensor.device())?;
+    let values = values.index_select(&Tensor::from_vec(sources, len, tensor.device())?, 0)?;
+    // Scatters into zeros and selects with a mask, so that the values are copied exactly
+    let scattered = flat.zeros_like()?.index_add(&indices, &values, 0)?;
+    let ones = Tensor::ones(len, DType::U8, tensor.device())?;
+    let mask = Tensor::zeros(flat.elem_count(), DType::U8, tensor.device())?;
+    let mask = mask.index_add(&indices, &ones, 0)?;
+    mask.where_cond(&scattered, &flat)?.reshape(tensor.shape())
+}
+
+/// Returns `tensor` with the slice `[min, max)` with `step` set to `value`, broadcast to the shape
+/// of the slice. Axes past `min` are taken whole, `None` means the slice is empty.
+fn set_slice(
+    tensor: &Tensor,
+    min: &[i64],
+    max: &[i64],
+    step: &[i64],
+    value: &Tensor,
+) -> Result<Option<Tensor>> {
+    // The flattened position of every element of the slice
+    let dims = tensor.dims();
+    let mut positions: Vec<u32> = vec![0];
+    let mut shape = Vec::with_capacity(dims.len());
+    for axis in 0..dims.len() {
+        let stride = dims[axis + 1..].iter().product::<usize>() as u32;
+        let indices = match min.get(axis) {
+            Some(start) => {
+                let step = step.get(axis).copied().unwrap_or(1);
+                axis_indices(*start as usize, max[axis] as usize, step)?
+            }
+            None => (0..dims[axis] as u32).collect(),
+        };
+        shape.push(indices.len());
+        positions = positions
+            .iter()
+            .flat_map(|p| indices.iter().map(move |i| p + i * stride))
+            .collect();
+    }
+    let len = positions.len();
+    if len == 0 {
+        return Ok(None);
+    }
+    // The value may omit the squeezed axes of the slice, or be broadcast to it
+    let value = if value.elem_count() == len {
+        value.reshape(shape)?
+    } else {
+        match value.broadcast_as(shape.as_slice()) {
+            Ok(value) => value,
+            Err(_) => candle::bail!(
+                "Cannot assign {:?} to a slice of shape {shape:?}",
+                value.shape()
+            ),
+        }
+    };
+    let positions = Tensor::from_vec(positions, len, tensor.device())?;
+    let value = value.to_device(tensor.device())?;
+    put_flat(tensor, &positions, &value).map(Some)
+}
+
+// Non integer indices, e.g. labels, are truncated
+fn as_indices(indices: &Tensor) -> Result<Tensor> {
+    if indices.dtype().is_float() {
+        indices.to_dtype(DType::I64)
+    } else {
+        Ok(indices.clone())
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_setSlice<'local>(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    min: JLongArray<'local>,
+    max: JLongArray<'local>,
+    step: JLongArray<'local>,
+    value: jlong,
+) {
+    let min = as_vec(&mut env, &min);
+    let max = as_vec(&mut env, &max);
+    let step = as_vec(&mut env, &step);
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let value = cast_handle::<Tensor>(value);
+        match set_slice(tensor, &min, &max, &step, value)? {
+            Some(tensor) => assign(handle, tensor),
+            None => Ok(()),
+        }
+    };
+    if let Err(e) = op() {
+        throw_error(&mut env, e);
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_take(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    index_handle: jlong,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let index = as_indices(cast_handle::<Tensor>(index_handle))?;
+        tensor
+            .flatten_all()?
+            .index_select(&index.flatten_all()?, 0)?
+            .reshape(index.shape())
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_put(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    index_handle: jlong,
+    value_handle: jlong,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let index = as_indices(cast_handle::<Tensor>(index_handle))?;
+        let value = cast_handle::<Tensor>(value_handle);
+        put_flat(tensor, &index, value)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_pick(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    pick_handle: jlong,
+    axis: jint,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let index = as_indices(cast_handle::<Tensor>(pick_handle))?;
+        tensor.gather(&index.contiguous()?, axis as usize)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_gatherNd(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    index_handle: jlong,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        // `(M, Y...)`, the first `M` coordinates of every gathered element
+        let index = cast_handle::<Tensor>(index_handle).to_dtype(DType::I64)?;
+        let dims = tensor.dims();
+        let m = index.dim(0)?;
+        if m == 0 || m > dims.len() {
+            candle::bail!(
+                "Index of {:?} cannot gather from {:?}",
+                index.shape(),
+                tensor.shape()
+            );
+        }
+        let strides: Vec<i64> = (0..m)
+            .map(|i| dims[i + 1..m].iter().product::<usize>() as i64)
+            .collect();
+        let mut strides_dims = vec![1; index.rank()];
+        strides_dims[0] = m;
+        let strides = Tensor::from_vec(strides, strides_dims, tensor.device())?;
+        let positions = index.broadcast_mul(&strides)?.sum(0)?;
+
+        let mut data_dims = vec![dims[..m].iter().product::<usize>()];
+        data_dims.extend_from_slice(&dims[m..]);
+        let mut out_dims = positions.dims().to_vec();
+        out_dims.extend_from_slice(&dims[m..]);
+        tensor
+            .reshape(data_dims)?
+            .index_select(&positions.flatten_all()?, 0)?
+            .reshape(out_dims)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_booleanMask(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    index_handle: jlong,
+    axis: jint,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let mask = cast_handle::<Tensor>(index_handle);
+        let axis = axis as usize;
+        let rank = mask.rank();
+        let dims = tensor.dims();
+        if rank == 0 || axis + rank > dims.len() || &dims[axis..axis + rank] != mask.dims() {
+            candle::bail!(
+                "Mask of {:?} does not match {:?} at axis {axis}",
+                mask.shape(),
+                tensor.shape()
+            );
+        }
+        // The masked axes are flattened into one, holding the selected elements
+        let mask = mask
+            .flatten_all()?
+            .to_dtype(DType::U8)?
+            .to_device(&Device::Cpu)?
+            .to_vec1::<u8>()?;
+        let selected: Vec<u32> = mask
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| **m != 0)
+            .map(|(i, _)| i as u32)
+            .collect();
+        let len = selected.len();
+        let selected = Tensor::from_vec(selected, len, tensor.device())?;
+        tensor
+            .flatten(axis, axis + rank - 1)?
+            .index_select(&selected, axis)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_sequenceMask(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    length_handle: jlong,
+    value: jfloat,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let lengths = cast_handle::<Tensor>(length_handle);
+        // `(batch_size, max_sequence_length, ...)`
+        let (batch_size, max_len) = match tensor.dims() {
+            [batch_size, max_len, ..] => (*batch_size, *max_len),
+            _ => candle::bail!("Expected at least 2 dims, got {:?}", tensor.shape()),
+        };
+        let device = tensor.device();
+        let steps = Tensor::arange(0u32, max_len as u32, device)?
+            .to_dtype(DType::F32)?
+            .reshape((1, max_len))?;
+        let lengths = lengths.to_dtype(DType::F32)?.reshape((batch_size, 1))?;
+        let mut mask_dims = vec![1; tensor.rank()];
+        mask_dims[0] = batch_size;
+        mask_dims[1] = max_len;
+        let mask = steps
+            .broadcast_lt(&lengths)?
+            .reshape(mask_dims)?
+            .broadcast_as(tensor.shape())?;
+        let fill = Tensor::new(value, device)?
+            .to_dtype(tensor.dtype())?
+            .broadcast_as(tensor.shape())?;
+        mask.where_cond(tensor, &fill)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_put_flat() -> Result<()> {
+        let a = Tensor::new(&[[1f32, 2.], [3., 4.]], &Device::Cpu)?;
+        let indices = Tensor::new(&[3u32, 0, 3], &Device::Cpu)?;
+        let values = Tensor::new(&[5f32, 6., 7.], &Device::Cpu)?;
+        // the last value of a repeated index is written
+        let put = put_flat(&a, &indices, &values)?;
+        assert_eq!(put.to_vec2::<f32>()?, [[6., 2.], [3., 7.]]);
+        let put = put_flat(&a, &indices, &Tensor::new(&[9f32], &Device::Cpu)?)?;
+        assert_eq!(put.to_vec2::<f32>()?, [[9., 2.], [3., 9.]]);
+        assert!(put_flat(&a, &indices, &a).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_slice() -> Result<()> {
+        let a = Tensor::zeros((3, 2), DType::F32, &Device::Cpu)?;
+        let row = Tensor::new(&[1f32, 2.], &Device::Cpu)?;
+        // a[1:] = [1, 2], broadcast to every row
+        let set = set_slice(&a, &[1], &[3], &[1], &row)?.unwrap();
+        assert_eq!(set.to_vec2::<f32>()?, [[0., 0.], [1., 2.], [1., 2.]]);
+        // a[::2, 1] = [[1], [2]], the value has the shape of the slice
+        let column = Tensor::new(&[[1f32], [2.]], &Device::Cpu)?;
+        let set = set_slice(&a, &[0, 1], &[3, 2], &[2, 1], &column)?.unwrap();
+        assert_eq!(set.to_vec2::<f32>()?, [[0., 1.], [0., 0.], [0., 2.]]);
+        let err = set_slice(&a, &[0], &[3], &[1], &column).unwrap_err();
+        assert!(
+            err.to_string().contains("to a slice of shape [3, 2]"),
+            "{err}"
+        );
+        assert!(set_slice(&a, &[2], &[1], &[1], &row)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_strided_slice() -> Result<()> {
+        let a = Tensor::new(&[0f32, 1., 2., 3., 4.], &Device::Cpu)?;
+        // a[::-2] arrives as [0, 5)
+        let slice = strided_slice(&a, &[0], &[5], &[-2])?;
+        assert_eq!(slice.to_vec1::<f32>()?, [4., 2., 0.]);
+        // a[3:0:-1]
+        let slice = strided_slice(&a, &[3], &[0], &[-1])?;
+        assert_eq!(slice.to_vec1::<f32>()?, [3., 2., 1.]);
+        // a[4:0:-3]
+        let slice = strided_slice(&a, &[4], &[0], &[-3])?;
+        assert_eq!(slice.to_vec1::<f32>()?, [4., 1.]);
+        let slice = strided_slice(&a, &[2], &[2], &[-1])?;
+        assert_eq!(slice.dims(), [0]);
+        assert!(strided_slice(&a, &[0], &[5], &[0]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,574 @@
+use crate::cast_handle;
+use crate::ndarray::{as_float_dtype, return_handle, return_handles};
+use candle::{DType, Device, Result, Tensor};
+use jni::objects::{JIntArray, JLongArray, JObject, ReleaseMode};
+use jni::sys::{jboolean, jint, jlong, JNI_TRUE};
+use jni::JNIEnv;
+
+// The decompositions run in f64 on the CPU, one matrix of the batch at a time.
+
+/// A batch of `(rows, cols)` matrices, row major.
+struct Matrices {
+    batch: Vec<usize>,
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrices {
+    fn new(tensor: &Tensor) -> Result<Self> {
+        let dims = tensor.dims();
+        if dims.len() < 2 {
+            candle::bail!("Expected a batch of matrices, got {:?}", tensor.shape());
+        }
+        let (batch, matrix) = dims.split_at(dims.len() - 2);
+        let data = tensor
+            .to_dtype(DType::F64)?
+            .to_device(&Device::Cpu)?
+            .flatten_all()?
+            .to_vec1::<f64>()?;
+        Ok(Self {
+            batch: batch.to_vec(),
+            rows: matrix[0],
+            cols: matrix[1],
+            data,
+        })
+    }
+
+    fn square(tensor: &Tensor) -> Result<Self> {
+        let matrices = Self::new(tensor)?;
+        if matrices.rows != matrices.cols {
+            candle::bail!("Expected square matrices, got {:?}", tensor.shape());
+        }
+        Ok(matrices)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &[f64]> {
+        // `chunks` panics on 0, empty matrices have no elements anyway
+        self.data.chunks((self.rows * self.cols).max(1))
+    }
+
+    /// `data` of the batch, with `dims` for every matrix, in the float dtype and on the device of
+    /// `like`.
+    fn to_tensor(&self, data: Vec<f64>, dims: &[usize], like: &Tensor) -> Result<Tensor> {
+        let mut shape = self.batch.clone();
+        shape.extend_from_slice(dims);
+        Tensor::from_vec(data, shape, like.device())?.to_dtype(as_float_dtype(like.dtype()))
+    }
+}
+
+fn transpose(a: &[f64], rows: usize, cols: usize) -> Vec<f64> {
+    let mut t = vec![0.0; a.len()];
+    for i in 0..rows {
+        for j in 0..cols {
+            t[j * rows + i] = a[i * cols + j];
+        }
+    }
+    t
+}
+
+/// Solves `a x = b` by Gaussian elimination with partial pivoting, `a` is `(n, n)`, `b` is
+/// `(n, k)`.
+fn solve(a: &[f64], b: &[f64], n: usize, k: usize) -> Result<Vec<f64>> {
+    let (mut a, mut b) = (a.to_vec(), b.to_vec());
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&i, &j| a[i * n + col].abs().total_cmp(&a[j * n + col].abs()))
+            .unwrap();
+        if a[pivot * n + col] == 0.0 {
+            candle::bail!("The matrix is singular");
+        }
+        for j in 0..n {
+            a.swap(col * n + j, pivot * n + j);
+        }
+        for j in 0..k {
+            b.swap(col * k + j, pivot * k + j);
+        }
+        for row in col + 1..n {
+            let factor = a[row * n + col] / a[col * n + col];
+            for j in col..n {
+                a[row * n + j] -= factor * a[col * n + j];
+            }
+            for j in 0..k {
+                b[row * k + j] -= factor * b[col * k + j];
+            }
+        }
+    }
+    for col in (0..n).rev() {
+        for j in 0..k {
+            let sum: f64 = (col + 1..n).map(|c| a[col * n + c] * b[c * k + j]).sum();
+            b[col * k + j] = (b[col * k + j] - sum) / a[col * n + col];
+        }
+    }
+    Ok(b)
+}
+
+/// The determinant from the LU decomposition with partial pivoting.
+fn det(a: &[f64], n: usize) -> f64 {
+    let mut a = a.to_vec();
+    let mut det = 1.0;
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&i, &j| a[i * n + col].abs().total_cmp(&a[j * n + col].abs()))
+            .unwrap();
+        if a[pivot * n + col] == 0.0 {
+            return 0.0;
+        }
+        if pivot != col {
+            for j in 0..n {
+                a.swap(col * n + j, pivot * n + j);
+            }
+            det = -det;
+        }
+        det *= a[col * n + col];
+        for row in col + 1..n {
+            let factor = a[row * n + col] / a[col * n + col];
+            for j in col..n {
+                a[row * n + j] -= factor * a[col * n + j];
+            }
+        }
+    }
+    det
+}
+
+/// The lower triangular `l` with `a = l l^T`.
+fn cholesky(a: &[f64], n: usize) -> Result<Vec<f64>> {
+    let mut l = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|p| l[i * n + p] * l[j * n + p]).sum();
+            let value = a[i * n + j] - sum;
+            if i == j {
+                if value <= 0.0 {
+                    candle::bail!("The matrix is not positive-definite");
+                }
+                l[i * n + i] = value.sqrt();
+            } else {
+                l[i * n + j] = value / l[j * n + j];
+            }
+        }
+    }
+    Ok(l)
+}
+
+/// The reduced QR decomposition by Householder reflections, `q` is `(m, k)` and `r` is `(k, n)`
+/// with `k = min(m, n)`. The signs follow LAPACK.
+fn qr(a: &[f64], m: usize, n: usize) -> (Vec<f64>, Vec<f64>) {
+    let k = m.min(n);
+    let mut r = a.to_vec();
+    let mut q = vec![0.0; m * m];
+    for i in 0..m {
+        q[i * m + i] = 1.0;
+    }
+    for j in 0..k {
+        let mut v: Vec<f64> = (j..m).map(|i| r[i * n + j]).collect();
+        let below = v[1..].iter().map(|x| x * x).sum::<f64>();
+        // Like LAPACK, there is no reflection if the column is already zero below the diagonal
+        if below == 0.0 {
+            continue;
+        }
+        let norm = (v[0] * v[0] + below).sqrt();
+        let alpha = if v[0] < 0.0 { norm } else { -norm };
+        v[0] -= alpha;
+        let v_norm = v.iter().map(|x| x * x).sum::<f64>();
+        // r = H r and q = q H with H = I - 2 v v^T / (v^T v)
+        for c in j..n {
+            let s: f64 = v
+                .iter()
+                .enumerate()
+                .map(|(i, x)| x * r[(j + i) * n + c])
+                .sum();
+            for (i, x) in v.iter().enumerate() {
+                r[(j + i) * n + c] -= 2.0 * s * x / v_norm;
+            }
+        }
+        for row in 0..m {
+            let s: f64 = v
+                .iter()
+                .enumerate()
+                .map(|(i, x)| x * q[row * m + j + i])
+                .sum();
+            for (i, x) in v.iter().enumerate() {
+                q[row * m + j + i] -= 2.0 * s * x / v_norm;
+            }
+        }
+    }
+    let q = (0..m).flat_map(|i| q[i * m..i * m + k].to_vec()).collect();
+    let r = (0..k)
+        .flat_map(|i| (0..n).map(move |j| (i, j)))
+        .map(|(i, j)| if j < i { 0.0 } else { r[i * n + j] })
+        .collect();
+    (q, r)
+}
+
+/// The reduced SVD by one-sided Jacobi rotations, `u` is `(m, k)`, `s` is `(k)` in descending
+/// order and `vh` is `(k, n)` with `k = min(m, n)`.
+fn svd(a: &[f64], m: usize, n: usize) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+    if m < n {
+        // a^T = v s u^T
+        let (u, s, vh) = svd(&transpose(a, m, n), n, m);
+        return (transpose(&vh, m, m), s, transpose(&u, n, m));
+    }
+    let mut u = a.to_vec();
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+    // Orthogonalizes the columns of `u` pairwise until they converge
+    for _ in 0..64 {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in p + 1..n {
+                let (mut alpha, mut beta, mut gamma) = (0.0, 0.0, 0.0);
+                for i in 0..m {
+                    alpha += u[i * n + p] * u[i * n + p];
+                    beta += u[i * n + q] * u[i * n + q];
+                    gamma += u[i * n + p] * u[i * n + q];
+                }
+                if gamma == 0.0 || gamma.abs() <= f64::EPSILON * (alpha * beta).sqrt() {
+                    continue;
+                }
+                rotated = true;
+                let zeta = (beta - alpha) / (2.0 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+                for x in [&mut u, &mut v] {
+                    for i in 0..x.len() / n {
+                        let (xp, xq) = (x[i * n + p], x[i * n + q]);
+                        x[i * n + p] = c * xp - s * xq;
+                        x[i * n + q] = s * xp + c * xq;
+                    }
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+
+    let norms: Vec<f64> = (0..n)
+        .map(|j| {
+            (0..m)
+                .map(|i| u[i * n + j] * u[i * n + j])
+                .sum::<f64>()
+                .sqrt()
+        })
+        .collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&i, &j| norms[j].total_cmp(&norms[i]));
+    let mut u_sorted = vec![0.0; m * n];
+    let mut vh = vec![0.0; n * n];
+    for (k, &j) in order.iter().enumerate() {
+        for i in 0..m {
+            if norms[j] > 0.0 {
+                u_sorted[i * n + k] = u[i * n + j] / norms[j];
+            }
+        }
+        for i in 0..n {
+            vh[k * n + i] = v[i * n + j];
+        }
+    }
+    let s = order.iter().map(|&j| norms[j]).collect();
+    (u_sorted, s, vh)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_inverse(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let matrices = Matrices::square(tensor)?;
+        let n = matrices.rows;
+        let mut identity = vec![0.0; n * n];
+        for i in 0..n {
+            identity[i * n + i] = 1.0;
+        }
+        let mut data = Vec::with_capacity(matrices.data.len());
+        for a in matrices.iter() {
+            data.extend(solve(a, &identity, n, n)?);
+        }
+        matrices.to_tensor(data, &[n, n], tensor)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_det(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let matrices = Matrices::square(tensor)?;
+        let data = matrices.iter().map(|a| det(a, matrices.rows)).collect();
+        matrices.to_tensor(data, &[], tensor)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_solve(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    other_handle: jlong,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let other = cast_handle::<Tensor>(other_handle);
+        let a = Matrices::square(tensor)?;
+        // `b` is a batch of `(n, k)` matrices, or of `(n)` vectors
+        let vector = other.rank() + 1 == tensor.rank();
+        let b = if vector {
+            Matrices::new(&other.unsqueeze(other.rank())?)?
+        } else {
+            Matrices::new(other)?
+        };
+        if a.batch != b.batch || a.rows != b.rows {
+            candle::bail!("Cannot solve {:?} for {:?}", tensor.shape(), other.shape());
+        }
+        let (n, k) = (a.rows, b.cols);
+        let mut data = Vec::with_capacity(b.data.len());
+        for (a, b) in a.iter().zip(b.iter()) {
+            data.extend(solve(a, b, n, k)?);
+        }
+        if vector {
+            b.to_tensor(data, &[n], tensor)
+        } else {
+            b.to_tensor(data, &[n, k], tensor)
+        }
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_cholesky(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let matrices = Matrices::square(tensor)?;
+        let n = matrices.rows;
+        let mut data = Vec::with_capacity(matrices.data.len());
+        for a in matrices.iter() {
+            data.extend(cholesky(a, n)?);
+        }
+        matrices.to_tensor(data, &[n, n], tensor)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_qr<'local>(
+    mut env: JNIEnv<'local>,
+    _: JObject,
+    handle: jlong,
+) -> JLongArray<'local> {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let matrices = Matrices::new(tensor)?;
+        let (m, n) = (matrices.rows, matrices.cols);
+        let k = m.min(n);
+        let (mut q, mut r) = (Vec::new(), Vec::new());
+        for a in matrices.iter() {
+            let (q_i, r_i) = qr(a, m, n);
+            q.extend(q_i);
+            r.extend(r_i);
+        }
+        Ok(vec![
+            matrices.to_tensor(q, &[m, k], tensor)?,
+            matrices.to_tensor(r, &[k, n], tensor)?,
+        ])
+    };
+    let ret = op();
+    return_handles(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_svd<'local>(
+    mut env: JNIEnv<'local>,
+    _: JObject,
+    handle: jlong,
+) -> JLongArray<'local> {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let matrices = Matrices::new(tensor)?;
+        let (m, n) = (matrices.rows, matrices.cols);
+        let k = m.min(n);
+        let (mut u, mut s, mut vh) = (Vec::new(), Vec::new(), Vec::new());
+        for a in matrices.iter() {
+            let (u_i, s_i, vh_i) = svd(a, m, n);
+            u.extend(u_i);
+            s.extend(s_i);
+            vh.extend(vh_i);
+        }
+        Ok(vec![
+            matrices.to_tensor(u, &[m, k], tensor)?,
+            matrices.to_tensor(s, &[k], tensor)?,
+            matrices.to_tensor(vh, &[k, n], tensor)?,
+        ])
+    };
+    let ret = op();
+    return_handles(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_norm<'local>(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    ord: jint,
+    axes: JIntArray<'local>,
+    keep_dims: jboolean,
+) -> jlong {
+    let axes = unsafe { env.get_array_elements(&axes, ReleaseMode::NoCopyBack) }.unwrap();
+    let axes: Vec<i32> = axes.iter().copied().collect();
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let tensor = tensor.to_dtype(as_float_dtype(tensor.dtype()))?;
+        let keep_dims = keep_dims == JNI_TRUE;
+        let rank = tensor.rank() as i32;
+        let dims: Vec<usize> = axes
+            .iter()
+            .map(|&axis| if axis < 0 { rank + axis } else { axis } as usize)
+            .collect();
+        match dims[..] {
+            [row_dim, col_dim] => matrix_norm(&tensor, ord, row_dim, col_dim, keep_dims),
+            // Without axes, the vector norm of the flattened tensor
+            [] => vector_norm(
+                &tensor,
+                ord,
+                &(0..tensor.rank()).collect::<Vec<_>>(),
+                keep_dims,
+            ),
+            _ => vector_norm(&tensor, ord, &dims, keep_dims),
+        }
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+fn vector_norm(tensor: &Tensor, ord: i32, dims: &[usize], keep_dims: bool) -> Result<Tensor> {
+    let sum = |x: Tensor| {
+        if keep_dims {
+            x.sum_keepdim(dims)
+        } else {
+            x.sum(dims)
+        }
+    };
+    match ord {
+        0 => sum(tensor.ne(0f64)?.to_dtype(tensor.dtype())?),
+        1 => sum(tensor.abs()?),
+        2 => sum(tensor.sqr()?)?.sqrt(),
+        p => sum(tensor.abs()?.powf(p as f64)?)?.powf(1.0 / p as f64),
+    }
+}
+
+/// The matrix norms of `torch.linalg.matrix_norm` with an integer `ord`: the max (1) or min (-1)
+/// absolute column sum, or the largest (2) or smallest (-2) singular value.
+fn matrix_norm(
+    tensor: &Tensor,
+    ord: i32,
+    row_dim: usize,
+    col_dim: usize,
+    keep_dims: bool,
+) -> Result<Tensor> {
+    let rank = tensor.rank();
+    if row_dim >= rank || col_dim >= rank || row_dim == col_dim {
+        candle::bail!(
+            "Invalid matrix axes ({row_dim}, {col_dim}) of {:?}",
+            tensor.shape()
+        );
+    }
+    let mut kept_shape = tensor.dims().to_vec();
+    kept_shape[row_dim] = 1;
+    kept_shape[col_dim] = 1;
+    let norm = match ord {
+        1 | -1 => {
+            let sums = tensor.abs()?.sum_keepdim(row_dim)?;
+            if ord == 1 {
+                sums.max_keepdim(col_dim)?
+            } else {
+                sums.min_keepdim(col_dim)?
+            }
+        }
+        2 | -2 => {
+            // The matrices in the last two dimensions
+            let mut order: Vec<usize> = (0..rank)
+                .filter(|&d| d != row_dim && d != col_dim)
+                .collect();
+            order.extend([row_dim, col_dim]);
+            let matrices = Matrices::new(&tensor.permute(order)?)?;
+            let (m, n) = (matrices.rows, matrices.cols);
+            let norms = if m * n == 0 {
+                vec![0.0; matrices.batch.iter().product()]
+            } else {
+                let singular_values = matrices.iter().map(|a| svd(a, m, n).1.into_iter());
+                if ord == 2 {
+                    singular_values.map(|s| s.fold(0.0, f64::max)).collect()
+                } else {
+                    singular_values
+                        .map(|s| s.fold(f64::INFINITY, f64::min))
+                        .collect()
+                }
+            };
+            matrices
+                .to_tensor(norms, &[], tensor)?
+                .reshape(kept_shape.as_slice())?
+        }
+        _ => candle::bail!("Unsupported matrix norm order: {ord}, expected 1, -1, 2 or -2"),
+    };
+    if keep_dims {
+        Ok(norm)
+    } else {
+        let mut shape = tensor.dims().to_vec();
+        shape.remove(row_dim.max(col_dim));
+        shape.remove(row_dim.min(col_dim));
+        norm.reshape(shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matrix_norm() -> Result<()> {
+        let a = Tensor::new(&[[1f32, 2.], [3., 4.]], &Device::Cpu)?;
+        let norm = |ord, keep_dims| -> Result<f32> {
+            matrix_norm(&a, ord, 0, 1, keep_dims)?
+                .flatten_all()?
+                .to_vec1::<f32>()
+                .map(|v| v[0])
+        };
+        assert_eq!(norm(1, false)?, 6.);
+        assert_eq!(norm(-1, false)?, 4.);
+        // The singular values of `a`
+        assert!((norm(2, false)? - 5.4650).abs() < 1e-4);
+        assert!((norm(-2, false)? - 0.3660).abs() < 1e-4);
+        assert!(matrix_norm(&a, 3, 0, 1, false).is_err());
+
+        // The matrices of a batch, with swapped axes
+        let batch = Tensor::stack(&[&a, &(&a * 2.)?], 1)?;
+        let norms = matrix_norm(&batch, 2, 2, 0, true)?;
+        assert_eq!(norms.dims(), [1, 2, 1]);
+        let norms = matrix_norm(&batch, 2, 2, 0, false)?.to_vec1::<f32>()?;
+        assert!((norms[0] - 5.4650).abs() < 1e-4 && (norms[1] - 10.9300).abs() < 1e-4);
+        assert_eq!(
+            matrix_norm(&batch, 1, 0, 2, false)?.to_vec1::<f32>()?,
+            [6., 12.]
+        );
+        Ok(())
+    }
+}
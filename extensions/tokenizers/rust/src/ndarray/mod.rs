@@ -1,17 +1,25 @@
-use candle::{DType, Device, Error, Result, Shape, Tensor, WithDType};
-use half::{bf16, f16};
-use jni::objects::{JByteArray, JIntArray, JLongArray, JObject, JString, ReleaseMode};
-use jni::sys::{jbyte, jint, jlong, jsize};
+use candle::{DType, Device, DeviceLocation, Error, Result, Shape, Tensor};
+use jni::objects::{JByteArray, JByteBuffer, JIntArray, JLongArray, JObject, JString, ReleaseMode};
+use jni::sys::{jint, jlong, jsize};
 use jni::JNIEnv;
+use std::slice;
 
+use crate::ndarray::dtype::{with_cpu_bytes, DataType};
 use crate::{cast_handle, drop_handle, to_handle};
 
+mod autograd;
 mod binary;
 mod cmp;
 mod creation;
+mod dtype;
+mod fft;
+mod index;
+mod linalg;
 mod nn;
+mod optim;
 mod other;
 mod reduce;
+mod sort;
 mod unary;
 
 #[no_mangle]
@@ -21,7 +29,7 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_getDataType(
     handle: jlong,
 ) -> jint {
     let tensor = cast_handle::<Tensor>(handle);
-    to_data_type(tensor.dtype())
+    DataType::from_storage(tensor.dtype()).ordinal()
 }
 
 #[no_mangle]
@@ -33,17 +41,11 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_getDevice<'local>(
     let tensor = cast_handle::<Tensor>(handle);
     let device = tensor.device();
     let array = env.new_int_array(2).unwrap();
-    let mut device_type = 0;
-    let mut device_id = -1;
-    if device.is_cpu() {
-        device_type = 0;
-    } else if device.is_cuda() {
-        device_type = 1;
-        device_id = 0;
-    } else if device.is_metal() {
-        device_type = 2;
-    }
-    let values = [device_type, device_id];
+    let values = match device.location() {
+        DeviceLocation::Cpu => [0, -1],
+        DeviceLocation::Cuda { gpu_id } => [1, gpu_id as jint],
+        DeviceLocation::Metal { .. } => [2, -1],
+    };
     env.set_int_array_region(&array, 0, &values).unwrap();
     array
 }
@@ -70,23 +72,74 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_getShape<'local>(
 
 #[no_mangle]
 pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_toByteArray<'local>(
-    env: JNIEnv<'local>,
+    mut env: JNIEnv<'local>,
     _: JObject,
     handle: jlong,
+    dtype: jint,
 ) -> JByteArray<'local> {
-    let tensor = cast_handle::<Tensor>(handle).flatten_all().unwrap();
-    let vs = match tensor.dtype() {
-        DType::U8 => convert_back_::<u8>(tensor.to_vec1().unwrap()),
-        DType::U32 => convert_back_::<u32>(tensor.to_vec1().unwrap()),
-        DType::I64 => convert_back_::<i64>(tensor.to_vec1().unwrap()),
-        DType::F16 => convert_back_::<f16>(tensor.to_vec1().unwrap()),
-        DType::BF16 => convert_back_::<bf16>(tensor.to_vec1().unwrap()),
-        DType::F32 => convert_back_::<f32>(tensor.to_vec1().unwrap()),
-        DType::F64 => convert_back_::<f64>(tensor.to_vec1().unwrap()),
+    let tensor = cast_handle::<Tensor>(handle);
+    let dtype = match DataType::from_ordinal(dtype) {
+        Ok(dtype) => dtype,
+        Err(err) => {
+            throw_error(&mut env, err);
+            return JByteArray::default();
+        }
     };
-    let array = env.new_byte_array(vs.len() as jsize).unwrap();
-    env.set_byte_array_region(&array, 0, &vs).unwrap();
-    array
+    // Copies straight from the tensor memory when possible
+    if dtype.is_native(tensor.dtype()) {
+        if let Some(array) = with_cpu_bytes(tensor, |bytes| env.byte_array_from_slice(bytes)) {
+            return array.unwrap();
+        }
+    }
+    match dtype.encode(tensor) {
+        Ok(bytes) => env.byte_array_from_slice(&bytes).unwrap(),
+        Err(err) => {
+            throw_error(&mut env, err);
+            JByteArray::default()
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_toDirectByteBuffer<'local>(
+    mut env: JNIEnv<'local>,
+    _: JObject,
+    handle: jlong,
+    dtype: jint,
+) -> JByteBuffer<'local> {
+    let tensor = cast_handle::<Tensor>(handle);
+    let memory = match DataType::from_ordinal(dtype) {
+        Ok(dtype) if dtype.is_native(tensor.dtype()) => {
+            with_cpu_bytes(tensor, |bytes| (bytes.as_ptr() as *mut u8, bytes.len()))
+        }
+        _ => None,
+    };
+    match memory {
+        // The buffer shares the memory of the tensor, it is only valid until the tensor is
+        // deleted. JNI has no read-only direct buffers, the caller must not write through it.
+        Some((data, len)) if len > 0 => unsafe { env.new_direct_byte_buffer(data, len) }.unwrap(),
+        _ => JByteBuffer::from(JObject::null()),
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_copyToBuffer<'local>(
+    mut env: JNIEnv<'local>,
+    _: JObject,
+    handle: jlong,
+    dtype: jint,
+    buffer: JByteBuffer<'local>,
+) {
+    let len = env.get_direct_buffer_capacity(&buffer).unwrap();
+    let data = env.get_direct_buffer_address(&buffer).unwrap();
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let out = unsafe { slice::from_raw_parts_mut(data, len) };
+        DataType::from_ordinal(dtype)?.write(tensor, out)
+    };
+    if let Err(err) = op() {
+        throw_error(&mut env, err);
+    }
 }
 
 #[no_mangle]
@@ -107,37 +160,23 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_toDevice<'local>(
 }
 
 #[no_mangle]
-pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_toDataType<'local>(
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_toDataType(
     mut env: JNIEnv,
     _: JObject,
     handle: jlong,
-    dtype: jint,
+    from_dtype: jint,
+    to_dtype: jint,
 ) -> jlong {
     let to_data_type = || {
-        let dtype = as_data_type(dtype)?;
+        let from_dtype = DataType::from_ordinal(from_dtype)?;
+        let to_dtype = DataType::from_ordinal(to_dtype)?;
         let tensor = cast_handle::<Tensor>(handle);
-        tensor.to_dtype(dtype)
+        from_dtype.cast(tensor, to_dtype)
     };
     let ret = to_data_type();
     return_handle(&mut env, ret)
 }
 
-#[no_mangle]
-pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_toBoolean<'local>(
-    mut env: JNIEnv,
-    _: JObject,
-    handle: jlong,
-) -> jlong {
-    let to_boolean = || {
-        let tensor = cast_handle::<Tensor>(handle);
-        let tensor = tensor.to_dtype(DType::U8)?;
-        let zeros = tensor.zeros_like()?;
-        tensor.ne(&zeros)
-    };
-    let ret = to_boolean();
-    return_handle(&mut env, ret)
-}
-
 #[no_mangle]
 pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_fullSlice<'local>(
     mut env: JNIEnv,
@@ -145,25 +184,20 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_fullSlice<'local>(
     handle: jlong,
     min: JLongArray<'local>,
     max: JLongArray<'local>,
-    _: JLongArray<'local>,
+    step: JLongArray<'local>,
 ) -> jlong {
-    let mut index = || {
+    let mut slice = || {
         let tensor = cast_handle::<Tensor>(handle);
         let min = unsafe { env.get_array_elements(&min, ReleaseMode::NoCopyBack) }.unwrap();
-        let min = min.into_iter().map(|i| *i as usize).collect::<Vec<usize>>();
         let max = unsafe { env.get_array_elements(&max, ReleaseMode::NoCopyBack) }.unwrap();
-        let max = max.into_iter().map(|i| *i as usize).collect::<Vec<usize>>();
+        let step = unsafe { env.get_array_elements(&step, ReleaseMode::NoCopyBack) }.unwrap();
         if min.len() == 0 {
             tensor.copy()
         } else {
-            let mut slice = tensor.narrow(0, min[0], max[0] - min[0])?;
-            for i in 1..min.len() {
-                slice = slice.narrow(i, min[i], max[i] - min[i])?;
-            }
-            Ok(slice)
+            index::strided_slice(tensor, &min, &max, &step)
         }
     };
-    let ret = index();
+    let ret = slice();
     return_handle(&mut env, ret)
 }
 
@@ -234,23 +268,10 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_deleteTensor(
     _: JObject,
     handle: jlong,
 ) {
+    autograd::remove_gradient(cast_handle::<Tensor>(handle));
     drop_handle::<Tensor>(handle);
 }
 
-fn convert_back_<T: WithDType>(mut vs: Vec<T>) -> Vec<jbyte> {
-    let size_in_bytes = T::DTYPE.size_in_bytes();
-    let length = vs.len() * size_in_bytes;
-    let capacity = vs.capacity() * size_in_bytes;
-    let ptr = vs.as_mut_ptr() as *mut jbyte;
-    // Don't run the destructor for Vec<T>
-    std::mem::forget(vs);
-    // SAFETY:
-    //
-    // Every T is larger than u8, so there is no issue regarding alignment.
-    // This re-interpret the Vec<T> as a Vec<u8>.
-    unsafe { Vec::from_raw_parts(ptr, length, capacity) }
-}
-
 fn as_shape<'local>(env: &mut JNIEnv, shape: &JLongArray<'local>) -> Shape {
     let shape = unsafe { env.get_array_elements(&shape, ReleaseMode::NoCopyBack) }.unwrap();
     let shape = shape
@@ -260,31 +281,25 @@ fn as_shape<'local>(env: &mut JNIEnv, shape: &JLongArray<'local>) -> Shape {
     Shape::from_dims(&shape)
 }
 
-pub fn as_data_type(data_type: i32) -> Result<DType> {
-    match data_type {
-        0 => Ok(DType::F32),
-        1 => Ok(DType::F64),
-        2 => Ok(DType::F16),
-        3 => Ok(DType::U8),
-        6 => Ok(DType::I64),
-        11 => Ok(DType::BF16),
-        13 => Ok(DType::U32),
-        _ => Err(Error::Msg(format!("Unsupported data type: {}", data_type))),
-    }
+fn as_vec(env: &mut JNIEnv, array: &JLongArray) -> Vec<i64> {
+    let array = unsafe { env.get_array_elements(array, ReleaseMode::NoCopyBack) }.unwrap();
+    array.iter().copied().collect()
 }
 
-fn to_data_type(data_type: DType) -> i32 {
-    match data_type {
-        DType::F32 => 0,
-        DType::F64 => 1,
-        DType::F16 => 2,
-        DType::U8 => 3,
-        DType::I64 => 6,
-        DType::BF16 => 11,
-        DType::U32 => 13,
+/// The dtype of float results, integers become F32.
+fn as_float_dtype(dtype: DType) -> DType {
+    if dtype.is_float() {
+        dtype
+    } else {
+        DType::F32
     }
 }
 
+/// The candle dtype holding the values of a DJL data type.
+pub fn as_data_type(data_type: i32) -> Result<DType> {
+    DataType::from_ordinal(data_type).map(DataType::storage)
+}
+
 pub fn as_device<'local>(
     env: &mut JNIEnv<'local>,
     device_type: JString,
@@ -297,7 +312,11 @@ pub fn as_device<'local>(
 
     match device_type.as_str() {
         "cpu" => Ok(Device::Cpu),
-        "gpu" => Device::new_cuda(device_id),
+        "gpu" => {
+            #[cfg(feature = "cuda")]
+            crate::compute_cap::check_gpu(device_id)?;
+            Device::new_cuda(device_id)
+        }
         "mps" => Device::new_metal(device_id),
         _ => Err(Error::Msg(format!("Invalid device type: {}", device_type))),
     }
@@ -307,19 +326,41 @@ fn return_handle(env: &mut JNIEnv, tensor: Result<Tensor>) -> jlong {
     match tensor {
         Ok(output) => to_handle(output),
         Err(err) => {
-            let msg = format!("{err:?}");
-            match err {
-                Error::UnexpectedDType { .. }
-                | Error::DTypeMismatchBinaryOp { .. }
-                | Error::UnsupportedDTypeForOp(_, _) => {
-                    env.throw_new("java/lang/UnsupportedOperationException", msg)
-                        .unwrap();
-                }
-                _ => {
-                    env.throw_new("ai/djl/engine/EngineException", msg).unwrap();
-                }
-            }
+            throw_error(env, err);
             0
         }
     }
 }
+
+fn return_handles<'local>(
+    env: &mut JNIEnv<'local>,
+    tensors: Result<Vec<Tensor>>,
+) -> JLongArray<'local> {
+    match tensors {
+        Ok(tensors) => {
+            let handles: Vec<jlong> = tensors.into_iter().map(to_handle).collect();
+            let ret = env.new_long_array(handles.len() as jsize).unwrap();
+            env.set_long_array_region(&ret, 0, &handles).unwrap();
+            ret
+        }
+        Err(err) => {
+            throw_error(env, err);
+            JLongArray::default()
+        }
+    }
+}
+
+fn throw_error(env: &mut JNIEnv, err: Error) {
+    let msg = format!("{err:?}");
+    match err {
+        Error::UnexpectedDType { .. }
+        | Error::DTypeMismatchBinaryOp { .. }
+        | Error::UnsupportedDTypeForOp(_, _) => {
+            env.throw_new("java/lang/UnsupportedOperationException", msg)
+                .unwrap();
+        }
+        _ => {
+            env.throw_new("ai/djl/engine/EngineException", msg).unwrap();
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use crate::cast_handle;
-use crate::ndarray::return_handle;
-use candle::Tensor;
-use jni::objects::JObject;
+use crate::ndarray::{as_shape, return_handle};
+use candle::{DType, Module, Result, Shape, Tensor, D};
+use candle_nn::{Embedding, Linear, PReLU};
+use jni::objects::{JLongArray, JObject};
 use jni::sys::{jfloat, jint, jlong};
 use jni::JNIEnv;
 
@@ -51,3 +52,261 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_logSoftmax<'local>(
     let ret = candle_nn::ops::log_softmax(&tensor, axis as usize);
     return_handle(&mut env, ret)
 }
+
+// `0` is passed for an absent optional tensor, e.g. a bias
+fn as_optional(handle: jlong) -> Option<&'static Tensor> {
+    if handle == 0 {
+        None
+    } else {
+        Some(cast_handle::<Tensor>(handle))
+    }
+}
+
+// candle takes a single stride, padding and dilation for every spatial dim
+fn as_uniform(name: &str, values: &Shape) -> Result<usize> {
+    let values = values.dims();
+    match values.first() {
+        Some(first) if values.iter().all(|v| v == first) => Ok(*first),
+        _ => {
+            candle::bail!("Only the same {name} on every spatial dim is supported, got {values:?}")
+        }
+    }
+}
+
+// Adds a per channel bias, `(C)`, to a `(N, C, ...)` tensor
+fn add_channel_bias(xs: Tensor, bias: Option<&Tensor>) -> Result<Tensor> {
+    match bias {
+        Some(bias) => {
+            let mut dims = vec![1; xs.rank()];
+            dims[1] = bias.elem_count();
+            xs.broadcast_add(&bias.reshape(dims)?)
+        }
+        None => Ok(xs),
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_convolution<'local>(
+    mut env: JNIEnv,
+    _: JObject,
+    input: jlong,
+    weight: jlong,
+    bias: jlong,
+    stride: JLongArray<'local>,
+    padding: JLongArray<'local>,
+    dilation: JLongArray<'local>,
+    groups: jint,
+) -> jlong {
+    let mut op = || {
+        let input = cast_handle::<Tensor>(input);
+        let weight = cast_handle::<Tensor>(weight);
+        let stride = as_uniform("stride", &as_shape(&mut env, &stride))?;
+        let padding = as_uniform("padding", &as_shape(&mut env, &padding))?;
+        let dilation = as_uniform("dilation", &as_shape(&mut env, &dilation))?;
+        let groups = groups as usize;
+        let ret = match weight.rank() {
+            3 => input.conv1d(weight, padding, stride, dilation, groups)?,
+            4 => input.conv2d(weight, padding, stride, dilation, groups)?,
+            rank => candle::bail!("Only 1d and 2d convolution are supported, got {rank}d weight"),
+        };
+        add_channel_bias(ret, as_optional(bias))
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_deconvolution<'local>(
+    mut env: JNIEnv,
+    _: JObject,
+    input: jlong,
+    weight: jlong,
+    bias: jlong,
+    stride: JLongArray<'local>,
+    padding: JLongArray<'local>,
+    out_padding: JLongArray<'local>,
+    dilation: JLongArray<'local>,
+    groups: jint,
+) -> jlong {
+    let mut op = || {
+        let input = cast_handle::<Tensor>(input);
+        let weight = cast_handle::<Tensor>(weight);
+        let stride = as_uniform("stride", &as_shape(&mut env, &stride))?;
+        let padding = as_uniform("padding", &as_shape(&mut env, &padding))?;
+        let out_padding = as_uniform("output padding", &as_shape(&mut env, &out_padding))?;
+        let dilation = as_uniform("dilation", &as_shape(&mut env, &dilation))?;
+        let groups = groups as usize;
+        let ret = match weight.rank() {
+            3 => input.conv_transpose1d(weight, padding, out_padding, stride, dilation, groups)?,
+            4 if groups == 1 => {
+                input.conv_transpose2d(weight, padding, out_padding, stride, dilation)?
+            }
+            4 => candle::bail!("Grouped 2d deconvolution is not supported"),
+            rank => {
+                candle::bail!("Only 1d and 2d deconvolution are supported, got {rank}d weight")
+            }
+        };
+        add_channel_bias(ret, as_optional(bias))
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_linear(
+    mut env: JNIEnv,
+    _: JObject,
+    input: jlong,
+    weight: jlong,
+    bias: jlong,
+) -> jlong {
+    let input = cast_handle::<Tensor>(input);
+    let weight = cast_handle::<Tensor>(weight);
+    let linear = Linear::new(weight.clone(), as_optional(bias).cloned());
+    let ret = linear.forward(input);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_embedding(
+    mut env: JNIEnv,
+    _: JObject,
+    input: jlong,
+    weight: jlong,
+) -> jlong {
+    let op = || {
+        let input = cast_handle::<Tensor>(input);
+        let weight = cast_handle::<Tensor>(weight);
+        let ids = if input.dtype().is_float() {
+            input.to_dtype(DType::U32)?
+        } else {
+            input.clone()
+        };
+        Embedding::new(weight.clone(), weight.dim(D::Minus1)?).forward(&ids)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_prelu(
+    mut env: JNIEnv,
+    _: JObject,
+    input: jlong,
+    alpha: jlong,
+) -> jlong {
+    let input = cast_handle::<Tensor>(input);
+    let alpha = cast_handle::<Tensor>(alpha);
+    let prelu = PReLU::new(alpha.clone(), alpha.elem_count() == 1);
+    let ret = prelu.forward(input);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_dropout(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    rate: jfloat,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = candle_nn::ops::dropout(tensor, rate);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_layerNorm<'local>(
+    mut env: JNIEnv,
+    _: JObject,
+    input: jlong,
+    normalized_shape: JLongArray<'local>,
+    gamma: jlong,
+    beta: jlong,
+    eps: jfloat,
+) -> jlong {
+    let mut op = || {
+        let input = cast_handle::<Tensor>(input);
+        let normalized_shape = as_shape(&mut env, &normalized_shape);
+        let dims = input.dims();
+        let rank = normalized_shape.rank();
+        if rank == 0 || rank > dims.len() || &dims[dims.len() - rank..] != normalized_shape.dims() {
+            candle::bail!(
+                "Normalized shape {normalized_shape:?} does not match the trailing dims of {:?}",
+                input.shape()
+            );
+        }
+        // Normalizes over the trailing dims flattened into one
+        let size = normalized_shape.elem_count();
+        let gamma = match as_optional(gamma) {
+            Some(gamma) => gamma.flatten_all()?,
+            None => Tensor::ones(size, input.dtype(), input.device())?,
+        };
+        let beta = match as_optional(beta) {
+            Some(beta) => beta.flatten_all()?,
+            None => Tensor::zeros(size, input.dtype(), input.device())?,
+        };
+        let xs = input.reshape(((), size))?.contiguous()?;
+        candle_nn::ops::layer_norm(&xs, &gamma, &beta, eps)?.reshape(dims)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_batchNorm(
+    mut env: JNIEnv,
+    _: JObject,
+    input: jlong,
+    running_mean: jlong,
+    running_var: jlong,
+    gamma: jlong,
+    beta: jlong,
+    axis: jint,
+    eps: jfloat,
+) -> jlong {
+    let op = || {
+        let input = cast_handle::<Tensor>(input);
+        let running_mean = cast_handle::<Tensor>(running_mean);
+        let running_var = cast_handle::<Tensor>(running_var);
+        let rank = input.rank();
+        let axis = if axis < 0 { rank as i32 + axis } else { axis };
+        if axis < 0 || axis as usize >= rank {
+            candle::bail!("Invalid axis {axis} for {:?}", input.shape());
+        }
+        // The statistics and the affine parameters, `(C)`, are broadcast along `axis`
+        let mut dims = vec![1; rank];
+        dims[axis as usize] = input.dim(axis as usize)?;
+        let mean = running_mean.reshape(dims.as_slice())?;
+        let std = (running_var.reshape(dims.as_slice())? + eps as f64)?.sqrt()?;
+        let mut ret = input.broadcast_sub(&mean)?.broadcast_div(&std)?;
+        if let Some(gamma) = as_optional(gamma) {
+            ret = ret.broadcast_mul(&gamma.reshape(dims.as_slice())?)?;
+        }
+        if let Some(beta) = as_optional(beta) {
+            ret = ret.broadcast_add(&beta.reshape(dims.as_slice())?)?;
+        }
+        Ok(ret)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_rmsNorm(
+    mut env: JNIEnv,
+    _: JObject,
+    input: jlong,
+    gamma: jlong,
+    eps: jfloat,
+) -> jlong {
+    let op = || {
+        let input = cast_handle::<Tensor>(input);
+        let gamma = match as_optional(gamma) {
+            Some(gamma) => gamma.clone(),
+            None => Tensor::ones(input.dim(D::Minus1)?, input.dtype(), input.device())?,
+        };
+        candle_nn::ops::rms_norm(&input.contiguous()?, &gamma, eps)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
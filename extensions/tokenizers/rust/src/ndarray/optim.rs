@@ -0,0 +1,95 @@
+use crate::cast_handle;
+use crate::ndarray::autograd::assign;
+use crate::ndarray::throw_error;
+use candle::{Result, Tensor};
+use jni::objects::JObject;
+use jni::sys::{jboolean, jfloat, jlong, JNI_TRUE};
+use jni::JNIEnv;
+
+// Follows MXNet: rescaled_grad = clip(rescale_grad * grad, clip_grad), a negative `clip_grad`
+// disables clipping
+fn rescale(grad: jlong, rescale_grad: jfloat, clip_grad: jfloat) -> Result<Tensor> {
+    let mut grad = cast_handle::<Tensor>(grad).detach();
+    if rescale_grad != 1.0 {
+        grad = (grad * rescale_grad as f64)?;
+    }
+    if clip_grad >= 0.0 {
+        grad = grad.clamp(-clip_grad, clip_grad)?;
+    }
+    Ok(grad)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_sgdUpdate(
+    mut env: JNIEnv,
+    _: JObject,
+    weight: jlong,
+    grad: jlong,
+    state: jlong,
+    learning_rate: jfloat,
+    weight_decay: jfloat,
+    rescale_grad: jfloat,
+    clip_grad: jfloat,
+    momentum: jfloat,
+) {
+    let op = || {
+        let value = cast_handle::<Tensor>(weight).detach();
+        let grad = rescale(grad, rescale_grad, clip_grad)?;
+        let grad = ((grad + (&value * weight_decay as f64)?)? * learning_rate as f64)?;
+        if momentum == 0.0 {
+            assign(weight, (value - grad)?)
+        } else {
+            let state_value = cast_handle::<Tensor>(state).detach();
+            let state_value = ((state_value * momentum as f64)? + grad)?;
+            assign(weight, (value - &state_value)?)?;
+            assign(state, state_value)
+        }
+    };
+    if let Err(e) = op() {
+        throw_error(&mut env, e);
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_adamUpdate(
+    mut env: JNIEnv,
+    _: JObject,
+    weight: jlong,
+    grad: jlong,
+    mean: jlong,
+    variance: jlong,
+    learning_rate: jfloat,
+    learning_rate_bias_correction: jfloat,
+    weight_decay: jfloat,
+    rescale_grad: jfloat,
+    clip_grad: jfloat,
+    beta1: jfloat,
+    beta2: jfloat,
+    eps: jfloat,
+    adamw: jboolean,
+) {
+    let op = || {
+        let mut value = cast_handle::<Tensor>(weight).detach();
+        let mut grad = rescale(grad, rescale_grad, clip_grad)?;
+        if adamw == JNI_TRUE {
+            // Decoupled weight decay
+            value = (&value - (&value * (learning_rate * weight_decay) as f64)?)?;
+        } else {
+            grad = (grad + (&value * weight_decay as f64)?)?;
+        }
+        let beta1 = beta1 as f64;
+        let beta2 = beta2 as f64;
+        let mean_value = cast_handle::<Tensor>(mean).detach();
+        let mean_value = ((mean_value * beta1)? + (&grad * (1.0 - beta1))?)?;
+        let variance_value = cast_handle::<Tensor>(variance).detach();
+        let variance_value = ((variance_value * beta2)? + (grad.sqr()? * (1.0 - beta2))?)?;
+        let step = ((&mean_value * learning_rate_bias_correction as f64)?
+            / (variance_value.sqrt()? + eps as f64)?)?;
+        assign(weight, (value - step)?)?;
+        assign(mean, mean_value)?;
+        assign(variance, variance_value)
+    };
+    if let Err(e) = op() {
+        throw_error(&mut env, e);
+    }
+}
@@ -1,9 +1,9 @@
-use candle::{Tensor, D};
-use jni::objects::{JIntArray, JLongArray, JObject, ReleaseMode};
+use candle::{Result, Tensor, D};
+use jni::objects::{JIntArray, JLongArray, JObject, JString, ReleaseMode};
 use jni::sys::{jdouble, jint, jlong, jsize};
 use jni::JNIEnv;
 
-use crate::ndarray::{as_shape, return_handle};
+use crate::ndarray::{as_shape, as_vec, return_handle};
 use crate::{cast_handle, to_handle};
 
 #[no_mangle]
@@ -256,3 +256,136 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_maxPool2d<'local>(
     let ret = op();
     return_handle(&mut env, ret)
 }
+
+/// Pads `dim` by `left` and `right` elements, in the `constant`, `reflect` or `replicate` mode of
+/// `torch.nn.functional.pad`.
+fn pad_dim(
+    tensor: &Tensor,
+    dim: usize,
+    left: usize,
+    right: usize,
+    mode: &str,
+    value: f64,
+) -> Result<Tensor> {
+    let len = tensor.dim(dim)?;
+    match mode {
+        "constant" => {
+            let mut dims = tensor.dims().to_vec();
+            let mut parts = Vec::with_capacity(3);
+            for (size, part) in [(left, None), (len, Some(tensor)), (right, None)] {
+                match part {
+                    Some(part) => parts.push(part.clone()),
+                    None if size > 0 => {
+                        dims[dim] = size;
+                        let fill = Tensor::new(value, tensor.device())?.to_dtype(tensor.dtype())?;
+                        parts.push(fill.broadcast_as(dims.as_slice())?.contiguous()?);
+                    }
+                    None => {}
+                }
+            }
+            Tensor::cat(&parts, dim)
+        }
+        "reflect" => {
+            if left >= len || right >= len {
+                candle::bail!("Padding of ({left}, {right}) must be less than the size {len}");
+            }
+            // The mirrored elements, excluding the edges
+            let mut indices: Vec<u32> = (1..=left as u32).rev().collect();
+            indices.extend(0..len as u32);
+            indices.extend((len - 1 - right..len - 1).rev().map(|i| i as u32));
+            let count = indices.len();
+            let indices = Tensor::from_vec(indices, count, tensor.device())?;
+            tensor.index_select(&indices, dim)
+        }
+        "replicate" => tensor.pad_with_same(dim, left, right),
+        _ => candle::bail!("Unsupported padding mode: {mode}"),
+    }
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_pad<'local>(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    padding: JLongArray<'local>,
+    mode: JString,
+    value: jdouble,
+) -> jlong {
+    let padding = as_vec(&mut env, &padding);
+    let mode: String = env
+        .get_string(&mode)
+        .expect("Couldn't get java string!")
+        .into();
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let rank = tensor.rank();
+        // `(left, right)` pairs from the last dim backward, like PyTorch
+        let pairs = padding.chunks_exact(2);
+        if !pairs.remainder().is_empty() || pairs.len() > rank {
+            candle::bail!("Invalid padding {padding:?} for {:?}", tensor.shape());
+        }
+        let mut padded = tensor.clone();
+        for (i, pair) in pairs.enumerate() {
+            padded = pad_dim(
+                &padded,
+                rank - 1 - i,
+                pair[0] as usize,
+                pair[1] as usize,
+                &mode,
+                value,
+            )?;
+        }
+        Ok(padded)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_trace(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    offset: jint,
+    axis1: jint,
+    axis2: jint,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let rank = tensor.rank() as i32;
+        let axis1 = if axis1 < 0 { rank + axis1 } else { axis1 } as usize;
+        let axis2 = if axis2 < 0 { rank + axis2 } else { axis2 } as usize;
+        if axis1 == axis2 || axis1 >= tensor.rank() || axis2 >= tensor.rank() {
+            candle::bail!("Invalid axes ({axis1}, {axis2}) for {:?}", tensor.shape());
+        }
+        // Moves the two axes last and sums the diagonal of the flattened matrices
+        let mut dims: Vec<usize> = (0..tensor.rank())
+            .filter(|d| *d != axis1 && *d != axis2)
+            .collect();
+        let batch_dims: Vec<usize> = dims.iter().map(|d| tensor.dims()[*d]).collect();
+        dims.extend([axis1, axis2]);
+        let (rows, cols) = (tensor.dim(axis1)?, tensor.dim(axis2)?);
+        let (row, col) = if offset >= 0 {
+            (0, offset as usize)
+        } else {
+            (offset.unsigned_abs() as usize, 0)
+        };
+        let len = rows.saturating_sub(row).min(cols.saturating_sub(col));
+        if len == 0 {
+            return Tensor::zeros(batch_dims, tensor.dtype(), tensor.device());
+        }
+        let positions: Vec<u32> = (0..len)
+            .map(|i| ((row + i) * cols + col + i) as u32)
+            .collect();
+        let positions = Tensor::from_vec(positions, len, tensor.device())?;
+        let mut flat_dims = batch_dims.clone();
+        flat_dims.push(rows * cols);
+        tensor
+            .permute(dims)?
+            .reshape(flat_dims)?
+            .index_select(&positions, batch_dims.len())?
+            .sum(batch_dims.len())
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
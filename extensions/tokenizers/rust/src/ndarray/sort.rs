@@ -0,0 +1,227 @@
+use crate::cast_handle;
+use crate::ndarray::{return_handle, return_handles};
+use candle::{DType, Device, Result, Tensor};
+use jni::objects::{JLongArray, JObject};
+use jni::sys::{jboolean, jint, jlong, JNI_TRUE};
+use jni::JNIEnv;
+use std::cmp::Ordering;
+
+fn as_axis(tensor: &Tensor, axis: jint) -> Result<usize> {
+    let rank = tensor.rank() as i32;
+    let dim = if axis < 0 { rank + axis } else { axis };
+    if dim < 0 || dim >= rank {
+        candle::bail!("Axis {axis} is out of range for {:?}", tensor.shape());
+    }
+    Ok(dim as usize)
+}
+
+/// The `U32` positions that sort `tensor` along `axis`. Equal elements keep their order on the
+/// CPU, the CUDA kernel of candle is a bitonic sort and gives no such guarantee.
+fn arg_sort(tensor: &Tensor, axis: usize, ascending: bool) -> Result<Tensor> {
+    // candle sorts along the last dim only
+    let last = tensor.rank() - 1;
+    tensor
+        .transpose(axis, last)?
+        .contiguous()?
+        .arg_sort_last_dim(ascending)?
+        .transpose(axis, last)?
+        .contiguous()
+}
+
+/// Groups equal rows, returning the first row of every group, the group of every row and the
+/// size of every group. Groups are ordered by value if `sorted`, by first occurrence otherwise.
+/// `cmp` must be a total order of the elements.
+fn group_rows<T, F>(rows: &[Vec<T>], sorted: bool, cmp: F) -> (Vec<u32>, Vec<i64>, Vec<i64>)
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    let cmp_rows = |i: usize, j: usize| {
+        let pairs = rows[i].iter().zip(&rows[j]);
+        pairs
+            .map(|(a, b)| cmp(a, b))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    };
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    // A stable sort, the first row of every group is its first occurrence
+    order.sort_by(|&i, &j| cmp_rows(i, j));
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for i in order {
+        match groups.last_mut() {
+            Some(group) if cmp_rows(group[0], i).is_eq() => group.push(i),
+            _ => groups.push(vec![i]),
+        }
+    }
+    if !sorted {
+        groups.sort_by_key(|group| group[0]);
+    }
+
+    let mut inverse = vec![0i64; rows.len()];
+    for (id, group) in groups.iter().enumerate() {
+        for i in group {
+            inverse[*i] = id as i64;
+        }
+    }
+    let first = groups.iter().map(|group| group[0] as u32).collect();
+    let counts = groups.iter().map(|group| group.len() as i64).collect();
+    (first, inverse, counts)
+}
+
+/// A total order of floats where `-0.0` equals `0.0` and NaNs sort last, like `torch.unique`.
+fn cmp_float(a: &f64, b: &f64) -> Ordering {
+    // Adding 0.0 turns -0.0 into 0.0
+    (a + 0.0).total_cmp(&(b + 0.0))
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_sort(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    axis: jint,
+    ascending: jboolean,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        if tensor.rank() == 0 {
+            return Ok(tensor.clone());
+        }
+        let axis = as_axis(tensor, axis)?;
+        let indices = arg_sort(tensor, axis, ascending == JNI_TRUE)?;
+        tensor.contiguous()?.gather(&indices, axis)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_argSort(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    axis: jint,
+    ascending: jboolean,
+) -> jlong {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        if tensor.rank() == 0 {
+            return Tensor::zeros((), DType::I64, tensor.device());
+        }
+        let axis = as_axis(tensor, axis)?;
+        arg_sort(tensor, axis, ascending == JNI_TRUE)?.to_dtype(DType::I64)
+    };
+    let ret = op();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_topK<'local>(
+    mut env: JNIEnv<'local>,
+    _: JObject,
+    handle: jlong,
+    k: jint,
+    axis: jint,
+    largest: jboolean,
+    _sorted: jboolean,
+) -> JLongArray<'local> {
+    // The top k elements are always returned in order
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        let axis = as_axis(tensor, axis)?;
+        let k = k as usize;
+        if k > tensor.dim(axis)? {
+            candle::bail!("k ({k}) is out of range for {:?}", tensor.shape());
+        }
+        let indices = arg_sort(tensor, axis, largest != JNI_TRUE)?
+            .narrow(axis, 0, k)?
+            .contiguous()?;
+        let values = tensor.contiguous()?.gather(&indices, axis)?;
+        Ok(vec![values, indices.to_dtype(DType::I64)?])
+    };
+    let ret = op();
+    return_handles(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_unique<'local>(
+    mut env: JNIEnv<'local>,
+    _: JObject,
+    handle: jlong,
+    dim: jint,
+    sorted: jboolean,
+) -> JLongArray<'local> {
+    let op = || {
+        let tensor = cast_handle::<Tensor>(handle);
+        // A negative dim flattens the tensor, like `dim=None` in PyTorch
+        let flatten = dim < 0;
+        let (input, dim) = if flatten {
+            (tensor.flatten_all()?, 0)
+        } else {
+            (tensor.clone(), as_axis(tensor, dim)?)
+        };
+        let n = input.dim(dim)?;
+        let rows = input
+            .transpose(0, dim)?
+            .contiguous()?
+            .reshape((n, input.elem_count() / n.max(1)))?
+            .to_device(&Device::Cpu)?;
+        // Integers are compared as i64 and floats as f64, both lossless
+        let sorted = sorted == JNI_TRUE;
+        let (first, inverse, counts) = if rows.dtype().is_int() {
+            group_rows(
+                &rows.to_dtype(DType::I64)?.to_vec2::<i64>()?,
+                sorted,
+                i64::cmp,
+            )
+        } else {
+            let rows = rows.to_dtype(DType::F64)?.to_vec2::<f64>()?;
+            group_rows(&rows, sorted, cmp_float)
+        };
+
+        let device = tensor.device();
+        let len = first.len();
+        let first = Tensor::from_vec(first, len, device)?;
+        let output = input.index_select(&first, dim)?;
+        let inverse = if flatten {
+            Tensor::from_vec(inverse, tensor.shape(), device)?
+        } else {
+            Tensor::from_vec(inverse, n, device)?
+        };
+        let counts = Tensor::from_vec(counts, len, device)?;
+        Ok(vec![output, inverse, counts])
+    };
+    let ret = op();
+    return_handles(&mut env, ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_rows() {
+        let rows = vec![vec![3i64], vec![1], vec![3], vec![2]];
+        let (first, inverse, counts) = group_rows(&rows, true, i64::cmp);
+        assert_eq!(first, [1, 3, 0]);
+        assert_eq!(inverse, [2, 0, 2, 1]);
+        assert_eq!(counts, [1, 1, 2]);
+        let (first, _, _) = group_rows(&rows, false, i64::cmp);
+        assert_eq!(first, [0, 1, 3]);
+    }
+
+    #[test]
+    fn test_group_rows_nan() {
+        let nan = f64::NAN;
+        let rows = vec![
+            vec![nan, 1.0],
+            vec![0.0, 2.0],
+            vec![nan, 1.0],
+            vec![-0.0, 2.0],
+            vec![1.0, nan],
+        ];
+        let (first, inverse, counts) = group_rows(&rows, true, cmp_float);
+        assert_eq!(first, [1, 4, 0]);
+        assert_eq!(inverse, [2, 0, 2, 0, 1]);
+        assert_eq!(counts, [2, 1, 2]);
+    }
+}
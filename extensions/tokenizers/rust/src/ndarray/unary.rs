@@ -1,10 +1,106 @@
-use candle::Tensor;
+use candle::cpu::erf::erf_inv;
+use candle::cpu_backend::{unary_map, Map1};
+use candle::{CpuStorage, CustomOp1, DType, Device, Layout, Result, Shape, Tensor, WithDType};
 use jni::objects::JObject;
-use jni::sys::jlong;
+use jni::sys::{jfloat, jlong};
 use jni::JNIEnv;
+use std::f64::consts::PI;
 
 use crate::cast_handle;
-use crate::ndarray::return_handle;
+use crate::ndarray::{as_float_dtype, return_handle};
+
+/// An elementwise kernel, the function is evaluated in f64 for every dtype.
+struct Unary<F> {
+    name: &'static str,
+    func: F,
+}
+
+impl<F: Fn(f64) -> f64> Map1 for Unary<F> {
+    fn f<T: WithDType>(&self, vs: &[T], layout: &Layout) -> Result<Vec<T>> {
+        Ok(unary_map(vs, layout, |v| {
+            T::from_f64((self.func)(v.to_f64()))
+        }))
+    }
+}
+
+impl<F: Fn(f64) -> f64> CustomOp1 for Unary<F> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn cpu_fwd(&self, storage: &CpuStorage, layout: &Layout) -> Result<(CpuStorage, Shape)> {
+        Ok((self.map(storage, layout)?, layout.shape().clone()))
+    }
+}
+
+/// Applies `func` to every element, keeping the dtype. The kernel runs on the CPU, tensors on
+/// other devices are copied over and back.
+pub(crate) fn map_unary<F: Fn(f64) -> f64>(
+    tensor: &Tensor,
+    name: &'static str,
+    func: F,
+) -> Result<Tensor> {
+    let op = Unary { name, func };
+    if tensor.device().is_cpu() {
+        tensor.apply_op1_no_bwd(&op)
+    } else {
+        let cpu = tensor.to_device(&Device::Cpu)?;
+        cpu.apply_op1_no_bwd(&op)?.to_device(tensor.device())
+    }
+}
+
+/// Like [map_unary], integer tensors are converted to F32 first.
+pub(crate) fn map_float<F: Fn(f64) -> f64>(
+    tensor: &Tensor,
+    name: &'static str,
+    func: F,
+) -> Result<Tensor> {
+    let tensor = tensor.to_dtype(as_float_dtype(tensor.dtype()))?;
+    map_unary(&tensor, name, func)
+}
+
+/// Like [map_unary], returns a boolean (U8) tensor.
+pub(crate) fn map_bool<F: Fn(f64) -> bool>(
+    tensor: &Tensor,
+    name: &'static str,
+    func: F,
+) -> Result<Tensor> {
+    map_unary(tensor, name, |v| if func(v) { 1.0 } else { 0.0 })?.to_dtype(DType::U8)
+}
+
+/// The natural logarithm of the absolute value of the gamma function, using the Lanczos
+/// approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // The reflection formula, gamma(x) * gamma(1 - x) = pi / sin(pi * x)
+        return PI.ln() - (PI * x).sin().abs().ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + G + 0.5;
+    let mut sum = COEFFICIENTS[0];
+    for (i, c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// The logarithm in `base`, integer tensors are converted to F32 first.
+fn log_base(tensor: &Tensor, base: f64) -> Result<Tensor> {
+    let tensor = tensor.to_dtype(as_float_dtype(tensor.dtype()))?;
+    tensor.log()?.affine(1.0 / base.ln(), 0.0)
+}
 
 #[no_mangle]
 pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_exp<'local>(
@@ -170,3 +266,263 @@ pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_erf<'local>(
     let ret = tensor.erf();
     return_handle(&mut env, ret)
 }
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_log10(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = log_base(tensor, 10.0);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_log2(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = log_base(tensor, 2.0);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_tan(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "tan", f64::tan);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_asin(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "asin", f64::asin);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_acos(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "acos", f64::acos);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_atan(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "atan", f64::atan);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_sinh(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "sinh", f64::sinh);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_cosh(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "cosh", f64::cosh);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_asinh(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "asinh", f64::asinh);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_acosh(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "acosh", f64::acosh);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_atanh(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "atanh", f64::atanh);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_gammaln(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "gammaln", ln_gamma);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_erfinv(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "erfinv", erf_inv);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_sign(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = tensor.sign();
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_trunc(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = if tensor.dtype().is_int() {
+        Ok(tensor.clone())
+    } else {
+        // Rounds the magnitude down, keeping native ops for the gradient and the device
+        tensor
+            .abs()
+            .and_then(|t| t.floor())
+            .and_then(|t| t.mul(&tensor.sign()?))
+    };
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_softPlus(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "softPlus", |v| {
+        v.max(0.0) + (-v.abs()).exp().ln_1p()
+    });
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_softSign(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_float(tensor, "softSign", |v| v / (1.0 + v.abs()));
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_elu(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+    alpha: jfloat,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = tensor
+        .to_dtype(as_float_dtype(tensor.dtype()))
+        .and_then(|t| t.elu(alpha as f64));
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_selu(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    const ALPHA: f64 = 1.673_263_242_354_377_3;
+    const SCALE: f64 = 1.050_700_987_355_480_5;
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = tensor
+        .to_dtype(as_float_dtype(tensor.dtype()))
+        .and_then(|t| t.elu(ALPHA))
+        .and_then(|t| t.affine(SCALE, 0.0));
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_logicalNot(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_bool(tensor, "logicalNot", |v| v == 0.0);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_isInf(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_bool(tensor, "isInf", f64::is_infinite);
+    return_handle(&mut env, ret)
+}
+
+#[no_mangle]
+pub extern "system" fn Java_ai_djl_engine_rust_RustLibrary_isNaN(
+    mut env: JNIEnv,
+    _: JObject,
+    handle: jlong,
+) -> jlong {
+    let tensor = cast_handle::<Tensor>(handle);
+    let ret = map_bool(tensor, "isNaN", f64::is_nan);
+    return_handle(&mut env, ret)
+}
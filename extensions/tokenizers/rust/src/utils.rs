@@ -1,4 +1,5 @@
-use candle::{Result, Tensor};
+use candle::{DType, Result, Tensor};
+use half::{bf16, f16};
 
 /// Repeats a key or value tensor for grouped query attention
 /// The input tensor should have a shape `(batch, num_kv_heads, seq_len, head_dim)`,
@@ -13,3 +14,40 @@ pub fn repeat_kv(xs: Tensor, n_rep: usize) -> Result<Tensor> {
         Tensor::cat(&vec![&xs; n_rep], 2)?.reshape((b_sz, n_kv_head * n_rep, seq_len, head_dim))
     }
 }
+
+/// Applies rotary embeddings to a `(batch, heads, seq_len, head_dim)` tensor where each row of
+/// the batch starts at its own position, as is the case when decoding with a key/value cache.
+pub fn rope_with_offsets(
+    xs: &Tensor,
+    cos: &Tensor,
+    sin: &Tensor,
+    offsets: &[usize],
+) -> Result<Tensor> {
+    let (_b_sz, _h, seq_len, _n_embd) = xs.dims4()?;
+    let rows = offsets
+        .iter()
+        .enumerate()
+        .map(|(i, &offset)| {
+            let cos = cos.narrow(0, offset, seq_len)?;
+            let sin = sin.narrow(0, offset, seq_len)?;
+            candle_nn::rotary_emb::rope(&xs.narrow(0, i, 1)?.contiguous()?, &cos, &sin)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Tensor::cat(&rows, 0)
+}
+
+/// Converts a `(batch, seq_len)` padding mask of ones and zeros into an additive attention mask
+/// of shape `(batch, 1, 1, seq_len)`, using the lowest value of `dtype` for the masked positions.
+pub fn get_extended_attention_mask(attention_mask: &Tensor, dtype: DType) -> Result<Tensor> {
+    let (b_sz, seq_len) = attention_mask.dims2()?;
+    let min = match dtype {
+        DType::F16 => f16::MIN.to_f64(),
+        DType::BF16 => bf16::MIN.to_f64(),
+        _ => f32::MIN as f64,
+    };
+    attention_mask
+        .to_dtype(DType::F32)?
+        .affine(-min, min)?
+        .reshape((b_sz, 1, 1, seq_len))?
+        .to_dtype(dtype)
+}
@@ -0,0 +1,82 @@
+/*
+ * Copyright 2024 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License"). You may not use this file except in compliance
+ * with the License. A copy of the License is located at
+ *
+ * http://aws.amazon.com/apache2.0/
+ *
+ * or in the "license" file accompanying this file. This file is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES
+ * OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+package ai.djl.engine.rust;
+
+/** {@code GpuInfo} describes a GPU device visible to the Rust engine. */
+public final class GpuInfo {
+
+    private int deviceId;
+    private String name;
+    private String computeCapability;
+    private long totalMemory;
+    private boolean supported;
+
+    GpuInfo(int deviceId, String[] info) {
+        this.deviceId = deviceId;
+        name = info[0];
+        computeCapability = info[1];
+        totalMemory = Long.parseLong(info[2]);
+        supported = Boolean.parseBoolean(info[3]);
+    }
+
+    /**
+     * Returns the CUDA ordinal of the device.
+     *
+     * @return the CUDA ordinal of the device
+     */
+    public int getDeviceId() {
+        return deviceId;
+    }
+
+    /**
+     * Returns the name of the device.
+     *
+     * @return the name of the device
+     */
+    public String getName() {
+        return name;
+    }
+
+    /**
+     * Returns the compute capability of the device, e.g. {@code 86} for 8.6.
+     *
+     * @return the compute capability of the device
+     */
+    public String getComputeCapability() {
+        return computeCapability;
+    }
+
+    /**
+     * Returns the total memory of the device in bytes.
+     *
+     * @return the total memory of the device in bytes
+     */
+    public long getTotalMemory() {
+        return totalMemory;
+    }
+
+    /**
+     * Returns whether the Rust engine kernels support the compute capability of the device.
+     *
+     * @return whether the Rust engine kernels support the compute capability of the device
+     */
+    public boolean isSupported() {
+        return supported;
+    }
+
+    /** {@inheritDoc} */
+    @Override
+    public String toString() {
+        return "gpu(" + deviceId + "): " + name + ", compute capability: " + computeCapability;
+    }
+}
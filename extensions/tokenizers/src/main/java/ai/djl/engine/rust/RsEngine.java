@@ -19,6 +19,7 @@ import ai.djl.engine.EngineException;
 import ai.djl.engine.StandardCapabilities;
 import ai.djl.huggingface.tokenizers.jni.LibUtils;
 import ai.djl.ndarray.NDManager;
+import ai.djl.training.GradientCollector;
 
 /** The {@code RsEngine} is an implementation of the {@link Engine} rust engine. */
 public final class RsEngine extends Engine {
@@ -75,6 +76,31 @@ public final class RsEngine extends Engine {
         return false;
     }
 
+    /**
+     * {@inheritDoc}
+     *
+     * <p>Counts every CUDA device, so that {@code gpu(i)} is the device with the CUDA ordinal
+     * {@code i}. Loading a model on a device whose compute capability the CUDA kernels are not
+     * built for fails, see {@link GpuInfo#isSupported()}.
+     */
+    @Override
+    public int getGpuCount() {
+        if (!hasCapability(StandardCapabilities.CUDA)) {
+            return 0;
+        }
+        return RustLibrary.getGpuCount();
+    }
+
+    /**
+     * Returns the properties of a GPU device.
+     *
+     * @param deviceId the CUDA ordinal of the device
+     * @return the properties of the GPU device
+     */
+    public GpuInfo getDeviceInfo(int deviceId) {
+        return new GpuInfo(deviceId, RustLibrary.getDeviceInfo(deviceId));
+    }
+
     /** {@inheritDoc} */
     @Override
     public Model newModel(String name, Device device) {
@@ -93,6 +119,12 @@ public final class RsEngine extends Engine {
         return RsNDManager.getSystemManager().newSubManager(device);
     }
 
+    /** {@inheritDoc} */
+    @Override
+    public GradientCollector newGradientCollector() {
+        return new RsGradientCollector();
+    }
+
     /** {@inheritDoc} */
     @Override
     public String toString() {
@@ -0,0 +1,69 @@
+/*
+ * Copyright 2024 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License"). You may not use this file except in compliance
+ * with the License. A copy of the License is located at
+ *
+ * http://aws.amazon.com/apache2.0/
+ *
+ * or in the "license" file accompanying this file. This file is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES
+ * OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+package ai.djl.engine.rust;
+
+import ai.djl.ndarray.NDArray;
+import ai.djl.training.GradientCollector;
+
+import java.util.concurrent.atomic.AtomicBoolean;
+
+/** {@code RsGradientCollector} is the Rust implementation of {@link GradientCollector}. */
+public final class RsGradientCollector implements GradientCollector {
+
+    private static AtomicBoolean isCollecting = new AtomicBoolean();
+
+    private long handle;
+
+    /** Constructs a new {@code RsGradientCollector} instance. */
+    public RsGradientCollector() {
+        boolean wasCollecting = isCollecting.getAndSet(true);
+        if (wasCollecting) {
+            throw new IllegalStateException(
+                    "A RsGradientCollector is already collecting. Only one can be collecting at a"
+                            + " time");
+        }
+        handle = RustLibrary.createGradientCollector();
+    }
+
+    /**
+     * {@inheritDoc}
+     *
+     * <p>The gradients are accumulated into the arrays that require gradient until they are
+     * zeroed, either by {@link #zeroGradients()} or by an optimizer update.
+     */
+    @Override
+    public void backward(NDArray target) {
+        RustLibrary.backward(handle, ((RsNDArray) target).getHandle());
+    }
+
+    /**
+     * {@inheritDoc}
+     *
+     * <p>Only the gradients of the arrays reached by the backward passes of this collector are
+     * zeroed.
+     */
+    @Override
+    public void zeroGradients() {
+        RustLibrary.zeroGradients(handle);
+    }
+
+    /** {@inheritDoc} */
+    @Override
+    public void close() {
+        if (handle != 0) {
+            RustLibrary.deleteGradientCollector(handle);
+            handle = 0;
+        }
+        isCollecting.set(false);
+    }
+}
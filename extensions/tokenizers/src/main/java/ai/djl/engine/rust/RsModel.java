@@ -17,13 +17,16 @@ import ai.djl.Device;
 import ai.djl.MalformedModelException;
 import ai.djl.Model;
 import ai.djl.ndarray.types.DataType;
+import ai.djl.translate.ArgumentsUtil;
 
 import java.io.FileNotFoundException;
 import java.io.IOException;
 import java.nio.file.Files;
 import java.nio.file.Path;
+import java.util.Arrays;
 import java.util.Map;
 import java.util.concurrent.atomic.AtomicReference;
+import java.util.stream.Collectors;
 
 /** {@code RsModel} is the Rust implementation of {@link Model}. */
 public class RsModel extends BaseModel {
@@ -55,25 +58,73 @@ public class RsModel extends BaseModel {
         setModelDir(modelPath);
         if (block == null) {
             Device device = manager.getDevice();
+            // scores query/document pairs with a cross-encoder or a yes/no LLM reranker
+            boolean reranker = options != null && ArgumentsUtil.booleanValue(options, "reranker");
+            // "int8" quantizes the Linear weights of transformers models, GGUF files are
+            // loaded with their own quantization
+            String quantization =
+                    options == null ? null : ArgumentsUtil.stringValue(options, "quantization");
+            // comma separated PEFT LoRA adapter directories, merged into the model weights
+            String[] adapterPaths = new String[0];
+            String adapters =
+                    options == null ? null : ArgumentsUtil.stringValue(options, "adapters");
+            if (adapters != null && !adapters.isEmpty()) {
+                adapterPaths =
+                        Arrays.stream(adapters.split(","))
+                                .map(p -> modelDir.resolve(p.trim()).toAbsolutePath().toString())
+                                .toArray(String[]::new);
+            }
             handle.set(
                     RustLibrary.loadModel(
                             modelDir.toAbsolutePath().toString(),
                             dataType.ordinal(),
                             device.getDeviceType(),
-                            device.getDeviceId()));
-            block = new RsSymbolBlock((RsNDManager) manager, handle.get());
+                            device.getDeviceId(),
+                            reranker,
+                            quantization,
+                            adapterPaths));
+            RsSymbolBlock symbolBlock = new RsSymbolBlock((RsNDManager) manager, handle.get());
+            // comma separated outputs to compute, the model defaults if absent
+            String outputNames =
+                    options == null ? null : ArgumentsUtil.stringValue(options, "outputNames");
+            if (outputNames != null && !outputNames.isEmpty()) {
+                symbolBlock.setOutputNames(
+                        Arrays.stream(outputNames.split(","))
+                                .map(String::trim)
+                                .collect(Collectors.toList()));
+            }
+            block = symbolBlock;
         } else {
             loadBlock(prefix, options);
         }
     }
 
+    /**
+     * Returns the metadata of the loaded model as a JSON document.
+     *
+     * <p>The document contains the {@code model_type}, {@code architectures}, {@code
+     * hidden_size}, {@code max_position_embeddings}, {@code id2label} and {@code input_names} of
+     * the model, and the {@code dtype}, {@code device_type} and {@code device_id} it was loaded
+     * with.
+     *
+     * @return the model metadata as a JSON document
+     */
+    public String getModelInfo() {
+        Long pointer = handle.get();
+        if (pointer == null) {
+            throw new IllegalStateException("Model has not been loaded yet.");
+        }
+        return RustLibrary.getModelInfo(pointer);
+    }
+
     /** {@inheritDoc} */
     @Override
     public void close() {
+        // close the schedulers attached to the manager before releasing the model
+        super.close();
         Long pointer = handle.getAndSet(null);
         if (pointer != null) {
             RustLibrary.deleteModel(pointer);
         }
-        super.close();
     }
 }
@@ -167,54 +167,84 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
         if (dataType.equals(getDataType()) && !copy) {
             return this;
         }
-        if (dataType == DataType.BOOLEAN) {
-            long newHandle = RustLibrary.toBoolean(getHandle());
-            return toArray(newHandle, dataType, false, true);
-        }
-        if (this.dataType == DataType.INT64
-                && dataType == DataType.FLOAT16
-                && getDevice().isGpu()) {
-            // TODO:
-            throw new UnsupportedOperationException("FP16 to I64 is not supported on GPU.");
-        }
-        int dType = manager.toRustDataType(dataType);
-        long newHandle = RustLibrary.toDataType(getHandle(), dType);
+        int fromType = manager.toRustDataType(getDataType());
+        int toType = manager.toRustDataType(dataType);
+        long newHandle = RustLibrary.toDataType(getHandle(), fromType, toType);
         return toArray(newHandle, dataType, false, true);
     }
 
     /** {@inheritDoc} */
     @Override
     public void setRequiresGradient(boolean requiresGrad) {
-        throw new UnsupportedOperationException("Not implemented");
+        RustLibrary.setRequiresGradient(getHandle(), requiresGrad);
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray getGradient() {
-        throw new UnsupportedOperationException("Not implemented");
+        if (!hasGradient()) {
+            throw new IllegalStateException(
+                    "No gradient attached to this NDArray, please call array.setRequiresGradient()"
+                            + " on your NDArray or block.setInitializer() on your Block");
+        }
+        return new RsNDArray(manager, RustLibrary.getGradient(getHandle()));
     }
 
     /** {@inheritDoc} */
     @Override
     public boolean hasGradient() {
-        return false;
+        return RustLibrary.requiresGradient(getHandle());
     }
 
     /** {@inheritDoc} */
     @Override
     public NDArray stopGradient() {
-        throw new UnsupportedOperationException("Not implemented");
+        return new RsNDArray(manager, RustLibrary.stopGradient(getHandle()));
     }
 
-    /** {@inheritDoc} */
+    /**
+     * {@inheritDoc}
+     *
+     * <p>A direct buffer is read-only and shares the memory of a contiguous CPU tensor. It stays
+     * valid after this array is updated in place or closed, until the manager of this array is
+     * closed.
+     */
     @Override
     public ByteBuffer toByteBuffer(boolean tryDirect) {
-        byte[] buf = RustLibrary.toByteArray(getHandle());
+        int dType = manager.toRustDataType(getDataType());
+        if (tryDirect) {
+            // a detached handle owns the memory, the array may replace its own handle
+            RsNDArray owner =
+                    new RsNDArray(manager, RustLibrary.stopGradient(getHandle()), getDataType());
+            NDScope.unregister(owner);
+            ByteBuffer bb = RustLibrary.toDirectByteBuffer(owner.getHandle(), dType);
+            if (bb != null) {
+                return bb.asReadOnlyBuffer().order(ByteOrder.nativeOrder());
+            }
+            owner.close();
+        }
+        byte[] buf = RustLibrary.toByteArray(getHandle(), dType);
         ByteBuffer bb = ByteBuffer.wrap(buf);
         bb.order(ByteOrder.nativeOrder());
         return bb;
     }
 
+    /**
+     * Copies the content of this {@code NDArray} into a direct {@code ByteBuffer}.
+     *
+     * <p>The buffer must be direct and have a capacity of exactly {@code size() *
+     * getDataType().getNumOfBytes()} bytes, the values are written in native byte order.
+     *
+     * @param buffer the direct {@code ByteBuffer} to write to
+     */
+    public void copyTo(ByteBuffer buffer) {
+        if (!buffer.isDirect()) {
+            throw new IllegalArgumentException("Only direct ByteBuffer is supported.");
+        }
+        int dType = manager.toRustDataType(getDataType());
+        RustLibrary.copyToBuffer(getHandle(), dType, buffer);
+    }
+
     /** {@inheritDoc} */
     @Override
     public String[] toStringArray(Charset charset) {
@@ -263,7 +293,10 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     /** {@inheritDoc} */
     @Override
     public NDArray gatherNd(NDArray index) {
-        throw new UnsupportedOperationException("Not implemented");
+        try (NDScope ignore = new NDScope()) {
+            long indexHandle = manager.from(index).getHandle();
+            return toArray(RustLibrary.gatherNd(getHandle(), indexHandle), true);
+        }
     }
 
     /** {@inheritDoc} */
@@ -342,19 +375,25 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     /** {@inheritDoc} */
     @Override
     public RsNDArray booleanMask(NDArray index, int axis) {
-        throw new UnsupportedOperationException("Not implemented");
+        try (NDScope ignore = new NDScope()) {
+            long indexHandle = manager.from(index).getHandle();
+            return toArray(RustLibrary.booleanMask(getHandle(), indexHandle, axis), true);
+        }
     }
 
     /** {@inheritDoc} */
     @Override
     public NDArray sequenceMask(NDArray sequenceLength, float value) {
-        throw new UnsupportedOperationException("Not implemented yet");
+        try (NDScope ignore = new NDScope()) {
+            long lengthHandle = manager.from(sequenceLength).getHandle();
+            return toArray(RustLibrary.sequenceMask(getHandle(), lengthHandle, value), true);
+        }
     }
 
     /** {@inheritDoc} */
     @Override
     public NDArray sequenceMask(NDArray sequenceLength) {
-        throw new UnsupportedOperationException("Not implemented yet");
+        return sequenceMask(sequenceLength, 0);
     }
 
     /** {@inheritDoc} */
@@ -544,9 +583,7 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     /** {@inheritDoc} */
     @Override
     public RsNDArray mod(Number n) {
-        try (NDArray number = manager.create(n)) {
-            return mod(number);
-        }
+        return toArray(RustLibrary.remainderScalar(getHandle(), n.doubleValue()));
     }
 
     /** {@inheritDoc} */
@@ -561,9 +598,7 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     /** {@inheritDoc} */
     @Override
     public RsNDArray pow(Number n) {
-        try (NDArray number = manager.create(n)) {
-            return pow(number);
-        }
+        return toArray(RustLibrary.powScalar(getHandle(), n.doubleValue()));
     }
 
     /** {@inheritDoc} */
@@ -758,7 +793,7 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     @Override
     public NDArray countNonzero() {
         try (NDScope ignore = new NDScope()) {
-            return toArray(RustLibrary.countNonzero(getHandle()), true);
+            return toArray(RustLibrary.countNonzero(getHandle()), DataType.INT64, true, false);
         }
     }
 
@@ -766,7 +801,8 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     @Override
     public NDArray countNonzero(int axis) {
         try (NDScope ignore = new NDScope()) {
-            return toArray(RustLibrary.countNonzeroWithAxis(getHandle(), axis), true);
+            long newHandle = RustLibrary.countNonzeroWithAxis(getHandle(), axis);
+            return toArray(newHandle, DataType.INT64, true, false);
         }
     }
 
@@ -834,8 +870,8 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
 
     /** {@inheritDoc} */
     @Override
-    public NDArray gammaln() {
-        throw new UnsupportedOperationException("Not implemented yet.");
+    public RsNDArray gammaln() {
+        return toArray(RustLibrary.gammaln(getHandle()));
     }
 
     /** {@inheritDoc} */
@@ -922,19 +958,19 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     /** {@inheritDoc} */
     @Override
     public RsNDArray asinh() {
-        throw new UnsupportedOperationException("Not implemented");
+        return toArray(RustLibrary.asinh(getHandle()));
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray acosh() {
-        throw new UnsupportedOperationException("Not implemented");
+        return toArray(RustLibrary.acosh(getHandle()));
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray atanh() {
-        throw new UnsupportedOperationException("Not implemented");
+        return toArray(RustLibrary.atanh(getHandle()));
     }
 
     /** {@inheritDoc} */
@@ -1059,7 +1095,7 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     /** {@inheritDoc} */
     @Override
     public RsNDArray trace(int offset, int axis1, int axis2) {
-        throw new UnsupportedOperationException("Not implemented");
+        return toArray(RustLibrary.trace(getHandle(), offset, axis1, axis2));
     }
 
     /** {@inheritDoc} */
@@ -1092,56 +1128,83 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
 
     /** {@inheritDoc} */
     @Override
-    public NDArray fft(long length, long axis) {
-        throw new UnsupportedOperationException("Not implemented");
+    public RsNDArray fft(long length, long axis) {
+        long newHandle = RustLibrary.fft(getHandle(), length, axis, isComplex());
+        return toArray(newHandle, DataType.COMPLEX64, false, false);
     }
 
     /** {@inheritDoc} */
     @Override
-    public NDArray rfft(long length, long axis) {
-        throw new UnsupportedOperationException("Not implemented");
+    public RsNDArray rfft(long length, long axis) {
+        long newHandle = RustLibrary.rfft(getHandle(), length, axis, isComplex());
+        return toArray(newHandle, DataType.COMPLEX64, false, false);
     }
 
     /** {@inheritDoc} */
     @Override
-    public NDArray ifft(long length, long axis) {
-        throw new UnsupportedOperationException("Not implemented");
+    public RsNDArray ifft(long length, long axis) {
+        long newHandle = RustLibrary.ifft(getHandle(), length, axis, isComplex());
+        return toArray(newHandle, DataType.COMPLEX64, false, false);
     }
 
     /** {@inheritDoc} */
     @Override
-    public NDArray irfft(long length, long axis) {
-        throw new UnsupportedOperationException("Not implemented");
+    public RsNDArray irfft(long length, long axis) {
+        return toArray(RustLibrary.irfft(getHandle(), length, axis, isComplex()));
     }
 
     /** {@inheritDoc} */
     @Override
-    public NDArray stft(
+    public RsNDArray stft(
             long nFft,
             long hopLength,
             boolean center,
             NDArray window,
             boolean normalize,
             boolean returnComplex) {
-        throw new UnsupportedOperationException("Not implemented");
+        // complex numbers are always a trailing dimension of 2, regardless of returnComplex
+        try (NDScope ignore = new NDScope()) {
+            long windowHandle = window == null ? 0 : manager.from(window).getHandle();
+            long newHandle =
+                    RustLibrary.stft(getHandle(), nFft, hopLength, windowHandle, center, normalize);
+            return toArray(newHandle, DataType.COMPLEX64, true, false);
+        }
     }
 
     /** {@inheritDoc} */
     @Override
-    public NDArray fft2(long[] sizes, long[] axes) {
-        throw new UnsupportedOperationException("Not implemented");
+    public RsNDArray fft2(long[] sizes, long[] axes) {
+        long newHandle = RustLibrary.fft2(getHandle(), sizes, axes, isComplex());
+        return toArray(newHandle, DataType.COMPLEX64, false, false);
     }
 
     /** {@inheritDoc} */
     @Override
-    public NDArray pad(Shape padding, double value) {
-        throw new UnsupportedOperationException("Not implemented");
+    public RsNDArray pad(Shape padding, double value) {
+        return pad(padding, "constant", value);
+    }
+
+    /**
+     * Pads this {@code NDArray} with the given mode.
+     *
+     * <p>The padding is given as (left, right) pairs starting from the last axis, like {@link
+     * #pad(Shape, double)}. The supported modes are {@code constant}, {@code reflect} and {@code
+     * replicate}.
+     *
+     * @param padding the padding sizes, must be of even size
+     * @param mode the padding mode
+     * @param value the value to pad with in {@code constant} mode
+     * @return the padded {@code NDArray}
+     */
+    public RsNDArray pad(Shape padding, String mode, double value) {
+        return toArray(RustLibrary.pad(getHandle(), padding.getShape(), mode, value));
     }
 
     /** {@inheritDoc} */
     @Override
-    public NDArray ifft2(long[] sizes, long[] axes) {
-        throw new UnsupportedOperationException("Not implemented");
+    public RsNDArray ifft2(long[] sizes, long[] axes) {
+        long newHandle = RustLibrary.ifft2(getHandle(), sizes, axes, isComplex());
+        return toArray(newHandle, DataType.COMPLEX64, false, false);
     }
 
     /** {@inheritDoc} */
@@ -1185,7 +1248,9 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     /** {@inheritDoc} */
     @Override
     public NDList unique(Integer dim, boolean sorted, boolean returnInverse, boolean returnCounts) {
-        throw new UnsupportedOperationException("Not implemented");
+        // like the PyTorch engine, the inverse indices and counts are always returned
+        int axis = dim == null ? -1 : Math.floorMod(dim, getShape().dimension());
+        return toList(RustLibrary.unique(getHandle(), axis, sorted));
     }
 
     /** {@inheritDoc} */
@@ -1193,7 +1258,8 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     public RsNDArray logicalAnd(NDArray other) {
         try (NDScope ignore = new NDScope()) {
             long otherHandle = manager.from(other).getHandle();
-            return toArray(RustLibrary.logicalAnd(getHandle(), otherHandle), true);
+            long newHandle = RustLibrary.logicalAnd(getHandle(), otherHandle);
+            return toArray(newHandle, DataType.BOOLEAN, true, false);
         }
     }
 
@@ -1202,7 +1268,8 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     public RsNDArray logicalOr(NDArray other) {
         try (NDScope ignore = new NDScope()) {
             long otherHandle = manager.from(other).getHandle();
-            return toArray(RustLibrary.logicalOr(getHandle(), otherHandle), true);
+            long newHandle = RustLibrary.logicalOr(getHandle(), otherHandle);
+            return toArray(newHandle, DataType.BOOLEAN, true, false);
         }
     }
 
@@ -1211,20 +1278,22 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     public RsNDArray logicalXor(NDArray other) {
         try (NDScope ignore = new NDScope()) {
             long otherHandle = manager.from(other).getHandle();
-            return toArray(RustLibrary.logicalXor(getHandle(), otherHandle), true);
+            long newHandle = RustLibrary.logicalXor(getHandle(), otherHandle);
+            return toArray(newHandle, DataType.BOOLEAN, true, false);
         }
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray logicalNot() {
-        return toArray(RustLibrary.logicalNot(getHandle()));
+        return toArray(RustLibrary.logicalNot(getHandle()), DataType.BOOLEAN, false, false);
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray argSort(int axis, boolean ascending) {
-        return toArray(RustLibrary.argSort(getHandle(), axis, ascending));
+        long newHandle = RustLibrary.argSort(getHandle(), axis, ascending);
+        return toArray(newHandle, DataType.INT64, false, false);
     }
 
     /** {@inheritDoc} */
@@ -1236,7 +1305,7 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     /** {@inheritDoc} */
     @Override
     public RsNDArray sort(int axis) {
-        return toArray(RustLibrary.sort(getHandle(), axis, false));
+        return toArray(RustLibrary.sort(getHandle(), axis, true));
     }
 
     /** {@inheritDoc} */
@@ -1289,13 +1358,13 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
     /** {@inheritDoc} */
     @Override
     public RsNDArray isInfinite() {
-        return toArray(RustLibrary.isInf(getHandle()));
+        return toArray(RustLibrary.isInf(getHandle()), DataType.BOOLEAN, false, false);
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray isNaN() {
-        return toArray(RustLibrary.isNaN(getHandle()));
+        return toArray(RustLibrary.isNaN(getHandle()), DataType.BOOLEAN, false, false);
     }
 
     /** {@inheritDoc} */
@@ -1481,7 +1550,7 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
         if (isScalar()) {
             return (RsNDArray) manager.create(0L);
         }
-        return toArray(RustLibrary.argMax(getHandle()));
+        return toArray(RustLibrary.argMax(getHandle()), DataType.INT64, false, false);
     }
 
     /** {@inheritDoc} */
@@ -1490,7 +1559,8 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
         if (isScalar()) {
             return (RsNDArray) manager.create(0L);
         }
-        return toArray(RustLibrary.argMaxWithAxis(getHandle(), axis, false));
+        long newHandle = RustLibrary.argMaxWithAxis(getHandle(), axis, false);
+        return toArray(newHandle, DataType.INT64, false, false);
     }
 
     /** {@inheritDoc} */
@@ -1508,7 +1578,7 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
         if (isScalar()) {
             return (RsNDArray) manager.create(0L);
         }
-        return toArray(RustLibrary.argMin(getHandle()));
+        return toArray(RustLibrary.argMin(getHandle()), DataType.INT64, false, false);
     }
 
     /** {@inheritDoc} */
@@ -1517,7 +1587,8 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
         if (isScalar()) {
             return (RsNDArray) manager.create(0L);
         }
-        return toArray(RustLibrary.argMinWithAxis(getHandle(), axis, false));
+        long newHandle = RustLibrary.argMinWithAxis(getHandle(), axis, false);
+        return toArray(newHandle, DataType.INT64, false, false);
     }
 
     /** {@inheritDoc} */
@@ -1586,6 +1657,55 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
         return toArray(RustLibrary.inverse(getHandle()));
     }
 
+    /**
+     * Returns the determinant of the square matrices in the last two axes.
+     *
+     * @return the determinant {@code NDArray}
+     */
+    public RsNDArray det() {
+        return toArray(RustLibrary.det(getHandle()));
+    }
+
+    /**
+     * Solves the linear system {@code this * x = b} for the square matrices in the last two axes.
+     *
+     * @param b the right-hand side, a matrix or a vector
+     * @return the solution {@code x}
+     */
+    public RsNDArray solve(NDArray b) {
+        try (NDScope ignore = new NDScope()) {
+            return toArray(RustLibrary.solve(getHandle(), manager.from(b).getHandle()), true);
+        }
+    }
+
+    /**
+     * Returns the lower triangular Cholesky factor of the positive-definite matrices in the last
+     * two axes.
+     *
+     * @return the Cholesky factor {@code L}
+     */
+    public RsNDArray cholesky() {
+        return toArray(RustLibrary.cholesky(getHandle()));
+    }
+
+    /**
+     * Returns the reduced QR decomposition of the matrices in the last two axes.
+     *
+     * @return the {@code Q} and {@code R} factors
+     */
+    public NDList qr() {
+        return toList(RustLibrary.qr(getHandle()));
+    }
+
+    /**
+     * Returns the reduced singular value decomposition of the matrices in the last two axes.
+     *
+     * @return {@code U}, the singular values in descending order and {@code Vh}
+     */
+    public NDList svd() {
+        return toList(RustLibrary.svd(getHandle()));
+    }
+
     /** {@inheritDoc} */
     @Override
     public NDArray norm(boolean keepDims) {
@@ -1607,20 +1727,33 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
 
     /** {@inheritDoc} */
     @Override
-    public NDArray batchDot(NDArray other) {
-        throw new UnsupportedOperationException("Not implemented");
+    public RsNDArray batchDot(NDArray other) {
+        if (getShape().dimension() < 3 || other.getShape().dimension() < 3) {
+            throw new IllegalArgumentException("batchDot() requires at least 3D inputs");
+        }
+        try (NDScope ignore = new NDScope()) {
+            long otherHandle = manager.from(other).getHandle();
+            return toArray(RustLibrary.batchDot(getHandle(), otherHandle), true);
+        }
     }
 
     /** {@inheritDoc} */
     @Override
     public NDArray complex() {
-        return toArray(RustLibrary.complex(getHandle()));
+        // complex64 is emulated, complex numbers are a trailing dimension of 2
+        Shape shape = getShape();
+        if (shape.isScalar() || shape.tail() != 2) {
+            throw new IllegalArgumentException("The last dimension must be 2, got " + shape);
+        }
+        long newHandle = RustLibrary.duplicate(getHandle());
+        return toArray(newHandle, DataType.COMPLEX64, false, true);
     }
 
     /** {@inheritDoc} */
     @Override
     public NDArray real() {
-        return toArray(RustLibrary.real(getHandle()));
+        long newHandle = RustLibrary.duplicate(getHandle());
+        return toArray(newHandle, DataType.FLOAT32, false, true);
     }
 
     /** {@inheritDoc} */
@@ -1675,12 +1808,33 @@ public class RsNDArray extends NativeResource<Long> implements NDArray {
         dataRef = null;
     }
 
+    /** Whether this array holds emulated complex numbers, a trailing dimension of 2. */
+    private boolean isComplex() {
+        return getDataType() == DataType.COMPLEX64;
+    }
+
     private RsNDArray toArray(long newHandle) {
         return toArray(newHandle, false);
     }
 
     private RsNDArray toArray(long newHandle, boolean unregister) {
-        return toArray(newHandle, null, unregister, false);
+        return toArray(newHandle, resultType(newHandle), unregister, false);
+    }
+
+    private DataType resultType(long newHandle) {
+        // emulated integer types are held in a wider storage, a result still in that storage keeps
+        // the data type of this array, otherwise it is read from the storage
+        switch (getDataType()) {
+            case INT8:
+            case INT16:
+            case INT32:
+            case UINT16:
+            case UINT64:
+                int storage = RustLibrary.getDataType(newHandle);
+                return storage == RustLibrary.getDataType(getHandle()) ? dataType : null;
+            default:
+                return null;
+        }
     }
 
     private RsNDArray toArray(
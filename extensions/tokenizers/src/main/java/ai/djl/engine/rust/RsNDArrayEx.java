@@ -44,7 +44,8 @@ public class RsNDArrayEx implements NDArrayEx {
     /** {@inheritDoc} */
     @Override
     public RsNDArray rdiv(Number n) {
-        return rdiv(array.getManager().create(n));
+        return new RsNDArray(
+                array.getManager(), RustLibrary.rdivScalar(array.getHandle(), n.doubleValue()));
     }
 
     /** {@inheritDoc} */
@@ -56,73 +57,84 @@ public class RsNDArrayEx implements NDArrayEx {
     /** {@inheritDoc} */
     @Override
     public RsNDArray rdivi(Number n) {
-        throw new UnsupportedOperationException("Not implemented");
+        array.intern(rdiv(n));
+        return array;
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rdivi(NDArray b) {
-        throw new UnsupportedOperationException("Not implemented");
+        array.intern(rdiv(b));
+        return array;
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rsub(Number n) {
-        throw new UnsupportedOperationException("Not implemented");
+        return new RsNDArray(
+                array.getManager(), RustLibrary.rsubScalar(array.getHandle(), n.doubleValue()));
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rsub(NDArray b) {
-        throw new UnsupportedOperationException("Not implemented");
+        return (RsNDArray) b.sub(array);
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rsubi(Number n) {
-        throw new UnsupportedOperationException("Not implemented");
+        array.intern(rsub(n));
+        return array;
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rsubi(NDArray b) {
-        throw new UnsupportedOperationException("Not implemented");
+        array.intern(rsub(b));
+        return array;
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rmod(Number n) {
-        throw new UnsupportedOperationException("Not implemented");
+        return new RsNDArray(
+                array.getManager(),
+                RustLibrary.rremainderScalar(array.getHandle(), n.doubleValue()));
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rmod(NDArray b) {
-        throw new UnsupportedOperationException("Not implemented");
+        return (RsNDArray) b.mod(array);
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rmodi(Number n) {
-        throw new UnsupportedOperationException("Not implemented");
+        array.intern(rmod(n));
+        return array;
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rmodi(NDArray b) {
-        throw new UnsupportedOperationException("Not implemented");
+        array.intern(rmod(b));
+        return array;
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rpow(Number n) {
-        throw new UnsupportedOperationException("Not implemented");
+        return new RsNDArray(
+                array.getManager(), RustLibrary.rpowScalar(array.getHandle(), n.doubleValue()));
     }
 
     /** {@inheritDoc} */
     @Override
     public RsNDArray rpowi(Number n) {
-        throw new UnsupportedOperationException("Not implemented");
+        array.intern(rpow(n));
+        return array;
     }
 
     /** {@inheritDoc} */
@@ -299,7 +311,22 @@ public class RsNDArrayEx implements NDArrayEx {
             float epsilon,
             boolean lazyUpdate,
             boolean adamw) {
-        throw new UnsupportedOperationException("Not implemented");
+        RsNDManager manager = array.getManager();
+        RustLibrary.adamUpdate(
+                manager.from(inputs.get(0)).getHandle(),
+                manager.from(inputs.get(1)).getHandle(),
+                manager.from(inputs.get(2)).getHandle(),
+                manager.from(inputs.get(3)).getHandle(),
+                learningRate,
+                learningRateBiasCorrection,
+                weightDecay,
+                rescaleGrad,
+                clipGrad,
+                beta1,
+                beta2,
+                epsilon,
+                adamw);
+        RustLibrary.zeroGradient(manager.from(weights.singletonOrThrow()).getHandle());
     }
 
     /** {@inheritDoc} */
@@ -342,7 +369,17 @@ public class RsNDArrayEx implements NDArrayEx {
             float clipGrad,
             float momentum,
             boolean lazyUpdate) {
-        throw new UnsupportedOperationException("Not implemented");
+        RsNDManager manager = array.getManager();
+        RustLibrary.sgdUpdate(
+                manager.from(inputs.get(0)).getHandle(),
+                manager.from(inputs.get(1)).getHandle(),
+                (momentum == 0f) ? 0L : manager.from(inputs.get(2)).getHandle(),
+                learningRate,
+                weightDecay,
+                rescaleGrad,
+                clipGrad,
+                momentum);
+        RustLibrary.zeroGradient(manager.from(weights.singletonOrThrow()).getHandle());
     }
 
     /** {@inheritDoc} */
@@ -355,7 +392,18 @@ public class RsNDArrayEx implements NDArrayEx {
             Shape padding,
             Shape dilation,
             int groups) {
-        throw new UnsupportedOperationException("Not implemented");
+        RsNDManager manager = array.getManager();
+        return new NDList(
+                new RsNDArray(
+                        manager,
+                        RustLibrary.convolution(
+                                manager.from(input).getHandle(),
+                                manager.from(weight).getHandle(),
+                                getHandle(manager.from(bias)),
+                                stride.getShape(),
+                                padding.getShape(),
+                                dilation.getShape(),
+                                groups)));
     }
 
     /** {@inheritDoc} */
@@ -369,38 +417,105 @@ public class RsNDArrayEx implements NDArrayEx {
             Shape outPadding,
             Shape dilation,
             int groups) {
-        throw new UnsupportedOperationException("Not implemented");
+        RsNDManager manager = array.getManager();
+        return new NDList(
+                new RsNDArray(
+                        manager,
+                        RustLibrary.deconvolution(
+                                manager.from(input).getHandle(),
+                                manager.from(weight).getHandle(),
+                                getHandle(manager.from(bias)),
+                                stride.getShape(),
+                                padding.getShape(),
+                                outPadding.getShape(),
+                                dilation.getShape(),
+                                groups)));
     }
 
     /** {@inheritDoc} */
     @Override
     public NDList linear(NDArray input, NDArray weight, NDArray bias) {
-        throw new UnsupportedOperationException("Not implemented");
+        RsNDManager manager = array.getManager();
+        return new NDList(
+                new RsNDArray(
+                        manager,
+                        RustLibrary.linear(
+                                manager.from(input).getHandle(),
+                                manager.from(weight).getHandle(),
+                                getHandle(manager.from(bias)))));
     }
 
     /** {@inheritDoc} */
     @Override
     public NDList embedding(NDArray input, NDArray weight, SparseFormat sparseFormat) {
-        throw new UnsupportedOperationException("Not implemented");
+        if (!sparseFormat.equals(SparseFormat.DENSE)) {
+            throw new IllegalArgumentException("Rust engine only supports dense embedding");
+        }
+        RsNDManager manager = array.getManager();
+        return new NDList(
+                new RsNDArray(
+                        manager,
+                        RustLibrary.embedding(
+                                manager.from(input).getHandle(),
+                                manager.from(weight).getHandle())));
     }
 
     /** {@inheritDoc} */
     @Override
     public NDList prelu(NDArray input, NDArray alpha) {
-        throw new UnsupportedOperationException("Not implemented");
+        RsNDManager manager = array.getManager();
+        return new NDList(
+                new RsNDArray(
+                        manager,
+                        RustLibrary.prelu(
+                                manager.from(input).getHandle(),
+                                manager.from(alpha).getHandle())));
     }
 
     /** {@inheritDoc} */
     @Override
     public NDList dropout(NDArray input, float rate, boolean training) {
-        throw new UnsupportedOperationException("Not implemented");
+        RsNDManager manager = array.getManager();
+        if (!training || rate == 0f) {
+            return new NDList(manager.from(input).duplicate());
+        }
+        return new NDList(
+                new RsNDArray(manager, RustLibrary.dropout(manager.from(input).getHandle(), rate)));
     }
 
     /** {@inheritDoc} */
     @Override
     public NDList layerNorm(
             NDArray input, Shape normalizedShape, NDArray gamma, NDArray beta, float eps) {
-        throw new UnsupportedOperationException("Not implemented");
+        RsNDManager manager = array.getManager();
+        return new NDList(
+                new RsNDArray(
+                        manager,
+                        RustLibrary.layerNorm(
+                                manager.from(input).getHandle(),
+                                normalizedShape.getShape(),
+                                getHandle(manager.from(gamma)),
+                                getHandle(manager.from(beta)),
+                                eps)));
+    }
+
+    /**
+     * Applies RMS normalization over the last axis of the input.
+     *
+     * @param input the input {@code NDArray}
+     * @param gamma the scale, {@code null} for no scaling
+     * @param eps the value added to the mean square for numerical stability
+     * @return the normalized {@code NDArray}
+     */
+    public NDList rmsNorm(NDArray input, NDArray gamma, float eps) {
+        RsNDManager manager = array.getManager();
+        return new NDList(
+                new RsNDArray(
+                        manager,
+                        RustLibrary.rmsNorm(
+                                manager.from(input).getHandle(),
+                                getHandle(manager.from(gamma)),
+                                eps)));
     }
 
     /** {@inheritDoc} */
@@ -415,7 +530,22 @@ public class RsNDArrayEx implements NDArrayEx {
             float momentum,
             float eps,
             boolean training) {
-        throw new UnsupportedOperationException("Not implemented");
+        if (training) {
+            throw new UnsupportedOperationException(
+                    "Rust engine only supports batchNorm in inference mode");
+        }
+        RsNDManager manager = array.getManager();
+        return new NDList(
+                new RsNDArray(
+                        manager,
+                        RustLibrary.batchNorm(
+                                manager.from(input).getHandle(),
+                                manager.from(runningMean).getHandle(),
+                                manager.from(runningVar).getHandle(),
+                                getHandle(manager.from(gamma)),
+                                getHandle(manager.from(beta)),
+                                axis,
+                                eps)));
     }
 
     /** {@inheritDoc} */
@@ -618,6 +748,10 @@ public class RsNDArrayEx implements NDArrayEx {
         return array;
     }
 
+    private static long getHandle(RsNDArray array) {
+        return array == null ? 0L : array.getHandle();
+    }
+
     private Shape getPoolShape(NDArray array) {
         switch (array.getShape().dimension() - 2) {
             case 1:
@@ -20,7 +20,9 @@ import ai.djl.ndarray.index.full.NDIndexFullSlice;
 import ai.djl.ndarray.index.full.NDIndexFullTake;
 import ai.djl.ndarray.types.Shape;
 
+import java.util.ArrayList;
 import java.util.Arrays;
+import java.util.List;
 
 /** The {@link NDArrayIndexer} used by the {@link RsNDArray}. */
 @SuppressWarnings("try")
@@ -64,19 +66,16 @@ public class RsNDArrayIndexer extends NDArrayIndexer {
         long[] max = fullSlice.getMax();
         long[] step = fullSlice.getStep();
         long[] s = array.getShape().getShape().clone();
-        if (Arrays.stream(step).anyMatch(i -> i != 1)) {
-            throw new UnsupportedOperationException("only step 1 is supported");
-        }
         for (int i = 0; i < min.length; i++) {
             if (min[i] >= max[i] || min[i] >= s[i]) {
-                Shape shape = fullSlice.getSqueezedShape();
+                Shape shape = getSqueezedShape(fullSlice);
                 return manager.create(shape, array.getDataType(), array.getDevice());
             }
         }
         try (NDScope ignore = new NDScope()) {
             long handle = manager.from(array).getHandle();
             long tmp = RustLibrary.fullSlice(handle, min, max, step);
-            long newHandle = RustLibrary.reshape(tmp, fullSlice.getSqueezedShape().getShape());
+            long newHandle = RustLibrary.squeeze(tmp, fullSlice.getToSqueeze());
             RustLibrary.deleteTensor(tmp);
             RsNDArray ret = new RsNDArray(manager, newHandle, array.getDataType());
             NDScope.unregister(ret);
@@ -87,7 +86,14 @@ public class RsNDArrayIndexer extends NDArrayIndexer {
     /** {@inheritDoc} */
     @Override
     public void set(NDArray array, NDIndexFullSlice fullSlice, NDArray value) {
-        throw new UnsupportedOperationException("Not implemented");
+        try (NDScope ignore = new NDScope()) {
+            RustLibrary.setSlice(
+                    manager.from(array).getHandle(),
+                    fullSlice.getMin(),
+                    fullSlice.getMax(),
+                    fullSlice.getStep(),
+                    manager.from(value).getHandle());
+        }
     }
 
     /** {@inheritDoc} */
@@ -95,4 +101,23 @@ public class RsNDArrayIndexer extends NDArrayIndexer {
     public void set(NDArray array, NDIndexFullSlice fullSlice, Number value) {
         set(array, fullSlice, array.getManager().create(value));
     }
+
+    // A negative step walks [min, max) backward, or from min down to max when min > max, so the
+    // size of an axis does not depend on the sign of its step
+    private static Shape getSqueezedShape(NDIndexFullSlice fullSlice) {
+        long[] min = fullSlice.getMin();
+        long[] max = fullSlice.getMax();
+        long[] step = fullSlice.getStep();
+        int[] toSqueeze = fullSlice.getToSqueeze();
+        List<Long> shape = new ArrayList<>();
+        for (int i = 0; i < min.length; i++) {
+            if (Arrays.binarySearch(toSqueeze, i) < 0) {
+                long size = max[i] - min[i];
+                size = step[i] < 0 ? Math.abs(size) : Math.max(size, 0);
+                long absStep = Math.abs(step[i]);
+                shape.add((size + absStep - 1) / absStep);
+            }
+        }
+        return new Shape(shape);
+    }
 }
@@ -72,6 +72,7 @@ public class RsNDManager extends BaseNDManager {
         BaseNDManager.validateBuffer(data, dataType, size);
         ByteBuffer buf;
         if (data.isDirect() && data instanceof ByteBuffer) {
+            // the native code copies the buffer once into the tensor
             buf = (ByteBuffer) data;
         } else {
             buf = allocateDirect(size * dataType.getNumOfBytes());
@@ -237,19 +238,22 @@ public class RsNDManager extends BaseNDManager {
     }
 
     int toRustDataType(DataType dataType) {
+        // int8, int16, int32, uint16, uint64, boolean and complex64 are emulated on the Rust side
         switch (dataType) {
-            case BOOLEAN:
-            case INT8:
-                return DataType.UINT8.ordinal();
-            case INT32:
-                return DataType.UINT32.ordinal();
             case FLOAT16:
             case BFLOAT16:
             case FLOAT32:
             case FLOAT64:
+            case COMPLEX64:
             case UINT8:
+            case UINT16:
             case UINT32:
+            case UINT64:
+            case INT8:
+            case INT16:
+            case INT32:
             case INT64:
+            case BOOLEAN:
                 return dataType.ordinal();
             default:
                 throw new UnsupportedOperationException("Unsupported data type: " + dataType);
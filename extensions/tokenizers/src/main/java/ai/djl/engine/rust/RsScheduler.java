@@ -0,0 +1,135 @@
+/*
+ * Copyright 2024 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License"). You may not use this file except in compliance
+ * with the License. A copy of the License is located at
+ *
+ * http://aws.amazon.com/apache2.0/
+ *
+ * or in the "license" file accompanying this file. This file is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES
+ * OR CONDITIONS OF ANY KIND, either express or implied. See the License for the specific language governing permissions
+ * and limitations under the License.
+ */
+package ai.djl.engine.rust;
+
+import ai.djl.Model;
+import ai.djl.nn.Block;
+import ai.djl.util.NativeResource;
+
+/**
+ * {@code RsScheduler} batches concurrent text generation requests of a causal language model.
+ *
+ * <p>Requests can be submitted from any thread. The native scheduler merges the decoding steps of
+ * all running requests into shared forward passes, new requests join the batch as soon as their
+ * prompt is processed and finished requests leave it.
+ */
+public class RsScheduler extends NativeResource<Long> {
+
+    private RsNDManager manager;
+
+    /**
+     * Constructs a {@code RsScheduler} for the given model.
+     *
+     * @param model the causal language model loaded by the Rust engine
+     * @param maxBatchSize the maximum number of requests decoded together
+     */
+    @SuppressWarnings("this-escape")
+    public RsScheduler(Model model, int maxBatchSize) {
+        super(RustLibrary.createScheduler(getModelHandle(model), maxBatchSize));
+        manager = (RsNDManager) model.getNDManager();
+        manager.attachInternal(getUid(), this);
+    }
+
+    /**
+     * Submits a generation request.
+     *
+     * @param inputIds the token ids of the prompt
+     * @param maxNewTokens the maximum number of tokens to generate
+     * @param temperature the sampling temperature, greedy decoding is used if not positive
+     * @param topP the nucleus sampling probability, disabled if not in (0, 1)
+     * @param topK the number of most likely tokens to sample from, disabled if 0
+     * @param seed the random seed
+     * @param eosTokenIds the token ids that stop the generation
+     * @return the {@link Generation} that streams the generated tokens
+     */
+    public Generation submit(
+            long[] inputIds,
+            int maxNewTokens,
+            float temperature,
+            float topP,
+            int topK,
+            long seed,
+            long... eosTokenIds) {
+        long handle =
+                RustLibrary.submitGeneration(
+                        getHandle(),
+                        inputIds,
+                        maxNewTokens,
+                        temperature,
+                        topP,
+                        topK,
+                        seed,
+                        eosTokenIds);
+        return new Generation(handle);
+    }
+
+    /** {@inheritDoc} */
+    @Override
+    public void close() {
+        Long pointer = handle.getAndSet(null);
+        if (pointer != null) {
+            RustLibrary.deleteScheduler(pointer);
+            manager.detachInternal(getUid());
+            manager = null;
+        }
+    }
+
+    private static long getModelHandle(Model model) {
+        Block block = model.getBlock();
+        if (!(block instanceof RsSymbolBlock)) {
+            throw new IllegalArgumentException("The model is not loaded by the Rust engine");
+        }
+        return ((RsSymbolBlock) block).getHandle();
+    }
+
+    /** A running generation request. */
+    public static final class Generation extends NativeResource<Long> {
+
+        Generation(long handle) {
+            super(handle);
+        }
+
+        /**
+         * Returns the tokens generated since the last call, waiting up to the given timeout.
+         *
+         * @param timeoutMillis the maximum time to wait in milliseconds
+         * @return the new tokens, empty if the timeout expired or the generation is finished
+         */
+        public long[] poll(long timeoutMillis) {
+            return RustLibrary.pollGeneration(getHandle(), timeoutMillis);
+        }
+
+        /**
+         * Returns {@code true} if the generation is finished and all tokens have been polled.
+         *
+         * @return {@code true} if the generation is finished
+         */
+        public boolean isFinished() {
+            return RustLibrary.isGenerationFinished(getHandle());
+        }
+
+        /** Stops the generation at the next decoding step. */
+        public void cancel() {
+            RustLibrary.cancelGeneration(getHandle());
+        }
+
+        /** {@inheritDoc} */
+        @Override
+        public void close() {
+            Long pointer = handle.getAndSet(null);
+            if (pointer != null) {
+                RustLibrary.deleteGenerationRequest(pointer);
+            }
+        }
+    }
+}
@@ -12,6 +12,7 @@
  */
 package ai.djl.engine.rust;
 
+import ai.djl.ndarray.NDArray;
 import ai.djl.ndarray.NDList;
 import ai.djl.nn.AbstractSymbolBlock;
 import ai.djl.nn.ParameterList;
@@ -20,6 +21,7 @@ import ai.djl.training.ParameterStore;
 import ai.djl.util.PairList;
 
 import java.util.Arrays;
+import java.util.List;
 import java.util.concurrent.atomic.AtomicReference;
 
 /** {@code RsSymbolBlock} is the Rust implementation of {@link SymbolBlock}. */
@@ -28,6 +30,7 @@ public class RsSymbolBlock extends AbstractSymbolBlock implements AutoCloseable
     private AtomicReference<Long> handle;
     private String uid;
     private RsNDManager manager;
+    private String[] outputNames;
 
     /**
      * Constructs a {@code RsSymbolBlock}.
@@ -43,6 +46,7 @@ public class RsSymbolBlock extends AbstractSymbolBlock implements AutoCloseable
         this.handle = new AtomicReference<>(handle);
         this.manager = manager;
         inputNames = Arrays.asList(RustLibrary.getInputNames(handle));
+        outputNames = new String[0];
         uid = String.valueOf(handle);
         manager.attachInternal(uid, this);
     }
@@ -54,19 +58,59 @@ public class RsSymbolBlock extends AbstractSymbolBlock implements AutoCloseable
             NDList inputs,
             boolean training,
             PairList<String, Object> params) {
-        if (inputNames.size() != inputs.size()) {
+        // Inputs are bound by name if they are all named, by position otherwise. The model
+        // rejects unknown or repeated names and fills in omitted optional inputs.
+        boolean byName = inputs.stream().allMatch(input -> input.getName() != null);
+        if (!byName && inputs.size() > inputNames.size()) {
             throw new IllegalArgumentException("Input size mismatch, requires: " + inputNames);
         }
         try (RsNDManager sub = (RsNDManager) manager.newSubManager()) {
+            String[] names = new String[inputs.size()];
             long[] inputHandles = new long[inputs.size()];
             for (int i = 0; i < inputs.size(); i++) {
-                inputHandles[i] = sub.from(inputs.get(i)).getHandle();
+                NDArray input = inputs.get(i);
+                names[i] = byName ? input.getName() : inputNames.get(i);
+                inputHandles[i] = sub.from(input).getHandle();
+            }
+            long[] outputHandles =
+                    RustLibrary.runInference(handle.get(), names, inputHandles, outputNames);
+            NDList outputs = new NDList(outputHandles.length);
+            for (long outputHandle : outputHandles) {
+                RsNDArray output = new RsNDArray(manager, outputHandle);
+                output.attach(inputs.head().getManager());
+                outputs.add(output);
+            }
+            return outputs;
+        }
+    }
+
+    /**
+     * Returns the names of all the outputs the model can compute.
+     *
+     * @return the names of all the outputs the model can compute
+     */
+    public List<String> getAvailableOutputNames() {
+        return Arrays.asList(RustLibrary.getOutputNames(getHandle()));
+    }
+
+    /**
+     * Sets the outputs computed by {@link #forward}, in order.
+     *
+     * <p>Outputs that are not requested are not computed. An empty list selects the default outputs
+     * of the model.
+     *
+     * @param outputNames the names of the outputs to compute
+     * @throws IllegalArgumentException if the model cannot compute one of the outputs
+     */
+    public void setOutputNames(List<String> outputNames) {
+        List<String> available = getAvailableOutputNames();
+        for (String name : outputNames) {
+            if (!available.contains(name)) {
+                throw new IllegalArgumentException(
+                        "Unsupported output: " + name + ", available outputs: " + available);
             }
-            long outputHandle = RustLibrary.runInference(handle.get(), inputHandles);
-            RsNDArray output = new RsNDArray(manager, outputHandle);
-            output.attach(inputs.head().getManager());
-            return new NDList(output);
         }
+        this.outputNames = outputNames.toArray(new String[0]);
     }
 
     /** {@inheritDoc} */
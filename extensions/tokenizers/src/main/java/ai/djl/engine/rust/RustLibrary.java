@@ -22,14 +22,51 @@ public final class RustLibrary {
 
     public static native boolean isCudaAvailable();
 
+    public static native int getGpuCount();
+
+    public static native String[] getDeviceInfo(int deviceId);
+
     public static native long loadModel(
-            String modelPath, int dtype, String deviceType, int deviceId);
+            String modelPath,
+            int dtype,
+            String deviceType,
+            int deviceId,
+            boolean reranker,
+            String quantization,
+            String[] adapterPaths);
 
     public static native long deleteModel(long handle);
 
     public static native String[] getInputNames(long handle);
 
-    public static native long runInference(long handle, long[] inputHandles);
+    public static native String getModelInfo(long handle);
+
+    public static native String[] getOutputNames(long handle);
+
+    public static native long[] runInference(
+            long handle, String[] inputNames, long[] inputHandles, String[] outputNames);
+
+    public static native long createScheduler(long modelHandle, int maxBatchSize);
+
+    public static native void deleteScheduler(long handle);
+
+    public static native long submitGeneration(
+            long handle,
+            long[] inputIds,
+            int maxNewTokens,
+            float temperature,
+            float topP,
+            int topK,
+            long seed,
+            long[] eosTokenIds);
+
+    public static native long[] pollGeneration(long handle, long timeoutMillis);
+
+    public static native boolean isGenerationFinished(long handle);
+
+    public static native void cancelGeneration(long handle);
+
+    public static native void deleteGenerationRequest(long handle);
 
     public static native long tensorOf(
             ByteBuffer buf, long[] shape, int dataType, String deviceType, int deviceId);
@@ -67,9 +104,7 @@ public final class RustLibrary {
     public static native long randomNormal(
             float loc, float scale, long[] shape, int dataType, String deviceType, int deviceId);
 
-    public static long hannWindow(long numPoints, String deviceType, int deviceId) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long hannWindow(long numPoints, String deviceType, int deviceId);
 
     public static native void deleteTensor(long handle);
 
@@ -83,29 +118,50 @@ public final class RustLibrary {
 
     public static native long toDevice(long handle, String deviceType, int deviceId);
 
-    public static native long toBoolean(long handle);
+    public static native long toDataType(long handle, int fromType, int toType);
+
+    public static native void setRequiresGradient(long handle, boolean requiresGrad);
+
+    public static native boolean requiresGradient(long handle);
+
+    public static native long getGradient(long handle);
 
-    public static native long toDataType(long handle, int dataType);
+    public static native long stopGradient(long handle);
 
-    public static native byte[] toByteArray(long handle);
+    public static native long createGradientCollector();
+
+    public static native void deleteGradientCollector(long handle);
+
+    public static native void backward(long collectorHandle, long handle);
+
+    public static native void zeroGradient(long handle);
+
+    public static native void zeroGradients(long collectorHandle);
+
+    public static native byte[] toByteArray(long handle, int dataType);
+
+    public static native ByteBuffer toDirectByteBuffer(long handle, int dataType);
+
+    public static native void copyToBuffer(long handle, int dataType, ByteBuffer buffer);
 
     public static native long fullSlice(long handle, long[] min, long[] max, long[] step);
 
     public static native long gather(long handle, long indexHandle, int axis);
 
-    public static long take(long handle, long indexHandle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native void setSlice(
+            long handle, long[] min, long[] max, long[] step, long valueHandle);
 
-    public static long put(long handle, long indexHandle, long valueHandle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long gatherNd(long handle, long indexHandle);
+
+    public static native long take(long handle, long indexHandle);
+
+    public static native long put(long handle, long indexHandle, long valueHandle);
 
     public static native long scatter(long handle, long indexHandle, long valueHandle, int axis);
 
-    public static long booleanMask(long handle, long indexHandle, int axis) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long booleanMask(long handle, long indexHandle, int axis);
+
+    public static native long sequenceMask(long handle, long lengthHandle, float value);
 
     // comparison ops
 
@@ -137,15 +193,23 @@ public final class RustLibrary {
 
     public static native long maximum(long handle, long other);
 
-    public static long remainder(long handle, long other) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long remainder(long handle, long other);
 
     public static native long pow(long handle, long other);
 
-    public static long xlogy(long handle, long other) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long remainderScalar(long handle, double value);
+
+    public static native long powScalar(long handle, double value);
+
+    public static native long rsubScalar(long handle, double value);
+
+    public static native long rdivScalar(long handle, double value);
+
+    public static native long rremainderScalar(long handle, double value);
+
+    public static native long rpowScalar(long handle, double value);
+
+    public static native long xlogy(long handle, long other);
 
     // unary ops
 
@@ -153,55 +217,43 @@ public final class RustLibrary {
 
     public static native long log(long handle);
 
-    public static long log10(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long log10(long handle);
 
-    public static long log2(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long log2(long handle);
 
     public static native long sin(long handle);
 
     public static native long cos(long handle);
 
-    public static long tan(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long tan(long handle);
 
-    public static long asin(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long asin(long handle);
 
-    public static long acos(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long acos(long handle);
 
-    public static long atan(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long atan(long handle);
 
-    public static long atan2(long handle, long other) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long atan2(long handle, long other);
 
-    public static long sinh(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long sinh(long handle);
 
-    public static long cosh(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long cosh(long handle);
 
     public static native long tanh(long handle);
 
+    public static native long asinh(long handle);
+
+    public static native long acosh(long handle);
+
+    public static native long atanh(long handle);
+
+    public static native long gammaln(long handle);
+
     public static native long abs(long handle);
 
     public static native long neg(long handle);
 
-    public static long sign(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long sign(long handle);
 
     public static native long square(long handle);
 
@@ -213,9 +265,7 @@ public final class RustLibrary {
 
     public static native long round(long handle);
 
-    public static long trunc(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long trunc(long handle);
 
     public static native long countNonzero(long handle);
 
@@ -227,9 +277,8 @@ public final class RustLibrary {
 
     public static native long sumWithAxis(long handle, int[] axes, boolean keepDims);
 
-    public static long[] topK(long handle, int k, int axis, boolean largest, boolean sorted) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long[] topK(
+            long handle, int k, int axis, boolean largest, boolean sorted);
 
     public static native long max(long handle);
 
@@ -299,29 +348,19 @@ public final class RustLibrary {
 
     public static native long squeeze(long handle, int[] axes);
 
-    public static long logicalAnd(long handle, long other) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long logicalAnd(long handle, long other);
 
-    public static long logicalOr(long handle, long other) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long logicalOr(long handle, long other);
 
-    public static long logicalXor(long handle, long other) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long logicalXor(long handle, long other);
 
-    public static long logicalNot(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long logicalNot(long handle);
 
-    public static long argSort(long handle, int axis, boolean ascending) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long argSort(long handle, int axis, boolean ascending);
 
-    public static long sort(long handle, int axis, boolean ascending) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long sort(long handle, int axis, boolean ascending);
+
+    public static native long[] unique(long handle, int dim, boolean sorted);
 
     public static native long softmax(long handle, int axis);
 
@@ -329,13 +368,9 @@ public final class RustLibrary {
 
     public static native long cumSum(long handle, int axis);
 
-    public static long isInf(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long isInf(long handle);
 
-    public static long isNaN(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long isNaN(long handle);
 
     public static long tile(long handle, long[] repeats) {
         throw new UnsupportedOperationException("Not implemented");
@@ -361,6 +396,8 @@ public final class RustLibrary {
 
     public static native long batchMatMul(long handle, long other);
 
+    public static native long batchDot(long handle, long other);
+
     public static native long clip(long handle, double min, double max);
 
     public static native long transpose(long handle, int axis1, int axis2);
@@ -377,45 +414,63 @@ public final class RustLibrary {
         throw new UnsupportedOperationException("Not implemented");
     }
 
-    public static long inverse(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
-
-    public static long norm(long handle, int i, int[] ints, boolean keepDims) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long norm(long handle, int ord, int[] axes, boolean keepDims);
 
     public static long oneHot(long handle, int depth, float onValue, float offValue, int dataType) {
         throw new UnsupportedOperationException("Not implemented");
     }
 
-    public static long complex(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long pad(long handle, long[] padding, String mode, double value);
 
-    public static long real(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long trace(long handle, int offset, int axis1, int axis2);
+
+    // linalg ops
+
+    public static native long inverse(long handle);
+
+    public static native long det(long handle);
+
+    public static native long solve(long handle, long other);
+
+    public static native long cholesky(long handle);
+
+    public static native long[] qr(long handle);
+
+    public static native long[] svd(long handle);
+
+    // fft ops
+
+    public static native long fft(long handle, long length, long axis, boolean complex);
+
+    public static native long ifft(long handle, long length, long axis, boolean complex);
+
+    public static native long rfft(long handle, long length, long axis, boolean complex);
+
+    public static native long irfft(long handle, long length, long axis, boolean complex);
+
+    public static native long fft2(long handle, long[] sizes, long[] axes, boolean complex);
+
+    public static native long ifft2(long handle, long[] sizes, long[] axes, boolean complex);
+
+    public static native long stft(
+            long handle,
+            long nFft,
+            long hopLength,
+            long window,
+            boolean center,
+            boolean normalize);
 
     public static native long sigmoid(long handle);
 
-    public static long softPlus(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long softPlus(long handle);
 
-    public static long softSign(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long softSign(long handle);
 
     public static native long leakyRelu(long handle, float alpha);
 
-    public static long elu(long handle, float alpha) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long elu(long handle, float alpha);
 
-    public static long selu(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long selu(long handle);
 
     public static native long relu(long handle);
 
@@ -423,9 +478,7 @@ public final class RustLibrary {
 
     public static native long erf(long handle);
 
-    public static long erfinv(long handle) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long erfinv(long handle);
 
     public static long maxPool(
             long handle, long[] kernelShape, long[] stride, long[] padding, boolean ceilMode) {
@@ -447,6 +500,72 @@ public final class RustLibrary {
         throw new UnsupportedOperationException("Not implemented");
     }
 
+    public static native void sgdUpdate(
+            long weight,
+            long grad,
+            long state,
+            float learningRate,
+            float weightDecay,
+            float rescaleGrad,
+            float clipGrad,
+            float momentum);
+
+    public static native void adamUpdate(
+            long weight,
+            long grad,
+            long mean,
+            long variance,
+            float learningRate,
+            float learningRateBiasCorrection,
+            float weightDecay,
+            float rescaleGrad,
+            float clipGrad,
+            float beta1,
+            float beta2,
+            float eps,
+            boolean adamw);
+
+    public static native long convolution(
+            long input,
+            long weight,
+            long bias,
+            long[] stride,
+            long[] padding,
+            long[] dilation,
+            int groups);
+
+    public static native long deconvolution(
+            long input,
+            long weight,
+            long bias,
+            long[] stride,
+            long[] padding,
+            long[] outPadding,
+            long[] dilation,
+            int groups);
+
+    public static native long linear(long input, long weight, long bias);
+
+    public static native long embedding(long input, long weight);
+
+    public static native long prelu(long input, long alpha);
+
+    public static native long dropout(long handle, float rate);
+
+    public static native long layerNorm(
+            long input, long[] normalizedShape, long gamma, long beta, float eps);
+
+    public static native long batchNorm(
+            long input,
+            long runningMean,
+            long runningVar,
+            long gamma,
+            long beta,
+            int axis,
+            float eps);
+
+    public static native long rmsNorm(long input, long gamma, float eps);
+
     public static long where(long conditionHandle, long handle, long otherHandle) {
         throw new UnsupportedOperationException("Not implemented");
     }
@@ -455,7 +574,5 @@ public final class RustLibrary {
 
     public static native long concat(long[] srcArray, int axis);
 
-    public static long pick(long handle, long pickHandle, int axis) {
-        throw new UnsupportedOperationException("Not implemented");
-    }
+    public static native long pick(long handle, long pickHandle, int axis);
 }
@@ -13,18 +13,33 @@
 package ai.djl.engine.rust;
 
 import ai.djl.Device;
+import ai.djl.engine.Engine;
 import ai.djl.ndarray.NDArray;
 import ai.djl.ndarray.NDList;
 import ai.djl.ndarray.NDManager;
+import ai.djl.ndarray.index.NDIndex;
 import ai.djl.ndarray.types.DataType;
 import ai.djl.ndarray.types.Shape;
+import ai.djl.ndarray.types.SparseFormat;
 import ai.djl.nn.Activation;
+import ai.djl.nn.convolutional.Conv1d;
+import ai.djl.nn.convolutional.Conv2d;
+import ai.djl.nn.convolutional.Conv2dTranspose;
+import ai.djl.nn.core.Embedding;
+import ai.djl.nn.core.Linear;
+import ai.djl.nn.core.Prelu;
+import ai.djl.nn.norm.Dropout;
+import ai.djl.nn.norm.LayerNorm;
 import ai.djl.testing.Assertions;
+import ai.djl.training.GradientCollector;
+import ai.djl.training.optimizer.Optimizer;
+import ai.djl.training.tracker.Tracker;
 
 import org.testng.Assert;
 import org.testng.annotations.Test;
 
 import java.nio.ByteBuffer;
+import java.nio.FloatBuffer;
 
 public class NDArrayTests {
 
@@ -62,7 +77,10 @@ public class NDArrayTests {
             array = manager.create(true);
             Assert.assertEquals(array.getDataType(), DataType.BOOLEAN);
 
-            Assert.assertThrows(() -> manager.create(new Shape(1), DataType.INT16));
+            array = manager.create(new Shape(1), DataType.INT16);
+            Assert.assertEquals(array.getDataType(), DataType.INT16);
+
+            Assert.assertThrows(() -> manager.create(new Shape(1), DataType.STRING));
 
             array = manager.zeros(expected);
             Assert.assertEquals(array.getShape(), expected);
@@ -122,13 +140,106 @@ public class NDArrayTests {
             NDArray array = manager.create(2);
             Assert.assertEquals(array.getDataType(), DataType.INT32);
             NDArray int64 = array.toType(DataType.INT64, false);
-            try {
-                NDArray f16 = int64.toType(DataType.FLOAT16, false);
-                NDArray bool = f16.toType(DataType.BOOLEAN, false);
-                Assert.assertTrue(bool.getBoolean());
-            } catch (UnsupportedOperationException e) {
-                // ignore on GPU
-            }
+            NDArray f16 = int64.toType(DataType.FLOAT16, false);
+            NDArray bool = f16.toType(DataType.BOOLEAN, false);
+            Assert.assertTrue(bool.getBoolean());
+            Assert.assertEquals(f16.toType(DataType.INT64, false).getLong(), 2L);
+        }
+    }
+
+    @Test
+    public void testEmulatedDataType() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            int[] ints = {-1, 2, Integer.MAX_VALUE};
+            NDArray array = manager.create(ints);
+            Assert.assertEquals(array.getDataType(), DataType.INT32);
+            Assert.assertEquals(array.toIntArray(), ints);
+            float[] floats = {-1f, 2f, Integer.MAX_VALUE};
+            Assert.assertEquals(array.toType(DataType.FLOAT32, false).toFloatArray(), floats);
+
+            byte[] bytes = {-128, 5, 127};
+            array = manager.create(bytes);
+            Assert.assertEquals(array.getDataType(), DataType.INT8);
+            Assert.assertEquals(array.toByteArray(), bytes);
+            long[] longs = {-128, 5, 127};
+            Assert.assertEquals(array.toType(DataType.INT64, false).toLongArray(), longs);
+
+            // ops keep the emulated data type
+            array = manager.create(new int[] {1, Integer.MAX_VALUE});
+            NDArray sum = array.add(array);
+            Assert.assertEquals(sum.getDataType(), DataType.INT32);
+            Assert.assertEquals(sum.toIntArray(), new int[] {2, -2});
+            Assert.assertEquals(array.argMax().getDataType(), DataType.INT64);
+            Assert.assertEquals(array.gt(1).getDataType(), DataType.BOOLEAN);
+
+            // floats are truncated and integers wrap around
+            array = manager.create(new float[] {-1.7f, 2.9f, 300f});
+            ints = array.toType(DataType.INT32, false).toIntArray();
+            Assert.assertEquals(ints, new int[] {-1, 2, 300});
+            bytes = array.toType(DataType.INT8, false).toByteArray();
+            Assert.assertEquals(bytes, new byte[] {-1, 2, 44});
+            array = manager.create(new int[] {-1, 256});
+            ints = array.toType(DataType.UINT8, false).toUint8Array();
+            Assert.assertEquals(ints, new int[] {255, 0});
+
+            boolean[] booleans = {true, false, true};
+            array = manager.create(booleans);
+            Assert.assertEquals(array.toBooleanArray(), booleans);
+            ints = array.toType(DataType.INT32, false).toIntArray();
+            Assert.assertEquals(ints, new int[] {1, 0, 1});
+            array = manager.create(new float[] {0f, -2.5f});
+            booleans = array.toType(DataType.BOOLEAN, false).toBooleanArray();
+            Assert.assertEquals(booleans, new boolean[] {false, true});
+
+            array = manager.create(new float[] {1f, 2f});
+            NDArray complex = array.toType(DataType.COMPLEX64, false);
+            Assert.assertEquals(complex.getShape(), new Shape(2, 2));
+            Assert.assertEquals(complex.real().toFloatArray(), new float[] {1f, 0f, 2f, 0f});
+            Assert.assertEquals(complex.toType(DataType.FLOAT32, false), array);
+        }
+    }
+
+    @Test
+    public void testDirectBuffer() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            float[] floats = {1f, 2f, 3f, 4f};
+            NDArray array = manager.create(floats, new Shape(2, 2));
+            Assert.assertEquals(array.toFloatArray(), floats);
+
+            // the direct buffer shares the memory of the tensor, read only
+            ByteBuffer bb = array.toByteBuffer(true);
+            Assert.assertTrue(bb.isDirect());
+            Assert.assertTrue(bb.isReadOnly());
+            Assert.assertThrows(() -> bb.putFloat(0, 5f));
+            Assert.assertEquals(bb.getFloat(4), 2f);
+            Assert.assertFalse(array.toByteBuffer().isDirect());
+
+            // it stays valid after the array is closed
+            NDArray other = manager.create(floats);
+            ByteBuffer otherBuffer = other.toByteBuffer(true);
+            other.close();
+            Assert.assertEquals(otherBuffer.getFloat(12), 4f);
+
+            // heap buffers are copied into a new tensor
+            NDArray copied = manager.create(FloatBuffer.wrap(floats), new Shape(4));
+            floats[0] = 5f;
+            Assert.assertEquals(copied.getFloat(0), 1f);
+
+            // transposed views and emulated data types are copied
+            Assert.assertFalse(array.transpose().toByteBuffer(true).isDirect());
+            int[] ints = {-1, 2, 3};
+            NDArray intArray = manager.create(ints);
+            Assert.assertFalse(intArray.toByteBuffer(true).isDirect());
+
+            ByteBuffer buf = manager.allocateDirect(12);
+            ((RsNDArray) intArray).copyTo(buf);
+            int[] actual = new int[3];
+            buf.asIntBuffer().get(actual);
+            Assert.assertEquals(actual, ints);
+            buf = manager.allocateDirect(8);
+            ((RsNDArray) array.transpose().get(0)).copyTo(buf);
+            Assert.assertEquals(buf.getFloat(4), 3f);
+            Assert.assertThrows(() -> ((RsNDArray) array).copyTo(ByteBuffer.allocate(16)));
         }
     }
 
@@ -178,6 +289,75 @@ public class NDArrayTests {
         }
     }
 
+    @Test
+    public void testElementwiseOp() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.create(new float[] {0f, 1f});
+            Assertions.assertAlmostEquals(array.asinh(), manager.create(new float[] {0f, 0.8814f}));
+            array = manager.create(new float[] {1f, 2f});
+            Assertions.assertAlmostEquals(array.acosh(), manager.create(new float[] {0f, 1.317f}));
+            array = manager.create(new float[] {0f, 0.5f});
+            Assertions.assertAlmostEquals(array.atanh(), manager.create(new float[] {0f, 0.5493f}));
+            NDArray expected = manager.create(new float[] {0f, 0.4769f});
+            Assertions.assertAlmostEquals(array.erfinv(), expected);
+
+            array = manager.create(new float[] {0.5f, 1f, 1.5f});
+            expected = manager.create(new float[] {0.5724f, 0f, -0.1208f});
+            Assertions.assertAlmostEquals(array.gammaln(), expected);
+
+            array = manager.create(new float[] {-3f, 0f, 2f});
+            Assert.assertEquals(array.sign(), manager.create(new float[] {-1f, 0f, 1f}));
+            array = manager.create(new float[] {-1.5f, 1.5f});
+            Assert.assertEquals(array.trunc(), manager.create(new float[] {-1f, 1f}));
+
+            array = manager.create(new float[] {-7f, 7f, -7f, 7f});
+            NDArray other = manager.create(new float[] {3f, 3f, -3f, -3f});
+            expected = manager.create(new float[] {2f, 1f, -1f, -2f});
+            Assert.assertEquals(array.mod(other), expected);
+            expected = manager.create(new float[] {0f, 1f, 2f, 0f, 1f, 2f});
+            Assert.assertEquals(manager.arange(6f).mod(3), expected);
+            array = manager.create(new float[] {-2f, 3f});
+            Assert.assertEquals(array.pow(2), manager.create(new float[] {4f, 9f}));
+
+            array = manager.create(new float[] {1f, 1f});
+            other = manager.create(new float[] {1f, -1f});
+            expected = manager.create(new float[] {0.7854f, 2.3562f});
+            Assertions.assertAlmostEquals(array.atan2(other), expected);
+            array = manager.create(new float[] {0f, 2f});
+            other = manager.create(new float[] {0f, (float) Math.E});
+            Assertions.assertAlmostEquals(array.xlogy(other), manager.create(new float[] {0f, 2f}));
+
+            array = manager.create(new float[] {1f, 2f, 3f, 4f});
+            RsNDArrayEx ex = ((RsNDArray) array).getNDArrayInternal();
+            Assert.assertEquals(ex.rpow(2), manager.create(new float[] {2f, 4f, 8f, 16f}));
+            Assert.assertEquals(ex.rsub(10), manager.create(new float[] {9f, 8f, 7f, 6f}));
+            Assert.assertEquals(ex.rdiv(12), manager.create(new float[] {12f, 6f, 4f, 3f}));
+            Assert.assertEquals(ex.rmod(7), manager.create(new float[] {0f, 1f, 1f, 3f}));
+        }
+    }
+
+    @Test
+    public void testLogicalOp() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.create(new boolean[] {true, false, true});
+            NDArray other = manager.create(new boolean[] {true, true, false});
+            NDArray expected = manager.create(new boolean[] {true, false, false});
+            Assert.assertEquals(array.logicalAnd(other), expected);
+            expected = manager.create(new boolean[] {true, true, true});
+            Assert.assertEquals(array.logicalOr(other), expected);
+            expected = manager.create(new boolean[] {false, true, true});
+            Assert.assertEquals(array.logicalXor(other), expected);
+            expected = manager.create(new boolean[] {false, true, false});
+            Assert.assertEquals(array.logicalNot(), expected);
+
+            array = manager.create(new float[] {1f, Float.NaN, Float.POSITIVE_INFINITY});
+            expected = manager.create(new boolean[] {false, true, false});
+            Assert.assertEquals(array.isNaN(), expected);
+            expected = manager.create(new boolean[] {false, false, true});
+            Assert.assertEquals(array.isInfinite(), expected);
+        }
+    }
+
     @Test
     public void testShapesOp() {
         try (NDManager manager = NDManager.newBaseManager("Rust")) {
@@ -282,6 +462,445 @@ public class NDArrayTests {
         }
     }
 
+    @Test
+    public void testConvolution() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray input = manager.arange(1f, 5f).reshape(1, 1, 4);
+            NDArray weight = manager.ones(new Shape(1, 1, 2));
+            NDArray bias = manager.create(new float[] {1f});
+            NDArray expected = manager.create(new float[] {4f, 6f, 8f}, new Shape(1, 1, 3));
+            Assert.assertEquals(Conv1d.conv1d(input, weight, bias).singletonOrThrow(), expected);
+
+            input = manager.arange(9f).reshape(1, 1, 3, 3);
+            weight = manager.ones(new Shape(1, 1, 2, 2));
+            expected = manager.create(new float[] {8f, 12f, 20f, 24f}, new Shape(1, 1, 2, 2));
+            Assert.assertEquals(Conv2d.conv2d(input, weight).singletonOrThrow(), expected);
+
+            input = manager.ones(new Shape(1, 1, 2, 2));
+            float[] data = {1f, 2f, 1f, 2f, 4f, 2f, 1f, 2f, 1f};
+            expected = manager.create(data, new Shape(1, 1, 3, 3));
+            Assert.assertEquals(
+                    Conv2dTranspose.conv2dTranspose(input, weight).singletonOrThrow(), expected);
+        }
+    }
+
+    @Test
+    public void testLinear() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray input = manager.create(new float[][] {{1f, 2f, 3f}});
+            NDArray weight = manager.create(new float[][] {{1f, 0f, 1f}, {0f, 1f, 0f}});
+            NDArray bias = manager.create(new float[] {1f, -1f});
+            NDArray expected = manager.create(new float[][] {{5f, 1f}});
+            Assert.assertEquals(Linear.linear(input, weight, bias).singletonOrThrow(), expected);
+        }
+    }
+
+    @Test
+    public void testEmbedding() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray input = manager.create(new long[] {2, 0});
+            NDArray weight = manager.arange(6f).reshape(3, 2);
+            NDArray expected = manager.create(new float[][] {{4f, 5f}, {0f, 1f}});
+            NDArray ret =
+                    Embedding.embedding(input, weight, SparseFormat.DENSE).singletonOrThrow();
+            Assert.assertEquals(ret, expected);
+        }
+    }
+
+    @Test
+    public void testPrelu() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray input = manager.create(new float[] {-2f, -1f, 0f, 1f});
+            NDArray alpha = manager.create(0.25f);
+            NDArray expected = manager.create(new float[] {-0.5f, -0.25f, 0f, 1f});
+            Assert.assertEquals(Prelu.prelu(input, alpha).singletonOrThrow(), expected);
+        }
+    }
+
+    @Test
+    public void testNorm() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray input = manager.create(new float[][] {{1f, 2f, 3f, 4f}});
+            NDArray gamma = manager.ones(new Shape(4));
+            NDArray beta = manager.zeros(new Shape(4));
+            float[] data = {-1.3416354f, -0.4472118f, 0.4472118f, 1.3416354f};
+            NDArray expected = manager.create(data, new Shape(1, 4));
+            NDArray ret =
+                    LayerNorm.layerNorm(input, new Shape(4), gamma, beta, 1e-5f).singletonOrThrow();
+            Assertions.assertAlmostEquals(ret, expected);
+
+            RsNDArrayEx ex = ((RsNDArray) input).getNDArrayInternal();
+            data = new float[] {0.3651483f, 0.7302967f, 1.0954450f, 1.4605934f};
+            expected = manager.create(data, new Shape(1, 4));
+            ret = ex.rmsNorm(input, null, 1e-6f).singletonOrThrow();
+            Assertions.assertAlmostEquals(ret, expected);
+
+            input = manager.create(new float[] {1f, 2f, 3f, 4f}, new Shape(1, 2, 2));
+            NDArray mean = manager.create(new float[] {1f, 3f});
+            NDArray var = manager.create(new float[] {4f, 1f});
+            gamma = manager.create(new float[] {1f, 2f});
+            beta = manager.create(new float[] {0f, 1f});
+            expected = manager.create(new float[] {0f, 0.5f, 1f, 3f}, new Shape(1, 2, 2));
+            ret = ex.batchNorm(input, mean, var, gamma, beta, 1, 0.9f, 0f, false).get(0);
+            Assertions.assertAlmostEquals(ret, expected);
+        }
+    }
+
+    @Test
+    public void testDropout() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray input = manager.ones(new Shape(100));
+            Assert.assertEquals(Dropout.dropout(input, 0.5f, false).singletonOrThrow(), input);
+
+            NDArray ret = Dropout.dropout(input, 0.5f, true).singletonOrThrow();
+            Assert.assertTrue(ret.eq(0f).logicalOr(ret.eq(2f)).all().getBoolean());
+        }
+    }
+
+    @Test
+    public void testAutograd() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray x = manager.create(new float[] {1f, 2f, 3f});
+            Assert.assertFalse(x.hasGradient());
+            x.setRequiresGradient(true);
+            Assert.assertTrue(x.hasGradient());
+            Assert.assertEquals(x.getGradient(), manager.zeros(new Shape(3)));
+
+            Engine engine = Engine.getEngine("Rust");
+            try (GradientCollector collector = engine.newGradientCollector()) {
+                collector.backward(x.mul(x).sum());
+            }
+            Assert.assertEquals(x.getGradient(), manager.create(new float[] {2f, 4f, 6f}));
+
+            // gradients accumulate until they are zeroed
+            try (GradientCollector collector = engine.newGradientCollector()) {
+                collector.backward(x.sum());
+                Assert.assertEquals(x.getGradient(), manager.create(new float[] {3f, 5f, 7f}));
+                collector.zeroGradients();
+            }
+            Assert.assertEquals(x.getGradient(), manager.zeros(new Shape(3)));
+
+            // a collector only zeroes the gradients its backward passes reached
+            NDArray y = manager.create(new float[] {1f, 2f});
+            y.setRequiresGradient(true);
+            try (GradientCollector collector = engine.newGradientCollector()) {
+                collector.backward(x.sum());
+            }
+            try (GradientCollector collector = engine.newGradientCollector()) {
+                collector.backward(y.sum());
+                collector.zeroGradients();
+            }
+            Assert.assertEquals(x.getGradient(), manager.ones(new Shape(3)));
+
+            NDArray detached = x.stopGradient();
+            Assert.assertFalse(detached.hasGradient());
+            Assert.assertEquals(detached, x);
+        }
+    }
+
+    @Test
+    public void testOptimizer() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray weight = manager.create(new float[] {1f, 2f});
+            weight.setRequiresGradient(true);
+            NDArray grad = manager.create(new float[] {0.5f, 1f});
+            Optimizer sgd = Optimizer.sgd().setLearningRateTracker(Tracker.fixed(0.1f)).build();
+            sgd.update("weight", weight, grad);
+            Assertions.assertAlmostEquals(weight, manager.create(new float[] {0.95f, 1.9f}));
+            // the update keeps the parameter a variable
+            Assert.assertTrue(weight.hasGradient());
+
+            weight = manager.create(new float[] {1f, 2f});
+            weight.setRequiresGradient(true);
+            Optimizer adam = Optimizer.adam().optLearningRateTracker(Tracker.fixed(0.1f)).build();
+            adam.update("weight", weight, grad);
+            Assertions.assertAlmostEquals(weight, manager.create(new float[] {0.9f, 1.9f}));
+        }
+    }
+
+    @Test
+    public void testStridedSlice() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.arange(6f);
+            Assert.assertEquals(array.get("::2"), manager.create(new float[] {0f, 2f, 4f}));
+            NDArray expected = manager.create(new float[] {5f, 4f, 3f, 2f, 1f, 0f});
+            Assert.assertEquals(array.get("::-1"), expected);
+            Assert.assertEquals(array.get("1:5:-2"), manager.create(new float[] {4f, 2f}));
+
+            array = array.reshape(2, 3);
+            expected = manager.create(new float[][] {{2f, 1f, 0f}, {5f, 4f, 3f}});
+            Assert.assertEquals(array.get(":, ::-1"), expected);
+            Assert.assertEquals(array.get("1, ::2"), manager.create(new float[] {3f, 5f}));
+        }
+    }
+
+    @Test
+    public void testSetSlice() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.arange(6f).reshape(2, 3);
+            array.set(new NDIndex("1"), manager.create(new float[] {7f, 8f, 9f}));
+            NDArray expected = manager.create(new float[][] {{0f, 1f, 2f}, {7f, 8f, 9f}});
+            Assert.assertEquals(array, expected);
+
+            array.set(new NDIndex(":, ::2"), 0f);
+            expected = manager.create(new float[][] {{0f, 1f, 0f}, {0f, 8f, 0f}});
+            Assert.assertEquals(array, expected);
+
+            // the value is broadcast to the slice
+            array.set(new NDIndex(":, 1:"), manager.create(new float[] {4f, 5f}));
+            expected = manager.create(new float[][] {{0f, 4f, 5f}, {0f, 4f, 5f}});
+            Assert.assertEquals(array, expected);
+
+            array = manager.arange(4f);
+            NDArray index = manager.create(new long[] {0, 3});
+            expected = manager.create(new float[] {9f, 1f, 2f, 9f});
+            Assert.assertEquals(array.put(index, manager.create(new float[] {9f, 9f})), expected);
+            Assert.assertEquals(array.take(index), manager.create(new float[] {0f, 3f}));
+            // the last value of a repeated index is written
+            index = manager.create(new long[] {1, 1});
+            expected = manager.create(new float[] {0f, 6f, 2f, 3f});
+            Assert.assertEquals(array.put(index, manager.create(new float[] {5f, 6f})), expected);
+        }
+    }
+
+    @Test
+    public void testGatherNd() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.arange(6f).reshape(2, 3);
+            NDArray index = manager.create(new long[][] {{1, 0}, {2, 1}});
+            Assert.assertEquals(array.gatherNd(index), manager.create(new float[] {5f, 1f}));
+
+            index = manager.create(new long[][] {{1, 0}});
+            NDArray expected = manager.create(new float[][] {{3f, 4f, 5f}, {0f, 1f, 2f}});
+            Assert.assertEquals(array.gatherNd(index), expected);
+        }
+    }
+
+    @Test
+    public void testBooleanMask() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.arange(6f).reshape(2, 3);
+            NDArray mask = manager.create(new boolean[] {true, false, true});
+            NDArray expected = manager.create(new float[][] {{0f, 2f}, {3f, 5f}});
+            Assert.assertEquals(array.booleanMask(mask, 1), expected);
+
+            expected = manager.create(new float[] {3f, 4f, 5f});
+            Assert.assertEquals(array.booleanMask(array.gt(2f)), expected);
+        }
+    }
+
+    @Test
+    public void testSequenceMask() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.ones(new Shape(2, 3));
+            NDArray lengths = manager.create(new long[] {1, 3});
+            NDArray expected = manager.create(new float[][] {{1f, 0f, 0f}, {1f, 1f, 1f}});
+            Assert.assertEquals(array.sequenceMask(lengths), expected);
+
+            expected = manager.create(new float[][] {{1f, -1f, -1f}, {1f, 1f, 1f}});
+            Assert.assertEquals(array.sequenceMask(lengths, -1f), expected);
+        }
+    }
+
+    @Test
+    public void testSort() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.create(new float[] {3f, 1f, 2f, 1f});
+            Assert.assertEquals(array.sort(), manager.create(new float[] {1f, 1f, 2f, 3f}));
+            Assert.assertEquals(array.argSort(), manager.create(new long[] {1, 3, 2, 0}));
+            NDArray expected = manager.create(new long[] {0, 2, 1, 3});
+            Assert.assertEquals(array.argSort(-1, false), expected);
+
+            array = manager.create(new float[][] {{3f, 1f, 2f}, {0f, 5f, 4f}});
+            expected = manager.create(new float[][] {{0f, 1f, 2f}, {3f, 5f, 4f}});
+            Assert.assertEquals(array.sort(0), expected);
+        }
+    }
+
+    @Test
+    public void testTopK() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.create(new float[][] {{3f, 1f, 2f}, {0f, 5f, 4f}});
+            NDList result = array.topK(2, -1, true, true);
+            NDArray expected = manager.create(new float[][] {{3f, 2f}, {5f, 4f}});
+            Assert.assertEquals(result.get(0), expected);
+            Assert.assertEquals(result.get(1), manager.create(new long[][] {{0, 2}, {1, 2}}));
+
+            result = array.topK(1, 0, false, true);
+            Assert.assertEquals(result.get(0), manager.create(new float[][] {{0f, 1f, 2f}}));
+            Assert.assertEquals(result.get(1), manager.create(new long[][] {{1, 0, 0}}));
+        }
+    }
+
+    @Test
+    public void testUnique() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            float[] data = {3f, 1f, 2f, 3f, 1f, 2f, 1f, 3f, 2f};
+            NDArray array = manager.create(data, new Shape(3, 3));
+            NDList result = array.unique(0, true, true, true);
+            NDArray expected = manager.create(new float[][] {{1f, 3f, 2f}, {3f, 1f, 2f}});
+            Assert.assertEquals(result.get(0), expected);
+            Assert.assertEquals(result.get(1), manager.create(new long[] {1, 1, 0}));
+            Assert.assertEquals(result.get(2), manager.create(new long[] {1, 2}));
+
+            array = manager.create(new float[] {2f, 1f, 2f, 3f});
+            result = array.unique(true, true, true);
+            Assert.assertEquals(result.get(0), manager.create(new float[] {1f, 2f, 3f}));
+            Assert.assertEquals(result.get(1), manager.create(new long[] {1, 0, 1, 2}));
+            Assert.assertEquals(result.get(2), manager.create(new long[] {1, 2, 1}));
+
+            result = array.unique(false, true, true);
+            Assert.assertEquals(result.get(0), manager.create(new float[] {2f, 1f, 3f}));
+            Assert.assertEquals(result.get(1), manager.create(new long[] {0, 1, 0, 2}));
+            Assert.assertEquals(result.get(2), manager.create(new long[] {2, 1, 1}));
+        }
+    }
+
+    @Test
+    public void testFft() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.create(new float[] {1f, 2f, 3f, 4f, 5f});
+            float[] data = {
+                15f, 0f, -2.5f, 3.440955f, -2.5f, 0.8122992f, -2.5f, -0.8122992f, -2.5f, -3.440955f
+            };
+            NDArray expected = manager.create(data, new Shape(5, 2));
+            NDArray fft = array.fft(5);
+            Assert.assertEquals(fft.getDataType(), DataType.COMPLEX64);
+            Assertions.assertAlmostEquals(fft.real(), expected);
+            Assertions.assertAlmostEquals(fft.ifft(5).real().get(":, 0"), array);
+            // a forward transform of complex numbers, the axis excludes the trailing dimension
+            Assertions.assertAlmostEquals(fft.ifft(5, 0).fft(5, 0).real(), expected);
+            Assertions.assertAlmostEquals(expected.complex().ifft(5).fft(5).real(), expected);
+
+            NDArray rfft = array.rfft(5);
+            Assertions.assertAlmostEquals(rfft.real(), expected.get(":3"));
+            Assertions.assertAlmostEquals(rfft.irfft(5), array);
+
+            array = manager.create(new float[][] {{1f, 2f}, {3f, 4f}});
+            long[] sizes = {2, 2};
+            NDArray fft2 = array.fft2(sizes);
+            data = new float[] {10f, 0f, -2f, 0f, -4f, 0f, 0f, 0f};
+            Assertions.assertAlmostEquals(fft2.real(), manager.create(data, new Shape(2, 2, 2)));
+            Assertions.assertAlmostEquals(fft2.ifft2(sizes).real().get(":, :, 0"), array);
+            Assertions.assertAlmostEquals(fft2.ifft2(sizes).fft2(sizes).real(), fft2.real());
+        }
+    }
+
+    @Test
+    public void testStft() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.arange(1f, 9f);
+            NDArray result = array.stft(4, 2, false, null, true);
+            float[] data = {
+                10f, 0f, 18f, 0f, 26f, 0f, -2f, 2f, -2f, 2f, -2f, 2f, -2f, 0f, -2f, 0f, -2f, 0f
+            };
+            Assertions.assertAlmostEquals(
+                    result.real(), manager.create(data, new Shape(3, 3, 2)));
+
+            array = manager.ones(new Shape(20));
+            NDArray window = manager.hanningWindow(20);
+            result = array.stft(20, 16, false, window, true);
+            data = new float[22];
+            data[0] = 10f;
+            data[2] = -5f;
+            Assertions.assertAlmostEquals(
+                    result.real(), manager.create(data, new Shape(11, 1, 2)));
+        }
+    }
+
+    @Test
+    public void testPad() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            RsNDArray array = (RsNDArray) manager.create(new float[] {1f, 2f, 3f});
+            NDArray expected = manager.create(new float[] {0f, 0f, 1f, 2f, 3f, 0f});
+            Assert.assertEquals(array.pad(new Shape(2, 1), 0), expected);
+
+            expected = manager.create(new float[] {3f, 2f, 1f, 2f, 3f, 2f});
+            Assert.assertEquals(array.pad(new Shape(2, 1), "reflect", 0), expected);
+
+            expected = manager.create(new float[] {1f, 1f, 1f, 2f, 3f, 3f});
+            Assert.assertEquals(array.pad(new Shape(2, 1), "replicate", 0), expected);
+
+            array = (RsNDArray) manager.create(new float[] {1f, 2f, 3f, 4f}, new Shape(2, 2));
+            float[] data = {0f, 0f, 0f, 0f, 0f, 1f, 2f, 0f, 0f, 3f, 4f, 0f};
+            expected = manager.create(data, new Shape(3, 4));
+            Assert.assertEquals(array.pad(new Shape(1, 1, 1, 0), 0), expected);
+        }
+    }
+
+    @Test
+    public void testTrace() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.arange(8f).reshape(2, 2, 2);
+            Assert.assertEquals(array.trace(), manager.create(new float[] {6f, 8f}));
+            Assert.assertEquals(array.trace(1), manager.create(new float[] {2f, 3f}));
+            Assert.assertEquals(array.trace(1, 1, 2), manager.create(new float[] {1f, 5f}));
+            Assert.assertEquals(array.trace(0, 1, 2), manager.create(new float[] {3f, 11f}));
+        }
+    }
+
+    @Test
+    public void testBatchDot() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            NDArray array = manager.ones(new Shape(2, 1, 4));
+            NDArray other = manager.ones(new Shape(2, 4, 6));
+            NDArray expected = manager.full(new Shape(2, 1, 6), 4f);
+            Assert.assertEquals(array.batchDot(other), expected);
+        }
+    }
+
+    @Test
+    public void testLinalg() {
+        try (NDManager manager = NDManager.newBaseManager("Rust")) {
+            RsNDArray array =
+                    (RsNDArray) manager.create(new float[] {4f, 7f, 2f, 6f}, new Shape(2, 2));
+            float[] data = {0.6f, -0.7f, -0.2f, 0.4f};
+            NDArray expected = manager.create(data, new Shape(2, 2));
+            Assertions.assertAlmostEquals(array.inverse(), expected);
+            Assertions.assertAlmostEquals(array.det(), manager.create(10f));
+
+            array = (RsNDArray) manager.create(new float[] {3f, 1f, 1f, 2f}, new Shape(2, 2));
+            NDArray b = manager.create(new float[] {9f, 8f});
+            Assertions.assertAlmostEquals(array.solve(b), manager.create(new float[] {2f, 3f}));
+
+            data = new float[] {4f, 12f, -16f, 12f, 37f, -43f, -16f, -43f, 98f};
+            array = (RsNDArray) manager.create(data, new Shape(3, 3));
+            data = new float[] {2f, 0f, 0f, 6f, 1f, 0f, -8f, 5f, 3f};
+            expected = manager.create(data, new Shape(3, 3));
+            Assertions.assertAlmostEquals(array.cholesky(), expected);
+
+            data = new float[] {12f, -51f, 4f, 6f, 167f, -68f, -4f, 24f, -41f};
+            array = (RsNDArray) manager.create(data, new Shape(3, 3));
+            NDList qr = array.qr();
+            data = new float[] {-14f, -21f, 14f, 0f, -175f, 70f, 0f, 0f, -35f};
+            Assertions.assertAlmostEquals(qr.get(1), manager.create(data, new Shape(3, 3)));
+            Assertions.assertAlmostEquals(qr.get(0).matMul(qr.get(1)), array);
+
+            data = new float[] {3f, 2f, 2f, 2f, 3f, -2f};
+            array = (RsNDArray) manager.create(data, new Shape(2, 3));
+            NDList svd = array.svd();
+            Assert.assertEquals(svd.get(0).getShape(), new Shape(2, 2));
+            Assert.assertEquals(svd.get(2).getShape(), new Shape(2, 3));
+            Assertions.assertAlmostEquals(svd.get(1), manager.create(new float[] {5f, 3f}));
+            NDArray reconstructed = svd.get(0).mul(svd.get(1)).matMul(svd.get(2));
+            Assertions.assertAlmostEquals(reconstructed, array);
+
+            array = (RsNDArray) manager.create(new float[] {1f, 2f, 3f, 4f}, new Shape(2, 2));
+            expected = manager.create(new float[] {3.1623f, 4.4721f}, new Shape(1, 2));
+            Assertions.assertAlmostEquals(array.norm(2, new int[] {0}, true), expected);
+            Assertions.assertAlmostEquals(array.norm(), manager.create(5.4772f));
+            expected = manager.create(new float[] {3f, 7f});
+            Assertions.assertAlmostEquals(array.norm(1, new int[] {1}, false), expected);
+            // matrix norms: the largest singular value and the max absolute column sum
+            expected = manager.create(new float[] {5.4650f}, new Shape(1, 1));
+            Assertions.assertAlmostEquals(array.norm(2, new int[] {0, 1}, true), expected);
+            Assertions.assertAlmostEquals(
+                    array.norm(1, new int[] {0, 1}, false), manager.create(6f));
+            NDArray matrix = array;
+            Assert.assertThrows(() -> matrix.norm(3, new int[] {0, 1}, false));
+        }
+    }
+
     @Test
     public void testExpandDim() {
         try (NDManager manager = NDManager.newBaseManager("Rust")) {
@@ -13,6 +13,7 @@
 package ai.djl.engine.rust;
 
 import ai.djl.engine.Engine;
+import ai.djl.engine.StandardCapabilities;
 
 import org.testng.Assert;
 import org.testng.annotations.Test;
@@ -24,4 +25,24 @@ public class RsEngineTest {
         Engine engine = Engine.getEngine("Rust");
         Assert.assertEquals(engine.getVersion(), Engine.getDjlVersion());
     }
+
+    @Test
+    public void testGpuInfo() {
+        RsEngine engine = (RsEngine) Engine.getEngine("Rust");
+        int gpuCount = engine.getGpuCount();
+        if (!engine.hasCapability(StandardCapabilities.CUDA)) {
+            Assert.assertEquals(gpuCount, 0);
+        }
+        boolean supported = false;
+        for (int i = 0; i < gpuCount; ++i) {
+            GpuInfo info = engine.getDeviceInfo(i);
+            Assert.assertEquals(info.getDeviceId(), i);
+            Assert.assertTrue(info.getTotalMemory() > 0);
+            supported |= info.isSupported();
+        }
+        // unsupported devices are counted, CUDA needs at least one supported device
+        Assert.assertEquals(supported, engine.hasCapability(StandardCapabilities.CUDA));
+        Assert.assertThrows(() -> engine.getDeviceInfo(gpuCount));
+        Assert.assertEquals(engine.getDevices().length, Math.max(gpuCount, 1));
+    }
 }